@@ -91,6 +91,7 @@ async fn main() {
             format: "text".to_string(),
             limit: 48,
             prefix_filter: "databend_".to_string(),
+            ..Default::default()
         },
         stderr: StderrConfig {
             on: true,
@@ -100,7 +101,13 @@ async fn main() {
         ..Default::default()
     };
 
-    let _guards = init_logging("databend-metabench", &log_config, BTreeMap::new());
+    let _guards = match init_logging("databend-metabench", &log_config, BTreeMap::new()) {
+        Ok(guards) => guards,
+        Err(e) => {
+            eprintln!("failed to init logging: {}", e);
+            return;
+        }
+    };
 
     println!("config: {:?}", config);
     if config.grpc_api_address.is_empty() {
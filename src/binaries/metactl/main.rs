@@ -128,11 +128,13 @@ async fn main() -> anyhow::Result<()> {
             format: "text".to_string(),
             limit: 48,
             prefix_filter: "databend_".to_string(),
+            ..Default::default()
         },
         ..Default::default()
     };
 
-    let _guards = init_logging("metactl", &log_config, BTreeMap::new());
+    let _guards =
+        init_logging("metactl", &log_config, BTreeMap::new()).map_err(anyhow::Error::msg)?;
 
     if config.status {
         return show_status(&config).await;
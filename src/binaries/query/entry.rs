@@ -26,6 +26,7 @@ use databend_common_exception::Result;
 use databend_common_meta_client::MIN_METASRV_SEMVER;
 use databend_common_storage::DataOperator;
 use databend_common_tracing::set_panic_hook;
+use databend_common_tracing::GlobalLogger;
 use databend_enterprise_background_service::get_background_service_handler;
 use databend_query::api::HttpService;
 use databend_query::api::RpcService;
@@ -240,8 +241,10 @@ pub async fn start_services(conf: &InnerConfig) -> Result<()> {
     println!("    file: {}", conf.log.file);
     println!("    stderr: {}", conf.log.stderr);
     println!("    otlp: {}", conf.log.otlp);
+    println!("    syslog: {}", conf.log.syslog);
     println!("    query: {}", conf.log.query);
     println!("    tracing: {}", conf.log.tracing);
+    println!("    dedup: {}", conf.log.dedup);
     println!(
         "Meta: {}",
         if conf.meta.is_embedded_meta()? {
@@ -351,6 +354,9 @@ pub async fn start_services(conf: &InnerConfig) -> Result<()> {
         shutdown_handle.wait_for_termination_request().await;
     }
     info!("Shutdown server.");
+    if let Err(e) = GlobalLogger::shutdown() {
+        eprintln!("{}", e);
+    }
     Ok(())
 }
 
@@ -149,6 +149,10 @@ build_exceptions! {
     UnknownCatalog(1119),
     UnknownCatalogType(1120),
     UnmatchMaskPolicyReturnType(1121),
+    // The keys of a GROUP BY bucket take more bytes than `group_by_final_max_arena_bytes`.
+    GroupByArenaExceeded(1122),
+    // The GROUP BY has more distinct groups than `group_by_max_groups`.
+    TooManyGroups(1123),
 
     // Data Related Errors
 
@@ -355,6 +359,7 @@ build_exceptions! {
     InvalidOperation(3905),
     StorageOther(4000),
     UnresolvableConflict(4001),
+    CommitRetriesExhausted(4002),
 }
 
 // Service errors [5001,6000].
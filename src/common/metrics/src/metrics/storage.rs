@@ -109,6 +109,8 @@ static COMMIT_MUTATION_MODIFIED_SEGMENT_EXISTS_IN_LATEST: LazyLock<Counter> =
     LazyLock::new(|| register_counter("fuse_commit_mutation_modified_segment_exists_in_latest"));
 static COMMIT_MUTATION_RETRY: LazyLock<Counter> =
     LazyLock::new(|| register_counter("fuse_commit_mutation_retry"));
+static COMMIT_MUTATION_RETRIES_EXHAUSTED: LazyLock<Counter> =
+    LazyLock::new(|| register_counter("fuse_commit_mutation_retries_exhausted"));
 static COMMIT_MUTATION_SUCCESS: LazyLock<Counter> =
     LazyLock::new(|| register_counter("fuse_commit_mutation_success"));
 static COMMIT_COPIED_FILES: LazyLock<Counter> =
@@ -412,6 +414,10 @@ pub fn metrics_inc_commit_mutation_retry() {
     COMMIT_MUTATION_RETRY.inc();
 }
 
+pub fn metrics_inc_commit_mutation_retries_exhausted() {
+    COMMIT_MUTATION_RETRIES_EXHAUSTED.inc();
+}
+
 pub fn metrics_inc_commit_mutation_success() {
     COMMIT_MUTATION_SUCCESS.inc();
 }
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use std::collections::BTreeMap;
+use std::collections::HashSet;
 use std::fmt::Display;
 use std::fmt::Formatter;
 
@@ -22,10 +23,14 @@ pub struct Config {
     pub file: FileConfig,
     pub stderr: StderrConfig,
     pub otlp: OTLPConfig,
+    pub syslog: SyslogConfig,
     pub query: QueryLogConfig,
     pub profile: ProfileLogConfig,
     pub structlog: StructLogConfig,
     pub tracing: TracingConfig,
+    pub dedup: DedupConfig,
+    /// Keys of the log key-values written as `***` by all the sinks, case-insensitive.
+    pub redact_keys: HashSet<String>,
 }
 
 impl Config {
@@ -39,6 +44,18 @@ impl Config {
                 format: "text".to_string(),
                 limit: 48,
                 prefix_filter: "databend_".to_string(),
+                rotation: "hourly".to_string(),
+                compress: false,
+                compression_level: 6,
+                json_field_order: vec![],
+                target_routes: vec![],
+                level_routes: vec![],
+                timezone: "UTC".to_string(),
+                timestamp_format: "rfc3339_micros".to_string(),
+                json_pid: false,
+                json_tid: false,
+                max_message_bytes: 0,
+                flatten_kvs: false,
             },
             stderr: StderrConfig {
                 on: true,
@@ -58,6 +75,33 @@ pub struct FileConfig {
     pub format: String,
     pub limit: usize,
     pub prefix_filter: String,
+    /// When to start a new log file: hourly, daily, minutely, never, or `size:<bytes>` once
+    /// the file would exceed the bytes.
+    pub rotation: String,
+    /// Gzip the log files once they are rotated out.
+    pub compress: bool,
+    /// Gzip level of the rotated log files, from 0 to 9.
+    pub compression_level: u32,
+    /// Order of the top-level keys in json logs, empty means the default order.
+    pub json_field_order: Vec<String>,
+    /// Target prefixes whose logs are written into `{dir}/{prefix}` instead of `dir`.
+    pub target_routes: Vec<String>,
+    /// Level bands whose logs are written into files named after the prefix instead of the
+    /// default name, each one is `<level>:<file name prefix>`.
+    pub level_routes: Vec<String>,
+    /// Timezone of the log timestamps: `UTC`, `local` or a fixed offset like `+08:00`.
+    pub timezone: String,
+    /// Format of the log timestamps: `rfc3339_micros`, `rfc3339_millis`, `epoch_millis`
+    /// or `epoch_nanos`, not applied to the `ecs_json` format.
+    pub timestamp_format: String,
+    /// Adds the process id as `pid` to json logs.
+    pub json_pid: bool,
+    /// Adds the id of the logging thread as `tid` to json logs.
+    pub json_tid: bool,
+    /// Truncates the log messages and key-values longer than it, 0 means unlimited.
+    pub max_message_bytes: usize,
+    /// Logs each key-value of a text or json record as a record of its own.
+    pub flatten_kvs: bool,
 }
 
 impl Display for FileConfig {
@@ -79,6 +123,18 @@ impl Default for FileConfig {
             format: "json".to_string(),
             limit: 48,
             prefix_filter: "databend_".to_string(),
+            rotation: "hourly".to_string(),
+            compress: false,
+            compression_level: 6,
+            json_field_order: vec![],
+            target_routes: vec![],
+            level_routes: vec![],
+            timezone: "UTC".to_string(),
+            timestamp_format: "rfc3339_micros".to_string(),
+            json_pid: false,
+            json_tid: false,
+            max_message_bytes: 0,
+            flatten_kvs: false,
         }
     }
 }
@@ -122,7 +178,19 @@ pub struct OTLPConfig {
     pub on: bool,
     pub level: String,
     pub endpoint: String,
+    /// Transport of the exporter: grpc or http/protobuf.
+    pub protocol: String,
     pub labels: BTreeMap<String, String>,
+    /// Timeout of exporting one batch of logs.
+    pub timeout_secs: u64,
+    /// Capacity of the queue of the logs waiting to be exported.
+    pub max_queue_size: usize,
+    /// Interval of the batch exports.
+    pub scheduled_delay_ms: u64,
+    /// Retries of a failed export, the batch is dropped after that.
+    pub max_retries: u32,
+    /// Backoff before the first retry, doubled on each of the following ones.
+    pub retry_base_delay_ms: u64,
 }
 
 impl Display for OTLPConfig {
@@ -135,8 +203,17 @@ impl Display for OTLPConfig {
             .join(",");
         write!(
             f,
-            "enabled={}, level={}, endpoint={}, labels={}",
-            self.on, self.level, self.endpoint, labels
+            "enabled={}, level={}, endpoint={}, protocol={}, labels={}, timeout_secs={}, max_queue_size={}, scheduled_delay_ms={}, max_retries={}, retry_base_delay_ms={}",
+            self.on,
+            self.level,
+            self.endpoint,
+            self.protocol,
+            labels,
+            self.timeout_secs,
+            self.max_queue_size,
+            self.scheduled_delay_ms,
+            self.max_retries,
+            self.retry_base_delay_ms
         )
     }
 }
@@ -147,7 +224,44 @@ impl Default for OTLPConfig {
             on: false,
             level: "INFO".to_string(),
             endpoint: "http://127.0.0.1:4317".to_string(),
+            protocol: "grpc".to_string(),
             labels: BTreeMap::new(),
+            timeout_secs: 10,
+            max_queue_size: 2048,
+            scheduled_delay_ms: 1000,
+            max_retries: 3,
+            retry_base_delay_ms: 200,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct SyslogConfig {
+    pub on: bool,
+    pub level: String,
+    /// `udp://host:port`, `tcp://host:port` or `unix://path`, e.g. `unix:///dev/log`.
+    pub endpoint: String,
+    /// `kern`, `user`, `daemon` or `local0` to `local7`.
+    pub facility: String,
+}
+
+impl Display for SyslogConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "enabled={}, level={}, endpoint={}, facility={}",
+            self.on, self.level, self.endpoint, self.facility
+        )
+    }
+}
+
+impl Default for SyslogConfig {
+    fn default() -> Self {
+        Self {
+            on: false,
+            level: "INFO".to_string(),
+            endpoint: "unix:///dev/log".to_string(),
+            facility: "user".to_string(),
         }
     }
 }
@@ -157,6 +271,8 @@ pub struct QueryLogConfig {
     pub on: bool,
     pub dir: String,
     pub otlp_endpoint: String,
+    /// Transport of the exporter: grpc or http/protobuf.
+    pub otlp_protocol: String,
     pub labels: BTreeMap<String, String>,
 }
 
@@ -170,8 +286,8 @@ impl Display for QueryLogConfig {
             .join(",");
         write!(
             f,
-            "enabled={}, dir={}, otlp_endpoint={}, labels={}",
-            self.on, self.dir, self.otlp_endpoint, labels,
+            "enabled={}, dir={}, otlp_endpoint={}, otlp_protocol={}, labels={}",
+            self.on, self.dir, self.otlp_endpoint, self.otlp_protocol, labels,
         )
     }
 }
@@ -182,6 +298,7 @@ impl Default for QueryLogConfig {
             on: false,
             dir: "".to_string(),
             otlp_endpoint: "".to_string(),
+            otlp_protocol: "grpc".to_string(),
             labels: BTreeMap::new(),
         }
     }
@@ -192,6 +309,8 @@ pub struct ProfileLogConfig {
     pub on: bool,
     pub dir: String,
     pub otlp_endpoint: String,
+    /// Transport of the exporter: grpc or http/protobuf.
+    pub otlp_protocol: String,
     pub labels: BTreeMap<String, String>,
 }
 
@@ -205,8 +324,8 @@ impl Display for ProfileLogConfig {
             .join(",");
         write!(
             f,
-            "enabled={}, dir={}, otlp_endpoint={}, labels={}",
-            self.on, self.dir, self.otlp_endpoint, labels,
+            "enabled={}, dir={}, otlp_endpoint={}, otlp_protocol={}, labels={}",
+            self.on, self.dir, self.otlp_endpoint, self.otlp_protocol, labels,
         )
     }
 }
@@ -217,6 +336,7 @@ impl Default for ProfileLogConfig {
             on: false,
             dir: "".to_string(),
             otlp_endpoint: "".to_string(),
+            otlp_protocol: "grpc".to_string(),
             labels: BTreeMap::new(),
         }
     }
@@ -269,3 +389,34 @@ impl Default for TracingConfig {
         }
     }
 }
+
+/// Collapses the repeated lines of the system logs, the query, profile and structlog logs
+/// are never collapsed.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct DedupConfig {
+    pub on: bool,
+    /// At most `max_repeats` occurrences of a line are logged within any window of
+    /// `window_ms`, the dropped ones are logged as a single line with a `repeated` count.
+    pub window_ms: u64,
+    pub max_repeats: u64,
+}
+
+impl Display for DedupConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "enabled={}, window_ms={}, max_repeats={}",
+            self.on, self.window_ms, self.max_repeats
+        )
+    }
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            on: false,
+            window_ms: 1000,
+            max_repeats: 1,
+        }
+    }
+}
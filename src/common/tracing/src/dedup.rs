@@ -0,0 +1,266 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::fmt;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use hashlink::linked_hash_map::RawEntryMut;
+use hashlink::LinkedHashMap;
+
+/// Number of distinct log lines tracked, the least recently seen one is evicted once it is
+/// exceeded.
+const DEDUP_CAPACITY: usize = 1024;
+
+/// A logger that drops the repetitive log lines before they reach the inner logger.
+///
+/// A line is identified by its target, level and message. An occurrence of a line is passed
+/// through if less than `max_repeats` occurrences of it are passed within the `window` before
+/// it, otherwise it is counted only. The window slides with each occurrence, the passed ones
+/// expire one by one as they get older than `window`.
+///
+/// The dropped occurrences are summarized into one line with a `repeated` key-value, along
+/// with the location and the key-values of the first occurrence, once the line is passed
+/// again, or is not seen for a whole window, or is evicted, or on `flush`.
+pub struct DedupLogger<L> {
+    inner: L,
+    max_repeats: u64,
+    window: Duration,
+    state: Mutex<DedupState>,
+}
+
+struct DedupState {
+    // ordered from the least recently seen line.
+    lines: LinkedHashMap<u64, DedupLine>,
+}
+
+#[derive(Clone)]
+struct DedupLine {
+    target: String,
+    level: log::Level,
+    message: String,
+    module_path: Option<String>,
+    file: Option<String>,
+    line: Option<u32>,
+    key_values: Vec<(String, String)>,
+    // times of the occurrences passed within the window, from the oldest.
+    passed: VecDeque<Instant>,
+    last_seen: Instant,
+    // the occurrences dropped since the last summary.
+    dropped: u64,
+}
+
+impl DedupLine {
+    fn new(record: &log::Record<'_>, now: Instant) -> Self {
+        let mut key_values = KeyValuesCollector(vec![]);
+        record.key_values().visit(&mut key_values).ok();
+        DedupLine {
+            target: record.target().to_string(),
+            level: record.level(),
+            message: record.args().to_string(),
+            module_path: record.module_path().map(|path| path.to_string()),
+            file: record.file().map(|file| file.to_string()),
+            line: record.line(),
+            key_values: key_values.0,
+            passed: VecDeque::new(),
+            last_seen: now,
+            dropped: 0,
+        }
+    }
+
+    // takes the occurrences dropped since the last summary.
+    fn take_repeated(&mut self) -> Option<u64> {
+        match std::mem::take(&mut self.dropped) {
+            0 => None,
+            repeated => Some(repeated),
+        }
+    }
+}
+
+struct KeyValuesCollector(Vec<(String, String)>);
+
+impl<'kvs> log::kv::Visitor<'kvs> for KeyValuesCollector {
+    fn visit_pair(
+        &mut self,
+        key: log::kv::Key<'kvs>,
+        value: log::kv::Value<'kvs>,
+    ) -> Result<(), log::kv::Error> {
+        self.0.push((key.to_string(), value.to_string()));
+        Ok(())
+    }
+}
+
+// the key-values of a summary line, the ones of the first occurrence plus `repeated`.
+struct SummaryKeyValues<'a> {
+    key_values: &'a [(String, String)],
+    repeated: u64,
+}
+
+impl<'a> log::kv::Source for SummaryKeyValues<'a> {
+    fn visit<'kvs>(
+        &'kvs self,
+        visitor: &mut dyn log::kv::Visitor<'kvs>,
+    ) -> Result<(), log::kv::Error> {
+        for (key, value) in self.key_values {
+            visitor.visit_pair(key.as_str().into(), value.as_str().into())?;
+        }
+        visitor.visit_pair("repeated".into(), self.repeated.into())
+    }
+}
+
+// feeds the formatted message into the hasher, without formatting it into a string.
+struct HashWriter<'a, H>(&'a mut H);
+
+impl<'a, H: Hasher> fmt::Write for HashWriter<'a, H> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.write(s.as_bytes());
+        Ok(())
+    }
+}
+
+fn line_key(record: &log::Record<'_>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    record.target().hash(&mut hasher);
+    record.level().hash(&mut hasher);
+    match record.args().as_str() {
+        Some(message) => hasher.write(message.as_bytes()),
+        None => {
+            fmt::write(&mut HashWriter(&mut hasher), *record.args()).ok();
+        }
+    }
+    hasher.finish()
+}
+
+impl<L: log::Log> DedupLogger<L> {
+    pub fn new(inner: L, max_repeats: u64, window: Duration) -> Self {
+        DedupLogger {
+            inner,
+            max_repeats,
+            window,
+            state: Mutex::new(DedupState {
+                lines: LinkedHashMap::new(),
+            }),
+        }
+    }
+
+    fn emit_summaries(&self, summaries: Vec<(DedupLine, u64)>) {
+        for (line, repeated) in summaries {
+            let key_values = SummaryKeyValues {
+                key_values: &line.key_values,
+                repeated,
+            };
+            self.inner.log(
+                &log::Record::builder()
+                    .target(&line.target)
+                    .level(line.level)
+                    .module_path(line.module_path.as_deref())
+                    .file(line.file.as_deref())
+                    .line(line.line)
+                    .key_values(&key_values)
+                    .args(format_args!("{}", line.message))
+                    .build(),
+            );
+        }
+    }
+}
+
+impl<L: log::Log> log::Log for DedupLogger<L> {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        let key = line_key(record);
+        let now = Instant::now();
+
+        let mut summaries = vec![];
+        let pass = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            // the lines not seen for a whole window are summarized and forgotten, from the
+            // least recently seen.
+            while let Some((_, line)) = state.lines.front() {
+                if now.duration_since(line.last_seen) < self.window {
+                    break;
+                }
+                if let Some((_, mut line)) = state.lines.pop_front() {
+                    if let Some(repeated) = line.take_repeated() {
+                        summaries.push((line, repeated));
+                    }
+                }
+            }
+
+            if state.lines.len() >= DEDUP_CAPACITY && !state.lines.contains_key(&key) {
+                if let Some((_, mut line)) = state.lines.pop_front() {
+                    if let Some(repeated) = line.take_repeated() {
+                        summaries.push((line, repeated));
+                    }
+                }
+            }
+
+            let line = match state.lines.raw_entry_mut().from_key(&key) {
+                RawEntryMut::Occupied(mut occupied) => {
+                    occupied.to_back();
+                    occupied.into_mut()
+                }
+                RawEntryMut::Vacant(vacant) => vacant.insert(key, DedupLine::new(record, now)).1,
+            };
+            line.last_seen = now;
+            while let Some(passed) = line.passed.front() {
+                if now.duration_since(*passed) < self.window {
+                    break;
+                }
+                line.passed.pop_front();
+            }
+
+            if (line.passed.len() as u64) < self.max_repeats {
+                // the dropped occurrences are summarized before the line is passed again.
+                if let Some(repeated) = line.take_repeated() {
+                    summaries.push((line.clone(), repeated));
+                }
+                line.passed.push_back(now);
+                true
+            } else {
+                line.dropped += 1;
+                false
+            }
+        };
+
+        // emit without holding the lock, the inner logger may be slow.
+        self.emit_summaries(summaries);
+        if pass {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        let summaries = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            state
+                .lines
+                .iter_mut()
+                .filter_map(|(_, line)| {
+                    let repeated = line.take_repeated()?;
+                    Some((line.clone(), repeated))
+                })
+                .collect::<Vec<_>>()
+        };
+        self.emit_summaries(summaries);
+        self.inner.flush();
+    }
+}
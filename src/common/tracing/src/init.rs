@@ -15,6 +15,8 @@
 use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::io::Write;
+use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Duration;
 
 use databend_common_base::base::tokio;
@@ -24,27 +26,73 @@ use log::Log;
 use minitrace::prelude::*;
 use opentelemetry_otlp::WithExportConfig;
 
+use crate::level::DynamicLevelLogger;
+use crate::level::LogLevelHandle;
 use crate::loggers::formatter;
 use crate::loggers::new_file_log_writer;
+use crate::loggers::new_level_routed_file_logger;
+use crate::loggers::new_target_routed_file_logger;
+use crate::loggers::parse_json_field_order;
+use crate::loggers::parse_level_routes;
+use crate::loggers::FormatOptions;
 use crate::loggers::MinitraceLogger;
 use crate::loggers::OpenTelemetryLogger;
+use crate::loggers::OtlpExporterOptions;
+use crate::loggers::RedactKeys;
+use crate::loggers::SyslogLogger;
+use crate::loggers::DEFAULT_LOG_BUFFER_CAPACITY;
+use crate::rolling::RotationPolicy;
+use crate::shutdown::LogShutdown;
+use crate::shutdown::LogShutdownError;
 use crate::structlog::StructLogReporter;
 use crate::Config;
+use crate::DedupLogger;
 
 const HEADER_TRACE_PARENT: &str = "traceparent";
 
-#[allow(dyn_drop)]
+#[derive(Clone)]
 pub struct GlobalLogger {
-    _guards: Vec<Box<dyn Drop + Send + Sync + 'static>>,
+    shutdown: Arc<Mutex<Option<LogShutdown>>>,
 }
 
 impl GlobalLogger {
-    pub fn init(name: &str, cfg: &Config, labels: BTreeMap<String, String>) {
-        let _guards = init_logging(name, cfg, labels);
-        GlobalInstance::set(Self { _guards });
+    pub fn init(name: &str, cfg: &Config, labels: BTreeMap<String, String>) -> Result<(), String> {
+        let (shutdown, level_handle) = init_logging_with_level_handle(name, cfg, labels)?;
+        GlobalInstance::set(Self {
+            shutdown: Arc::new(Mutex::new(Some(shutdown))),
+        });
+        GlobalInstance::set(level_handle);
+        Ok(())
+    }
+
+    /// Flushes all the log sinks, the logs after it may be lost. Only the first call
+    /// flushes the sinks.
+    pub fn shutdown() -> Result<(), LogShutdownError> {
+        let logger: GlobalLogger = GlobalInstance::get();
+        let shutdown = logger
+            .shutdown
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        match shutdown {
+            Some(shutdown) => shutdown.shutdown(),
+            None => Ok(()),
+        }
+    }
+
+    /// The handle to change the log levels at runtime.
+    pub fn level_handle() -> LogLevelHandle {
+        GlobalInstance::get()
     }
 }
 
+/// Changes the level of the logs of the target prefix, or the default level if `target` is
+/// `None`, without restarting. It applies to the file, OpenTelemetry, syslog and minitrace logs,
+/// the query, profile and structlog logs are not filtered by level.
+pub fn reload_log_level(target: Option<&str>, level: LevelFilter) {
+    GlobalLogger::level_handle().set_level(target, level);
+}
+
 pub fn start_trace_for_remote_request<T>(name: &'static str, request: &tonic::Request<T>) -> Span {
     let span_context = try {
         let traceparent = request.metadata().get(HEADER_TRACE_PARENT)?.to_str().ok()?;
@@ -68,13 +116,36 @@ pub fn inject_span_to_tonic_request<T>(msg: impl tonic::IntoRequest<T>) -> tonic
     request
 }
 
+/// Fails if the config has a log format not supported, nothing is logged in that case.
 #[allow(dyn_drop)]
 pub fn init_logging(
+    name: &str,
+    cfg: &Config,
+    labels: BTreeMap<String, String>,
+) -> Result<Vec<Box<dyn Drop + Send + Sync + 'static>>, String> {
+    let (shutdown, _) = init_logging_with_level_handle(name, cfg, labels)?;
+    Ok(vec![Box::new(shutdown)])
+}
+
+/// Same as [`init_logging`], returns the [`LogShutdown`] to flush the log sinks, and the handle
+/// to change the levels of the file, OpenTelemetry, syslog and minitrace loggers at runtime.
+/// The configured level of each logger is its default level.
+///
+/// On shutdown, the loggers are flushed first, then the OpenTelemetry exporters and the
+/// writers of the log files, then the spans of minitrace.
+pub fn init_logging_with_level_handle(
     name: &str,
     cfg: &Config,
     mut labels: BTreeMap<String, String>,
-) -> Vec<Box<dyn Drop + Send + Sync + 'static>> {
-    let mut guards: Vec<Box<dyn Drop + Send + Sync + 'static>> = Vec::new();
+) -> Result<(LogShutdown, LogLevelHandle), String> {
+    let level_handle = LogLevelHandle::default();
+    let mut shutdown = LogShutdown::default();
+    // the buffered writers of the log files are flushed into the non-blocking writers.
+    shutdown.register("logger", || {
+        log::logger().flush();
+        Ok(())
+    });
+    let mut trace_shutdown = LogShutdown::default();
     let log_name = name;
     let trace_name = match labels.get("node_id") {
         None => name.to_string(),
@@ -139,16 +210,22 @@ pub fn init_logging(
             minitrace::set_reporter(otlp_reporter, minitrace::collector::Config::default());
         }
 
-        guards.push(Box::new(defer::defer(minitrace::flush)));
-        guards.push(Box::new(defer::defer(|| {
+        trace_shutdown.register("minitrace", || {
+            minitrace::flush();
+            Ok(())
+        });
+        trace_shutdown.register("tracing reporter", || {
             std::thread::spawn(move || std::mem::drop(reporter_rt))
                 .join()
-                .unwrap()
-        })));
+                .map_err(|_| "failed to stop the reporter runtime".to_string())
+        });
     } else if cfg.structlog.on {
         let reporter = StructLogReporter::new();
         minitrace::set_reporter(reporter, minitrace::collector::Config::default());
-        guards.push(Box::new(defer::defer(minitrace::flush)));
+        trace_shutdown.register("minitrace", || {
+            minitrace::flush();
+            Ok(())
+        });
     }
 
     // Initialize logging
@@ -157,38 +234,166 @@ pub fn init_logging(
     let mut profile_logger = fern::Dispatch::new();
     let mut structlog_logger = fern::Dispatch::new();
 
+    let format_options = FormatOptions {
+        // the order has been validated while loading config
+        json_field_order: parse_json_field_order(&cfg.file.json_field_order).unwrap_or_default(),
+        // the timezone has been validated while loading config
+        timezone: cfg.file.timezone.parse().unwrap_or_default(),
+        // the timestamp format has been validated while loading config
+        timestamp_format: cfg.file.timestamp_format.parse().unwrap_or_default(),
+        json_pid: cfg.file.json_pid,
+        json_tid: cfg.file.json_tid,
+        max_message_bytes: (cfg.file.max_message_bytes > 0).then_some(cfg.file.max_message_bytes),
+        app_name: log_name.to_string(),
+        flatten_kvs: cfg.file.flatten_kvs,
+        redact_keys: RedactKeys::new(&cfg.redact_keys),
+    };
+
+    // the rotation has been validated while loading config
+    let rotation: RotationPolicy = cfg.file.rotation.parse().unwrap_or_default();
+    let compression_level = cfg.file.compress.then_some(cfg.file.compression_level);
+
+    // the repeated lines are collapsed by each sink after its level is checked, the lines
+    // dropped by the level or the target filters are not counted.
+    let dedup = |logger: Box<dyn Log>| -> Box<dyn Log> {
+        match cfg.dedup.on {
+            true => Box::new(DedupLogger::new(
+                logger,
+                cfg.dedup.max_repeats,
+                Duration::from_millis(cfg.dedup.window_ms),
+            )),
+            false => logger,
+        }
+    };
+
     // File logger
     if cfg.file.on {
-        let (normal_log_file, flush_guard) =
-            new_file_log_writer(&cfg.file.dir, log_name, cfg.file.limit);
-        guards.push(Box::new(flush_guard));
-        let dispatch = fern::Dispatch::new()
-            .level(cfg.file.level.parse().unwrap_or(LevelFilter::Info))
-            .format(formatter(&cfg.file.format))
-            .chain(Box::new(normal_log_file) as Box<dyn Write + Send>);
-        normal_logger = normal_logger.chain(dispatch);
+        let dispatch = fern::Dispatch::new();
+        let dispatch = if !cfg.file.level_routes.is_empty() {
+            let (routed_logger, flush_guards) = new_level_routed_file_logger(
+                &cfg.file.dir,
+                log_name,
+                // the routes have been validated while loading config
+                &parse_level_routes(&cfg.file.level_routes).unwrap_or_default(),
+                cfg.file.limit,
+                rotation,
+                DEFAULT_LOG_BUFFER_CAPACITY,
+                compression_level,
+                &cfg.file.format,
+                &format_options,
+            )?;
+            shutdown.register_guard("file", flush_guards);
+            dispatch.chain(Box::new(routed_logger) as Box<dyn Log>)
+        } else if cfg.file.target_routes.is_empty() {
+            let (normal_log_file, flush_guard) = new_file_log_writer(
+                &cfg.file.dir,
+                log_name,
+                cfg.file.limit,
+                rotation,
+                DEFAULT_LOG_BUFFER_CAPACITY,
+                compression_level,
+            );
+            shutdown.register_guard("file", flush_guard);
+            dispatch
+                .format(formatter(&cfg.file.format, &format_options)?)
+                .chain(Box::new(normal_log_file) as Box<dyn Write + Send>)
+        } else {
+            let (routed_logger, flush_guards) = new_target_routed_file_logger(
+                &cfg.file.dir,
+                log_name,
+                &cfg.file.target_routes,
+                cfg.file.limit,
+                rotation,
+                DEFAULT_LOG_BUFFER_CAPACITY,
+                compression_level,
+                &cfg.file.format,
+                &format_options,
+            )?;
+            shutdown.register_guard("file", flush_guards);
+            dispatch.chain(Box::new(routed_logger) as Box<dyn Log>)
+        };
+        // the level is checked by the wrapper, so that it can be changed at runtime.
+        let (_, file_logger) = dispatch.into_log();
+        let file_logger = DynamicLevelLogger::new(
+            dedup(file_logger),
+            &level_handle,
+            cfg.file.level.parse().unwrap_or(LevelFilter::Info),
+        );
+        normal_logger = normal_logger.chain(Box::new(file_logger) as Box<dyn Log>);
     }
 
     // Console logger
     if cfg.stderr.on {
+        let (_, stderr_logger) = fern::Dispatch::new()
+            .format(formatter(&cfg.stderr.format, &format_options)?)
+            .chain(std::io::stderr())
+            .into_log();
         let dispatch = fern::Dispatch::new()
             .level(cfg.stderr.level.parse().unwrap_or(LevelFilter::Info))
-            .format(formatter(&cfg.stderr.format))
-            .chain(std::io::stderr());
+            .chain(dedup(stderr_logger));
         normal_logger = normal_logger.chain(dispatch)
     }
 
+    // the exporter options of `log.otlp` apply to the query and profile exporters too.
+    let exporter_options = OtlpExporterOptions {
+        timeout: Duration::from_secs(cfg.otlp.timeout_secs),
+        max_queue_size: cfg.otlp.max_queue_size,
+        scheduled_delay: Duration::from_millis(cfg.otlp.scheduled_delay_ms),
+        max_retries: cfg.otlp.max_retries,
+        retry_base_delay: Duration::from_millis(cfg.otlp.retry_base_delay_ms),
+        ..Default::default()
+    };
+
     // OpenTelemetry logger
     if cfg.otlp.on {
         let mut labels = labels.clone();
         labels.insert("category".to_string(), "system".to_string());
         labels.extend(cfg.otlp.labels.clone());
-        let logger = OpenTelemetryLogger::new(log_name, &cfg.otlp.endpoint, labels);
-        let dispatch = fern::Dispatch::new()
-            .level(cfg.otlp.level.parse().unwrap_or(LevelFilter::Info))
-            .format(formatter("json"))
-            .chain(Box::new(logger) as Box<dyn Log>);
-        normal_logger = normal_logger.chain(dispatch);
+        let logger = OpenTelemetryLogger::new(
+            log_name,
+            &cfg.otlp.endpoint,
+            // the protocol has been validated while loading config
+            cfg.otlp.protocol.parse().unwrap_or_default(),
+            &exporter_options,
+            labels,
+            format_options.redact_keys.clone(),
+        );
+        shutdown.register("otlp", logger.shutdown_flush());
+        // fern drops the key-values of the formatted record, they are kept in the json body.
+        let (_, logger) = fern::Dispatch::new()
+            .format(formatter("json", &format_options)?)
+            .chain(Box::new(logger) as Box<dyn Log>)
+            .into_log();
+        let logger = DynamicLevelLogger::new(
+            dedup(logger),
+            &level_handle,
+            cfg.otlp.level.parse().unwrap_or(LevelFilter::Info),
+        );
+        normal_logger = normal_logger.chain(Box::new(logger) as Box<dyn Log>);
+    }
+
+    // Syslog logger
+    if cfg.syslog.on {
+        // the endpoint is resolved again, it may fail even if validated while loading config.
+        match cfg.syslog.endpoint.parse() {
+            Ok(transport) => {
+                let logger = SyslogLogger::new(
+                    transport,
+                    // the facility has been validated while loading config
+                    cfg.syslog.facility.parse().unwrap_or_default(),
+                    None,
+                )
+                .with_redact_keys(format_options.redact_keys.clone());
+                shutdown.register("syslog", logger.shutdown_flush());
+                let logger = DynamicLevelLogger::new(
+                    dedup(Box::new(logger)),
+                    &level_handle,
+                    cfg.syslog.level.parse().unwrap_or(LevelFilter::Info),
+                );
+                normal_logger = normal_logger.chain(Box::new(logger) as Box<dyn Log>);
+            }
+            Err(e) => eprintln!("syslog logger is not initialized: {e}"),
+        }
     }
 
     // Log to minitrace
@@ -199,26 +404,44 @@ pub fn init_logging(
             .parse()
             .ok()
             .unwrap_or(LevelFilter::Info);
-        normal_logger = normal_logger.chain(
-            fern::Dispatch::new()
-                .level(level)
-                .chain(Box::new(MinitraceLogger) as Box<dyn Log>),
+        let logger = DynamicLevelLogger::new(
+            dedup(Box::new(MinitraceLogger {
+                timezone: format_options.timezone,
+                redact_keys: format_options.redact_keys.clone(),
+            })),
+            &level_handle,
+            level,
         );
+        normal_logger = normal_logger.chain(Box::new(logger) as Box<dyn Log>);
     }
 
     // Query logger
     if cfg.query.on {
         if !cfg.query.dir.is_empty() {
-            let (query_log_file, flush_guard) =
-                new_file_log_writer(&cfg.query.dir, log_name, cfg.file.limit);
-            guards.push(Box::new(flush_guard));
+            let (query_log_file, flush_guard) = new_file_log_writer(
+                &cfg.query.dir,
+                log_name,
+                cfg.file.limit,
+                rotation,
+                DEFAULT_LOG_BUFFER_CAPACITY,
+                compression_level,
+            );
+            shutdown.register_guard("query file", flush_guard);
             query_logger = query_logger.chain(Box::new(query_log_file) as Box<dyn Write + Send>);
         }
         if !cfg.query.otlp_endpoint.is_empty() {
             let mut labels = labels.clone();
             labels.insert("category".to_string(), "query".to_string());
             labels.extend(cfg.query.labels.clone());
-            let logger = OpenTelemetryLogger::new(log_name, &cfg.query.otlp_endpoint, labels);
+            let logger = OpenTelemetryLogger::new(
+                log_name,
+                &cfg.query.otlp_endpoint,
+                cfg.query.otlp_protocol.parse().unwrap_or_default(),
+                &exporter_options,
+                labels,
+                format_options.redact_keys.clone(),
+            );
+            shutdown.register("query otlp", logger.shutdown_flush());
             query_logger = query_logger.chain(Box::new(logger) as Box<dyn Log>);
         }
     }
@@ -226,9 +449,15 @@ pub fn init_logging(
     // Profile logger
     if cfg.profile.on {
         if !cfg.profile.dir.is_empty() {
-            let (profile_log_file, flush_guard) =
-                new_file_log_writer(&cfg.profile.dir, log_name, cfg.file.limit);
-            guards.push(Box::new(flush_guard));
+            let (profile_log_file, flush_guard) = new_file_log_writer(
+                &cfg.profile.dir,
+                log_name,
+                cfg.file.limit,
+                rotation,
+                DEFAULT_LOG_BUFFER_CAPACITY,
+                compression_level,
+            );
+            shutdown.register_guard("profile file", flush_guard);
             profile_logger =
                 profile_logger.chain(Box::new(profile_log_file) as Box<dyn Write + Send>);
         }
@@ -236,16 +465,30 @@ pub fn init_logging(
             let mut labels = labels.clone();
             labels.insert("category".to_string(), "profile".to_string());
             labels.extend(cfg.profile.labels.clone());
-            let logger = OpenTelemetryLogger::new(log_name, &cfg.profile.otlp_endpoint, labels);
+            let logger = OpenTelemetryLogger::new(
+                log_name,
+                &cfg.profile.otlp_endpoint,
+                cfg.profile.otlp_protocol.parse().unwrap_or_default(),
+                &exporter_options,
+                labels,
+                format_options.redact_keys.clone(),
+            );
+            shutdown.register("profile otlp", logger.shutdown_flush());
             profile_logger = profile_logger.chain(Box::new(logger) as Box<dyn Log>);
         }
     }
 
     // Error logger
     if cfg.structlog.on && !cfg.structlog.dir.is_empty() {
-        let (structlog_log_file, flush_guard) =
-            new_file_log_writer(&cfg.structlog.dir, log_name, cfg.file.limit);
-        guards.push(Box::new(flush_guard));
+        let (structlog_log_file, flush_guard) = new_file_log_writer(
+            &cfg.structlog.dir,
+            log_name,
+            cfg.file.limit,
+            rotation,
+            DEFAULT_LOG_BUFFER_CAPACITY,
+            compression_level,
+        );
+        shutdown.register_guard("structlog file", flush_guard);
         structlog_logger =
             structlog_logger.chain(Box::new(structlog_log_file) as Box<dyn Write + Send>);
     }
@@ -287,16 +530,34 @@ pub fn init_logging(
                 .chain(structlog_logger),
         );
 
+    // the spans are flushed after the logs, the logs captured by minitrace are attached to them.
+    shutdown.append(trace_shutdown);
+
+    // the levels of the other loggers are fixed, the global max level is recomputed from them
+    // and the levels of the handle.
+    let mut fixed_max_level = LevelFilter::Off;
+    if cfg.stderr.on {
+        fixed_max_level =
+            fixed_max_level.max(cfg.stderr.level.parse().unwrap_or(LevelFilter::Info));
+    }
+    if cfg.query.on || cfg.profile.on || cfg.structlog.on {
+        fixed_max_level = fixed_max_level.max(LevelFilter::Info);
+    }
+    level_handle.set_fixed_max_level(fixed_max_level);
+
     // Set global logger
     if logger.apply().is_err() {
         eprintln!("logger has already been set");
-        return Vec::new();
+        return Ok((LogShutdown::default(), level_handle));
     }
+    // fern sets the global max level to the most verbose one, the loggers of the handle check
+    // the levels themselves.
+    level_handle.apply_max_level();
 
     #[cfg(feature = "console")]
     init_tokio_console();
 
-    guards
+    Ok((shutdown, level_handle))
 }
 
 #[cfg(feature = "console")]
@@ -0,0 +1,191 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Reverse;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use arc_swap::ArcSwap;
+use log::LevelFilter;
+
+/// The level of the records to log, a default level plus the overrides of some targets.
+///
+/// A target uses the level of the longest target prefix it starts with, or the default
+/// level if there is none. A prefix matches whole modules only, `databend_query` matches
+/// `databend_query::servers` but not `databend_query_x`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelMap {
+    default: LevelFilter,
+    /// Sorted by the length of the prefix, longest first.
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl LevelMap {
+    pub fn new(default: LevelFilter) -> Self {
+        LevelMap {
+            default,
+            targets: vec![],
+        }
+    }
+
+    pub fn level(&self, target: &str) -> LevelFilter {
+        self.targets
+            .iter()
+            .find(|(prefix, _)| is_module_prefix(prefix, target))
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    /// The most verbose level of the map.
+    pub fn max_level(&self) -> LevelFilter {
+        self.targets
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, std::cmp::max)
+    }
+
+    fn set(&mut self, target: Option<&str>, level: LevelFilter) {
+        let Some(target) = target else {
+            self.default = level;
+            return;
+        };
+        match self.targets.iter_mut().find(|(prefix, _)| prefix == target) {
+            Some((_, current)) => *current = level,
+            None => {
+                self.targets.push((target.to_string(), level));
+                self.targets
+                    .sort_by_key(|(prefix, _)| Reverse(prefix.len()));
+            }
+        }
+    }
+}
+
+fn is_module_prefix(prefix: &str, target: &str) -> bool {
+    match target.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with("::"),
+        None => false,
+    }
+}
+
+/// A logger that drops the records above the level of their target in its [`LevelMap`],
+/// the map is changed at runtime through the [`LogLevelHandle`] the logger is created from.
+pub struct DynamicLevelLogger<L> {
+    inner: L,
+    levels: Arc<ArcSwap<LevelMap>>,
+}
+
+impl<L: log::Log> DynamicLevelLogger<L> {
+    /// `default` is the default level of this logger, the loggers of a handle may start
+    /// with different default levels.
+    pub fn new(inner: L, handle: &LogLevelHandle, default: LevelFilter) -> Self {
+        let levels = Arc::new(ArcSwap::from_pointee(LevelMap::new(default)));
+        handle.register(levels.clone());
+        DynamicLevelLogger { inner, levels }
+    }
+}
+
+impl<L: log::Log> log::Log for DynamicLevelLogger<L> {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        metadata.level() <= self.levels.load().level(metadata.target())
+            && self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if record.level() <= self.levels.load().level(record.target()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Changes the levels of the [`DynamicLevelLogger`]s created from it without restarting them.
+#[derive(Clone, Default)]
+pub struct LogLevelHandle {
+    // only locked to register a logger or to set a level, never on the log path.
+    inner: Arc<Mutex<LogLevelHandleInner>>,
+}
+
+#[derive(Default)]
+struct LogLevelHandleInner {
+    loggers: Vec<Arc<ArcSwap<LevelMap>>>,
+    // the most verbose level of the loggers whose level can't be changed, if any.
+    fixed_max_level: Option<LevelFilter>,
+}
+
+impl LogLevelHandleInner {
+    fn max_level(&self) -> LevelFilter {
+        self.loggers
+            .iter()
+            .map(|levels| levels.load().max_level())
+            .fold(
+                self.fixed_max_level.unwrap_or(LevelFilter::Off),
+                std::cmp::max,
+            )
+    }
+}
+
+impl LogLevelHandle {
+    fn register(&self, levels: Arc<ArcSwap<LevelMap>>) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.loggers.push(levels);
+    }
+
+    /// Sets the most verbose level of the loggers which are not created from the handle but
+    /// share the global max level with its loggers, e.g. the stderr logger.
+    pub fn set_fixed_max_level(&self, level: LevelFilter) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.fixed_max_level = Some(level);
+    }
+
+    /// The most verbose level of all the loggers.
+    pub fn max_level(&self) -> LevelFilter {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.max_level()
+    }
+
+    /// Sets the global max level of the `log` macros to the most verbose level of all the
+    /// loggers, the records above it are skipped before calling any logger.
+    pub fn apply_max_level(&self) {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        log::set_max_level(inner.max_level());
+    }
+
+    /// Sets the level of the target prefix in all the loggers, or their default level if
+    /// `target` is `None`.
+    pub fn set_level(&self, target: Option<&str>, level: LevelFilter) {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        for levels in inner.loggers.iter() {
+            levels.rcu(|levels| {
+                let mut levels = LevelMap::clone(levels);
+                levels.set(target, level);
+                levels
+            });
+        }
+        // recomputed from all the levels, so that lowering a level lowers the global max
+        // level again.
+        log::set_max_level(inner.max_level());
+    }
+
+    /// The current levels of each logger, in the order they are created.
+    pub fn levels(&self) -> Vec<LevelMap> {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner
+            .loggers
+            .iter()
+            .map(|levels| LevelMap::clone(&levels.load()))
+            .collect()
+    }
+}
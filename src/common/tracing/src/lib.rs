@@ -16,25 +16,67 @@
 #![allow(clippy::uninlined_format_args)]
 
 mod config;
+mod dedup;
 mod init;
+mod level;
 mod loggers;
 mod panic_hook;
+mod rolling;
+mod shutdown;
 mod structlog;
 
 pub use crate::config::Config;
+pub use crate::config::DedupConfig;
 pub use crate::config::FileConfig;
 pub use crate::config::OTLPConfig;
 pub use crate::config::ProfileLogConfig;
 pub use crate::config::QueryLogConfig;
 pub use crate::config::StderrConfig;
 pub use crate::config::StructLogConfig;
+pub use crate::config::SyslogConfig;
 pub use crate::config::TracingConfig;
+pub use crate::dedup::DedupLogger;
 pub use crate::init::init_logging;
+pub use crate::init::init_logging_with_level_handle;
 pub use crate::init::inject_span_to_tonic_request;
+pub use crate::init::reload_log_level;
 pub use crate::init::start_trace_for_remote_request;
 pub use crate::init::GlobalLogger;
+pub use crate::level::DynamicLevelLogger;
+pub use crate::level::LevelMap;
+pub use crate::level::LogLevelHandle;
+pub use crate::loggers::current_thread_log_id;
+pub use crate::loggers::formatter;
+pub use crate::loggers::new_file_log_writer;
+pub use crate::loggers::new_level_routed_file_logger;
+pub use crate::loggers::new_target_routed_file_logger;
+pub use crate::loggers::otlp_dropped_log_records;
+pub use crate::loggers::otlp_log_attributes;
+pub use crate::loggers::parse_json_field_order;
+pub use crate::loggers::parse_level_routes;
+pub use crate::loggers::syslog_dropped_log_records;
+pub use crate::loggers::CallbackLogger;
+pub use crate::loggers::FormatOptions;
+pub use crate::loggers::JsonField;
+pub use crate::loggers::LevelRoutedLogger;
+pub use crate::loggers::LogFormatter;
+pub use crate::loggers::LogRecordView;
+pub use crate::loggers::LogTimezone;
+pub use crate::loggers::OtlpExporterOptions;
+pub use crate::loggers::OtlpProtocol;
+pub use crate::loggers::RedactKeys;
+pub use crate::loggers::RetryLogExporter;
+pub use crate::loggers::SyslogFacility;
+pub use crate::loggers::SyslogLogger;
+pub use crate::loggers::SyslogTransport;
+pub use crate::loggers::TargetRoutedLogger;
+pub use crate::loggers::TimestampFormat;
+pub use crate::loggers::DEFAULT_LOG_BUFFER_CAPACITY;
 pub use crate::panic_hook::log_panic;
 pub use crate::panic_hook::set_panic_hook;
+pub use crate::rolling::RotationPolicy;
+pub use crate::shutdown::LogShutdown;
+pub use crate::shutdown::LogShutdownError;
 pub use crate::structlog::DummyReporter;
 pub use crate::structlog::StructLogReporter;
 
@@ -12,24 +12,71 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::cmp::Reverse;
 use std::collections::BTreeMap;
+use std::collections::HashSet;
 use std::fmt;
+use std::fmt::Write as _;
+use std::io;
 use std::io::BufWriter;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::net::TcpStream;
+use std::net::ToSocketAddrs;
+use std::net::UdpSocket;
+use std::os::unix::net::UnixDatagram;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::SyncSender;
+use std::sync::mpsc::TrySendError;
+use std::sync::Arc;
 use std::time::Duration;
+use std::time::Instant;
 use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
 
+use chrono::DateTime;
+use chrono::FixedOffset;
+use chrono::Local;
+use chrono::SecondsFormat;
+use chrono::Utc;
+use databend_common_base::base::tokio;
+use databend_common_base::runtime::catch_unwind;
 use fern::FormatCallback;
+use itertools::Itertools;
+use log::LevelFilter;
+use minitrace::prelude::SpanContext;
 use opentelemetry::logs::AnyValue;
+use opentelemetry::logs::LogError;
+use opentelemetry::logs::LogResult;
 use opentelemetry::logs::Logger;
 use opentelemetry::logs::LoggerProvider;
 use opentelemetry::logs::Severity;
+use opentelemetry::Key;
 use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::export::logs::LogData;
+use opentelemetry_sdk::export::logs::LogExporter;
 use serde_json::Map;
 use tracing_appender::non_blocking::NonBlocking;
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_appender::rolling::RollingFileAppender;
 use tracing_appender::rolling::Rotation;
 
+use crate::rolling::LogFiles;
+use crate::rolling::RotationPolicy;
+use crate::rolling::SizeRollingFileAppender;
+use crate::rolling::TimeRollingFileAppender;
+
+/// The default capacity of the `BufWriter` in front of a rolling file logger.
+pub const DEFAULT_LOG_BUFFER_CAPACITY: usize = 64 * 1024 * 1024;
+
 /// Create a `BufWriter<NonBlocking>` for a rolling file logger.
 ///
 /// `BufWriter` collects log segments into a whole before sending to underlying writer.
@@ -39,24 +86,280 @@ use tracing_appender::rolling::Rotation;
 /// Note that `NonBlocking` will discard logs if there are too many `io::Write::write(NonBlocking)`,
 /// especially when `fern` sends log segments one by one to the `Writer`.
 /// Therefore a `BufWriter` is used to reduce the number of `io::Write::write(NonBlocking)`.
-pub(crate) fn new_file_log_writer(
+///
+/// `buffer_capacity` is the size of the `BufWriter`, [`DEFAULT_LOG_BUFFER_CAPACITY`] by default.
+/// A larger buffer sends fewer and bigger writes to the worker thread, but holds more memory
+/// and the logs reach the disk later, only when the buffer is full or flushed. The buffered
+/// logs are flushed once the writer is dropped, then the `WorkerGuard` must be dropped to
+/// wait for the worker thread to write them.
+pub fn new_file_log_writer(
     dir: &str,
     name: impl ToString,
     max_files: usize,
+    policy: RotationPolicy,
+    buffer_capacity: usize,
+    compression_level: Option<u32>,
 ) -> (BufWriter<NonBlocking>, WorkerGuard) {
-    let rolling = RollingFileAppender::builder()
-        .rotation(Rotation::HOURLY)
-        .filename_prefix(name.to_string())
-        .max_log_files(max_files)
-        .build(dir)
-        .expect("failed to initialize rolling file appender");
-    let (non_blocking, flush_guard) = tracing_appender::non_blocking(rolling);
-    let buffered_non_blocking = BufWriter::with_capacity(64 * 1024 * 1024, non_blocking);
+    let files = LogFiles {
+        dir: dir.into(),
+        prefix: name.to_string(),
+        max_files,
+        compression_level,
+    };
+    let (non_blocking, flush_guard) = match policy {
+        RotationPolicy::BySize(max_size) => {
+            let rolling = SizeRollingFileAppender::new(max_size, files)
+                .expect("failed to initialize rolling file appender");
+            tracing_appender::non_blocking(rolling)
+        }
+        RotationPolicy::Hourly | RotationPolicy::Daily | RotationPolicy::Minutely
+            if compression_level.is_some() =>
+        {
+            let (rotation, period_secs) = match policy {
+                RotationPolicy::Daily => (Rotation::DAILY, 24 * 60 * 60),
+                RotationPolicy::Minutely => (Rotation::MINUTELY, 60),
+                _ => (Rotation::HOURLY, 60 * 60),
+            };
+            let rolling = TimeRollingFileAppender::new(rotation, period_secs, files)
+                .expect("failed to initialize rolling file appender");
+            tracing_appender::non_blocking(rolling)
+        }
+        RotationPolicy::Hourly
+        | RotationPolicy::Daily
+        | RotationPolicy::Minutely
+        | RotationPolicy::Never => {
+            let rotation = match policy {
+                RotationPolicy::Daily => Rotation::DAILY,
+                RotationPolicy::Minutely => Rotation::MINUTELY,
+                RotationPolicy::Never => Rotation::NEVER,
+                _ => Rotation::HOURLY,
+            };
+            let rolling = RollingFileAppender::builder()
+                .rotation(rotation)
+                .filename_prefix(files.prefix)
+                .max_log_files(max_files)
+                .build(dir)
+                .expect("failed to initialize rolling file appender");
+            tracing_appender::non_blocking(rolling)
+        }
+    };
+    let buffered_non_blocking = BufWriter::with_capacity(buffer_capacity, non_blocking);
 
     (buffered_non_blocking, flush_guard)
 }
 
-pub(crate) struct MinitraceLogger;
+/// Create a file logger that writes the records of each target prefix into rolling files
+/// under `{dir}/{prefix}`, and the records matching no prefix into rolling files under `dir`.
+///
+/// A record goes to the longest prefix its target starts with. All the `WorkerGuard`s are
+/// returned, the pending logs of a writer are lost once its guard is dropped.
+///
+/// Fails if the format is not supported.
+pub fn new_target_routed_file_logger(
+    dir: &str,
+    name: impl ToString,
+    target_prefixes: &[String],
+    max_files: usize,
+    policy: RotationPolicy,
+    buffer_capacity: usize,
+    compression_level: Option<u32>,
+    format: &str,
+    options: &FormatOptions,
+) -> Result<(TargetRoutedLogger, Vec<WorkerGuard>), String> {
+    let name = name.to_string();
+    let mut guards = Vec::with_capacity(target_prefixes.len() + 1);
+    let mut new_logger = |dir: &str| -> Result<Box<dyn log::Log>, String> {
+        let (writer, guard) = new_file_log_writer(
+            dir,
+            &name,
+            max_files,
+            policy,
+            buffer_capacity,
+            compression_level,
+        );
+        guards.push(guard);
+        let (_, logger) = fern::Dispatch::new()
+            .format(formatter(format, options)?)
+            .chain(Box::new(writer) as Box<dyn Write + Send>)
+            .into_log();
+        Ok(logger)
+    };
+
+    let mut routes = Vec::with_capacity(target_prefixes.len());
+    for prefix in target_prefixes.iter().unique() {
+        let route_dir = Path::new(dir).join(prefix);
+        routes.push((prefix.clone(), new_logger(&route_dir.to_string_lossy())?));
+    }
+    let default = new_logger(dir)?;
+
+    Ok((TargetRoutedLogger::new(routes, default), guards))
+}
+
+/// A logger that dispatches each record to the logger of the longest target prefix
+/// the target of the record starts with, or to the default logger if there is none.
+pub struct TargetRoutedLogger {
+    /// Sorted by the length of the prefix, longest first.
+    routes: Vec<(String, Box<dyn log::Log>)>,
+    default: Box<dyn log::Log>,
+}
+
+impl TargetRoutedLogger {
+    pub fn new(mut routes: Vec<(String, Box<dyn log::Log>)>, default: Box<dyn log::Log>) -> Self {
+        routes.sort_by_key(|(prefix, _)| Reverse(prefix.len()));
+        TargetRoutedLogger { routes, default }
+    }
+
+    fn route(&self, target: &str) -> &dyn log::Log {
+        self.routes
+            .iter()
+            .find(|(prefix, _)| target.starts_with(prefix.as_str()))
+            .map_or(self.default.as_ref(), |(_, logger)| logger.as_ref())
+    }
+}
+
+impl log::Log for TargetRoutedLogger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        self.route(metadata.target()).enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        self.route(record.target()).log(record)
+    }
+
+    fn flush(&self) {
+        for (_, logger) in &self.routes {
+            logger.flush();
+        }
+        self.default.flush();
+    }
+}
+
+/// Create a file logger that writes the records of each level band into rolling files named
+/// after the prefix of the band, and the records of no band into rolling files named `name`.
+/// All the files are under `dir`.
+///
+/// A route `(level, prefix)` takes the records at `level` or more severe, which are not taken
+/// by a route of a more severe level, so a record is written into exactly one file.
+///
+/// Fails if the format is not supported.
+pub fn new_level_routed_file_logger(
+    dir: &str,
+    name: impl ToString,
+    level_routes: &[(LevelFilter, String)],
+    max_files: usize,
+    policy: RotationPolicy,
+    buffer_capacity: usize,
+    compression_level: Option<u32>,
+    format: &str,
+    options: &FormatOptions,
+) -> Result<(LevelRoutedLogger, Vec<WorkerGuard>), String> {
+    let mut guards = Vec::with_capacity(level_routes.len() + 1);
+    let mut new_logger = |name: &str| -> Result<Box<dyn log::Log>, String> {
+        let (writer, guard) = new_file_log_writer(
+            dir,
+            name,
+            max_files,
+            policy,
+            buffer_capacity,
+            compression_level,
+        );
+        guards.push(guard);
+        let (_, logger) = fern::Dispatch::new()
+            .format(formatter(format, options)?)
+            .chain(Box::new(writer) as Box<dyn Write + Send>)
+            .into_log();
+        Ok(logger)
+    };
+
+    let routes = level_routes
+        .iter()
+        .map(|(level, prefix)| Ok((*level, new_logger(prefix)?)))
+        .collect::<Result<_, String>>()?;
+    let default = new_logger(&name.to_string())?;
+
+    Ok((LevelRoutedLogger::new(routes, default), guards))
+}
+
+/// A logger that dispatches each record to the logger of the most severe level band
+/// the level of the record falls in, or to the default logger if there is none.
+pub struct LevelRoutedLogger {
+    /// Sorted by the level, most severe first.
+    routes: Vec<(LevelFilter, Box<dyn log::Log>)>,
+    default: Box<dyn log::Log>,
+}
+
+impl LevelRoutedLogger {
+    pub fn new(
+        mut routes: Vec<(LevelFilter, Box<dyn log::Log>)>,
+        default: Box<dyn log::Log>,
+    ) -> Self {
+        routes.sort_by_key(|(level, _)| *level);
+        LevelRoutedLogger { routes, default }
+    }
+
+    fn route(&self, level: log::Level) -> &dyn log::Log {
+        self.routes
+            .iter()
+            .find(|(max_level, _)| level <= *max_level)
+            .map_or(self.default.as_ref(), |(_, logger)| logger.as_ref())
+    }
+}
+
+impl log::Log for LevelRoutedLogger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        self.route(metadata.level()).enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        self.route(record.level()).log(record)
+    }
+
+    fn flush(&self) {
+        for (_, logger) in &self.routes {
+            logger.flush();
+        }
+        self.default.flush();
+    }
+}
+
+/// Parses the level routes of the file logs, each one is `<level>:<file name prefix>`,
+/// e.g. `warn:databend-query-errors`.
+pub fn parse_level_routes(routes: &[String]) -> Result<Vec<(LevelFilter, String)>, String> {
+    let mut parsed: Vec<(LevelFilter, String)> = Vec::with_capacity(routes.len());
+    for route in routes {
+        let Some((level, prefix)) = route.split_once(':') else {
+            return Err(format!(
+                "invalid log level route `{route}`, expected `<level>:<file name prefix>`"
+            ));
+        };
+        let level = match LevelFilter::from_str(level.trim()) {
+            Ok(LevelFilter::Off) | Err(_) => {
+                return Err(format!(
+                    "invalid level `{level}` of log level route `{route}`, expected one of error, warn, info, debug, trace"
+                ));
+            }
+            Ok(level) => level,
+        };
+        let prefix = prefix.trim();
+        if prefix.is_empty() || prefix.contains('/') {
+            return Err(format!(
+                "invalid file name prefix `{prefix}` of log level route `{route}`"
+            ));
+        }
+        if parsed.iter().any(|(l, p)| *l == level || p == prefix) {
+            return Err(format!(
+                "log level route `{route}` overlaps with another route"
+            ));
+        }
+        parsed.push((level, prefix.to_string()));
+    }
+    Ok(parsed)
+}
+
+pub(crate) struct MinitraceLogger {
+    pub timezone: LogTimezone,
+    pub redact_keys: RedactKeys,
+}
 
 impl log::Log for MinitraceLogger {
     fn enabled(&self, _metadata: &log::Metadata<'_>) -> bool {
@@ -64,16 +367,18 @@ impl log::Log for MinitraceLogger {
     }
 
     fn log(&self, record: &log::Record<'_>) {
+        let timestamp = self.timezone.format_now();
         let mut message = format!(
             "{} {:>5} {}{}",
-            humantime::format_rfc3339_micros(SystemTime::now()),
+            timestamp,
             record.level(),
             record.args(),
-            KvDisplay::new(record.key_values()),
+            KvDisplay::new(record.key_values()).with_redact_keys(&self.redact_keys),
         );
         if message.contains('\n') {
             // Align multi-line log messages with the first line after `level``.
-            message = message.replace('\n', "\n                                  ");
+            let indent = " ".repeat(timestamp.len() + 7);
+            message = message.replace('\n', &format!("\n{indent}"));
         }
         minitrace::Event::add_to_local_parent(message, || []);
     }
@@ -81,44 +386,278 @@ impl log::Log for MinitraceLogger {
     fn flush(&self) {}
 }
 
+/// Transport of the OpenTelemetry OTLP log exporter.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OtlpProtocol {
+    #[default]
+    Grpc,
+    HttpProtobuf,
+}
+
+impl FromStr for OtlpProtocol {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "grpc" => Ok(OtlpProtocol::Grpc),
+            "http/protobuf" => Ok(OtlpProtocol::HttpProtobuf),
+            // the exporter does not implement it yet, reject it instead of falling back to grpc.
+            "http/json" => Err("otlp protocol `http/json` is not supported yet".to_string()),
+            _ => Err(format!(
+                "unknown otlp protocol `{s}`, expected one of: grpc, http/protobuf"
+            )),
+        }
+    }
+}
+
+impl OtlpProtocol {
+    /// Rejects an endpoint on the well known port of the other transport, e.g. `:4317` with
+    /// `http/protobuf`, which would fail only once the logs are exported.
+    pub fn check_endpoint(&self, endpoint: &str) -> Result<(), String> {
+        let authority = endpoint
+            .split_once("://")
+            .map_or(endpoint, |(_, rest)| rest)
+            .split('/')
+            .next()
+            .unwrap_or_default();
+        let port = authority
+            .rsplit_once(':')
+            .and_then(|(_, port)| port.parse::<u16>().ok());
+        match (self, port) {
+            (OtlpProtocol::Grpc, Some(4318)) => Err(format!(
+                "otlp endpoint `{endpoint}` is on the OTLP/HTTP port 4318, but the protocol is grpc, use http/protobuf or port 4317"
+            )),
+            (OtlpProtocol::HttpProtobuf, Some(4317)) => Err(format!(
+                "otlp endpoint `{endpoint}` is on the OTLP/gRPC port 4317, but the protocol is http/protobuf, use grpc or port 4318"
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for OtlpProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OtlpProtocol::Grpc => write!(f, "grpc"),
+            OtlpProtocol::HttpProtobuf => write!(f, "http/protobuf"),
+        }
+    }
+}
+
+/// Options of the OTLP exporter and of the batch processor in front of it.
+///
+/// The defaults are the ones of the OpenTelemetry SDK, except for the retries which
+/// the SDK does not have.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OtlpExporterOptions {
+    /// Timeout of exporting one batch.
+    pub timeout: Duration,
+    /// Logs are dropped once this many logs are waiting to be exported.
+    pub max_queue_size: usize,
+    pub max_export_batch_size: usize,
+    /// Delay between two consecutive exports.
+    pub scheduled_delay: Duration,
+    /// Number of retries of a failed export before the batch is dropped.
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on each of the following ones.
+    pub retry_base_delay: Duration,
+    /// Max time of exporting one batch, including the retries, the batch is dropped once
+    /// it is reached.
+    pub max_export_timeout: Duration,
+}
+
+impl OtlpExporterOptions {
+    /// The config of the exporter to `endpoint`.
+    pub fn export_config(
+        &self,
+        endpoint: &str,
+        protocol: OtlpProtocol,
+    ) -> opentelemetry_otlp::ExportConfig {
+        opentelemetry_otlp::ExportConfig {
+            endpoint: endpoint.to_string(),
+            protocol: match protocol {
+                OtlpProtocol::Grpc => opentelemetry_otlp::Protocol::Grpc,
+                OtlpProtocol::HttpProtobuf => opentelemetry_otlp::Protocol::HttpBinary,
+            },
+            timeout: self.timeout,
+        }
+    }
+}
+
+impl Default for OtlpExporterOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(opentelemetry_otlp::OTEL_EXPORTER_OTLP_TIMEOUT_DEFAULT),
+            max_queue_size: 2048,
+            max_export_batch_size: 512,
+            scheduled_delay: Duration::from_millis(1000),
+            max_retries: 3,
+            retry_base_delay: Duration::from_millis(200),
+            max_export_timeout: DEFAULT_OTLP_MAX_EXPORT_TIMEOUT,
+        }
+    }
+}
+
+/// The max export timeout of the batch processor of the OpenTelemetry SDK.
+const DEFAULT_OTLP_MAX_EXPORT_TIMEOUT: Duration = Duration::from_secs(30);
+
+static OTLP_DROPPED_LOG_RECORDS: AtomicU64 = AtomicU64::new(0);
+
+/// Number of log records dropped by the OTLP exporters after running out of retries or time,
+/// since the process started.
+pub fn otlp_dropped_log_records() -> u64 {
+    OTLP_DROPPED_LOG_RECORDS.load(Ordering::Relaxed)
+}
+
+/// Retries the failed exports of the inner exporter with exponential backoff, so that
+/// a restart of the collector does not lose the logs.
+///
+/// An export, including its retries, is given up once its deadline has passed, 30 seconds by
+/// default, so that it is not cancelled by the batch processor before the batch is counted as
+/// dropped.
+#[derive(Debug)]
+pub struct RetryLogExporter<E> {
+    inner: E,
+    max_retries: u32,
+    base_delay: Duration,
+    deadline: Duration,
+}
+
+impl<E: LogExporter> RetryLogExporter<E> {
+    pub fn new(inner: E, max_retries: u32, base_delay: Duration) -> Self {
+        Self {
+            inner,
+            max_retries,
+            base_delay,
+            deadline: DEFAULT_OTLP_MAX_EXPORT_TIMEOUT,
+        }
+    }
+
+    /// Gives up an export once `deadline` has passed since it started, the attempt in flight
+    /// is cancelled, and no retry starts if its backoff would end after the deadline.
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl<E: LogExporter> LogExporter for RetryLogExporter<E> {
+    async fn export(&mut self, mut batch: Vec<LogData>) -> LogResult<()> {
+        let num_records = batch.len() as u64;
+        let deadline = tokio::time::Instant::now() + self.deadline;
+        let mut retries = 0;
+        loop {
+            // the last attempt does not need a copy of the batch.
+            let attempt = match retries < self.max_retries {
+                true => batch.clone(),
+                false => std::mem::take(&mut batch),
+            };
+            let result = tokio::time::timeout_at(deadline, self.inner.export(attempt))
+                .await
+                .unwrap_or(Err(LogError::ExportTimedOut(self.deadline)));
+            let delay = self.base_delay * 2u32.saturating_pow(retries);
+            match result {
+                Ok(()) => return Ok(()),
+                Err(_)
+                    if retries < self.max_retries
+                        && tokio::time::Instant::now() + delay < deadline =>
+                {
+                    tokio::time::sleep(delay).await;
+                    retries += 1;
+                }
+                Err(e) => {
+                    OTLP_DROPPED_LOG_RECORDS.fetch_add(num_records, Ordering::Relaxed);
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    fn shutdown(&mut self) {
+        self.inner.shutdown()
+    }
+}
+
 pub(crate) struct OpenTelemetryLogger {
     logger: opentelemetry_sdk::logs::Logger,
     // keep provider alive
     provider: opentelemetry_sdk::logs::LoggerProvider,
+    redact_keys: RedactKeys,
 }
 
 impl OpenTelemetryLogger {
     pub(crate) fn new(
         name: impl ToString,
         endpoint: &str,
+        protocol: OtlpProtocol,
+        options: &OtlpExporterOptions,
         labels: BTreeMap<String, String>,
+        redact_keys: RedactKeys,
     ) -> Self {
         let kvs = labels
             .into_iter()
             .map(|(k, v)| opentelemetry::KeyValue::new(k, v))
             .collect::<Vec<_>>();
-        let export_config = opentelemetry_otlp::ExportConfig {
-            endpoint: endpoint.to_string(),
-            protocol: opentelemetry_otlp::Protocol::Grpc,
-            timeout: Duration::from_secs(opentelemetry_otlp::OTEL_EXPORTER_OTLP_TIMEOUT_DEFAULT),
-        };
-        let exporter_builder: opentelemetry_otlp::LogExporterBuilder =
-            opentelemetry_otlp::new_exporter()
+        let export_config = options.export_config(endpoint, protocol);
+        let exporter_builder: opentelemetry_otlp::LogExporterBuilder = match protocol {
+            OtlpProtocol::Grpc => opentelemetry_otlp::new_exporter()
                 .tonic()
                 .with_export_config(export_config)
-                .into();
+                .into(),
+            OtlpProtocol::HttpProtobuf => opentelemetry_otlp::new_exporter()
+                .http()
+                .with_export_config(export_config)
+                .into(),
+        };
         let exporter = exporter_builder
             .build_log_exporter()
             .expect("build log exporter");
+        let exporter =
+            RetryLogExporter::new(exporter, options.max_retries, options.retry_base_delay)
+                .with_deadline(options.max_export_timeout);
+        // the export batch size is capped by the queue size, so set the queue size first.
+        let processor = opentelemetry_sdk::logs::BatchLogProcessor::builder(
+            exporter,
+            opentelemetry_sdk::runtime::Tokio,
+        )
+        .with_max_queue_size(options.max_queue_size)
+        .with_max_export_batch_size(options.max_export_batch_size)
+        .with_scheduled_delay(options.scheduled_delay)
+        // the exporter gives up before the processor cancels it, so that the records of the
+        // batch are counted as dropped.
+        .with_max_timeout(options.max_export_timeout + Duration::from_secs(1))
+        .build();
         let provider = opentelemetry_sdk::logs::LoggerProvider::builder()
-            .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+            .with_log_processor(processor)
             .with_config(
                 opentelemetry_sdk::logs::Config::default()
                     .with_resource(opentelemetry_sdk::Resource::new(kvs)),
             )
             .build();
         let logger = provider.versioned_logger(name.to_string(), None, None, None);
-        Self { logger, provider }
+        Self {
+            logger,
+            provider,
+            redact_keys,
+        }
+    }
+
+    /// Returns the function to export the pending log records on shutdown, the errors of
+    /// the processors are joined into one.
+    pub(crate) fn shutdown_flush(&self) -> impl FnOnce() -> Result<(), String> + Send + Sync {
+        let provider = self.provider.clone();
+        move || {
+            let errors = provider
+                .force_flush()
+                .into_iter()
+                .filter_map(|r| r.err().map(|e| e.to_string()))
+                .collect::<Vec<_>>();
+            match errors.is_empty() {
+                true => Ok(()),
+                false => Err(errors.join("; ")),
+            }
+        }
     }
 }
 
@@ -133,7 +672,8 @@ impl log::Log for OpenTelemetryLogger {
             .with_observed_timestamp(SystemTime::now())
             .with_severity_number(map_severity_to_otel_severity(record.level()))
             .with_severity_text(record.level().as_str())
-            .with_body(AnyValue::from(record.args().to_string()));
+            .with_body(AnyValue::from(record.args().to_string()))
+            .with_attributes(otlp_log_attributes(record, &self.redact_keys));
         self.logger.emit(builder.build())
     }
 
@@ -147,74 +687,1412 @@ impl log::Log for OpenTelemetryLogger {
     }
 }
 
-pub fn formatter(
-    format: &str,
-) -> fn(out: FormatCallback, message: &fmt::Arguments, record: &log::Record) {
-    match format {
-        "text" => format_text_log,
-        "json" => format_json_log,
-        _ => unreachable!("file logging format {format} is not supported"),
+/// The key-values of the record as OpenTelemetry attributes. The booleans and the numbers keep
+/// their types, the other values are formatted as strings.
+pub fn otlp_log_attributes(
+    record: &log::Record<'_>,
+    redact_keys: &RedactKeys,
+) -> Vec<(Key, AnyValue)> {
+    struct AttributeCollector<'a> {
+        attributes: Vec<(Key, AnyValue)>,
+        redact_keys: &'a RedactKeys,
+    }
+
+    impl<'a, 'kvs> log::kv::Visitor<'kvs> for AttributeCollector<'a> {
+        fn visit_pair(
+            &mut self,
+            key: log::kv::Key<'kvs>,
+            value: log::kv::Value<'kvs>,
+        ) -> Result<(), log::kv::Error> {
+            let value = if self.redact_keys.contains(key.as_str()) {
+                AnyValue::from(REDACTED)
+            } else if let Some(v) = value.to_bool() {
+                AnyValue::Boolean(v)
+            } else if let Some(v) = value.to_i64() {
+                AnyValue::Int(v)
+            } else if let Some(v) = value.to_f64() {
+                AnyValue::Double(v)
+            } else {
+                // including the u64 out of the range of i64.
+                AnyValue::from(value.to_string())
+            };
+            self.attributes
+                .push((Key::new(key.as_str().to_string()), value));
+            Ok(())
+        }
+    }
+
+    let mut visitor = AttributeCollector {
+        attributes: vec![],
+        redact_keys,
+    };
+    record.key_values().visit(&mut visitor).ok();
+    visitor.attributes
+}
+
+/// Where a [`SyslogLogger`] sends the messages to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SyslogTransport {
+    Udp(SocketAddr),
+    /// Messages are framed by octet counting, see RFC 6587.
+    Tcp(SocketAddr),
+    /// A local datagram socket, usually `/dev/log`.
+    UnixSocket(PathBuf),
+}
+
+/// Syslog facilities, the ones not used by a database server are left out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SyslogFacility {
+    Kern,
+    #[default]
+    User,
+    Daemon,
+    Local0,
+    Local1,
+    Local2,
+    Local3,
+    Local4,
+    Local5,
+    Local6,
+    Local7,
+}
+
+impl SyslogFacility {
+    fn code(&self) -> u8 {
+        match self {
+            SyslogFacility::Kern => 0,
+            SyslogFacility::User => 1,
+            SyslogFacility::Daemon => 3,
+            SyslogFacility::Local0 => 16,
+            SyslogFacility::Local1 => 17,
+            SyslogFacility::Local2 => 18,
+            SyslogFacility::Local3 => 19,
+            SyslogFacility::Local4 => 20,
+            SyslogFacility::Local5 => 21,
+            SyslogFacility::Local6 => 22,
+            SyslogFacility::Local7 => 23,
+        }
+    }
+}
+
+impl FromStr for SyslogFacility {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "kern" => Ok(SyslogFacility::Kern),
+            "user" => Ok(SyslogFacility::User),
+            "daemon" => Ok(SyslogFacility::Daemon),
+            "local0" => Ok(SyslogFacility::Local0),
+            "local1" => Ok(SyslogFacility::Local1),
+            "local2" => Ok(SyslogFacility::Local2),
+            "local3" => Ok(SyslogFacility::Local3),
+            "local4" => Ok(SyslogFacility::Local4),
+            "local5" => Ok(SyslogFacility::Local5),
+            "local6" => Ok(SyslogFacility::Local6),
+            "local7" => Ok(SyslogFacility::Local7),
+            _ => Err(format!(
+                "unknown syslog facility `{s}`, expected one of: kern, user, daemon, local0 to local7"
+            )),
+        }
+    }
+}
+
+impl FromStr for SyslogTransport {
+    type Err = String;
+
+    /// Parses an endpoint like `udp://127.0.0.1:514`, `tcp://syslog:601` or `unix:///dev/log`,
+    /// the host name is resolved here.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid syslog endpoint `{s}`, expected `udp://host:port`, `tcp://host:port` or `unix://path`"
+            )
+        };
+        let (scheme, address) = s.split_once("://").ok_or_else(invalid)?;
+        let resolve = || {
+            address
+                .to_socket_addrs()
+                .ok()
+                .and_then(|mut addrs| addrs.next())
+                .ok_or_else(|| format!("failed to resolve syslog address `{address}`"))
+        };
+        match scheme.to_lowercase().as_str() {
+            "udp" => Ok(SyslogTransport::Udp(resolve()?)),
+            "tcp" => Ok(SyslogTransport::Tcp(resolve()?)),
+            "unix" if !address.is_empty() => Ok(SyslogTransport::UnixSocket(address.into())),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// SD-ID of the structured data element carrying the record key-values, 32473 is the
+/// private enterprise number reserved for documentation.
+const SYSLOG_SD_ID: &str = "kv@32473";
+
+enum SyslogConnection {
+    Udp(UdpSocket),
+    Tcp(BufWriter<TcpStream>),
+    Unix(UnixDatagram),
+}
+
+fn local_hostname() -> Option<String> {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| {
+            std::fs::read_to_string("/proc/sys/kernel/hostname")
+                .ok()
+                .map(|name| name.trim().to_string())
+        })
+        .filter(|name| !name.is_empty())
+}
+
+fn executable_name() -> Option<String> {
+    let path = std::env::current_exe().ok()?;
+    Some(path.file_name()?.to_str()?.to_string())
+}
+
+/// Max number of messages waiting to be sent by a [`SyslogLogger`], the new ones are
+/// dropped once it is reached.
+const SYSLOG_QUEUE_CAPACITY: usize = 10_000;
+
+/// Delay before reconnecting after a failed connect, doubled on each failure up to
+/// [`SYSLOG_MAX_RECONNECT_DELAY`].
+const SYSLOG_RECONNECT_DELAY: Duration = Duration::from_millis(100);
+const SYSLOG_MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Max time a flush of a [`SyslogLogger`] waits for the queued messages to be sent.
+const SYSLOG_FLUSH_TIMEOUT: Duration = Duration::from_secs(3);
+
+static SYSLOG_DROPPED_LOG_RECORDS: AtomicU64 = AtomicU64::new(0);
+
+/// Number of log records dropped by the syslog loggers, because the queue is full or the
+/// daemon can't be reached, since the process started.
+pub fn syslog_dropped_log_records() -> u64 {
+    SYSLOG_DROPPED_LOG_RECORDS.load(Ordering::Relaxed)
+}
+
+enum SyslogCommand {
+    Message(String),
+    // flush the connection, and notify once the messages queued before are sent.
+    Flush(mpsc::Sender<()>),
+}
+
+/// Sends the records to a syslog daemon in the RFC 5424 format.
+///
+/// The records are formatted on the caller's thread and sent by a background thread, through
+/// a bounded queue. A record is dropped if the queue is full, or if it fails to be sent, see
+/// [`syslog_dropped_log_records`]. The connection is opened on the first record, and opened again
+/// after a failed send, with a backoff if the connect fails.
+pub struct SyslogLogger {
+    facility: SyslogFacility,
+    hostname: String,
+    app_name: String,
+    redact_keys: RedactKeys,
+    sender: SyncSender<SyslogCommand>,
+}
+
+impl SyslogLogger {
+    /// The hostname of the machine is used unless `hostname` is given.
+    pub fn new(
+        transport: SyslogTransport,
+        facility: SyslogFacility,
+        hostname: Option<String>,
+    ) -> Self {
+        let hostname = hostname
+            .or_else(local_hostname)
+            .unwrap_or_else(|| "-".to_string());
+        let app_name = executable_name().unwrap_or_else(|| "-".to_string());
+        let (sender, receiver) = mpsc::sync_channel(SYSLOG_QUEUE_CAPACITY);
+        // the thread exits once the logger is dropped and the queue is drained.
+        std::thread::Builder::new()
+            .name("syslog-logger".to_string())
+            .spawn(move || SyslogSender::new(transport).run(receiver))
+            .expect("spawn syslog logger thread");
+        Self {
+            facility,
+            hostname: syslog_header_field(&hostname, 255),
+            app_name: syslog_header_field(&app_name, 48),
+            redact_keys: RedactKeys::default(),
+            sender,
+        }
+    }
+
+    /// Sends the values of the keys in `redact_keys` as `***`.
+    pub fn with_redact_keys(mut self, redact_keys: RedactKeys) -> Self {
+        self.redact_keys = redact_keys;
+        self
+    }
+
+    /// Returns the function to send the queued messages on shutdown.
+    pub(crate) fn shutdown_flush(&self) -> impl FnOnce() -> Result<(), String> + Send + Sync {
+        let sender = self.sender.clone();
+        move || flush_syslog_queue(&sender, Instant::now() + SYSLOG_FLUSH_TIMEOUT)
+    }
+
+    /// Renders the record as an RFC 5424 message, without the transport framing.
+    pub fn format(&self, record: &log::Record<'_>) -> String {
+        let pri = self.facility.code() * 8 + map_severity_to_syslog_severity(record.level());
+        let mut message = format!(
+            "<{}>1 {} {} {} {} - ",
+            pri,
+            humantime::format_rfc3339_micros(SystemTime::now()),
+            self.hostname,
+            self.app_name,
+            std::process::id(),
+        );
+
+        let mut visitor = SyslogSdWriter {
+            params: String::new(),
+            redact_keys: &self.redact_keys,
+        };
+        record.key_values().visit(&mut visitor).ok();
+        if visitor.params.is_empty() {
+            message.push('-');
+        } else {
+            write!(message, "[{}{}]", SYSLOG_SD_ID, visitor.params).ok();
+        }
+        write!(message, " {}", record.args()).ok();
+        message
+    }
+}
+
+impl log::Log for SyslogLogger {
+    fn enabled(&self, _metadata: &log::Metadata<'_>) -> bool {
+        // we handle level and target filter with fern
+        true
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        let message = self.format(record);
+        if self
+            .sender
+            .try_send(SyslogCommand::Message(message))
+            .is_err()
+        {
+            SYSLOG_DROPPED_LOG_RECORDS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn flush(&self) {
+        if let Err(e) = flush_syslog_queue(&self.sender, Instant::now() + SYSLOG_FLUSH_TIMEOUT) {
+            eprintln!("flush syslog failed: {}", e);
+        }
+    }
+}
+
+// waits for the messages queued so far to be sent, or for `deadline`.
+fn flush_syslog_queue(sender: &SyncSender<SyslogCommand>, deadline: Instant) -> Result<(), String> {
+    let (notify, done) = mpsc::channel();
+    let mut command = SyslogCommand::Flush(notify);
+    // the queue may be full, wait for it to make room.
+    loop {
+        match sender.try_send(command) {
+            Ok(()) => break,
+            Err(TrySendError::Full(c)) if Instant::now() < deadline => {
+                command = c;
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            Err(TrySendError::Full(_)) => return Err("timed out".to_string()),
+            Err(TrySendError::Disconnected(_)) => return Err("sender stopped".to_string()),
+        }
+    }
+    done.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        .map_err(|e| e.to_string())
+}
+
+// The connection of a [`SyslogLogger`], only used by its background thread.
+struct SyslogSender {
+    transport: SyslogTransport,
+    connection: Option<SyslogConnection>,
+    // no connect is attempted before it after a failed one.
+    reconnect_at: Option<Instant>,
+    reconnect_delay: Duration,
+}
+
+impl SyslogSender {
+    fn new(transport: SyslogTransport) -> Self {
+        Self {
+            transport,
+            connection: None,
+            reconnect_at: None,
+            reconnect_delay: SYSLOG_RECONNECT_DELAY,
+        }
+    }
+
+    fn run(mut self, receiver: Receiver<SyslogCommand>) {
+        for command in receiver {
+            match command {
+                SyslogCommand::Message(message) => {
+                    if self.send(&message).is_err() {
+                        SYSLOG_DROPPED_LOG_RECORDS.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+                SyslogCommand::Flush(notify) => {
+                    if let Some(SyslogConnection::Tcp(stream)) = self.connection.as_mut() {
+                        if stream.flush().is_err() {
+                            self.connection = None;
+                        }
+                    }
+                    notify.send(()).ok();
+                }
+            }
+        }
+    }
+
+    fn connect(&self) -> io::Result<SyslogConnection> {
+        match &self.transport {
+            SyslogTransport::Udp(addr) => {
+                let bind_addr: SocketAddr = match addr {
+                    SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+                    SocketAddr::V6(_) => ([0u16; 8], 0).into(),
+                };
+                let socket = UdpSocket::bind(bind_addr)?;
+                socket.connect(addr)?;
+                Ok(SyslogConnection::Udp(socket))
+            }
+            SyslogTransport::Tcp(addr) => {
+                let stream = TcpStream::connect_timeout(addr, Duration::from_secs(3))?;
+                Ok(SyslogConnection::Tcp(BufWriter::new(stream)))
+            }
+            SyslogTransport::UnixSocket(path) => {
+                let socket = UnixDatagram::unbound()?;
+                socket.connect(path)?;
+                Ok(SyslogConnection::Unix(socket))
+            }
+        }
+    }
+
+    // connects if there is no connection, unless a connect failed within the backoff.
+    fn connection(&mut self) -> io::Result<&mut SyslogConnection> {
+        if self.connection.is_none() {
+            if let Some(reconnect_at) = self.reconnect_at {
+                if Instant::now() < reconnect_at {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotConnected,
+                        "waiting to reconnect",
+                    ));
+                }
+            }
+            match self.connect() {
+                Ok(connection) => {
+                    self.connection = Some(connection);
+                    self.reconnect_at = None;
+                    self.reconnect_delay = SYSLOG_RECONNECT_DELAY;
+                }
+                Err(e) => {
+                    self.reconnect_at = Some(Instant::now() + self.reconnect_delay);
+                    self.reconnect_delay =
+                        std::cmp::min(self.reconnect_delay * 2, SYSLOG_MAX_RECONNECT_DELAY);
+                    return Err(e);
+                }
+            }
+        }
+        Ok(self.connection.as_mut().unwrap())
+    }
+
+    fn send(&mut self, message: &str) -> io::Result<()> {
+        let result = match self.connection()? {
+            SyslogConnection::Udp(socket) => socket.send(message.as_bytes()).map(|_| ()),
+            SyslogConnection::Tcp(stream) => {
+                // the buffer only joins the frame header and the message into one write, the
+                // message is not held until the next flush of the logger.
+                write!(stream, "{} {}", message.len(), message).and_then(|_| stream.flush())
+            }
+            SyslogConnection::Unix(socket) => socket.send(message.as_bytes()).map(|_| ()),
+        };
+        if result.is_err() {
+            // reconnect on the next message.
+            self.connection = None;
+        }
+        result
+    }
+}
+
+/// Replaces the characters not allowed in a header field, and truncates it to `max_len`.
+fn syslog_header_field(value: &str, max_len: usize) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_ascii_graphic() { c } else { '_' })
+        .take(max_len)
+        .collect()
+}
+
+struct SyslogSdWriter<'a> {
+    params: String,
+    redact_keys: &'a RedactKeys,
+}
+
+impl<'a, 'kvs> log::kv::Visitor<'kvs> for SyslogSdWriter<'a> {
+    fn visit_pair(
+        &mut self,
+        key: log::kv::Key<'kvs>,
+        value: log::kv::Value<'kvs>,
+    ) -> Result<(), log::kv::Error> {
+        // PARAM-NAME is up to 32 printable characters, except `=`, ` `, `]` and `"`.
+        let name = key
+            .as_str()
+            .chars()
+            .map(|c| match c {
+                '=' | ']' | '"' => '_',
+                c if c.is_ascii_graphic() => c,
+                _ => '_',
+            })
+            .take(32)
+            .collect::<String>();
+        let value = value.to_string();
+        let value = self
+            .redact_keys
+            .redact(key.as_str(), &value)
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace(']', "\\]");
+        write!(self.params, r#" {name}="{value}""#)?;
+        Ok(())
+    }
+}
+
+fn map_severity_to_syslog_severity(level: log::Level) -> u8 {
+    match level {
+        log::Level::Error => 3,
+        log::Level::Warn => 4,
+        log::Level::Info => 6,
+        log::Level::Debug | log::Level::Trace => 7,
+    }
+}
+
+/// A record as passed to the callback of a [`CallbackLogger`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogRecordView<'a> {
+    pub level: log::Level,
+    pub target: &'a str,
+    pub message: String,
+    pub module_path: Option<&'a str>,
+    pub file: Option<&'a str>,
+    pub line: Option<u32>,
+    /// The key-values of the record in their logged order, the values formatted as strings.
+    pub fields: Vec<(String, String)>,
+}
+
+impl<'a> LogRecordView<'a> {
+    pub fn new(record: &'a log::Record<'_>) -> Self {
+        let mut visitor = FieldsCollector { fields: vec![] };
+        record.key_values().visit(&mut visitor).ok();
+        Self {
+            level: record.level(),
+            target: record.target(),
+            message: record.args().to_string(),
+            module_path: record.module_path(),
+            file: record.file(),
+            line: record.line(),
+            fields: visitor.fields,
+        }
+    }
+}
+
+struct FieldsCollector {
+    fields: Vec<(String, String)>,
+}
+
+impl<'kvs> log::kv::Visitor<'kvs> for FieldsCollector {
+    fn visit_pair(
+        &mut self,
+        key: log::kv::Key<'kvs>,
+        value: log::kv::Value<'kvs>,
+    ) -> Result<(), log::kv::Error> {
+        self.fields.push((key.to_string(), value.to_string()));
+        Ok(())
+    }
+}
+
+thread_local! {
+    static IN_LOG_CALLBACK: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Forwards the records to a callback of the host, for embedding databend as a library.
+///
+/// The callback is called on the thread that logs, so it must not block: hand the record
+/// over to a channel or a buffer if it is going to do IO. A panic of the callback is caught,
+/// and the record is dropped. The records logged on a thread while it is running the callback,
+/// including the ones of the panic hook, are dropped instead of calling the callback again.
+pub struct CallbackLogger {
+    callback: Box<dyn Fn(&LogRecordView<'_>) + Send + Sync>,
+    panics: AtomicU64,
+}
+
+impl CallbackLogger {
+    pub fn new(callback: impl Fn(&LogRecordView<'_>) + Send + Sync + 'static) -> Self {
+        Self {
+            callback: Box::new(callback),
+            panics: AtomicU64::new(0),
+        }
+    }
+
+    /// Number of records whose callback panicked.
+    pub fn panics(&self) -> u64 {
+        self.panics.load(Ordering::Relaxed)
+    }
+}
+
+impl log::Log for CallbackLogger {
+    fn enabled(&self, _metadata: &log::Metadata<'_>) -> bool {
+        // we handle level and target filter with fern
+        true
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if IN_LOG_CALLBACK.with(|in_callback| in_callback.replace(true)) {
+            return;
+        }
+        let view = LogRecordView::new(record);
+        let result = catch_unwind(|| (self.callback)(&view));
+        IN_LOG_CALLBACK.with(|in_callback| in_callback.set(false));
+        if result.is_err() {
+            self.panics.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// A log formatter that can be installed with `fern::Dispatch::format`.
+pub type LogFormatter =
+    Box<dyn Fn(FormatCallback, &fmt::Arguments, &log::Record) + Sync + Send + 'static>;
+
+/// Options to customize how log records are rendered by [`formatter`].
+#[derive(Clone, Debug, Default)]
+pub struct FormatOptions {
+    /// Order of the top-level keys in json logs, `fields` is always written last.
+    pub json_field_order: Vec<JsonField>,
+    pub timezone: LogTimezone,
+    pub timestamp_format: TimestampFormat,
+    /// Adds the id of the process as `pid` to json logs.
+    pub json_pid: bool,
+    /// Adds a small id of the logging thread as `tid` to json logs, see [`current_thread_log_id`].
+    pub json_tid: bool,
+    /// Truncates the message, and each key-value on its own, to at most this many bytes.
+    /// `None` means unlimited.
+    pub max_message_bytes: Option<usize>,
+    /// Written as `name` in bunyan logs, the name of the executable if empty.
+    pub app_name: String,
+    /// Splits a text or json record with several key-values into the message alone, followed
+    /// by one `metric_name`/`metric_value` record per key-value.
+    pub flatten_kvs: bool,
+    /// The values of these key-values are written as `***`.
+    pub redact_keys: RedactKeys,
+}
+
+/// What a redacted key-value is written as.
+const REDACTED: &str = "***";
+
+/// Keys of the key-values that may hold secrets, matched case-insensitively.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RedactKeys(Arc<HashSet<String>>);
+
+impl RedactKeys {
+    pub fn new<'a>(keys: impl IntoIterator<Item = &'a String>) -> Self {
+        RedactKeys(Arc::new(
+            keys.into_iter().map(|k| k.to_lowercase()).collect(),
+        ))
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        !self.0.is_empty() && self.0.contains(&key.to_lowercase())
+    }
+
+    /// The value to write for the key-value, `***` if the key is redacted.
+    fn redact<'a>(&self, key: &str, value: &'a str) -> &'a str {
+        match self.contains(key) {
+            true => REDACTED,
+            false => value,
+        }
+    }
+}
+
+/// How the timestamps are rendered in text and json logs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// e.g. `2024-01-01T00:00:00.000000Z`.
+    #[default]
+    Rfc3339Micros,
+    /// e.g. `2024-01-01T00:00:00.000Z`.
+    Rfc3339Millis,
+    /// Milliseconds since the unix epoch, the timezone does not apply.
+    EpochMillis,
+    /// Nanoseconds since the unix epoch, the timezone does not apply.
+    EpochNanos,
+}
+
+impl TimestampFormat {
+    pub fn format(&self, time: SystemTime, timezone: LogTimezone) -> String {
+        match self {
+            TimestampFormat::Rfc3339Micros => timezone.format(time),
+            TimestampFormat::Rfc3339Millis => timezone.format_rfc3339(time, SecondsFormat::Millis),
+            TimestampFormat::EpochMillis => epoch(time).as_millis().to_string(),
+            TimestampFormat::EpochNanos => epoch(time).as_nanos().to_string(),
+        }
+    }
+
+    /// Whether the timestamp is a number, which is not quoted in json logs.
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            TimestampFormat::EpochMillis | TimestampFormat::EpochNanos
+        )
+    }
+}
+
+fn epoch(time: SystemTime) -> Duration {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default()
+}
+
+impl FromStr for TimestampFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "rfc3339_micros" => Ok(TimestampFormat::Rfc3339Micros),
+            "rfc3339_millis" => Ok(TimestampFormat::Rfc3339Millis),
+            "epoch_millis" => Ok(TimestampFormat::EpochMillis),
+            "epoch_nanos" => Ok(TimestampFormat::EpochNanos),
+            _ => Err(format!(
+                "unknown log timestamp format `{s}`, expected one of: rfc3339_micros, rfc3339_millis, epoch_millis, epoch_nanos"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for TimestampFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimestampFormat::Rfc3339Micros => write!(f, "rfc3339_micros"),
+            TimestampFormat::Rfc3339Millis => write!(f, "rfc3339_millis"),
+            TimestampFormat::EpochMillis => write!(f, "epoch_millis"),
+            TimestampFormat::EpochNanos => write!(f, "epoch_nanos"),
+        }
+    }
+}
+
+/// Timezone of the timestamps in logs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogTimezone {
+    #[default]
+    Utc,
+    /// The offset of the host timezone at the time of logging.
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl LogTimezone {
+    /// Formats the current time in RFC 3339 with microseconds, e.g. `2024-01-01T08:00:00.000000+08:00`.
+    pub fn format_now(&self) -> String {
+        self.format(SystemTime::now())
+    }
+
+    pub fn format(&self, time: SystemTime) -> String {
+        self.format_rfc3339(time, SecondsFormat::Micros)
+    }
+
+    fn format_rfc3339(&self, time: SystemTime, precision: SecondsFormat) -> String {
+        match self {
+            // keep the output of the previous versions: `2024-01-01T00:00:00.000000Z`.
+            LogTimezone::Utc if precision == SecondsFormat::Micros => {
+                humantime::format_rfc3339_micros(time).to_string()
+            }
+            LogTimezone::Utc if precision == SecondsFormat::Millis => {
+                humantime::format_rfc3339_millis(time).to_string()
+            }
+            LogTimezone::Utc => DateTime::<Utc>::from(time).to_rfc3339_opts(precision, true),
+            LogTimezone::Local => DateTime::<Local>::from(time).to_rfc3339_opts(precision, false),
+            LogTimezone::Fixed(offset) => DateTime::<Utc>::from(time)
+                .with_timezone(offset)
+                .to_rfc3339_opts(precision, false),
+        }
+    }
+}
+
+impl FromStr for LogTimezone {
+    type Err = String;
+
+    /// Parses `utc`, `local` or a fixed offset like `+08:00`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "utc" => Ok(LogTimezone::Utc),
+            "local" => Ok(LogTimezone::Local),
+            _ => s.parse::<FixedOffset>().map(LogTimezone::Fixed).map_err(|_| {
+                format!("unknown log timezone `{s}`, expected one of: utc, local, or an offset like +08:00")
+            }),
+        }
+    }
+}
+
+impl fmt::Display for LogTimezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogTimezone::Utc => write!(f, "utc"),
+            LogTimezone::Local => write!(f, "local"),
+            LogTimezone::Fixed(offset) => write!(f, "{offset}"),
+        }
+    }
+}
+
+/// Top-level keys of a json log line, besides the trailing `fields` object.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JsonField {
+    Timestamp,
+    Level,
+    /// Module path of the logging call.
+    Target,
+    File,
+    Line,
+}
+
+impl JsonField {
+    const ALL: [JsonField; 5] = [
+        JsonField::Timestamp,
+        JsonField::Level,
+        JsonField::Target,
+        JsonField::File,
+        JsonField::Line,
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            JsonField::Timestamp => "timestamp",
+            JsonField::Level => "level",
+            JsonField::Target => "target",
+            JsonField::File => "file",
+            JsonField::Line => "line",
+        }
+    }
+}
+
+impl FromStr for JsonField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        JsonField::ALL
+            .into_iter()
+            .find(|field| field.as_str() == s)
+            .ok_or_else(|| {
+                let known = JsonField::ALL.map(|field| field.as_str()).join(", ");
+                format!("unknown json log field `{s}`, expected one of: {known}, fields")
+            })
+    }
+}
+
+/// Parse the configured order of json log keys.
+///
+/// Keys that are not mentioned keep their default relative order after the requested ones.
+/// `fields` is always written last, so it's only accepted as the last key.
+pub fn parse_json_field_order(order: &[String]) -> Result<Vec<JsonField>, String> {
+    let mut fields = Vec::with_capacity(JsonField::ALL.len());
+    for (idx, key) in order.iter().enumerate() {
+        let key = key.trim();
+        if key == "fields" {
+            if idx + 1 != order.len() {
+                return Err("json log field `fields` must be the last one".to_string());
+            }
+            continue;
+        }
+        let field = key.parse::<JsonField>()?;
+        if fields.contains(&field) {
+            return Err(format!(
+                "json log field `{key}` is specified more than once"
+            ));
+        }
+        fields.push(field);
+    }
+    for field in JsonField::ALL {
+        if !fields.contains(&field) {
+            fields.push(field);
+        }
     }
+    Ok(fields)
+}
+
+/// The formatter of the log format, which is one of `text`, `json` and `ecs_json`.
+pub fn formatter(format: &str, options: &FormatOptions) -> Result<LogFormatter, String> {
+    let timezone = options.timezone;
+    let timestamp_format = options.timestamp_format;
+    let max_bytes = options.max_message_bytes;
+    let flatten_kvs = options.flatten_kvs;
+    let redact_keys = options.redact_keys.clone();
+    let formatter: LogFormatter = match format {
+        "text" => Box::new(move |out, message, record| {
+            let timestamp = timestamp_format.format(SystemTime::now(), timezone);
+            match flatten_kvs && record.key_values().count() > 1 {
+                true => format_flattened_text_log(
+                    out,
+                    message,
+                    record,
+                    &timestamp,
+                    max_bytes,
+                    &redact_keys,
+                ),
+                false => format_text_log(out, message, record, &timestamp, max_bytes, &redact_keys),
+            }
+        }),
+        "json" => {
+            let order = if options.json_field_order.is_empty() {
+                JsonField::ALL.to_vec()
+            } else {
+                options.json_field_order.clone()
+            };
+            // the pid does not change, no need to get it for every record.
+            let pid = options.json_pid.then(std::process::id);
+            let tid = options.json_tid;
+            Box::new(move |out, message, record| {
+                let timestamp = timestamp_format.format(SystemTime::now(), timezone);
+                let timestamp = match timestamp_format.is_numeric() {
+                    true => timestamp,
+                    false => format!(r#""{timestamp}""#),
+                };
+                format_json_log(
+                    out,
+                    message,
+                    record,
+                    &order,
+                    &timestamp,
+                    pid,
+                    tid,
+                    max_bytes,
+                    &redact_keys,
+                    flatten_kvs && record.key_values().count() > 1,
+                )
+            })
+        }
+        // ECS requires `@timestamp` to be a date string.
+        "ecs_json" => Box::new(move |out, message, record| {
+            format_ecs_json_log(
+                out,
+                message,
+                record,
+                &timezone.format_now(),
+                max_bytes,
+                &redact_keys,
+            )
+        }),
+        "bunyan" => {
+            // the same for all the records, quoted once here.
+            let name = match options.app_name.is_empty() {
+                true => executable_name().unwrap_or_default(),
+                false => options.app_name.clone(),
+            };
+            let name = serde_json::Value::from(name).to_string();
+            let hostname =
+                serde_json::Value::from(local_hostname().unwrap_or_default()).to_string();
+            let pid = std::process::id();
+            Box::new(move |out, message, record| {
+                let header = BunyanHeader {
+                    name: &name,
+                    hostname: &hostname,
+                    pid,
+                };
+                format_bunyan_log(
+                    out,
+                    message,
+                    record,
+                    &header,
+                    &timezone.format_now(),
+                    max_bytes,
+                    &redact_keys,
+                )
+            })
+        }
+        _ => {
+            return Err(format!(
+                "log format `{format}` is not supported, expect one of text, json, ecs_json and bunyan"
+            ));
+        }
+    };
+    Ok(formatter)
 }
 
-fn format_json_log(out: FormatCallback, message: &fmt::Arguments, record: &log::Record) {
+#[allow(clippy::too_many_arguments)]
+fn format_json_log(
+    out: FormatCallback,
+    message: &fmt::Arguments,
+    record: &log::Record,
+    order: &[JsonField],
+    // already quoted if it is a string.
+    timestamp: &str,
+    pid: Option<u32>,
+    tid: bool,
+    max_bytes: Option<usize>,
+    redact_keys: &RedactKeys,
+    flatten_kvs: bool,
+) {
     let mut fields = Map::new();
-    fields.insert("message".to_string(), format!("{}", message).into());
+    fields.insert(
+        "message".to_string(),
+        truncate_log_text(&message.to_string(), max_bytes)
+            .into_owned()
+            .into(),
+    );
+    let mut kvs = Map::new();
     let mut visitor = KvCollector {
+        fields: match flatten_kvs {
+            true => &mut kvs,
+            false => &mut fields,
+        },
+        max_bytes,
+        redact_keys,
+    };
+    record.key_values().visit(&mut visitor).ok();
+
+    let mut line = String::with_capacity(64);
+    line.push('{');
+    for field in order {
+        match field {
+            JsonField::Timestamp => write!(line, r#""timestamp":{},"#, timestamp),
+            JsonField::Level => write!(line, r#""level":"{}","#, record.level()),
+            JsonField::Target => write!(
+                line,
+                r#""target":{},"#,
+                serde_json::Value::from(record.module_path().unwrap_or(record.target()))
+            ),
+            JsonField::File => write!(
+                line,
+                r#""file":{},"#,
+                serde_json::Value::from(record.file().unwrap_or(""))
+            ),
+            JsonField::Line => write!(line, r#""line":{},"#, record.line().unwrap_or(0)),
+        }
+        .ok();
+    }
+    if let Some(pid) = pid {
+        write!(line, r#""pid":{pid},"#).ok();
+    }
+    if tid {
+        write!(line, r#""tid":{},"#, current_thread_log_id()).ok();
+    }
+    if let Some(span_context) = SpanContext::current_local_parent() {
+        let (trace_id, span_id) = trace_ids(&span_context);
+        write!(line, r#""trace_id":"{trace_id}","span_id":"{span_id}","#).ok();
+    }
+
+    let mut output = format!(
+        r#"{}"fields":{}}}"#,
+        line,
+        serde_json::to_string(&fields).unwrap_or_default(),
+    );
+    // the flattened records share the top-level keys of the base record.
+    for (name, value) in kvs {
+        let mut fields = Map::new();
+        fields.insert("metric_name".to_string(), name.into());
+        fields.insert("metric_value".to_string(), value);
+        write!(
+            output,
+            r#"
+{}"fields":{}}}"#,
+            line,
+            serde_json::to_string(&fields).unwrap_or_default(),
+        )
+        .ok();
+    }
+    out.finish(format_args!("{}", output));
+
+    struct KvCollector<'a> {
+        fields: &'a mut Map<String, serde_json::Value>,
+        max_bytes: Option<usize>,
+        redact_keys: &'a RedactKeys,
+    }
+
+    impl<'a, 'kvs> log::kv::Visitor<'kvs> for KvCollector<'a> {
+        fn visit_pair(
+            &mut self,
+            key: log::kv::Key<'kvs>,
+            value: log::kv::Value<'kvs>,
+        ) -> Result<(), log::kv::Error> {
+            let value = value.to_string();
+            let value = self.redact_keys.redact(key.as_str(), &value);
+            let value = truncate_log_text(value, self.max_bytes).into_owned();
+            self.fields.insert(key.as_str().to_string(), value.into());
+            Ok(())
+        }
+    }
+}
+
+/// A small id of the current thread, assigned in order on the first call of the thread.
+///
+/// Unlike the OS thread id it needs no syscall, and unlike `std::thread::ThreadId` it
+/// can be read as a number.
+pub fn current_thread_log_id() -> u64 {
+    static NEXT_THREAD_LOG_ID: AtomicU64 = AtomicU64::new(1);
+    thread_local! {
+        static THREAD_LOG_ID: u64 = NEXT_THREAD_LOG_ID.fetch_add(1, Ordering::Relaxed);
+    }
+    THREAD_LOG_ID.with(|id| *id)
+}
+
+/// Version of the Elastic Common Schema the `ecs_json` logs follow.
+const ECS_VERSION: &str = "1.6.0";
+
+/// ECS fields of the well known log key-values, the other key-values without a namespace
+/// go to `labels`.
+const ECS_KV_FIELDS: &[(&str, &str)] = &[("error", "error.message"), ("user", "user.name")];
+
+/// Formats the record in Elastic Common Schema, see https://www.elastic.co/guide/en/ecs-logging/overview/current/intro.html
+///
+/// `@timestamp`, `log.level` and `message` come first as dotted keys as ecs-logging requires,
+/// the other fields are nested objects.
+fn format_ecs_json_log(
+    out: FormatCallback,
+    message: &fmt::Arguments,
+    record: &log::Record,
+    timestamp: &str,
+    max_bytes: Option<usize>,
+    redact_keys: &RedactKeys,
+) {
+    let mut fields = Map::new();
+    insert_ecs_field(&mut fields, "ecs.version", ECS_VERSION.into());
+    insert_ecs_field(
+        &mut fields,
+        "log.logger",
+        record.module_path().unwrap_or(record.target()).into(),
+    );
+    if let Some(file) = record.file() {
+        insert_ecs_field(&mut fields, "log.origin.file.name", file.into());
+    }
+    if let Some(line) = record.line() {
+        insert_ecs_field(&mut fields, "log.origin.file.line", line.into());
+    }
+    if let Some(span_context) = SpanContext::current_local_parent() {
+        let (trace_id, span_id) = trace_ids(&span_context);
+        insert_ecs_field(&mut fields, "trace.id", trace_id.into());
+        insert_ecs_field(&mut fields, "span.id", span_id.into());
+    }
+    let mut visitor = EcsKvCollector {
         fields: &mut fields,
+        max_bytes,
+        redact_keys,
     };
     record.key_values().visit(&mut visitor).ok();
 
+    let fields = serde_json::to_string(&fields).unwrap_or_default();
     out.finish(format_args!(
-        r#"{{"timestamp":"{}","level":"{}","fields":{}}}"#,
-        humantime::format_rfc3339_micros(SystemTime::now()),
+        r#"{{"@timestamp":"{}","log.level":"{}","message":{},{}"#,
+        timestamp,
         record.level(),
-        serde_json::to_string(&fields).unwrap_or_default(),
+        serde_json::Value::from(truncate_log_text(&message.to_string(), max_bytes)),
+        // the remaining fields without the opening brace.
+        &fields[1..],
     ));
 
-    struct KvCollector<'a> {
+    struct EcsKvCollector<'a> {
         fields: &'a mut Map<String, serde_json::Value>,
+        max_bytes: Option<usize>,
+        redact_keys: &'a RedactKeys,
     }
 
-    impl<'a, 'kvs> log::kv::Visitor<'kvs> for KvCollector<'a> {
+    impl<'a, 'kvs> log::kv::Visitor<'kvs> for EcsKvCollector<'a> {
         fn visit_pair(
             &mut self,
             key: log::kv::Key<'kvs>,
             value: log::kv::Value<'kvs>,
         ) -> Result<(), log::kv::Error> {
-            self.fields
-                .insert(key.as_str().to_string(), value.to_string().into());
+            let key = key.as_str();
+            let path = match ECS_KV_FIELDS.iter().find(|(name, _)| *name == key) {
+                Some((_, path)) => path.to_string(),
+                // a dotted key is taken as an ECS field already.
+                None if key.contains('.') => key.to_string(),
+                None => format!("labels.{key}"),
+            };
+            let value = value.to_string();
+            let value = truncate_log_text(self.redact_keys.redact(key, &value), self.max_bytes)
+                .into_owned();
+            insert_ecs_field(self.fields, &path, value.into());
             Ok(())
         }
     }
 }
 
-fn format_text_log(out: FormatCallback, message: &fmt::Arguments, record: &log::Record) {
+/// The fields of bunyan logs that are the same for all the records, already quoted.
+struct BunyanHeader<'a> {
+    name: &'a str,
+    hostname: &'a str,
+    pid: u32,
+}
+
+/// Fields of the bunyan core schema, a key-value of the same name is written with a `_` prefix.
+const BUNYAN_FIELDS: &[&str] = &["v", "name", "hostname", "pid", "level", "time", "msg"];
+
+/// Formats the record for the node-bunyan tools, see https://github.com/trentm/node-bunyan#core-fields
+///
+/// The key-values are top-level fields, as bunyan expects.
+fn format_bunyan_log(
+    out: FormatCallback,
+    message: &fmt::Arguments,
+    record: &log::Record,
+    header: &BunyanHeader,
+    timestamp: &str,
+    max_bytes: Option<usize>,
+    redact_keys: &RedactKeys,
+) {
+    let mut line = String::with_capacity(128);
+    write!(
+        line,
+        r#"{{"v":0,"name":{},"hostname":{},"pid":{},"level":{},"time":"{}","msg":{}"#,
+        header.name,
+        header.hostname,
+        header.pid,
+        bunyan_level(record.level()),
+        timestamp,
+        serde_json::Value::from(truncate_log_text(&message.to_string(), max_bytes)),
+    )
+    .ok();
+    if let Some(span_context) = SpanContext::current_local_parent() {
+        let (trace_id, span_id) = trace_ids(&span_context);
+        write!(line, r#","trace_id":"{trace_id}","span_id":"{span_id}""#).ok();
+    }
+    let mut visitor = BunyanKvWriter {
+        line: &mut line,
+        max_bytes,
+        redact_keys,
+    };
+    record.key_values().visit(&mut visitor).ok();
+    line.push('}');
+
+    out.finish(format_args!("{}", line));
+
+    struct BunyanKvWriter<'a> {
+        line: &'a mut String,
+        max_bytes: Option<usize>,
+        redact_keys: &'a RedactKeys,
+    }
+
+    impl<'a, 'kvs> log::kv::Visitor<'kvs> for BunyanKvWriter<'a> {
+        fn visit_pair(
+            &mut self,
+            key: log::kv::Key<'kvs>,
+            value: log::kv::Value<'kvs>,
+        ) -> Result<(), log::kv::Error> {
+            let value = value.to_string();
+            let value = self.redact_keys.redact(key.as_str(), &value);
+            let key = match BUNYAN_FIELDS.contains(&key.as_str()) {
+                true => format!("_{}", key.as_str()),
+                false => key.as_str().to_string(),
+            };
+            write!(
+                self.line,
+                ",{}:{}",
+                serde_json::Value::from(key),
+                serde_json::Value::from(truncate_log_text(value, self.max_bytes)),
+            )?;
+            Ok(())
+        }
+    }
+}
+
+/// The numeric levels of bunyan, `fatal` (60) is not used.
+fn bunyan_level(level: log::Level) -> u8 {
+    match level {
+        log::Level::Error => 50,
+        log::Level::Warn => 40,
+        log::Level::Info => 30,
+        log::Level::Debug => 20,
+        log::Level::Trace => 10,
+    }
+}
+
+/// Inserts the value at the dotted `path`, creating the intermediate objects as needed.
+fn insert_ecs_field(
+    fields: &mut Map<String, serde_json::Value>,
+    path: &str,
+    value: serde_json::Value,
+) {
+    match path.split_once('.') {
+        None => {
+            fields.insert(path.to_string(), value);
+        }
+        Some((name, rest)) => {
+            let entry = fields
+                .entry(name)
+                .or_insert_with(|| serde_json::Value::Object(Map::new()));
+            if !entry.is_object() {
+                *entry = serde_json::Value::Object(Map::new());
+            }
+            if let serde_json::Value::Object(object) = entry {
+                insert_ecs_field(object, rest, value);
+            }
+        }
+    }
+}
+
+fn format_text_log(
+    out: FormatCallback,
+    message: &fmt::Arguments,
+    record: &log::Record,
+    timestamp: &str,
+    max_bytes: Option<usize>,
+    redact_keys: &RedactKeys,
+) {
+    let trace = match SpanContext::current_local_parent() {
+        Some(span_context) => {
+            let (trace_id, span_id) = trace_ids(&span_context);
+            format!(" trace_id={trace_id} span_id={span_id}")
+        }
+        None => String::new(),
+    };
+    // the message is rendered into a string only if it may be truncated.
+    let truncated;
+    let message: &dyn fmt::Display = match max_bytes {
+        Some(_) => {
+            truncated = truncate_log_text(&message.to_string(), max_bytes).into_owned();
+            &truncated
+        }
+        None => message,
+    };
     out.finish(format_args!(
-        "{} {:>5} {}: {}:{} {}{}",
-        humantime::format_rfc3339_micros(SystemTime::now()),
+        "{} {:>5} {}: {}:{} {}{}{}",
+        timestamp,
         record.level(),
         record.module_path().unwrap_or(""),
         record.file().unwrap_or(""),
         record.line().unwrap_or(0),
         message,
-        KvDisplay::new(record.key_values()),
+        KvDisplay::new(record.key_values())
+            .with_max_value_bytes(max_bytes)
+            .with_redact_keys(redact_keys),
+        trace,
     ));
 }
 
+/// Writes the message in the first line, then one `metric_name=<key> metric_value=<value>`
+/// line per key-value, all of them with the same prefix.
+fn format_flattened_text_log(
+    out: FormatCallback,
+    message: &fmt::Arguments,
+    record: &log::Record,
+    timestamp: &str,
+    max_bytes: Option<usize>,
+    redact_keys: &RedactKeys,
+) {
+    let trace = match SpanContext::current_local_parent() {
+        Some(span_context) => {
+            let (trace_id, span_id) = trace_ids(&span_context);
+            format!(" trace_id={trace_id} span_id={span_id}")
+        }
+        None => String::new(),
+    };
+    let prefix = format!(
+        "{} {:>5} {}: {}:{}",
+        timestamp,
+        record.level(),
+        record.module_path().unwrap_or(""),
+        record.file().unwrap_or(""),
+        record.line().unwrap_or(0),
+    );
+    let mut output = format!(
+        "{} {}{}",
+        prefix,
+        truncate_log_text(&message.to_string(), max_bytes),
+        trace
+    );
+    let mut visitor = FlattenedKvWriter {
+        output: &mut output,
+        prefix: &prefix,
+        trace: &trace,
+        max_bytes,
+        redact_keys,
+    };
+    record.key_values().visit(&mut visitor).ok();
+    out.finish(format_args!("{}", output));
+
+    struct FlattenedKvWriter<'a> {
+        output: &'a mut String,
+        prefix: &'a str,
+        trace: &'a str,
+        max_bytes: Option<usize>,
+        redact_keys: &'a RedactKeys,
+    }
+
+    impl<'a, 'kvs> log::kv::Visitor<'kvs> for FlattenedKvWriter<'a> {
+        fn visit_pair(
+            &mut self,
+            key: log::kv::Key<'kvs>,
+            value: log::kv::Value<'kvs>,
+        ) -> Result<(), log::kv::Error> {
+            let value = value.to_string();
+            write!(
+                self.output,
+                "\n{} metric_name={} metric_value={}{}",
+                self.prefix,
+                key,
+                truncate_log_text(
+                    self.redact_keys.redact(key.as_str(), &value),
+                    self.max_bytes
+                ),
+                self.trace
+            )?;
+            Ok(())
+        }
+    }
+}
+
+/// Truncates `text` longer than `max_bytes` at a UTF-8 boundary, and appends how many bytes
+/// are cut off.
+fn truncate_log_text(text: &str, max_bytes: Option<usize>) -> Cow<'_, str> {
+    match max_bytes {
+        Some(max_bytes) if text.len() > max_bytes => {
+            let mut end = max_bytes;
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            Cow::Owned(format!(
+                "{}…(truncated {} bytes)",
+                &text[..end],
+                text.len() - end
+            ))
+        }
+        _ => Cow::Borrowed(text),
+    }
+}
+
+/// Hex encoded trace id and span id, the same as in the w3c `traceparent` header.
+fn trace_ids(span_context: &SpanContext) -> (String, String) {
+    (
+        format!("{:032x}", span_context.trace_id.0),
+        format!("{:016x}", span_context.span_id.0),
+    )
+}
+
 pub struct KvDisplay<'kvs> {
     kv: &'kvs dyn log::kv::Source,
+    max_value_bytes: Option<usize>,
+    redact_keys: Option<&'kvs RedactKeys>,
 }
 
 impl<'kvs> KvDisplay<'kvs> {
     pub fn new(kv: &'kvs dyn log::kv::Source) -> Self {
-        Self { kv }
+        Self {
+            kv,
+            max_value_bytes: None,
+            redact_keys: None,
+        }
+    }
+
+    /// Writes the values of the keys in `redact_keys` as `***`.
+    pub fn with_redact_keys(mut self, redact_keys: &'kvs RedactKeys) -> Self {
+        self.redact_keys = Some(redact_keys);
+        self
+    }
+
+    /// Truncates each value longer than `max_value_bytes`, `None` means unlimited.
+    pub fn with_max_value_bytes(mut self, max_value_bytes: Option<usize>) -> Self {
+        self.max_value_bytes = max_value_bytes;
+        self
     }
 }
 
 impl fmt::Display for KvDisplay<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut visitor = KvWriter { writer: f };
+        let mut visitor = KvWriter {
+            writer: f,
+            max_value_bytes: self.max_value_bytes,
+            redact_keys: self.redact_keys,
+        };
         self.kv.visit(&mut visitor).ok();
         Ok(())
     }
@@ -222,6 +2100,8 @@ impl fmt::Display for KvDisplay<'_> {
 
 struct KvWriter<'a, 'kvs> {
     writer: &'kvs mut fmt::Formatter<'a>,
+    max_value_bytes: Option<usize>,
+    redact_keys: Option<&'kvs RedactKeys>,
 }
 
 impl<'a, 'kvs> log::kv::Visitor<'kvs> for KvWriter<'a, 'kvs> {
@@ -230,7 +2110,21 @@ impl<'a, 'kvs> log::kv::Visitor<'kvs> for KvWriter<'a, 'kvs> {
         key: log::kv::Key<'kvs>,
         value: log::kv::Value<'kvs>,
     ) -> Result<(), log::kv::Error> {
-        write!(self.writer, " {key}={value}")?;
+        if self
+            .redact_keys
+            .is_some_and(|keys| keys.contains(key.as_str()))
+        {
+            write!(self.writer, " {key}={REDACTED}")?;
+            return Ok(());
+        }
+        match self.max_value_bytes {
+            Some(_) => {
+                let value = value.to_string();
+                let value = truncate_log_text(&value, self.max_value_bytes);
+                write!(self.writer, " {key}={value}")?;
+            }
+            None => write!(self.writer, " {key}={value}")?,
+        }
         Ok(())
     }
 }
@@ -0,0 +1,356 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tracing_appender::rolling::RollingFileAppender;
+use tracing_appender::rolling::Rotation;
+
+/// Policy to decide when the rolling file logger starts a new file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RotationPolicy {
+    #[default]
+    Hourly,
+    Daily,
+    Minutely,
+    /// Never start a new file, all logs go to the same one.
+    Never,
+    /// Start a new file once the current one would exceed the given number of bytes.
+    BySize(u64),
+}
+
+impl FromStr for RotationPolicy {
+    type Err = String;
+
+    /// Parses the policies as they are displayed, `BySize` is `size:<bytes>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let policy = s.to_lowercase();
+        if let Some(max_size) = policy.strip_prefix("size:") {
+            return match max_size.trim().parse::<u64>() {
+                Ok(max_size) if max_size > 0 => Ok(RotationPolicy::BySize(max_size)),
+                _ => Err(format!(
+                    "invalid log rotation `{s}`, the size must be a positive number of bytes"
+                )),
+            };
+        }
+
+        match policy.as_str() {
+            "hourly" => Ok(RotationPolicy::Hourly),
+            "daily" => Ok(RotationPolicy::Daily),
+            "minutely" => Ok(RotationPolicy::Minutely),
+            "never" => Ok(RotationPolicy::Never),
+            _ => Err(format!(
+                "unknown log rotation `{s}`, expected one of: hourly, daily, minutely, never, size:<bytes>"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for RotationPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RotationPolicy::Hourly => write!(f, "hourly"),
+            RotationPolicy::Daily => write!(f, "daily"),
+            RotationPolicy::Minutely => write!(f, "minutely"),
+            RotationPolicy::Never => write!(f, "never"),
+            RotationPolicy::BySize(max_size) => write!(f, "size:{max_size}"),
+        }
+    }
+}
+
+/// Housekeeping of the files written by a rolling file appender: `{prefix}.{suffix}`,
+/// where the suffix of the older files sorts before that of the newer ones.
+#[derive(Clone)]
+pub(crate) struct LogFiles {
+    pub dir: PathBuf,
+    pub prefix: String,
+    pub max_files: usize,
+    /// Gzip the rotated files with this level, `None` means keep them uncompressed.
+    pub compression_level: Option<u32>,
+}
+
+impl LogFiles {
+    /// Lists the log files and whether they are compressed, oldest first.
+    fn list(&self) -> Vec<(PathBuf, bool)> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return vec![];
+        };
+        let mut files = entries
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let name = entry.file_name().into_string().ok()?;
+                let suffix = name.strip_prefix(&self.prefix)?.strip_prefix('.')?;
+                if !suffix.starts_with(|c: char| c.is_ascii_digit()) {
+                    return None;
+                }
+                let (suffix, compressed) = match suffix.strip_suffix(".gz") {
+                    Some(suffix) => (suffix.to_string(), true),
+                    None => (suffix.to_string(), false),
+                };
+                Some((suffix, entry.path(), compressed))
+            })
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+            .into_iter()
+            .map(|(_, path, compressed)| (path, compressed))
+            .collect()
+    }
+
+    /// Compresses the rotated files if enabled, then removes the oldest files
+    /// so that at most `max_files` files are kept.
+    fn clean_up(&self) {
+        let mut files = self.list();
+
+        if let Some(level) = self.compression_level {
+            // the newest file is the active one, leave it as is.
+            let num_rotated = files.len().saturating_sub(1);
+            for (path, compressed) in &mut files[..num_rotated] {
+                if *compressed {
+                    continue;
+                }
+                match compress_file(path, level) {
+                    Ok(compressed_path) => {
+                        *path = compressed_path;
+                        *compressed = true;
+                    }
+                    Err(err) => {
+                        eprintln!("Failed to compress log file {}: {}", path.display(), err)
+                    }
+                }
+            }
+        }
+
+        if self.max_files == 0 || files.len() <= self.max_files {
+            return;
+        }
+        for (path, _) in &files[..files.len() - self.max_files] {
+            if let Err(err) = fs::remove_file(path) {
+                eprintln!("Failed to remove old log file {}: {}", path.display(), err);
+            }
+        }
+    }
+}
+
+/// Cleans up the rotated log files each time a new file is opened.
+///
+/// Compressing a rotated file may take a while, so when compression is enabled the clean up
+/// runs in a dedicated thread instead of the thread writing the logs.
+pub(crate) struct LogFilesCleaner {
+    files: LogFiles,
+    sender: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl LogFilesCleaner {
+    pub fn new(files: LogFiles) -> io::Result<Self> {
+        let mut cleaner = LogFilesCleaner {
+            files,
+            sender: None,
+            handle: None,
+        };
+
+        if cleaner.files.compression_level.is_some() {
+            let (sender, receiver) = mpsc::channel::<()>();
+            let files = cleaner.files.clone();
+            let handle = thread::Builder::new()
+                .name(format!("log-files-cleaner-{}", files.prefix))
+                .spawn(move || {
+                    while receiver.recv().is_ok() {
+                        // one pass takes care of all the files rotated so far.
+                        while receiver.try_recv().is_ok() {}
+                        files.clean_up();
+                    }
+                })?;
+            cleaner.sender = Some(sender);
+            cleaner.handle = Some(handle);
+        }
+        Ok(cleaner)
+    }
+
+    pub fn files(&self) -> &LogFiles {
+        &self.files
+    }
+
+    /// Called once a new file is opened.
+    pub fn on_rotated(&self) {
+        match &self.sender {
+            Some(sender) => {
+                let _ = sender.send(());
+            }
+            None => self.files.clean_up(),
+        }
+    }
+}
+
+impl Drop for LogFilesCleaner {
+    fn drop(&mut self) {
+        // closing the channel stops the thread once the pending clean up is done.
+        self.sender.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Gzips `path` to `{path}.gz` and removes the original file.
+fn compress_file(path: &Path, level: u32) -> io::Result<PathBuf> {
+    let mut compressed_path = path.as_os_str().to_owned();
+    compressed_path.push(".gz");
+    let compressed_path = PathBuf::from(compressed_path);
+
+    let mut input = File::open(path)?;
+    let output = File::create(&compressed_path)?;
+    let mut encoder = GzEncoder::new(output, Compression::new(level));
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+
+    fs::remove_file(path)?;
+    Ok(compressed_path)
+}
+
+/// A `RollingFileAppender` rotated by wall clock, which takes care of the rotated files itself,
+/// since `RollingFileAppender` does not tell when it opens a new file.
+pub(crate) struct TimeRollingFileAppender {
+    period_secs: u64,
+    current_period: u64,
+    appender: RollingFileAppender,
+    cleaner: LogFilesCleaner,
+}
+
+impl TimeRollingFileAppender {
+    pub fn new(rotation: Rotation, period_secs: u64, files: LogFiles) -> io::Result<Self> {
+        let appender = RollingFileAppender::builder()
+            .rotation(rotation)
+            .filename_prefix(&files.prefix)
+            .build(&files.dir)
+            .map_err(io::Error::other)?;
+        let cleaner = LogFilesCleaner::new(files)?;
+        cleaner.on_rotated();
+
+        Ok(TimeRollingFileAppender {
+            period_secs,
+            current_period: unix_secs() / period_secs,
+            appender,
+            cleaner,
+        })
+    }
+}
+
+impl Write for TimeRollingFileAppender {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // `RollingFileAppender` opens a new file on the first write of a new period.
+        let written = self.appender.write(buf)?;
+        let period = unix_secs() / self.period_secs;
+        if period != self.current_period {
+            self.current_period = period;
+            self.cleaner.on_rotated();
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.appender.flush()
+    }
+}
+
+fn unix_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// A `RollingFileAppender` that rolls over by the number of bytes written instead of wall clock.
+///
+/// Files are named `{prefix}.{unix_millis}`.
+pub(crate) struct SizeRollingFileAppender {
+    max_size: u64,
+    written: u64,
+    last_suffix: u128,
+    appender: RollingFileAppender,
+    cleaner: LogFilesCleaner,
+}
+
+impl SizeRollingFileAppender {
+    pub fn new(max_size: u64, files: LogFiles) -> io::Result<Self> {
+        let (appender, last_suffix) = Self::open(&files, 0)?;
+        let cleaner = LogFilesCleaner::new(files)?;
+        cleaner.on_rotated();
+
+        Ok(SizeRollingFileAppender {
+            max_size,
+            written: 0,
+            last_suffix,
+            appender,
+            cleaner,
+        })
+    }
+
+    fn open(files: &LogFiles, last_suffix: u128) -> io::Result<(RollingFileAppender, u128)> {
+        // the suffix must increase, in case of rolling over more than once in a millisecond.
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let suffix = now.max(last_suffix + 1);
+
+        let appender = RollingFileAppender::builder()
+            .rotation(Rotation::NEVER)
+            .filename_prefix(&files.prefix)
+            .filename_suffix(suffix.to_string())
+            .build(&files.dir)
+            .map_err(io::Error::other)?;
+        Ok((appender, suffix))
+    }
+
+    fn roll_over(&mut self) -> io::Result<()> {
+        self.appender.flush()?;
+        let (appender, last_suffix) = Self::open(self.cleaner.files(), self.last_suffix)?;
+        self.appender = appender;
+        self.last_suffix = last_suffix;
+        self.written = 0;
+        self.cleaner.on_rotated();
+        Ok(())
+    }
+}
+
+impl Write for SizeRollingFileAppender {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_size {
+            self.roll_over()?;
+        }
+        let written = self.appender.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.appender.flush()
+    }
+}
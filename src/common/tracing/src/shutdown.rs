@@ -0,0 +1,103 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+type FlushFn = Box<dyn FnOnce() -> Result<(), String> + Send + Sync + 'static>;
+
+/// Flushes the log sinks on shutdown, one by one in the order they are registered.
+///
+/// A sink registers either a flush function, or a guard that flushes the sink once dropped,
+/// such as the `WorkerGuard` of a file writer. If it is dropped without calling
+/// [`LogShutdown::shutdown`], the sinks are flushed all the same and the failures are
+/// printed to stderr.
+#[derive(Default)]
+pub struct LogShutdown {
+    sinks: Vec<(String, FlushFn)>,
+}
+
+impl LogShutdown {
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        flush: impl FnOnce() -> Result<(), String> + Send + Sync + 'static,
+    ) {
+        self.sinks.push((name.into(), Box::new(flush)));
+    }
+
+    pub fn register_guard(&mut self, name: impl Into<String>, guard: impl Send + Sync + 'static) {
+        self.register(name, move || {
+            drop(guard);
+            Ok(())
+        });
+    }
+
+    /// Moves the sinks of `other` to the end of this one.
+    pub fn append(&mut self, mut other: LogShutdown) {
+        self.sinks.append(&mut other.sinks);
+    }
+
+    /// Names of the registered sinks, in the order they are flushed.
+    pub fn sink_names(&self) -> Vec<&str> {
+        self.sinks.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    /// Flushes all the sinks, a failed sink does not stop the following ones from being flushed.
+    pub fn shutdown(mut self) -> Result<(), LogShutdownError> {
+        let mut failures = vec![];
+        for (name, flush) in std::mem::take(&mut self.sinks) {
+            if let Err(e) = flush() {
+                failures.push((name, e));
+            }
+        }
+        match failures.is_empty() {
+            true => Ok(()),
+            false => Err(LogShutdownError { failures }),
+        }
+    }
+}
+
+impl Drop for LogShutdown {
+    fn drop(&mut self) {
+        if self.sinks.is_empty() {
+            return;
+        }
+        let sinks = std::mem::take(&mut self.sinks);
+        if let Err(e) = (LogShutdown { sinks }).shutdown() {
+            eprintln!("{}", e);
+        }
+    }
+}
+
+/// The sinks failed to flush on shutdown, with the error of each one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogShutdownError {
+    pub failures: Vec<(String, String)>,
+}
+
+impl Display for LogShutdownError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "failed to flush log sinks: ")?;
+        for (i, (name, e)) in self.failures.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}: {}", name, e)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for LogShutdownError {}
@@ -0,0 +1,193 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use databend_common_tracing::DedupLogger;
+use log::kv::Key;
+use log::Level;
+use log::Log;
+use log::Record;
+
+/// Message and `repeated` key-value of a logged record.
+type Line = (String, Option<u64>);
+
+#[derive(Clone, Default)]
+struct Collector(Arc<Mutex<Vec<Line>>>);
+
+impl Collector {
+    fn take(&self) -> Vec<Line> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+}
+
+impl Log for Collector {
+    fn enabled(&self, _metadata: &log::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        let repeated = record
+            .key_values()
+            .get(Key::from("repeated"))
+            .and_then(|value| value.to_u64());
+        self.0
+            .lock()
+            .unwrap()
+            .push((record.args().to_string(), repeated));
+    }
+
+    fn flush(&self) {}
+}
+
+fn log_message(logger: &dyn Log, level: Level, message: &str) {
+    logger.log(
+        &Record::builder()
+            .level(level)
+            .target("databend_query::servers")
+            .args(format_args!("{message}"))
+            .build(),
+    );
+}
+
+#[test]
+fn test_dedup_logger_flush() {
+    let collector = Collector::default();
+    let logger = DedupLogger::new(collector.clone(), 2, Duration::from_secs(3600));
+
+    for _ in 0..5 {
+        log_message(&logger, Level::Error, "retry");
+    }
+    // the same message of another level is another line.
+    log_message(&logger, Level::Warn, "retry");
+    assert_eq!(collector.take(), vec![
+        ("retry".to_string(), None),
+        ("retry".to_string(), None),
+        ("retry".to_string(), None),
+    ]);
+
+    logger.flush();
+    assert_eq!(collector.take(), vec![("retry".to_string(), Some(3))]);
+    // the dropped lines are summarized only once.
+    logger.flush();
+    assert!(collector.take().is_empty());
+
+    // still in the same window.
+    log_message(&logger, Level::Error, "retry");
+    logger.flush();
+    assert_eq!(collector.take(), vec![("retry".to_string(), Some(1))]);
+}
+
+#[test]
+fn test_dedup_logger_window_rollover() {
+    let collector = Collector::default();
+    let logger = DedupLogger::new(collector.clone(), 1, Duration::from_millis(50));
+
+    for _ in 0..4 {
+        log_message(&logger, Level::Error, "retry");
+    }
+    log_message(&logger, Level::Error, "other");
+    thread::sleep(Duration::from_millis(100));
+    log_message(&logger, Level::Error, "retry");
+
+    assert_eq!(collector.take(), vec![
+        ("retry".to_string(), None),
+        ("other".to_string(), None),
+        // summary of the previous window, before the first line of the new window.
+        ("retry".to_string(), Some(3)),
+        ("retry".to_string(), None),
+    ]);
+}
+
+#[test]
+fn test_dedup_logger_sliding_window() {
+    let collector = Collector::default();
+    let logger = DedupLogger::new(collector.clone(), 1, Duration::from_millis(400));
+
+    log_message(&logger, Level::Error, "other");
+    thread::sleep(Duration::from_millis(300));
+    log_message(&logger, Level::Error, "retry");
+    // 500ms after the first line, but only 200ms after the last "retry" passed.
+    thread::sleep(Duration::from_millis(200));
+    log_message(&logger, Level::Error, "retry");
+    // the passed "retry" is out of the window now.
+    thread::sleep(Duration::from_millis(300));
+    log_message(&logger, Level::Error, "retry");
+
+    assert_eq!(collector.take(), vec![
+        ("other".to_string(), None),
+        ("retry".to_string(), None),
+        // summary of the dropped occurrences, before the line is passed again.
+        ("retry".to_string(), Some(1)),
+        ("retry".to_string(), None),
+    ]);
+}
+
+/// Location and key-values of a logged record.
+type Location = (Option<String>, Option<u32>, Option<String>, Option<u64>);
+
+#[derive(Clone, Default)]
+struct LocationCollector(Arc<Mutex<Vec<Location>>>);
+
+impl Log for LocationCollector {
+    fn enabled(&self, _metadata: &log::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        let kvs = record.key_values();
+        self.0.lock().unwrap().push((
+            record.file().map(|file| file.to_string()),
+            record.line(),
+            kvs.get(Key::from("query_id")).map(|v| v.to_string()),
+            kvs.get(Key::from("repeated")).and_then(|v| v.to_u64()),
+        ));
+    }
+
+    fn flush(&self) {}
+}
+
+#[test]
+fn test_dedup_logger_summary_location() {
+    let collector = LocationCollector::default();
+    let logger = DedupLogger::new(collector.clone(), 1, Duration::from_secs(3600));
+
+    let kvs: &[(&str, &str)] = &[("query_id", "q1")];
+    // a formatted message, rather than a static one.
+    let attempt = 1;
+    for _ in 0..3 {
+        logger.log(
+            &Record::builder()
+                .level(Level::Error)
+                .target("databend_query::servers")
+                .file(Some("servers.rs"))
+                .line(Some(42))
+                .key_values(&kvs)
+                .args(format_args!("retry {attempt}"))
+                .build(),
+        );
+    }
+    logger.flush();
+
+    // the summary keeps the location and the key-values of the line.
+    let line = |repeated| {
+        let file = Some("servers.rs".to_string());
+        (file, Some(42), Some("q1".to_string()), repeated)
+    };
+    let lines = std::mem::take(&mut *collector.0.lock().unwrap());
+    assert_eq!(lines, vec![line(None), line(Some(2))]);
+}
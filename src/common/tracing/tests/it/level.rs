@@ -0,0 +1,207 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use databend_common_tracing::DynamicLevelLogger;
+use databend_common_tracing::LogLevelHandle;
+use log::Level;
+use log::LevelFilter;
+use log::Log;
+use log::Record;
+
+#[derive(Clone, Default)]
+struct Collector(Arc<Mutex<Vec<String>>>);
+
+impl Collector {
+    fn take(&self) -> Vec<String> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+}
+
+impl Log for Collector {
+    fn enabled(&self, _metadata: &log::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        self.0.lock().unwrap().push(record.args().to_string());
+    }
+
+    fn flush(&self) {}
+}
+
+fn log_message(logger: &dyn Log, target: &str, level: Level, message: &str) {
+    logger.log(
+        &Record::builder()
+            .level(level)
+            .target(target)
+            .args(format_args!("{message}"))
+            .build(),
+    );
+}
+
+fn log_all(logger: &dyn Log) {
+    log_message(
+        logger,
+        "databend_query::servers",
+        Level::Info,
+        "servers info",
+    );
+    log_message(
+        logger,
+        "databend_query::servers",
+        Level::Debug,
+        "servers debug",
+    );
+    log_message(
+        logger,
+        "databend_query::sessions",
+        Level::Info,
+        "sessions info",
+    );
+    log_message(
+        logger,
+        "databend_query::sessions",
+        Level::Debug,
+        "sessions debug",
+    );
+}
+
+#[test]
+fn test_dynamic_level_logger() {
+    let collector = Collector::default();
+    let handle = LogLevelHandle::default();
+    let logger = DynamicLevelLogger::new(collector.clone(), &handle, LevelFilter::Info);
+
+    log_all(&logger);
+    assert_eq!(collector.take(), vec!["servers info", "sessions info"]);
+
+    // flip a module to debug, the records suppressed before pass now.
+    handle.set_level(Some("databend_query::servers"), LevelFilter::Debug);
+    assert!(
+        logger.enabled(
+            &log::Metadata::builder()
+                .level(Level::Debug)
+                .target("databend_query::servers::http")
+                .build()
+        )
+    );
+    log_all(&logger);
+    assert_eq!(collector.take(), vec![
+        "servers info",
+        "servers debug",
+        "sessions info"
+    ]);
+    assert_eq!(handle.max_level(), LevelFilter::Debug);
+
+    // the longest prefix wins.
+    handle.set_level(Some("databend_query"), LevelFilter::Error);
+    log_all(&logger);
+    assert_eq!(collector.take(), vec!["servers info", "servers debug"]);
+
+    // the default level applies to the targets without override.
+    handle.set_level(Some("databend_query"), LevelFilter::Warn);
+    handle.set_level(None, LevelFilter::Off);
+    log_message(&logger, "opendal::services", Level::Error, "opendal error");
+    log_all(&logger);
+    assert_eq!(collector.take(), vec!["servers info", "servers debug"]);
+
+    let levels = handle.levels();
+    assert_eq!(levels.len(), 1);
+    assert_eq!(levels[0].level("opendal"), LevelFilter::Off);
+    assert_eq!(
+        levels[0].level("databend_query::sessions"),
+        LevelFilter::Warn
+    );
+    assert_eq!(levels[0].max_level(), LevelFilter::Debug);
+
+    // the max level is lowered again once no target is more verbose.
+    handle.set_level(Some("databend_query::servers"), LevelFilter::Info);
+    assert_eq!(handle.max_level(), LevelFilter::Info);
+}
+
+#[test]
+fn test_level_map_matches_modules() {
+    let handle = LogLevelHandle::default();
+    let collector = Collector::default();
+    let logger = DynamicLevelLogger::new(collector.clone(), &handle, LevelFilter::Info);
+    handle.set_level(Some("databend_query"), LevelFilter::Error);
+
+    let levels = handle.levels();
+    assert_eq!(levels[0].level("databend_query"), LevelFilter::Error);
+    assert_eq!(
+        levels[0].level("databend_query::servers"),
+        LevelFilter::Error
+    );
+    // a prefix of the name of another module doesn't match it.
+    assert_eq!(levels[0].level("databend_query_x"), LevelFilter::Info);
+    assert_eq!(
+        levels[0].level("databend_query_x::servers"),
+        LevelFilter::Info
+    );
+
+    log_message(&logger, "databend_query_x", Level::Info, "other info");
+    log_message(&logger, "databend_query", Level::Info, "query info");
+    assert_eq!(collector.take(), vec!["other info"]);
+}
+
+#[test]
+fn test_max_level_of_handle() {
+    let handle = LogLevelHandle::default();
+    let _file = DynamicLevelLogger::new(Collector::default(), &handle, LevelFilter::Warn);
+    assert_eq!(handle.max_level(), LevelFilter::Warn);
+
+    // the fixed level of the loggers outside of the handle is a floor.
+    handle.set_fixed_max_level(LevelFilter::Info);
+    assert_eq!(handle.max_level(), LevelFilter::Info);
+
+    handle.set_level(Some("databend_query"), LevelFilter::Trace);
+    assert_eq!(handle.max_level(), LevelFilter::Trace);
+    handle.set_level(Some("databend_query"), LevelFilter::Error);
+    assert_eq!(handle.max_level(), LevelFilter::Info);
+}
+
+#[test]
+fn test_dynamic_level_loggers_of_handle() {
+    let handle = LogLevelHandle::default();
+    let file = Collector::default();
+    let otlp = Collector::default();
+    let file_logger = DynamicLevelLogger::new(file.clone(), &handle, LevelFilter::Info);
+    let otlp_logger = DynamicLevelLogger::new(otlp.clone(), &handle, LevelFilter::Warn);
+
+    log_all(&file_logger);
+    log_all(&otlp_logger);
+    assert_eq!(file.take(), vec!["servers info", "sessions info"]);
+    assert!(otlp.take().is_empty());
+
+    // an override applies to all the loggers, the other targets keep their own default.
+    handle.set_level(Some("databend_query::servers"), LevelFilter::Debug);
+    log_all(&file_logger);
+    log_all(&otlp_logger);
+    assert_eq!(file.take(), vec![
+        "servers info",
+        "servers debug",
+        "sessions info"
+    ]);
+    assert_eq!(otlp.take(), vec!["servers info", "servers debug"]);
+
+    handle.set_level(None, LevelFilter::Info);
+    log_all(&otlp_logger);
+    assert_eq!(otlp.take(), vec![
+        "servers info",
+        "servers debug",
+        "sessions info"
+    ]);
+}
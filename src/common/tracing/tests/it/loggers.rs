@@ -0,0 +1,1207 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::borrow::Cow;
+use std::fs;
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::UdpSocket;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::UNIX_EPOCH;
+
+use databend_common_base::base::tokio;
+use databend_common_tracing::formatter;
+use databend_common_tracing::new_level_routed_file_logger;
+use databend_common_tracing::new_target_routed_file_logger;
+use databend_common_tracing::otlp_dropped_log_records;
+use databend_common_tracing::otlp_log_attributes;
+use databend_common_tracing::parse_json_field_order;
+use databend_common_tracing::parse_level_routes;
+use databend_common_tracing::syslog_dropped_log_records;
+use databend_common_tracing::CallbackLogger;
+use databend_common_tracing::FormatOptions;
+use databend_common_tracing::JsonField;
+use databend_common_tracing::LogRecordView;
+use databend_common_tracing::LogTimezone;
+use databend_common_tracing::OtlpExporterOptions;
+use databend_common_tracing::OtlpProtocol;
+use databend_common_tracing::RedactKeys;
+use databend_common_tracing::RetryLogExporter;
+use databend_common_tracing::RotationPolicy;
+use databend_common_tracing::SyslogFacility;
+use databend_common_tracing::SyslogLogger;
+use databend_common_tracing::SyslogTransport;
+use databend_common_tracing::TargetRoutedLogger;
+use databend_common_tracing::TimestampFormat;
+use log::Level;
+use log::LevelFilter;
+use log::Log;
+use log::Record;
+use minitrace::prelude::*;
+use opentelemetry::logs::AnyValue;
+use opentelemetry::logs::LogError;
+use opentelemetry::logs::LogResult;
+use opentelemetry_sdk::export::logs::LogData;
+use opentelemetry_sdk::export::logs::LogExporter;
+use opentelemetry_sdk::Resource;
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn lines(&self) -> Vec<String> {
+        let buf = self.0.lock().unwrap();
+        String::from_utf8_lossy(&buf)
+            .lines()
+            .map(|line| line.to_string())
+            .collect()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn build_logger(format: &str, options: &FormatOptions) -> (Box<dyn Log>, SharedBuffer) {
+    let buffer = SharedBuffer::default();
+    let (_, logger) = fern::Dispatch::new()
+        .format(formatter(format, options).unwrap())
+        .chain(Box::new(buffer.clone()) as Box<dyn Write + Send>)
+        .into_log();
+    (logger, buffer)
+}
+
+#[test]
+fn test_formatter_unsupported_format() {
+    for format in ["text", "json", "ecs_json", "bunyan"] {
+        assert!(formatter(format, &FormatOptions::default()).is_ok());
+    }
+    let err = formatter("jsonn", &FormatOptions::default()).err().unwrap();
+    assert!(err.contains("`jsonn` is not supported"), "{err}");
+}
+
+fn json_keys(line: &str) -> Vec<String> {
+    let value: serde_json::Value = serde_json::from_str(line).unwrap();
+    value.as_object().unwrap().keys().cloned().collect()
+}
+
+#[test]
+fn test_json_log_default_field_order() {
+    let (logger, buffer) = build_logger("json", &FormatOptions::default());
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .args(format_args!("hello"))
+            .build(),
+    );
+
+    let lines = buffer.lines();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(json_keys(&lines[0]), vec![
+        "timestamp",
+        "level",
+        "target",
+        "file",
+        "line",
+        "fields"
+    ]);
+}
+
+#[test]
+fn test_log_with_trace_ids() {
+    let log_hello = |logger: &dyn Log| {
+        logger.log(
+            &Record::builder()
+                .level(Level::Info)
+                .args(format_args!("hello"))
+                .build(),
+        )
+    };
+    let (json_logger, json_buffer) = build_logger("json", &FormatOptions::default());
+    let (text_logger, text_buffer) = build_logger("text", &FormatOptions::default());
+
+    // no active span, no trace ids.
+    log_hello(json_logger.as_ref());
+    log_hello(text_logger.as_ref());
+
+    let parent = SpanContext::random();
+    let root = Span::root("root", parent);
+    {
+        let _guard = root.set_local_parent();
+        log_hello(json_logger.as_ref());
+        log_hello(text_logger.as_ref());
+    }
+
+    let json_lines = json_buffer.lines();
+    assert_eq!(json_keys(&json_lines[0]), vec![
+        "timestamp",
+        "level",
+        "target",
+        "file",
+        "line",
+        "fields"
+    ]);
+    assert_eq!(json_keys(&json_lines[1]), vec![
+        "timestamp",
+        "level",
+        "target",
+        "file",
+        "line",
+        "trace_id",
+        "span_id",
+        "fields"
+    ]);
+    let value: serde_json::Value = serde_json::from_str(&json_lines[1]).unwrap();
+    let trace_id = format!("{:032x}", parent.trace_id.0);
+    assert_eq!(value["trace_id"], trace_id.as_str());
+    assert_eq!(value["span_id"].as_str().unwrap().len(), 16);
+
+    let text_lines = text_buffer.lines();
+    assert!(!text_lines[0].contains("trace_id="));
+    assert!(
+        text_lines[1].contains(&format!(" trace_id={trace_id} span_id=")),
+        "{}",
+        text_lines[1]
+    );
+}
+
+#[test]
+fn test_json_log_custom_field_order() {
+    let order = vec!["level".to_string(), "timestamp".to_string()];
+    let options = FormatOptions {
+        json_field_order: parse_json_field_order(&order).unwrap(),
+        ..Default::default()
+    };
+    let (logger, buffer) = build_logger("json", &options);
+    logger.log(
+        &Record::builder()
+            .level(Level::Warn)
+            .args(format_args!("hello"))
+            .build(),
+    );
+
+    let lines = buffer.lines();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(json_keys(&lines[0]), vec![
+        "level",
+        "timestamp",
+        "target",
+        "file",
+        "line",
+        "fields"
+    ]);
+}
+
+#[test]
+fn test_json_log_source_location() {
+    let (logger, buffer) = build_logger("json", &FormatOptions::default());
+    let kvs: &[(&str, &str)] = &[("user", "root")];
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .module_path(Some("databend_query::servers"))
+            .file(Some("src/servers/server.rs"))
+            .line(Some(42))
+            .key_values(&kvs)
+            .args(format_args!("hello"))
+            .build(),
+    );
+
+    let lines = buffer.lines();
+    let value: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+    assert_eq!(value["target"], "databend_query::servers");
+    assert_eq!(value["file"], "src/servers/server.rs");
+    assert_eq!(value["line"], 42);
+    // user key-values stay nested in `fields`.
+    assert_eq!(value["fields"]["message"], "hello");
+    assert_eq!(value["fields"]["user"], "root");
+}
+
+#[test]
+fn test_json_log_with_process_ids() {
+    let log_hello = |logger: &dyn Log| {
+        logger.log(
+            &Record::builder()
+                .level(Level::Info)
+                .args(format_args!("hello"))
+                .build(),
+        )
+    };
+
+    // off by default.
+    let (logger, buffer) = build_logger("json", &FormatOptions::default());
+    log_hello(logger.as_ref());
+    let keys = json_keys(&buffer.lines()[0]);
+    assert!(!keys.contains(&"pid".to_string()));
+    assert!(!keys.contains(&"tid".to_string()));
+
+    let options = FormatOptions {
+        json_pid: true,
+        json_tid: true,
+        ..Default::default()
+    };
+    let (logger, buffer) = build_logger("json", &options);
+    let logger: Arc<dyn Log> = Arc::from(logger);
+    log_hello(logger.as_ref());
+    std::thread::spawn({
+        let logger = logger.clone();
+        move || log_hello(logger.as_ref())
+    })
+    .join()
+    .unwrap();
+
+    let lines = buffer
+        .lines()
+        .iter()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    for line in &lines {
+        assert_eq!(line["pid"], std::process::id());
+    }
+    assert!(lines[0]["tid"].is_u64());
+    assert!(lines[1]["tid"].is_u64());
+    assert_ne!(lines[0]["tid"], lines[1]["tid"]);
+}
+
+#[test]
+fn test_log_max_message_bytes() {
+    let options = FormatOptions {
+        max_message_bytes: Some(4),
+        ..Default::default()
+    };
+    // `€` takes 3 bytes, the message is cut before it.
+    let kvs: &[(&str, &str)] = &[("plan", "abcdef"), ("query_id", "q1")];
+    let log_record = |logger: &dyn Log| {
+        logger.log(
+            &Record::builder()
+                .level(Level::Info)
+                .key_values(&kvs)
+                .args(format_args!("ab€cd"))
+                .build(),
+        )
+    };
+
+    let (logger, buffer) = build_logger("text", &options);
+    log_record(logger.as_ref());
+    let line = &buffer.lines()[0];
+    assert!(
+        line.ends_with(" ab…(truncated 5 bytes) plan=abcd…(truncated 2 bytes) query_id=q1"),
+        "{line}"
+    );
+
+    let (logger, buffer) = build_logger("json", &options);
+    log_record(logger.as_ref());
+    let value: serde_json::Value = serde_json::from_str(&buffer.lines()[0]).unwrap();
+    assert_eq!(value["fields"]["message"], "ab…(truncated 5 bytes)");
+    assert_eq!(value["fields"]["plan"], "abcd…(truncated 2 bytes)");
+    assert_eq!(value["fields"]["query_id"], "q1");
+
+    // unlimited by default.
+    let (logger, buffer) = build_logger("json", &FormatOptions::default());
+    log_record(logger.as_ref());
+    let value: serde_json::Value = serde_json::from_str(&buffer.lines()[0]).unwrap();
+    assert_eq!(value["fields"]["message"], "ab€cd");
+    assert_eq!(value["fields"]["plan"], "abcdef");
+}
+
+#[test]
+fn test_flatten_kvs_log() {
+    let options = FormatOptions {
+        flatten_kvs: true,
+        ..Default::default()
+    };
+    let kvs: &[(&str, &str)] = &[("rows", "10"), ("bytes", "1024"), ("files", "2")];
+    let log_record = |logger: &dyn Log| {
+        logger.log(
+            &Record::builder()
+                .level(Level::Info)
+                .key_values(&kvs)
+                .args(format_args!("copy finished"))
+                .build(),
+        )
+    };
+
+    let (logger, buffer) = build_logger("text", &options);
+    log_record(logger.as_ref());
+    let lines = buffer.lines();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].ends_with(" copy finished"), "{}", lines[0]);
+    assert!(lines[1].ends_with(" metric_name=rows metric_value=10"));
+    assert!(lines[2].ends_with(" metric_name=bytes metric_value=1024"));
+    assert!(lines[3].ends_with(" metric_name=files metric_value=2"));
+
+    let (logger, buffer) = build_logger("json", &options);
+    log_record(logger.as_ref());
+    let lines = buffer.lines();
+    assert_eq!(lines.len(), 4);
+    let values: Vec<serde_json::Value> = lines
+        .iter()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(
+        values[0]["fields"],
+        serde_json::json!({"message": "copy finished"})
+    );
+    let mut metrics: Vec<_> = values[1..]
+        .iter()
+        .map(|value| {
+            assert_eq!(value["level"], "INFO");
+            (
+                value["fields"]["metric_name"].as_str().unwrap().to_string(),
+                value["fields"]["metric_value"]
+                    .as_str()
+                    .unwrap()
+                    .to_string(),
+            )
+        })
+        .collect();
+    metrics.sort();
+    assert_eq!(metrics, vec![
+        ("bytes".to_string(), "1024".to_string()),
+        ("files".to_string(), "2".to_string()),
+        ("rows".to_string(), "10".to_string()),
+    ]);
+
+    // a record with a single key-value is not flattened.
+    let (logger, buffer) = build_logger("text", &options);
+    let kvs: &[(&str, &str)] = &[("rows", "10")];
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .key_values(&kvs)
+            .args(format_args!("copy finished"))
+            .build(),
+    );
+    let lines = buffer.lines();
+    assert_eq!(lines.len(), 1);
+    assert!(lines[0].ends_with(" copy finished rows=10"), "{}", lines[0]);
+}
+
+#[test]
+fn test_redact_keys() {
+    let options = FormatOptions {
+        redact_keys: RedactKeys::new(&["Password".to_string()]),
+        ..Default::default()
+    };
+    let kvs: &[(&str, &str)] = &[("user", "root"), ("PASSWORD", "abc123")];
+    let record = Record::builder()
+        .level(Level::Info)
+        .key_values(&kvs)
+        .args(format_args!("create connection"))
+        .build();
+
+    let (logger, buffer) = build_logger("text", &options);
+    logger.log(&record);
+    let line = &buffer.lines()[0];
+    assert!(line.ends_with(" user=root PASSWORD=***"), "{line}");
+    assert!(!line.contains("abc123"), "{line}");
+
+    let (logger, buffer) = build_logger("json", &options);
+    logger.log(&record);
+    let value: serde_json::Value = serde_json::from_str(&buffer.lines()[0]).unwrap();
+    assert_eq!(value["fields"]["user"], "root");
+    assert_eq!(value["fields"]["PASSWORD"], "***");
+
+    let attributes = otlp_log_attributes(&record, &options.redact_keys);
+    let attributes = attributes
+        .iter()
+        .map(|(k, v)| (k.as_str(), format!("{:?}", v)))
+        .collect::<Vec<_>>();
+    assert_eq!(attributes, vec![
+        ("user", format!("{:?}", AnyValue::from("root".to_string()))),
+        ("PASSWORD", format!("{:?}", AnyValue::from("***"))),
+    ]);
+}
+
+#[test]
+fn test_bunyan_log() {
+    let options = FormatOptions {
+        app_name: "databend-query".to_string(),
+        ..Default::default()
+    };
+    let (logger, buffer) = build_logger("bunyan", &options);
+    let kvs: &[(&str, &str)] = &[("query_id", "q1"), ("msg", "kv")];
+    logger.log(
+        &Record::builder()
+            .level(Level::Warn)
+            .key_values(&kvs)
+            .args(format_args!("hello \"bunyan\""))
+            .build(),
+    );
+
+    let lines = buffer.lines();
+    assert_eq!(lines.len(), 1);
+    let value: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+    assert_eq!(value["v"], 0);
+    assert_eq!(value["name"], "databend-query");
+    assert!(value["hostname"].is_string());
+    assert_eq!(value["pid"], std::process::id());
+    assert_eq!(value["level"], 40);
+    assert!(value["time"].is_string());
+    assert_eq!(value["msg"], "hello \"bunyan\"");
+    assert_eq!(value["query_id"], "q1");
+    // a key-value does not replace a core field.
+    assert_eq!(value["_msg"], "kv");
+}
+
+#[test]
+fn test_ecs_json_log() {
+    let (logger, buffer) = build_logger("ecs_json", &FormatOptions::default());
+    let kvs: &[(&str, &str)] = &[
+        ("user", "root"),
+        ("query_id", "q1"),
+        ("http.request.method", "POST"),
+    ];
+    logger.log(
+        &Record::builder()
+            .level(Level::Warn)
+            .module_path(Some("databend_query::servers"))
+            .line(Some(42))
+            .key_values(&kvs)
+            .args(format_args!("hello \"ecs\""))
+            .build(),
+    );
+
+    let lines = buffer.lines();
+    assert_eq!(lines.len(), 1);
+    // ecs-logging requires these keys to come first.
+    assert_eq!(json_keys(&lines[0])[..3], [
+        "@timestamp",
+        "log.level",
+        "message"
+    ]);
+    let value: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+    assert_eq!(value["log.level"], "WARN");
+    assert_eq!(value["message"], "hello \"ecs\"");
+    assert_eq!(value["log"]["logger"], "databend_query::servers");
+    assert_eq!(value["log"]["origin"]["file"]["line"], 42);
+    assert_eq!(value["user"]["name"], "root");
+    assert_eq!(value["labels"]["query_id"], "q1");
+    assert_eq!(value["http"]["request"]["method"], "POST");
+}
+
+#[test]
+fn test_log_with_fixed_timezone() {
+    let timezone: LogTimezone = "+08:00".parse().unwrap();
+    let options = FormatOptions {
+        timezone,
+        ..Default::default()
+    };
+    let (json_logger, json_buffer) = build_logger("json", &options);
+    let (text_logger, text_buffer) = build_logger("text", &options);
+    for logger in [json_logger.as_ref(), text_logger.as_ref()] {
+        logger.log(
+            &Record::builder()
+                .level(Level::Info)
+                .args(format_args!("hello"))
+                .build(),
+        );
+    }
+
+    let json_lines = json_buffer.lines();
+    let value: serde_json::Value = serde_json::from_str(&json_lines[0]).unwrap();
+    let timestamp = value["timestamp"].as_str().unwrap();
+    assert!(timestamp.ends_with("+08:00"), "{timestamp}");
+    let text_lines = text_buffer.lines();
+    let timestamp = text_lines[0].split(' ').next().unwrap();
+    assert!(timestamp.ends_with("+08:00"), "{timestamp}");
+
+    // 2024-01-01T00:00:00Z
+    let time = UNIX_EPOCH + Duration::from_secs(1704067200);
+    assert_eq!(timezone.format(time), "2024-01-01T08:00:00.000000+08:00");
+    assert_eq!(LogTimezone::Utc.format(time), "2024-01-01T00:00:00.000000Z");
+}
+
+#[test]
+fn test_parse_log_timezone() {
+    assert_eq!("UTC".parse::<LogTimezone>().unwrap(), LogTimezone::Utc);
+    assert_eq!("local".parse::<LogTimezone>().unwrap(), LogTimezone::Local);
+    assert_eq!(
+        "-05:30".parse::<LogTimezone>().unwrap().to_string(),
+        "-05:30"
+    );
+    assert!("Asia/Shanghai".parse::<LogTimezone>().is_err());
+}
+
+#[test]
+fn test_log_with_timestamp_format() {
+    // 2024-01-01T00:00:00.123456789Z
+    let time = UNIX_EPOCH + Duration::new(1704067200, 123456789);
+    let utc = LogTimezone::Utc;
+    assert_eq!(
+        TimestampFormat::Rfc3339Micros.format(time, utc),
+        "2024-01-01T00:00:00.123456Z"
+    );
+    assert_eq!(
+        TimestampFormat::Rfc3339Millis.format(time, utc),
+        "2024-01-01T00:00:00.123Z"
+    );
+    assert_eq!(
+        TimestampFormat::Rfc3339Millis.format(time, "+08:00".parse().unwrap()),
+        "2024-01-01T08:00:00.123+08:00"
+    );
+    assert_eq!(
+        TimestampFormat::EpochMillis.format(time, utc),
+        "1704067200123"
+    );
+    assert_eq!(
+        TimestampFormat::EpochNanos.format(time, utc),
+        "1704067200123456789"
+    );
+
+    let options = FormatOptions {
+        timestamp_format: TimestampFormat::EpochMillis,
+        ..Default::default()
+    };
+    let (json_logger, json_buffer) = build_logger("json", &options);
+    let (text_logger, text_buffer) = build_logger("text", &options);
+    for logger in [json_logger.as_ref(), text_logger.as_ref()] {
+        logger.log(
+            &Record::builder()
+                .level(Level::Info)
+                .args(format_args!("hello"))
+                .build(),
+        );
+    }
+
+    // epoch timestamps are json numbers.
+    let value: serde_json::Value = serde_json::from_str(&json_buffer.lines()[0]).unwrap();
+    assert!(value["timestamp"].as_u64().unwrap() >= 1704067200123);
+    let text_lines = text_buffer.lines();
+    let timestamp = text_lines[0].split(' ').next().unwrap();
+    assert!(timestamp.parse::<u64>().unwrap() >= 1704067200123);
+}
+
+#[test]
+fn test_parse_timestamp_format() {
+    for format in [
+        TimestampFormat::Rfc3339Micros,
+        TimestampFormat::Rfc3339Millis,
+        TimestampFormat::EpochMillis,
+        TimestampFormat::EpochNanos,
+    ] {
+        assert_eq!(
+            format.to_string().parse::<TimestampFormat>().unwrap(),
+            format
+        );
+    }
+    assert!("rfc3339".parse::<TimestampFormat>().is_err());
+}
+
+fn log_to_target(logger: &dyn Log, target: &str, message: &str) {
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .target(target)
+            .args(format_args!("{message}"))
+            .build(),
+    );
+}
+
+#[test]
+fn test_target_routed_logger() {
+    let (storage_logger, storage_buffer) = build_logger("text", &FormatOptions::default());
+    let (fuse_logger, fuse_buffer) = build_logger("text", &FormatOptions::default());
+    let (default_logger, default_buffer) = build_logger("text", &FormatOptions::default());
+    let logger = TargetRoutedLogger::new(
+        vec![
+            ("databend_common_storage".to_string(), storage_logger),
+            ("databend_common_storages_fuse".to_string(), fuse_logger),
+        ],
+        default_logger,
+    );
+
+    log_to_target(&logger, "databend_common_storages_fuse::io", "fuse");
+    log_to_target(&logger, "databend_common_storage::operator", "storage");
+    log_to_target(&logger, "databend_query::servers", "query");
+
+    // the longest matching prefix wins.
+    let fuse_lines = fuse_buffer.lines();
+    assert_eq!(fuse_lines.len(), 1);
+    assert!(fuse_lines[0].contains("fuse"));
+    let storage_lines = storage_buffer.lines();
+    assert_eq!(storage_lines.len(), 1);
+    assert!(storage_lines[0].contains("storage"));
+    let default_lines = default_buffer.lines();
+    assert_eq!(default_lines.len(), 1);
+    assert!(default_lines[0].contains("query"));
+}
+
+#[test]
+fn test_target_routed_file_logger() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir_path = dir.path().to_str().unwrap();
+
+    let (logger, guards) = new_target_routed_file_logger(
+        dir_path,
+        "test",
+        &["databend_common_storages_fuse".to_string()],
+        0,
+        RotationPolicy::Never,
+        16,
+        None,
+        "text",
+        &FormatOptions::default(),
+    )
+    .unwrap();
+    // writers of the routed prefix and the default one.
+    assert_eq!(guards.len(), 2);
+
+    log_to_target(&logger, "databend_common_storages_fuse::io", "fuse");
+    log_to_target(&logger, "databend_query::servers", "query");
+    logger.flush();
+    drop(logger);
+    // flush the pending logs to files.
+    drop(guards);
+
+    let read_logs = |path: &std::path::Path| {
+        let files = fs::read_dir(path)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.is_file())
+            .collect::<Vec<_>>();
+        assert_eq!(files.len(), 1);
+        fs::read_to_string(&files[0]).unwrap()
+    };
+    let fuse_logs = read_logs(&dir.path().join("databend_common_storages_fuse"));
+    assert!(fuse_logs.contains("fuse") && !fuse_logs.contains("query"));
+    let default_logs = read_logs(dir.path());
+    assert!(default_logs.contains("query") && !default_logs.contains("fuse"));
+}
+
+#[test]
+fn test_level_routed_file_logger() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir_path = dir.path().to_str().unwrap();
+
+    let routes = parse_level_routes(&["warn:errors".to_string()]).unwrap();
+    let (logger, guards) = new_level_routed_file_logger(
+        dir_path,
+        "test",
+        &routes,
+        0,
+        RotationPolicy::Never,
+        16,
+        None,
+        "text",
+        &FormatOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(guards.len(), 2);
+
+    for (level, message) in [(Level::Warn, "disk is slow"), (Level::Info, "query done")] {
+        logger.log(
+            &Record::builder()
+                .level(level)
+                .target("databend_query")
+                .args(format_args!("{}", message))
+                .build(),
+        );
+    }
+    logger.flush();
+    drop(logger);
+    drop(guards);
+
+    let read_logs = |prefix: &str| {
+        let files = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                let name = path.file_name().unwrap().to_string_lossy();
+                name.starts_with(prefix)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(files.len(), 1);
+        fs::read_to_string(&files[0]).unwrap()
+    };
+    let error_logs = read_logs("errors");
+    assert!(error_logs.contains("disk is slow") && !error_logs.contains("query done"));
+    let default_logs = read_logs("test");
+    assert!(default_logs.contains("query done") && !default_logs.contains("disk is slow"));
+}
+
+#[test]
+fn test_parse_level_routes() {
+    let parse = |routes: &[&str]| {
+        let routes = routes.iter().map(|r| r.to_string()).collect::<Vec<_>>();
+        parse_level_routes(&routes)
+    };
+    assert_eq!(parse(&["warn:errors", " DEBUG : debug "]).unwrap(), vec![
+        (LevelFilter::Warn, "errors".to_string()),
+        (LevelFilter::Debug, "debug".to_string())
+    ]);
+    assert!(parse(&["errors"]).is_err());
+    assert!(parse(&["off:errors"]).is_err());
+    assert!(parse(&["warn:"]).is_err());
+    assert!(parse(&["warn:a", "warn:b"]).is_err());
+    assert!(parse(&["warn:a", "error:a"]).is_err());
+}
+
+#[test]
+fn test_parse_json_field_order() {
+    let parse = |keys: &[&str]| {
+        let keys = keys.iter().map(|key| key.to_string()).collect::<Vec<_>>();
+        parse_json_field_order(&keys)
+    };
+
+    assert_eq!(parse(&[]).unwrap(), vec![
+        JsonField::Timestamp,
+        JsonField::Level,
+        JsonField::Target,
+        JsonField::File,
+        JsonField::Line
+    ]);
+    assert_eq!(parse(&["level", "line", "fields"]).unwrap(), vec![
+        JsonField::Level,
+        JsonField::Line,
+        JsonField::Timestamp,
+        JsonField::Target,
+        JsonField::File
+    ]);
+    assert!(parse(&["fields", "level"]).is_err());
+    assert!(parse(&["level", "level"]).is_err());
+    assert!(parse(&["message"]).is_err());
+}
+
+#[test]
+fn test_parse_otlp_protocol() {
+    assert_eq!("grpc".parse::<OtlpProtocol>().unwrap(), OtlpProtocol::Grpc);
+    assert_eq!(
+        "http/protobuf".parse::<OtlpProtocol>().unwrap(),
+        OtlpProtocol::HttpProtobuf
+    );
+    // must not silently fall back to grpc.
+    assert!("http/json".parse::<OtlpProtocol>().is_err());
+    assert!("http".parse::<OtlpProtocol>().is_err());
+}
+
+#[test]
+fn test_check_otlp_endpoint() {
+    let grpc = OtlpProtocol::Grpc;
+    let http = OtlpProtocol::HttpProtobuf;
+    assert!(grpc.check_endpoint("http://127.0.0.1:4317").is_ok());
+    assert!(http.check_endpoint("http://127.0.0.1:4318").is_ok());
+    assert!(http.check_endpoint("https://otlp.example.com/otlp").is_ok());
+    // other ports are not checked.
+    assert!(http.check_endpoint("http://127.0.0.1:8080").is_ok());
+
+    assert!(grpc.check_endpoint("http://127.0.0.1:4318").is_err());
+    assert!(
+        grpc.check_endpoint("http://collector:4318/v1/logs")
+            .is_err()
+    );
+    let err = http.check_endpoint("http://127.0.0.1:4317").unwrap_err();
+    assert!(err.contains("4317"), "{err}");
+}
+
+#[test]
+fn test_otlp_exporter_default_options() {
+    // keep the defaults of the OpenTelemetry SDK.
+    assert_eq!(OtlpExporterOptions::default(), OtlpExporterOptions {
+        timeout: Duration::from_secs(10),
+        max_queue_size: 2048,
+        max_export_batch_size: 512,
+        scheduled_delay: Duration::from_secs(1),
+        max_retries: 3,
+        retry_base_delay: Duration::from_millis(200),
+        max_export_timeout: Duration::from_secs(30),
+    });
+}
+
+/// Fails the first `failures` exports.
+#[derive(Debug)]
+struct FlakyExporter {
+    failures: usize,
+    attempts: Arc<Mutex<Vec<usize>>>,
+}
+
+#[async_trait::async_trait]
+impl LogExporter for FlakyExporter {
+    async fn export(&mut self, batch: Vec<LogData>) -> LogResult<()> {
+        let mut attempts = self.attempts.lock().unwrap();
+        attempts.push(batch.len());
+        if attempts.len() <= self.failures {
+            return Err(LogError::Other("collector unavailable".into()));
+        }
+        Ok(())
+    }
+}
+
+/// Never finishes an export.
+#[derive(Debug)]
+struct HangingExporter;
+
+#[async_trait::async_trait]
+impl LogExporter for HangingExporter {
+    async fn export(&mut self, _batch: Vec<LogData>) -> LogResult<()> {
+        std::future::pending().await
+    }
+}
+
+fn log_batch(len: usize) -> Vec<LogData> {
+    (0..len)
+        .map(|_| LogData {
+            record: Default::default(),
+            resource: Cow::Owned(Resource::default()),
+            instrumentation: Default::default(),
+        })
+        .collect()
+}
+
+#[test]
+fn test_retry_log_exporter() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+
+    // succeeds on the last retry.
+    let attempts = Arc::new(Mutex::new(vec![]));
+    let mut exporter = RetryLogExporter::new(
+        FlakyExporter {
+            failures: 3,
+            attempts: attempts.clone(),
+        },
+        3,
+        Duration::from_millis(1),
+    );
+    let dropped = otlp_dropped_log_records();
+    runtime.block_on(exporter.export(log_batch(2))).unwrap();
+    assert_eq!(*attempts.lock().unwrap(), vec![2, 2, 2, 2]);
+    assert_eq!(otlp_dropped_log_records(), dropped);
+
+    // runs out of retries.
+    let attempts = Arc::new(Mutex::new(vec![]));
+    let mut exporter = RetryLogExporter::new(
+        FlakyExporter {
+            failures: 3,
+            attempts: attempts.clone(),
+        },
+        2,
+        Duration::from_millis(1),
+    );
+    assert!(runtime.block_on(exporter.export(log_batch(2))).is_err());
+    assert_eq!(*attempts.lock().unwrap(), vec![2, 2, 2]);
+    assert_eq!(otlp_dropped_log_records(), dropped + 2);
+
+    // runs out of time, no retry starts if its backoff ends after the deadline.
+    let attempts = Arc::new(Mutex::new(vec![]));
+    let mut exporter = RetryLogExporter::new(
+        FlakyExporter {
+            failures: 3,
+            attempts: attempts.clone(),
+        },
+        3,
+        Duration::from_secs(60),
+    )
+    .with_deadline(Duration::from_millis(100));
+    assert!(runtime.block_on(exporter.export(log_batch(2))).is_err());
+    assert_eq!(*attempts.lock().unwrap(), vec![2]);
+    assert_eq!(otlp_dropped_log_records(), dropped + 4);
+
+    // the attempt in flight is cancelled at the deadline.
+    let mut exporter = RetryLogExporter::new(HangingExporter, 3, Duration::from_millis(1))
+        .with_deadline(Duration::from_millis(100));
+    let err = runtime.block_on(exporter.export(log_batch(2))).unwrap_err();
+    assert!(matches!(err, LogError::ExportTimedOut(_)), "{err}");
+    assert_eq!(otlp_dropped_log_records(), dropped + 6);
+}
+
+#[test]
+fn test_otlp_export_config() {
+    let options = OtlpExporterOptions {
+        timeout: Duration::from_secs(3),
+        ..Default::default()
+    };
+
+    let config = options.export_config("http://127.0.0.1:4317", OtlpProtocol::Grpc);
+    assert_eq!(config.endpoint, "http://127.0.0.1:4317");
+    assert_eq!(config.protocol, opentelemetry_otlp::Protocol::Grpc);
+    assert_eq!(config.timeout, Duration::from_secs(3));
+
+    let config = options.export_config("http://127.0.0.1:4318", OtlpProtocol::HttpProtobuf);
+    assert_eq!(config.protocol, opentelemetry_otlp::Protocol::HttpBinary);
+    assert_eq!(config.timeout, Duration::from_secs(3));
+}
+
+#[test]
+fn test_syslog_logger_udp() {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    server
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let logger = SyslogLogger::new(
+        SyslogTransport::Udp(server.local_addr().unwrap()),
+        SyslogFacility::Local0,
+        Some("node-1".to_string()),
+    );
+
+    let kvs: &[(&str, &str)] = &[("query_id", "q1"), ("sql", r#"select "a]""#)];
+    logger.log(
+        &Record::builder()
+            .level(Level::Warn)
+            .key_values(&kvs)
+            .args(format_args!("slow query"))
+            .build(),
+    );
+
+    let mut buf = [0u8; 1024];
+    let len = server.recv(&mut buf).unwrap();
+    let message = String::from_utf8_lossy(&buf[..len]).to_string();
+    // local0 * 8 + warning
+    assert!(message.starts_with("<132>1 "), "{message}");
+    let fields = message.splitn(7, ' ').collect::<Vec<_>>();
+    assert_eq!(fields[2], "node-1");
+    assert_eq!(fields[4], std::process::id().to_string());
+    assert_eq!(fields[5], "-");
+    assert_eq!(
+        fields[6],
+        r#"[kv@32473 query_id="q1" sql="select \"a\]\""] slow query"#
+    );
+}
+
+#[test]
+fn test_syslog_logger_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let logger = SyslogLogger::new(
+        SyslogTransport::Tcp(listener.local_addr().unwrap()),
+        SyslogFacility::default(),
+        Some("node-1".to_string()),
+    );
+
+    logger.log(
+        &Record::builder()
+            .level(Level::Error)
+            .args(format_args!("first"))
+            .build(),
+    );
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .args(format_args!("second"))
+            .build(),
+    );
+    logger.flush();
+    drop(logger);
+
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut received = String::new();
+    stream.read_to_string(&mut received).unwrap();
+
+    // octet counting framing: `{len} {message}`.
+    let mut messages = vec![];
+    let mut rest = received.as_str();
+    while let Some((len, tail)) = rest.split_once(' ') {
+        let len = len.parse::<usize>().unwrap();
+        messages.push(&tail[..len]);
+        rest = &tail[len..];
+    }
+    assert_eq!(messages.len(), 2);
+    // user * 8 + error, user * 8 + informational
+    assert!(messages[0].starts_with("<11>1 "), "{}", messages[0]);
+    assert!(messages[0].ends_with(" - first"), "{}", messages[0]);
+    assert!(messages[1].starts_with("<14>1 "), "{}", messages[1]);
+    assert!(messages[1].ends_with(" - second"), "{}", messages[1]);
+}
+
+#[test]
+fn test_syslog_logger_flush() {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let logger = SyslogLogger::new(
+        SyslogTransport::Udp(server.local_addr().unwrap()),
+        SyslogFacility::default(),
+        Some("node-1".to_string()),
+    );
+
+    for i in 0..100 {
+        logger.log(
+            &Record::builder()
+                .level(Level::Info)
+                .args(format_args!("message {i}"))
+                .build(),
+        );
+    }
+    // the queued messages are all sent once the flush returns.
+    logger.flush();
+
+    server.set_nonblocking(true).unwrap();
+    let mut buf = [0u8; 1024];
+    let mut received = 0;
+    while server.recv(&mut buf).is_ok() {
+        received += 1;
+    }
+    assert_eq!(received, 100);
+}
+
+#[test]
+fn test_syslog_logger_unreachable() {
+    // nothing listens on the port once the listener is dropped.
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let logger = SyslogLogger::new(
+        SyslogTransport::Tcp(addr),
+        SyslogFacility::default(),
+        Some("node-1".to_string()),
+    );
+
+    let dropped = syslog_dropped_log_records();
+    for i in 0..3 {
+        logger.log(
+            &Record::builder()
+                .level(Level::Error)
+                .args(format_args!("message {i}"))
+                .build(),
+        );
+    }
+
+    // the messages are sent by the background thread, and dropped once the connect fails.
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while syslog_dropped_log_records() < dropped + 3 {
+        assert!(Instant::now() < deadline, "messages are not dropped");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn test_parse_syslog_transport() {
+    assert_eq!(
+        "udp://127.0.0.1:514".parse::<SyslogTransport>().unwrap(),
+        SyslogTransport::Udp("127.0.0.1:514".parse().unwrap())
+    );
+    assert_eq!(
+        "TCP://127.0.0.1:601".parse::<SyslogTransport>().unwrap(),
+        SyslogTransport::Tcp("127.0.0.1:601".parse().unwrap())
+    );
+    assert_eq!(
+        "unix:///dev/log".parse::<SyslogTransport>().unwrap(),
+        SyslogTransport::UnixSocket("/dev/log".into())
+    );
+    assert!("127.0.0.1:514".parse::<SyslogTransport>().is_err());
+    assert!("udp://127.0.0.1".parse::<SyslogTransport>().is_err());
+    assert!("unix://".parse::<SyslogTransport>().is_err());
+    assert!("http://127.0.0.1:514".parse::<SyslogTransport>().is_err());
+}
+
+#[test]
+fn test_parse_syslog_facility() {
+    assert_eq!(
+        "daemon".parse::<SyslogFacility>().unwrap(),
+        SyslogFacility::Daemon
+    );
+    assert_eq!(
+        "LOCAL7".parse::<SyslogFacility>().unwrap(),
+        SyslogFacility::Local7
+    );
+    assert!("local8".parse::<SyslogFacility>().is_err());
+}
+
+#[test]
+fn test_otlp_log_attributes() {
+    let kvs: &[(&str, log::kv::Value)] = &[
+        ("rows", 42u64.into()),
+        ("ratio", 0.5f64.into()),
+        ("cached", true.into()),
+        ("table", "t1".into()),
+        ("bytes", u64::MAX.into()),
+    ];
+    let attributes = otlp_log_attributes(
+        &Record::builder()
+            .level(Level::Info)
+            .key_values(&kvs)
+            .args(format_args!("compacted"))
+            .build(),
+        &RedactKeys::default(),
+    );
+    // `AnyValue` is not comparable, so the values are compared by their debug format.
+    let attributes = attributes
+        .iter()
+        .map(|(k, v)| (k.as_str(), format!("{:?}", v)))
+        .collect::<Vec<_>>();
+    let expected = [
+        ("rows", AnyValue::Int(42)),
+        ("ratio", AnyValue::Double(0.5)),
+        ("cached", AnyValue::Boolean(true)),
+        ("table", AnyValue::from("t1".to_string())),
+        ("bytes", AnyValue::from(u64::MAX.to_string())),
+    ]
+    .iter()
+    .map(|(k, v)| (*k, format!("{:?}", v)))
+    .collect::<Vec<_>>();
+    assert_eq!(attributes, expected);
+}
+
+#[test]
+fn test_callback_logger() {
+    type Received = (Level, String, String, Vec<(String, String)>);
+    let received: Arc<Mutex<Vec<Received>>> = Arc::new(Mutex::new(vec![]));
+    let logger = {
+        let received = received.clone();
+        CallbackLogger::new(move |view: &LogRecordView<'_>| {
+            if view.message == "boom" {
+                panic!("callback panicked");
+            }
+            received.lock().unwrap().push((
+                view.level,
+                view.target.to_string(),
+                view.message.clone(),
+                view.fields.clone(),
+            ));
+        })
+    };
+
+    let kvs: &[(&str, log::kv::Value)] = &[("table", "t1".into()), ("rows", 42u64.into())];
+    logger.log(
+        &Record::builder()
+            .level(Level::Warn)
+            .target("databend_query::servers")
+            .key_values(&kvs)
+            .args(format_args!("slow query {}", 1))
+            .build(),
+    );
+    // a panic of the callback only drops its own record.
+    log_to_target(&logger, "databend_query::servers", "boom");
+    log_to_target(&logger, "databend_common_storages_fuse::io", "fuse");
+
+    assert_eq!(logger.panics(), 1);
+    assert_eq!(*received.lock().unwrap(), vec![
+        (
+            Level::Warn,
+            "databend_query::servers".to_string(),
+            "slow query 1".to_string(),
+            vec![
+                ("table".to_string(), "t1".to_string()),
+                ("rows".to_string(), "42".to_string()),
+            ],
+        ),
+        (
+            Level::Info,
+            "databend_common_storages_fuse::io".to_string(),
+            "fuse".to_string(),
+            vec![],
+        ),
+    ]);
+}
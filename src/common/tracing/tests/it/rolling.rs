@@ -0,0 +1,255 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use databend_common_tracing::new_file_log_writer;
+use databend_common_tracing::RotationPolicy;
+use databend_common_tracing::DEFAULT_LOG_BUFFER_CAPACITY;
+
+#[test]
+fn test_rotation_by_size() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir_path = dir.path().to_str().unwrap();
+
+    let (mut writer, guard) =
+        new_file_log_writer(dir_path, "test", 3, RotationPolicy::BySize(100), 16, None);
+    for i in 0..10 {
+        let line = format!("{:0>59}\n", i);
+        writer.write_all(line.as_bytes()).unwrap();
+        writer.flush().unwrap();
+    }
+    drop(writer);
+    // flush the pending logs to files.
+    drop(guard);
+
+    let files = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    // every file holds one line, and only the newest 3 files are kept.
+    assert_eq!(files.len(), 3);
+    for file in files {
+        let content = fs::read_to_string(file).unwrap();
+        assert_eq!(content.len(), 60);
+        assert!(content.ends_with("7\n") || content.ends_with("8\n") || content.ends_with("9\n"));
+    }
+}
+
+#[test]
+fn test_rotation_daily() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir_path = dir.path().to_str().unwrap();
+
+    let (mut writer, guard) = new_file_log_writer(
+        dir_path,
+        "test",
+        3,
+        "daily".parse().unwrap(),
+        DEFAULT_LOG_BUFFER_CAPACITY,
+        None,
+    );
+    writer.write_all(b"hello\n").unwrap();
+    writer.flush().unwrap();
+    drop(writer);
+    drop(guard);
+
+    let names = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names.len(), 1);
+
+    // the suffix is the day, e.g. `test.2024-01-23`, without the hour.
+    let suffix = names[0].strip_prefix("test.").unwrap();
+    let parts = suffix.split('-').collect::<Vec<_>>();
+    assert_eq!(parts.len(), 3, "unexpected log file name {}", names[0]);
+    assert_eq!(parts.iter().map(|p| p.len()).collect::<Vec<_>>(), vec![
+        4, 2, 2
+    ]);
+    assert!(parts.iter().all(|p| p.chars().all(|c| c.is_ascii_digit())));
+}
+
+#[test]
+fn test_small_buffer_flushed_on_drop() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir_path = dir.path().to_str().unwrap();
+
+    let (mut writer, guard) =
+        new_file_log_writer(dir_path, "test", 3, RotationPolicy::Never, 64, None);
+    // some lines overflow the buffer, the tail stays in the buffer without `flush`.
+    let lines = (0..100)
+        .map(|i| format!("line {i:0>3} of the small buffer\n"))
+        .collect::<String>();
+    for line in lines.split_inclusive('\n') {
+        writer.write_all(line.as_bytes()).unwrap();
+    }
+    drop(writer);
+    drop(guard);
+
+    let files = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    assert_eq!(files.len(), 1);
+    assert_eq!(fs::read_to_string(&files[0]).unwrap(), lines);
+}
+
+#[test]
+fn test_parse_rotation_policy() {
+    assert_eq!(
+        "hourly".parse::<RotationPolicy>().unwrap(),
+        RotationPolicy::Hourly
+    );
+    assert_eq!(
+        "DAILY".parse::<RotationPolicy>().unwrap(),
+        RotationPolicy::Daily
+    );
+    assert_eq!(
+        "minutely".parse::<RotationPolicy>().unwrap(),
+        RotationPolicy::Minutely
+    );
+    assert_eq!(
+        "never".parse::<RotationPolicy>().unwrap(),
+        RotationPolicy::Never
+    );
+    assert_eq!(
+        "size:1048576".parse::<RotationPolicy>().unwrap(),
+        RotationPolicy::BySize(1048576)
+    );
+    assert!("weekly".parse::<RotationPolicy>().is_err());
+    assert!("size:0".parse::<RotationPolicy>().is_err());
+    assert!("size:1MB".parse::<RotationPolicy>().is_err());
+}
+
+#[test]
+fn test_rotation_policy_round_trip() {
+    for policy in [
+        RotationPolicy::Hourly,
+        RotationPolicy::Daily,
+        RotationPolicy::Minutely,
+        RotationPolicy::Never,
+        RotationPolicy::BySize(100),
+    ] {
+        assert_eq!(policy.to_string().parse::<RotationPolicy>(), Ok(policy));
+    }
+}
+
+#[test]
+fn test_rotation_with_compression() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir_path = dir.path().to_str().unwrap();
+
+    let (mut writer, guard) = new_file_log_writer(
+        dir_path,
+        "test",
+        3,
+        RotationPolicy::BySize(100),
+        16,
+        Some(6),
+    );
+    for i in 0..10 {
+        let line = format!("{:0>59}\n", i);
+        writer.write_all(line.as_bytes()).unwrap();
+        writer.flush().unwrap();
+    }
+    drop(writer);
+    drop(guard);
+
+    // compressed files are counted by `max_files` too.
+    let names = wait_for_log_files(dir.path(), 3, 2);
+    assert_eq!(names.len(), 3);
+    assert!(names[0].ends_with(".gz"));
+    assert!(names[1].ends_with(".gz"));
+    assert!(!names[2].ends_with(".gz"));
+
+    for (name, i) in names.iter().zip(7..) {
+        let path = dir.path().join(name);
+        let content = if name.ends_with(".gz") {
+            let mut decoder = flate2::read::GzDecoder::new(fs::File::open(path).unwrap());
+            let mut content = String::new();
+            decoder.read_to_string(&mut content).unwrap();
+            content
+        } else {
+            fs::read_to_string(path).unwrap()
+        };
+        assert_eq!(content, format!("{:0>59}\n", i));
+    }
+}
+
+#[test]
+fn test_rotated_file_compressed_to_original_bytes() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir_path = dir.path().to_str().unwrap();
+
+    let (mut writer, guard) = new_file_log_writer(
+        dir_path,
+        "test",
+        0,
+        RotationPolicy::BySize(64 * 1024),
+        DEFAULT_LOG_BUFFER_CAPACITY,
+        Some(9),
+    );
+    // the first file is full of these lines, the second one forces the rollover.
+    let lines = (0..1000)
+        .map(|i| format!("{i} the quick brown fox jumps over the lazy dog\n"))
+        .collect::<String>();
+    writer.write_all(lines.as_bytes()).unwrap();
+    writer.flush().unwrap();
+    writer.write_all(&[b'x'; 64 * 1024]).unwrap();
+    writer.flush().unwrap();
+    drop(writer);
+    drop(guard);
+
+    let names = wait_for_log_files(dir.path(), 2, 1);
+    assert_eq!(names.len(), 2);
+    assert!(names[0].ends_with(".gz"));
+    assert!(!names[1].ends_with(".gz"));
+
+    let mut decoder =
+        flate2::read::GzDecoder::new(fs::File::open(dir.path().join(&names[0])).unwrap());
+    let mut content = vec![];
+    decoder.read_to_end(&mut content).unwrap();
+    assert_eq!(content, lines.as_bytes());
+    assert_eq!(fs::read(dir.path().join(&names[1])).unwrap(), vec![
+        b'x';
+        64 * 1024
+    ]);
+}
+
+// Rotated files are compressed in the background, waits until the expected
+// number of compressed files shows up, returns the sorted file names.
+fn wait_for_log_files(dir: &Path, num_files: usize, num_compressed: usize) -> Vec<String> {
+    let start = Instant::now();
+    loop {
+        let mut names = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        let compressed = names.iter().filter(|name| name.ends_with(".gz")).count();
+        if (names.len() == num_files && compressed == num_compressed)
+            || start.elapsed() > Duration::from_secs(10)
+        {
+            return names;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
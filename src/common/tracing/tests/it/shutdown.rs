@@ -0,0 +1,76 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use databend_common_tracing::LogShutdown;
+
+#[test]
+fn test_log_shutdown() {
+    let flushed = Arc::new(Mutex::new(vec![]));
+    let mut shutdown = LogShutdown::default();
+    shutdown.register("otlp", {
+        let flushed = flushed.clone();
+        move || {
+            flushed.lock().unwrap().push("otlp");
+            Err("export timed out".to_string())
+        }
+    });
+    shutdown.register("file", {
+        let flushed = flushed.clone();
+        move || {
+            flushed.lock().unwrap().push("file");
+            Ok(())
+        }
+    });
+    assert_eq!(shutdown.sink_names(), vec!["otlp", "file"]);
+
+    // the failed sink does not stop the next one.
+    let err = shutdown.shutdown().unwrap_err();
+    assert_eq!(*flushed.lock().unwrap(), vec!["otlp", "file"]);
+    assert_eq!(err.failures, vec![(
+        "otlp".to_string(),
+        "export timed out".to_string()
+    )]);
+    assert_eq!(
+        err.to_string(),
+        "failed to flush log sinks: otlp: export timed out"
+    );
+}
+
+#[test]
+fn test_log_shutdown_on_drop() {
+    struct Guard(Arc<Mutex<Vec<&'static str>>>);
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            self.0.lock().unwrap().push("guard");
+        }
+    }
+
+    let flushed = Arc::new(Mutex::new(vec![]));
+    let mut shutdown = LogShutdown::default();
+    shutdown.register("logger", {
+        let flushed = flushed.clone();
+        move || {
+            flushed.lock().unwrap().push("logger");
+            Ok(())
+        }
+    });
+    shutdown.register_guard("file", Guard(flushed.clone()));
+    assert!(flushed.lock().unwrap().is_empty());
+
+    drop(shutdown);
+    assert_eq!(*flushed.lock().unwrap(), vec!["logger", "guard"]);
+}
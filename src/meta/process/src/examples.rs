@@ -67,7 +67,8 @@ async fn upgrade_09() -> anyhow::Result<()> {
         "databend-meta-upgrade-09",
         &LogConfig::default(),
         BTreeMap::new(),
-    );
+    )
+    .map_err(anyhow::Error::msg)?;
 
     eprintln!("config: {}", pretty(&config)?);
 
@@ -68,7 +68,8 @@ fn setup_test() {
         let t = tempfile::tempdir().expect("create temp dir to sled db");
         databend_common_meta_sled_store::init_temp_sled_db(t);
 
-        let guards = init_logging("meta_unittests", &Config::new_testing(), BTreeMap::new());
+        let guards = init_logging("meta_unittests", &Config::new_testing(), BTreeMap::new())
+            .expect("init logging");
         Box::leak(Box::new(guards));
     });
 }
@@ -21,12 +21,14 @@ use databend_common_meta_raft_store::config::get_default_raft_advertise_host;
 use databend_common_meta_raft_store::config::RaftConfig as InnerRaftConfig;
 use databend_common_meta_types::MetaStartupError;
 use databend_common_tracing::Config as InnerLogConfig;
+use databend_common_tracing::DedupConfig;
 use databend_common_tracing::FileConfig as InnerFileLogConfig;
 use databend_common_tracing::OTLPConfig;
 use databend_common_tracing::ProfileLogConfig;
 use databend_common_tracing::QueryLogConfig;
 use databend_common_tracing::StderrConfig as InnerStderrLogConfig;
 use databend_common_tracing::StructLogConfig;
+use databend_common_tracing::SyslogConfig;
 use databend_common_tracing::TracingConfig;
 use serde::Deserialize;
 use serde::Serialize;
@@ -597,10 +599,13 @@ impl Into<InnerLogConfig> for LogConfig {
             file: self.file.into(),
             stderr: self.stderr.into(),
             otlp: OTLPConfig::default(),
+            syslog: SyslogConfig::default(),
             query: QueryLogConfig::default(),
             profile: ProfileLogConfig::default(),
             structlog: StructLogConfig::default(),
             tracing: TracingConfig::default(),
+            dedup: DedupConfig::default(),
+            redact_keys: Default::default(),
         }
     }
 }
@@ -663,6 +668,7 @@ impl Into<InnerFileLogConfig> for FileLogConfig {
             format: self.file_format,
             limit: self.file_limit,
             prefix_filter: self.file_prefix_filter,
+            ..Default::default()
         }
     }
 }
@@ -1005,6 +1005,7 @@ impl<'ast> Visitor<'ast> for AstFormatVisitor {
     fn visit_set_variable(
         &mut self,
         is_global: bool,
+        is_local: bool,
         variable: &'ast Identifier,
         value: &'ast Expr,
     ) {
@@ -1014,6 +1015,8 @@ impl<'ast> Visitor<'ast> for AstFormatVisitor {
 
         let name = if is_global {
             format!("SetGlobal {}", variable)
+        } else if is_local {
+            format!("SetLocal {}", variable)
         } else {
             format!("Set {}", variable)
         };
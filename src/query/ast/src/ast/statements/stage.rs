@@ -17,13 +17,15 @@ use std::default::Default;
 use std::fmt::Display;
 use std::fmt::Formatter;
 
+use databend_common_meta_app::schema::CreateOption;
+
 use crate::ast::write_comma_separated_map;
 use crate::ast::write_comma_separated_quoted_list;
 use crate::ast::UriLocation;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CreateStageStmt {
-    pub if_not_exists: bool,
+    pub create_option: CreateOption,
     pub stage_name: String,
 
     pub location: Option<UriLocation>,
@@ -37,9 +39,16 @@ pub struct CreateStageStmt {
 
 impl Display for CreateStageStmt {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "CREATE STAGE")?;
-        if self.if_not_exists {
-            write!(f, " IF NOT EXISTS")?;
+        match self.create_option {
+            CreateOption::CreateIfNotExists(if_not_exists) => {
+                write!(f, "CREATE STAGE")?;
+                if if_not_exists {
+                    write!(f, " IF NOT EXISTS")?;
+                }
+            }
+            CreateOption::CreateOrReplace => {
+                write!(f, "CREATE OR REPLACE STAGE")?;
+            }
         }
         write!(f, " {}", self.stage_name)?;
 
@@ -79,6 +79,8 @@ pub enum Statement {
 
     SetVariable {
         is_global: bool,
+        // only for the next statement of the session
+        is_local: bool,
         variable: Identifier,
         value: Box<Expr>,
     },
@@ -417,6 +419,7 @@ impl Display for Statement {
             }
             Statement::SetVariable {
                 is_global,
+                is_local,
                 variable,
                 value,
             } => {
@@ -424,6 +427,9 @@ impl Display for Statement {
                 if *is_global {
                     write!(f, "GLOBAL ")?;
                 }
+                if *is_local {
+                    write!(f, "LOCAL ")?;
+                }
                 write!(f, "{variable} = {value}")?;
             }
             Statement::UnSetVariable(unset) => write!(f, "{unset}")?,
@@ -398,12 +398,16 @@ pub fn statement(i: Input) -> IResult<StatementWithFormat> {
 
     let set_variable = map(
         rule! {
-            SET ~ GLOBAL? ~ #ident ~ "=" ~ #subexpr(0)
-        },
-        |(_, opt_is_global, variable, _, value)| Statement::SetVariable {
-            is_global: opt_is_global.is_some(),
-            variable,
-            value: Box::new(value),
+            SET ~ ( GLOBAL | LOCAL )? ~ #ident ~ "=" ~ #subexpr(0)
+        },
+        |(_, opt_scope, variable, _, value)| {
+            let scope = opt_scope.map(|scope| scope.kind);
+            Statement::SetVariable {
+                is_global: scope == Some(GLOBAL),
+                is_local: scope == Some(LOCAL),
+                variable,
+                value: Box::new(value),
+            }
         },
     );
 
@@ -1224,7 +1228,7 @@ pub fn statement(i: Input) -> IResult<StatementWithFormat> {
     // stages
     let create_stage = map_res(
         rule! {
-            CREATE ~ STAGE ~ ( IF ~ ^NOT ~ ^EXISTS )?
+            CREATE ~ (OR ~ REPLACE)? ~ STAGE ~ ( IF ~ ^NOT ~ ^EXISTS )?
             ~ ( #stage_name )
             ~ ( (URL ~ ^"=")? ~ #uri_location )?
             ~ ( #file_format_clause )?
@@ -1235,6 +1239,7 @@ pub fn statement(i: Input) -> IResult<StatementWithFormat> {
         },
         |(
             _,
+            opt_or_replace,
             _,
             opt_if_not_exists,
             stage,
@@ -1245,8 +1250,10 @@ pub fn statement(i: Input) -> IResult<StatementWithFormat> {
             validation_mode_opt,
             comment_opt,
         )| {
+            let create_option =
+                parse_create_option(opt_or_replace.is_some(), opt_if_not_exists.is_some())?;
             Ok(Statement::CreateStage(CreateStageStmt {
-                if_not_exists: opt_if_not_exists.is_some(),
+                create_option,
                 stage_name: stage.to_string(),
                 location: url_opt.map(|(_, location)| location),
                 file_format_options: file_format_opt.unwrap_or_default(),
@@ -1886,7 +1893,7 @@ pub fn statement(i: Input) -> IResult<StatementWithFormat> {
             | #show_user_functions : "`SHOW USER FUNCTIONS [<show_limit>]`"
         ),
         rule!(
-            #create_stage: "`CREATE STAGE [ IF NOT EXISTS ] <stage_name>
+            #create_stage: "`CREATE [ OR REPLACE ] STAGE [ IF NOT EXISTS ] <stage_name>
                 [ FILE_FORMAT = ( { TYPE = { CSV | PARQUET } [ formatTypeOptions ] ) } ]
                 [ COPY_OPTIONS = ( copyOptions ) ]
                 [ COMMENT = '<string_literal>' ]`"
@@ -423,6 +423,7 @@ pub trait Visitor<'ast>: Sized {
     fn visit_set_variable(
         &mut self,
         _is_global: bool,
+        _is_local: bool,
         _variable: &'ast Identifier,
         _value: &'ast Expr,
     ) {
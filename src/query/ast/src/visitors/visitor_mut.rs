@@ -435,6 +435,7 @@ pub trait VisitorMut: Sized {
     fn visit_set_variable(
         &mut self,
         _is_global: bool,
+        _is_local: bool,
         _variable: &mut Identifier,
         _value: &mut Box<Expr>,
     ) {
@@ -388,9 +388,10 @@ pub fn walk_statement<'a, V: Visitor<'a>>(visitor: &mut V, statement: &'a Statem
         } => visitor.visit_kill(kill_target, object_id),
         Statement::SetVariable {
             is_global,
+            is_local,
             variable,
             value,
-        } => visitor.visit_set_variable(*is_global, variable, value),
+        } => visitor.visit_set_variable(*is_global, *is_local, variable, value),
         Statement::UnSetVariable(stmt) => visitor.visit_unset_variable(stmt),
         Statement::SetRole {
             is_default,
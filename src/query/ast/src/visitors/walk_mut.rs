@@ -393,9 +393,10 @@ pub fn walk_statement_mut<V: VisitorMut>(visitor: &mut V, statement: &mut Statem
         } => visitor.visit_kill(kill_target, object_id),
         Statement::SetVariable {
             is_global,
+            is_local,
             variable,
             value,
-        } => visitor.visit_set_variable(*is_global, variable, value),
+        } => visitor.visit_set_variable(*is_global, *is_local, variable, value),
         Statement::UnSetVariable(stmt) => visitor.visit_unset_variable(stmt),
         Statement::SetRole {
             is_default,
@@ -319,9 +319,10 @@ pub trait Table: Sync + Send {
         &self,
         ctx: Arc<dyn TableContext>,
         lock: Arc<dyn Lock>,
+        bytes_per_segment: Option<u64>,
         limit: Option<usize>,
     ) -> Result<()> {
-        let (_, _, _) = (ctx, lock, limit);
+        let (_, _, _, _) = (ctx, lock, bytes_per_segment, limit);
 
         Err(ErrorCode::Unimplemented(format!(
             "The operation 'compact_segments' is not supported for the table '{}', which is using the '{}' engine.",
@@ -334,9 +335,10 @@ pub trait Table: Sync + Send {
     async fn compact_blocks(
         &self,
         ctx: Arc<dyn TableContext>,
+        block_size: Option<BlockSize>,
         limit: Option<usize>,
     ) -> Result<Option<(Partitions, Arc<TableSnapshot>)>> {
-        let (_, _) = (ctx, limit);
+        let (_, _, _) = (ctx, block_size, limit);
 
         Err(ErrorCode::Unimplemented(format!(
             "The 'compact_blocks' operation is not supported for the table '{}'. Table engine: '{}'.",
@@ -454,8 +456,43 @@ pub struct ColumnStatistics {
 }
 
 pub enum CompactTarget {
-    Blocks,
+    /// Compacts the blocks into ones of `block_size`, or of the block thresholds of the table
+    /// if not given.
+    Blocks {
+        block_size: Option<BlockSize>,
+    },
     Segments,
+    /// Compacts the segments into ones of about `bytes_per_segment` compressed bytes, rather
+    /// than of the number of blocks per segment of the table.
+    BySize {
+        bytes_per_segment: u64,
+    },
+}
+
+impl CompactTarget {
+    /// The target size of the compacted segments, `None` if measured in blocks.
+    pub fn bytes_per_segment(&self) -> Option<u64> {
+        match self {
+            CompactTarget::BySize { bytes_per_segment } => Some(*bytes_per_segment),
+            _ => None,
+        }
+    }
+
+    /// The target size of the compacted blocks, `None` if of the table.
+    pub fn block_size(&self) -> Option<BlockSize> {
+        match self {
+            CompactTarget::Blocks { block_size } => *block_size,
+            _ => None,
+        }
+    }
+}
+
+/// The size of the blocks written by the block compaction, the blocks already of the size
+/// are kept as they are.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum BlockSize {
+    Rows(usize),
+    Bytes(usize),
 }
 
 pub enum AppendMode {
@@ -18,6 +18,7 @@ use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fmt::Display;
 use std::net::SocketAddr;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use std::time::SystemTime;
 
@@ -157,6 +158,7 @@ pub trait TableContext: Send + Sync {
     fn get_id(&self) -> String;
     fn get_current_catalog(&self) -> String;
     fn check_aborting(&self) -> Result<()>;
+    fn get_aborting(&self) -> Arc<AtomicBool>;
     fn get_error(&self) -> Option<ErrorCode>;
     fn push_warning(&self, warning: String);
     fn get_current_database(&self) -> String;
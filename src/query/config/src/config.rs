@@ -44,13 +44,23 @@ use databend_common_meta_app::storage::StorageS3Config as InnerStorageS3Config;
 use databend_common_meta_app::storage::StorageWebhdfsConfig as InnerStorageWebhdfsConfig;
 use databend_common_meta_app::tenant::TenantQuota;
 use databend_common_storage::StorageConfig as InnerStorageConfig;
+use databend_common_tracing::parse_json_field_order;
+use databend_common_tracing::parse_level_routes;
 use databend_common_tracing::Config as InnerLogConfig;
+use databend_common_tracing::DedupConfig as InnerDedupLogConfig;
 use databend_common_tracing::FileConfig as InnerFileLogConfig;
+use databend_common_tracing::LogTimezone;
 use databend_common_tracing::OTLPConfig as InnerOTLPLogConfig;
+use databend_common_tracing::OtlpProtocol;
 use databend_common_tracing::ProfileLogConfig as InnerProfileLogConfig;
 use databend_common_tracing::QueryLogConfig as InnerQueryLogConfig;
+use databend_common_tracing::RotationPolicy;
 use databend_common_tracing::StderrConfig as InnerStderrLogConfig;
 use databend_common_tracing::StructLogConfig as InnerStructLogConfig;
+use databend_common_tracing::SyslogConfig as InnerSyslogLogConfig;
+use databend_common_tracing::SyslogFacility;
+use databend_common_tracing::SyslogTransport;
+use databend_common_tracing::TimestampFormat;
 use databend_common_tracing::TracingConfig as InnerTracingConfig;
 use databend_common_users::idm_config::IDMConfig as InnerIDMConfig;
 use serde::Deserialize;
@@ -1874,6 +1884,9 @@ pub struct LogConfig {
     #[clap(flatten)]
     pub otlp: OTLPLogConfig,
 
+    #[clap(flatten)]
+    pub syslog: SyslogLogConfig,
+
     #[clap(flatten)]
     pub query: QueryLogConfig,
 
@@ -1885,6 +1898,13 @@ pub struct LogConfig {
 
     #[clap(flatten)]
     pub tracing: TracingConfig,
+
+    #[clap(flatten)]
+    pub dedup: DedupLogConfig,
+
+    /// Keys of the log key-values to write as `***`, case-insensitive, e.g. `password,secret_access_key`
+    #[clap(long = "log-redact-keys", value_name = "VALUE", value_delimiter = ',')]
+    pub redact_keys: Vec<String>,
 }
 
 impl Default for LogConfig {
@@ -1972,10 +1992,13 @@ impl TryInto<InnerLogConfig> for LogConfig {
             file,
             stderr: self.stderr.try_into()?,
             otlp,
+            syslog: self.syslog.try_into()?,
             query,
             profile,
             structlog,
             tracing,
+            dedup: self.dedup.try_into()?,
+            redact_keys: self.redact_keys.into_iter().collect(),
         })
     }
 }
@@ -1988,10 +2011,17 @@ impl From<InnerLogConfig> for LogConfig {
             file: inner.file.into(),
             stderr: inner.stderr.into(),
             otlp: inner.otlp.into(),
+            syslog: inner.syslog.into(),
             query: inner.query.into(),
             profile: inner.profile.into(),
             structlog: inner.structlog.into(),
             tracing: inner.tracing.into(),
+            dedup: inner.dedup.into(),
+            redact_keys: {
+                let mut keys: Vec<String> = inner.redact_keys.into_iter().collect();
+                keys.sort();
+                keys
+            },
 
             // Deprecated fields
             log_dir: None,
@@ -2023,7 +2053,7 @@ pub struct FileLogConfig {
     #[serde(rename = "dir")]
     pub file_dir: String,
 
-    /// Log file format
+    /// Log file format <text|json|ecs_json|bunyan>
     #[clap(long = "log-file-format", value_name = "VALUE", default_value = "json")]
     #[serde(rename = "format")]
     pub file_format: String,
@@ -2041,6 +2071,99 @@ pub struct FileLogConfig {
     )]
     #[serde(rename = "prefix_filter")]
     pub file_prefix_filter: String,
+
+    /// Log file rotation <hourly|daily|minutely|never|size:BYTES>
+    #[clap(
+        long = "log-file-rotation",
+        value_name = "VALUE",
+        default_value = "hourly"
+    )]
+    #[serde(rename = "rotation")]
+    pub file_rotation: String,
+
+    /// Gzip the log files once they are rotated out
+    #[clap(long = "log-file-compress", value_name = "VALUE", default_value = "false", action = ArgAction::Set, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    #[serde(rename = "compress")]
+    pub file_compress: bool,
+
+    /// Gzip level of the rotated log files <0-9>
+    #[clap(
+        long = "log-file-compression-level",
+        value_name = "VALUE",
+        default_value = "6"
+    )]
+    #[serde(rename = "compression_level")]
+    pub file_compression_level: u32,
+
+    /// Order of the top-level keys in json log lines, e.g. `level,timestamp`
+    #[clap(
+        long = "log-file-json-field-order",
+        value_name = "VALUE",
+        value_delimiter = ','
+    )]
+    #[serde(rename = "json_field_order")]
+    pub file_json_field_order: Vec<String>,
+
+    /// Target prefixes whose logs are written into a sub dir named after the prefix, e.g. `databend_common_storages_fuse`
+    #[clap(
+        long = "log-file-target-routes",
+        value_name = "VALUE",
+        value_delimiter = ','
+    )]
+    #[serde(rename = "target_routes")]
+    pub file_target_routes: Vec<String>,
+
+    /// Level bands whose logs are written into files named after the prefix, e.g. `warn:databend-query-errors` for the WARN and ERROR logs
+    #[clap(
+        long = "log-file-level-routes",
+        value_name = "VALUE",
+        value_delimiter = ','
+    )]
+    #[serde(rename = "level_routes")]
+    pub file_level_routes: Vec<String>,
+
+    /// Timezone of the log timestamps <UTC|local|+08:00>
+    #[clap(
+        long = "log-file-timezone",
+        value_name = "VALUE",
+        default_value = "UTC"
+    )]
+    #[serde(rename = "timezone")]
+    pub file_timezone: String,
+
+    /// Format of the log timestamps <rfc3339_micros|rfc3339_millis|epoch_millis|epoch_nanos>
+    #[clap(
+        long = "log-file-timestamp-format",
+        value_name = "VALUE",
+        default_value = "rfc3339_micros"
+    )]
+    #[serde(rename = "timestamp_format")]
+    pub file_timestamp_format: String,
+
+    /// Add the process id as `pid` to json log lines
+    #[clap(long = "log-file-json-pid", value_name = "VALUE", default_value = "false", action = ArgAction::Set, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    #[serde(rename = "json_pid")]
+    pub file_json_pid: bool,
+
+    /// Add the id of the logging thread as `tid` to json log lines
+    #[clap(long = "log-file-json-tid", value_name = "VALUE", default_value = "false", action = ArgAction::Set, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    #[serde(rename = "json_tid")]
+    pub file_json_tid: bool,
+
+    /// Truncate the log messages and key-values longer than it in bytes, 0 means unlimited
+    #[clap(
+        long = "log-file-max-message-bytes",
+        value_name = "VALUE",
+        default_value = "0"
+    )]
+    #[serde(rename = "max_message_bytes")]
+    pub file_max_message_bytes: usize,
+
+    /// Write a log with several key-values as the message line followed by one
+    /// `metric_name`/`metric_value` line per key-value, for text and json logs
+    #[clap(long = "log-file-flatten-kvs", value_name = "VALUE", default_value = "false", action = ArgAction::Set, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    #[serde(rename = "flatten_kvs")]
+    pub file_flatten_kvs: bool,
 }
 
 impl Default for FileLogConfig {
@@ -2053,6 +2176,23 @@ impl TryInto<InnerFileLogConfig> for FileLogConfig {
     type Error = ErrorCode;
 
     fn try_into(self) -> Result<InnerFileLogConfig> {
+        RotationPolicy::from_str(&self.file_rotation).map_err(ErrorCode::InvalidConfig)?;
+        parse_json_field_order(&self.file_json_field_order).map_err(ErrorCode::InvalidConfig)?;
+        parse_level_routes(&self.file_level_routes).map_err(ErrorCode::InvalidConfig)?;
+        if !self.file_level_routes.is_empty() && !self.file_target_routes.is_empty() {
+            return Err(ErrorCode::InvalidConfig(
+                "log.file.level_routes and log.file.target_routes can not be set at the same time",
+            ));
+        }
+        LogTimezone::from_str(&self.file_timezone).map_err(ErrorCode::InvalidConfig)?;
+        TimestampFormat::from_str(&self.file_timestamp_format).map_err(ErrorCode::InvalidConfig)?;
+        if self.file_compression_level > 9 {
+            return Err(ErrorCode::InvalidConfig(format!(
+                "log.file.compression_level must be between 0 and 9, got {}",
+                self.file_compression_level
+            )));
+        }
+
         Ok(InnerFileLogConfig {
             on: self.file_on,
             level: self.file_level,
@@ -2060,6 +2200,18 @@ impl TryInto<InnerFileLogConfig> for FileLogConfig {
             format: self.file_format,
             limit: self.file_limit,
             prefix_filter: self.file_prefix_filter,
+            rotation: self.file_rotation,
+            compress: self.file_compress,
+            compression_level: self.file_compression_level,
+            json_field_order: self.file_json_field_order,
+            target_routes: self.file_target_routes,
+            level_routes: self.file_level_routes,
+            timezone: self.file_timezone,
+            timestamp_format: self.file_timestamp_format,
+            json_pid: self.file_json_pid,
+            json_tid: self.file_json_tid,
+            max_message_bytes: self.file_max_message_bytes,
+            flatten_kvs: self.file_flatten_kvs,
         })
     }
 }
@@ -2073,6 +2225,18 @@ impl From<InnerFileLogConfig> for FileLogConfig {
             file_format: inner.format,
             file_limit: inner.limit,
             file_prefix_filter: inner.prefix_filter,
+            file_rotation: inner.rotation,
+            file_compress: inner.compress,
+            file_compression_level: inner.compression_level,
+            file_json_field_order: inner.json_field_order,
+            file_target_routes: inner.target_routes,
+            file_level_routes: inner.level_routes,
+            file_timezone: inner.timezone,
+            file_timestamp_format: inner.timestamp_format,
+            file_json_pid: inner.json_pid,
+            file_json_tid: inner.json_tid,
+            file_max_message_bytes: inner.max_message_bytes,
+            file_flatten_kvs: inner.flatten_kvs,
         }
     }
 }
@@ -2151,10 +2315,64 @@ pub struct OTLPLogConfig {
     #[serde(rename = "endpoint")]
     pub otlp_endpoint: String,
 
+    /// Log OpenTelemetry OTLP protocol <grpc|http/protobuf>
+    #[clap(
+        long = "log-otlp-protocol",
+        value_name = "VALUE",
+        default_value = "grpc"
+    )]
+    #[serde(rename = "protocol")]
+    pub otlp_protocol: String,
+
     /// Log Labels
     #[clap(skip)]
     #[serde(rename = "labels")]
     pub otlp_labels: BTreeMap<String, String>,
+
+    /// Timeout in seconds of exporting one batch of logs
+    #[clap(
+        long = "log-otlp-timeout-secs",
+        value_name = "VALUE",
+        default_value = "10"
+    )]
+    #[serde(rename = "timeout_secs")]
+    pub otlp_timeout_secs: u64,
+
+    /// Max number of logs waiting to be exported, the logs beyond are dropped
+    #[clap(
+        long = "log-otlp-max-queue-size",
+        value_name = "VALUE",
+        default_value = "2048"
+    )]
+    #[serde(rename = "max_queue_size")]
+    pub otlp_max_queue_size: usize,
+
+    /// Delay in milliseconds between two consecutive exports
+    #[clap(
+        long = "log-otlp-scheduled-delay-ms",
+        value_name = "VALUE",
+        default_value = "1000"
+    )]
+    #[serde(rename = "scheduled_delay_ms")]
+    pub otlp_scheduled_delay_ms: u64,
+
+    /// Number of retries of a failed export before the logs are dropped
+    #[clap(
+        long = "log-otlp-max-retries",
+        value_name = "VALUE",
+        default_value = "3"
+    )]
+    #[serde(rename = "max_retries")]
+    pub otlp_max_retries: u32,
+
+    /// Delay in milliseconds before the first retry, doubled on each retry
+    #[clap(
+        long = "log-otlp-retry-base-delay-ms",
+        value_name = "VALUE",
+        default_value = "200"
+    )]
+    #[serde(rename = "retry_base_delay_ms")]
+    pub otlp_retry_base_delay_ms: u64,
 }
 
 impl Default for OTLPLogConfig {
@@ -2167,11 +2385,30 @@ impl TryInto<InnerOTLPLogConfig> for OTLPLogConfig {
     type Error = ErrorCode;
 
     fn try_into(self) -> Result<InnerOTLPLogConfig> {
+        let protocol =
+            OtlpProtocol::from_str(&self.otlp_protocol).map_err(ErrorCode::InvalidConfig)?;
+        if self.otlp_on {
+            protocol
+                .check_endpoint(&self.otlp_endpoint)
+                .map_err(ErrorCode::InvalidConfig)?;
+        }
+        if self.otlp_max_queue_size == 0 {
+            return Err(ErrorCode::InvalidConfig(
+                "log.otlp.max_queue_size must be greater than 0",
+            ));
+        }
+
         Ok(InnerOTLPLogConfig {
             on: self.otlp_on,
             level: self.otlp_level,
             endpoint: self.otlp_endpoint,
+            protocol: self.otlp_protocol,
             labels: self.otlp_labels,
+            timeout_secs: self.otlp_timeout_secs,
+            max_queue_size: self.otlp_max_queue_size,
+            scheduled_delay_ms: self.otlp_scheduled_delay_ms,
+            max_retries: self.otlp_max_retries,
+            retry_base_delay_ms: self.otlp_retry_base_delay_ms,
         })
     }
 }
@@ -2182,7 +2419,84 @@ impl From<InnerOTLPLogConfig> for OTLPLogConfig {
             otlp_on: inner.on,
             otlp_level: inner.level,
             otlp_endpoint: inner.endpoint,
+            otlp_protocol: inner.protocol,
             otlp_labels: inner.labels,
+            otlp_timeout_secs: inner.timeout_secs,
+            otlp_max_queue_size: inner.max_queue_size,
+            otlp_scheduled_delay_ms: inner.scheduled_delay_ms,
+            otlp_max_retries: inner.max_retries,
+            otlp_retry_base_delay_ms: inner.retry_base_delay_ms,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Args)]
+#[serde(default)]
+pub struct SyslogLogConfig {
+    #[clap(long = "log-syslog-on", value_name = "VALUE", default_value = "false", action = ArgAction::Set, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    #[serde(rename = "on")]
+    pub syslog_on: bool,
+
+    /// Log level <DEBUG|INFO|WARN|ERROR>
+    #[clap(
+        long = "log-syslog-level",
+        value_name = "VALUE",
+        default_value = "INFO"
+    )]
+    #[serde(rename = "level")]
+    pub syslog_level: String,
+
+    /// Syslog endpoint <udp://host:port|tcp://host:port|unix://path>
+    #[clap(
+        long = "log-syslog-endpoint",
+        value_name = "VALUE",
+        default_value = "unix:///dev/log"
+    )]
+    #[serde(rename = "endpoint")]
+    pub syslog_endpoint: String,
+
+    /// Syslog facility <kern|user|daemon|local0-local7>
+    #[clap(
+        long = "log-syslog-facility",
+        value_name = "VALUE",
+        default_value = "user"
+    )]
+    #[serde(rename = "facility")]
+    pub syslog_facility: String,
+}
+
+impl Default for SyslogLogConfig {
+    fn default() -> Self {
+        InnerSyslogLogConfig::default().into()
+    }
+}
+
+impl TryInto<InnerSyslogLogConfig> for SyslogLogConfig {
+    type Error = ErrorCode;
+
+    fn try_into(self) -> Result<InnerSyslogLogConfig> {
+        SyslogFacility::from_str(&self.syslog_facility).map_err(ErrorCode::InvalidConfig)?;
+        // resolves the host of the endpoint, only checked if it is used.
+        if self.syslog_on {
+            SyslogTransport::from_str(&self.syslog_endpoint).map_err(ErrorCode::InvalidConfig)?;
+        }
+
+        Ok(InnerSyslogLogConfig {
+            on: self.syslog_on,
+            level: self.syslog_level,
+            endpoint: self.syslog_endpoint,
+            facility: self.syslog_facility,
+        })
+    }
+}
+
+impl From<InnerSyslogLogConfig> for SyslogLogConfig {
+    fn from(inner: InnerSyslogLogConfig) -> Self {
+        Self {
+            syslog_on: inner.on,
+            syslog_level: inner.level,
+            syslog_endpoint: inner.endpoint,
+            syslog_facility: inner.facility,
         }
     }
 }
@@ -2208,6 +2522,15 @@ pub struct QueryLogConfig {
     #[serde(rename = "otlp_endpoint")]
     pub log_query_otlp_endpoint: String,
 
+    /// Query Log OpenTelemetry OTLP protocol <grpc|http/protobuf>
+    #[clap(
+        long = "log-query-otlp-protocol",
+        value_name = "VALUE",
+        default_value = "grpc"
+    )]
+    #[serde(rename = "otlp_protocol")]
+    pub log_query_otlp_protocol: String,
+
     /// Query Log Labels
     #[clap(skip)]
     #[serde(rename = "labels")]
@@ -2224,10 +2547,19 @@ impl TryInto<InnerQueryLogConfig> for QueryLogConfig {
     type Error = ErrorCode;
 
     fn try_into(self) -> Result<InnerQueryLogConfig> {
+        let protocol = OtlpProtocol::from_str(&self.log_query_otlp_protocol)
+            .map_err(ErrorCode::InvalidConfig)?;
+        if !self.log_query_otlp_endpoint.is_empty() {
+            protocol
+                .check_endpoint(&self.log_query_otlp_endpoint)
+                .map_err(ErrorCode::InvalidConfig)?;
+        }
+
         Ok(InnerQueryLogConfig {
             on: self.log_query_on,
             dir: self.log_query_dir,
             otlp_endpoint: self.log_query_otlp_endpoint,
+            otlp_protocol: self.log_query_otlp_protocol,
             labels: self.log_query_otlp_labels,
         })
     }
@@ -2239,6 +2571,7 @@ impl From<InnerQueryLogConfig> for QueryLogConfig {
             log_query_on: inner.on,
             log_query_dir: inner.dir,
             log_query_otlp_endpoint: inner.otlp_endpoint,
+            log_query_otlp_protocol: inner.otlp_protocol,
             log_query_otlp_labels: inner.labels,
         }
     }
@@ -2265,6 +2598,15 @@ pub struct ProfileLogConfig {
     #[serde(rename = "otlp_endpoint")]
     pub log_profile_otlp_endpoint: String,
 
+    /// Profile Log OpenTelemetry OTLP protocol <grpc|http/protobuf>
+    #[clap(
+        long = "log-profile-otlp-protocol",
+        value_name = "VALUE",
+        default_value = "grpc"
+    )]
+    #[serde(rename = "otlp_protocol")]
+    pub log_profile_otlp_protocol: String,
+
     /// Profile Log Labels
     #[clap(skip)]
     #[serde(rename = "labels")]
@@ -2281,10 +2623,19 @@ impl TryInto<InnerProfileLogConfig> for ProfileLogConfig {
     type Error = ErrorCode;
 
     fn try_into(self) -> Result<InnerProfileLogConfig> {
+        let protocol = OtlpProtocol::from_str(&self.log_profile_otlp_protocol)
+            .map_err(ErrorCode::InvalidConfig)?;
+        if !self.log_profile_otlp_endpoint.is_empty() {
+            protocol
+                .check_endpoint(&self.log_profile_otlp_endpoint)
+                .map_err(ErrorCode::InvalidConfig)?;
+        }
+
         Ok(InnerProfileLogConfig {
             on: self.log_profile_on,
             dir: self.log_profile_dir,
             otlp_endpoint: self.log_profile_otlp_endpoint,
+            otlp_protocol: self.log_profile_otlp_protocol,
             labels: self.log_profile_otlp_labels,
         })
     }
@@ -2296,6 +2647,7 @@ impl From<InnerProfileLogConfig> for ProfileLogConfig {
             log_profile_on: inner.on,
             log_profile_dir: inner.dir,
             log_profile_otlp_endpoint: inner.otlp_endpoint,
+            log_profile_otlp_protocol: inner.otlp_protocol,
             log_profile_otlp_labels: inner.labels,
         }
     }
@@ -2394,6 +2746,71 @@ impl From<InnerTracingConfig> for TracingConfig {
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Args)]
+#[serde(default)]
+pub struct DedupLogConfig {
+    #[clap(long = "log-dedup-on", value_name = "VALUE", default_value = "false", action = ArgAction::Set, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    #[serde(rename = "on")]
+    pub log_dedup_on: bool,
+
+    /// Sliding window in milliseconds within which the repeated log lines are collapsed
+    #[clap(
+        long = "log-dedup-window-ms",
+        value_name = "VALUE",
+        default_value = "1000"
+    )]
+    #[serde(rename = "window_ms")]
+    pub log_dedup_window_ms: u64,
+
+    /// Number of occurrences of a log line logged within a window before it is collapsed
+    #[clap(
+        long = "log-dedup-max-repeats",
+        value_name = "VALUE",
+        default_value = "1"
+    )]
+    #[serde(rename = "max_repeats")]
+    pub log_dedup_max_repeats: u64,
+}
+
+impl Default for DedupLogConfig {
+    fn default() -> Self {
+        InnerDedupLogConfig::default().into()
+    }
+}
+
+impl TryInto<InnerDedupLogConfig> for DedupLogConfig {
+    type Error = ErrorCode;
+
+    fn try_into(self) -> Result<InnerDedupLogConfig> {
+        if self.log_dedup_on && self.log_dedup_window_ms == 0 {
+            return Err(ErrorCode::InvalidConfig(
+                "log.dedup.window_ms must be greater than 0",
+            ));
+        }
+        if self.log_dedup_on && self.log_dedup_max_repeats == 0 {
+            return Err(ErrorCode::InvalidConfig(
+                "log.dedup.max_repeats must be greater than 0",
+            ));
+        }
+
+        Ok(InnerDedupLogConfig {
+            on: self.log_dedup_on,
+            window_ms: self.log_dedup_window_ms,
+            max_repeats: self.log_dedup_max_repeats,
+        })
+    }
+}
+
+impl From<InnerDedupLogConfig> for DedupLogConfig {
+    fn from(inner: InnerDedupLogConfig) -> Self {
+        Self {
+            log_dedup_on: inner.on,
+            log_dedup_window_ms: inner.window_ms,
+            log_dedup_max_repeats: inner.max_repeats,
+        }
+    }
+}
+
 /// Meta config group.
 /// deny_unknown_fields to check unknown field, like the deprecated `address`.
 /// TODO(xuanwo): All meta_xxx should be rename to xxx.
@@ -52,6 +52,21 @@ pub fn register(registry: &mut FunctionRegistry) {
         registry.register_additional_cast_rules(func_name, CAST_FROM_VARIANT_RULES());
     }
 
+    // The numbers, dates and timestamps are hashed by their text, e.g. `sha2(id, 256)`.
+    registry.register_additional_cast_rules("sha2", GENERAL_CAST_RULES.iter().cloned());
+    registry.register_additional_cast_rules("sha2", CAST_FROM_STRING_RULES.iter().cloned());
+    registry.register_additional_cast_rules("sha2", CAST_FROM_VARIANT_RULES());
+    registry.register_additional_cast_rules(
+        "sha2",
+        ALL_NUMERICS_TYPES
+            .iter()
+            .map(|ty| (DataType::Number(*ty), DataType::String)),
+    );
+    registry.register_additional_cast_rules("sha2", [
+        (DataType::Date, DataType::String),
+        (DataType::Timestamp, DataType::String),
+    ]);
+
     // Timestamp/Date --> other ints and floats
     // Now it only overload 'to_int64'
     for data_type in ALL_NUMERICS_TYPES
@@ -17,39 +17,57 @@
 use std::collections::hash_map::DefaultHasher;
 use std::hash::Hash;
 use std::hash::Hasher;
+use std::sync::Arc;
 
+use borsh::BorshSerialize;
 use databend_common_expression::types::decimal::DecimalType;
 use databend_common_expression::types::number::NumberScalar;
 use databend_common_expression::types::number::F32;
 use databend_common_expression::types::number::F64;
+use databend_common_expression::types::AnyType;
 use databend_common_expression::types::ArgType;
+use databend_common_expression::types::BinaryType;
 use databend_common_expression::types::BooleanType;
+use databend_common_expression::types::DataType;
 use databend_common_expression::types::DateType;
 use databend_common_expression::types::NumberClass;
 use databend_common_expression::types::NumberDataType;
 use databend_common_expression::types::NumberType;
 use databend_common_expression::types::StringType;
 use databend_common_expression::types::TimestampType;
+use databend_common_expression::types::UInt64Type;
 use databend_common_expression::types::VariantType;
 use databend_common_expression::types::ALL_INTEGER_TYPES;
 use databend_common_expression::types::ALL_NUMBER_CLASSES;
 use databend_common_expression::vectorize_with_builder_1_arg;
 use databend_common_expression::vectorize_with_builder_2_arg;
+use databend_common_expression::vectorize_with_builder_3_arg;
 use databend_common_expression::with_integer_mapped_type;
 use databend_common_expression::with_number_mapped_type;
+use databend_common_expression::EvalContext;
+use databend_common_expression::FromData;
+use databend_common_expression::Function;
 use databend_common_expression::FunctionDomain;
+use databend_common_expression::FunctionEval;
 use databend_common_expression::FunctionRegistry;
+use databend_common_expression::FunctionSignature;
 use databend_common_expression::Scalar;
+use databend_common_expression::Value;
+use databend_common_expression::ValueRef;
 use ethnum::i256;
+use hmac::Hmac;
+use hmac::Mac;
 use md5::Digest;
 use md5::Md5 as Md5Hasher;
 use naive_cityhash::cityhash64_with_seed;
-use num_traits::AsPrimitive;
 use twox_hash::XxHash32;
 use twox_hash::XxHash64;
 
 use crate::scalars::string::vectorize_string_to_string;
 
+/// Upper bound of the output length of `blake3` in bytes, to keep a row from blowing up.
+const BLAKE3_MAX_OUTPUT_LEN: u64 = 1024;
+
 pub fn register(registry: &mut FunctionRegistry) {
     registry.register_aliases("siphash64", &["siphash"]);
     registry.register_aliases("sha", &["sha1"]);
@@ -120,7 +138,7 @@ pub fn register(registry: &mut FunctionRegistry) {
         "blake3",
         |_, _| FunctionDomain::MayThrow,
         vectorize_string_to_string(
-            |col| col.data().len() * 64,
+            |col| col.len() * 64,
             |val, output, ctx| {
                 let old_len = output.data.len();
                 output.data.resize(old_len + 64, 0);
@@ -135,49 +153,412 @@ pub fn register(registry: &mut FunctionRegistry) {
         ),
     );
 
+    // BLAKE3 is an extendable-output function, the digest can be of any length.
+    registry.register_passthrough_nullable_2_arg::<StringType, NumberType<u64>, StringType, _, _>(
+        "blake3",
+        |_, _, _| FunctionDomain::MayThrow,
+        vectorize_with_builder_2_arg::<StringType, NumberType<u64>, StringType>(
+            |val, len, output, ctx| {
+                match blake3_xof_hex(val, len) {
+                    Ok(res) => output.put_str(&res),
+                    Err(err) => ctx.set_error(output.len(), err),
+                }
+                output.commit_row();
+            },
+        ),
+    );
+
     registry.register_passthrough_nullable_2_arg::<StringType, NumberType<u64>, StringType, _, _>(
         "sha2",
         |_, _, _| FunctionDomain::MayThrow,
         vectorize_with_builder_2_arg::<StringType, NumberType<u64>, StringType>(
             |val, l, output, ctx| {
-                let l: u64 = l.as_();
-                let res = match l {
-                    224 => {
-                        let mut h = sha2::Sha224::new();
-                        sha2::digest::Update::update(&mut h, val.as_bytes());
-                        format!("{:x}", h.finalize())
-                    }
-                    256 | 0 => {
-                        let mut h = sha2::Sha256::new();
-                        sha2::digest::Update::update(&mut h, val.as_bytes());
-                        format!("{:x}", h.finalize())
-                    }
-                    384 => {
-                        let mut h = sha2::Sha384::new();
-                        sha2::digest::Update::update(&mut h, val.as_bytes());
-                        format!("{:x}", h.finalize())
+                match sha2_hex(val, l) {
+                    Ok(res) => output.put_str(&res),
+                    Err(err) => ctx.set_error(output.len(), err),
+                }
+                output.commit_row();
+            },
+        ),
+    );
+
+    registry.register_passthrough_nullable_3_arg::<StringType, NumberType<u64>, NumberType<u64>, StringType, _, _>(
+        "sha2",
+        |_, _, _, _| FunctionDomain::MayThrow,
+        vectorize_with_builder_3_arg::<StringType, NumberType<u64>, NumberType<u64>, StringType>(
+            |val, l, truncation, output, ctx| {
+                match sha2_hex(val, l) {
+                    // the digest is in hex, so all characters are single bytes.
+                    Ok(res) if truncation >= 1 && truncation as usize <= res.len() => {
+                        output.put_str(&res[..truncation as usize])
                     }
-                    512 => {
-                        let mut h = sha2::Sha512::new();
-                        sha2::digest::Update::update(&mut h, val.as_bytes());
-                        format!("{:x}", h.finalize())
+                    Ok(res) => ctx.set_error(
+                        output.len(),
+                        format!(
+                            "Expected sha2 truncation length between 1 and {}, but got {}",
+                            res.len(),
+                            truncation
+                        ),
+                    ),
+                    Err(err) => ctx.set_error(output.len(), err),
+                }
+                output.commit_row();
+            },
+        ),
+    );
+
+    // some systems compare the digests case-sensitively against uppercase ones.
+    registry.register_passthrough_nullable_3_arg::<StringType, NumberType<u64>, BooleanType, StringType, _, _>(
+        "sha2",
+        |_, _, _, _| FunctionDomain::MayThrow,
+        vectorize_with_builder_3_arg::<StringType, NumberType<u64>, BooleanType, StringType>(
+            |val, l, uppercase, output, ctx| {
+                match sha2_digest(val, l) {
+                    Ok(res) if uppercase => output.put_str(&hex::encode_upper(res)),
+                    Ok(res) => output.put_str(&hex::encode(res)),
+                    Err(err) => ctx.set_error(output.len(), err),
+                }
+                output.commit_row();
+            },
+        ),
+    );
+
+    registry.register_passthrough_nullable_2_arg::<StringType, NumberType<u64>, BinaryType, _, _>(
+        "sha2_binary",
+        |_, _, _| FunctionDomain::MayThrow,
+        vectorize_with_builder_2_arg::<StringType, NumberType<u64>, BinaryType>(
+            |val, l, output, ctx| {
+                match sha2_digest(val, l) {
+                    Ok(res) => output.put_slice(&res),
+                    Err(err) => ctx.set_error(output.len(), err),
+                }
+                output.commit_row();
+            },
+        ),
+    );
+
+    registry
+        .register_passthrough_nullable_2_arg::<StringType, NumberType<u64>, NumberType<u32>, _, _>(
+            "hash_bucket",
+            |_, _, _| FunctionDomain::MayThrow,
+            vectorize_with_builder_2_arg::<StringType, NumberType<u64>, NumberType<u32>>(
+                |val, num_buckets, output, ctx| match hash_bucket(val, num_buckets) {
+                    Ok(bucket) => output.push(bucket),
+                    Err(err) => {
+                        ctx.set_error(output.len(), err);
+                        output.push(0);
                     }
-                    v => {
-                        ctx.set_error(
-                            output.len(),
-                            format!(
-                                "Expected [0, 224, 256, 384, 512] as sha2 encode options, but got {}",
-                                v
-                            ),
-                        );
-                        String::new()
-                    },
-                };
-                output.put_str(&res);
+                },
+            ),
+        );
+
+    registry.register_passthrough_nullable_2_arg::<StringType, NumberType<u64>, StringType, _, _>(
+        "sha3",
+        |_, _, _| FunctionDomain::MayThrow,
+        vectorize_with_builder_2_arg::<StringType, NumberType<u64>, StringType>(
+            |val, l, output, ctx| {
+                match sha3_hex(val, l) {
+                    Ok(res) => output.put_str(&res),
+                    Err(err) => ctx.set_error(output.len(), err),
+                }
+                output.commit_row();
+            },
+        ),
+    );
+
+    // the original Keccak padding, as used by Ethereum, differs from the one of SHA-3.
+    registry.register_passthrough_nullable_1_arg::<StringType, StringType, _, _>(
+        "keccak256",
+        |_, _| FunctionDomain::Full,
+        vectorize_string_to_string(
+            |col| col.len() * 64,
+            |val, output, _| {
+                output.put_str(&hex::encode(sha3::Keccak256::digest(val)));
                 output.commit_row();
             },
         ),
     );
+
+    registry.register_passthrough_nullable_3_arg::<StringType, StringType, NumberType<u64>, StringType, _, _>(
+        "hmac_sha2",
+        |_, _, _, _| FunctionDomain::MayThrow,
+        |val, key, l, ctx| match (key, l) {
+            // a constant key is hashed into the HMAC state once, then reused for every row.
+            (ValueRef::Scalar(key), ValueRef::Scalar(224)) => {
+                hmac_hex_with_const_key::<Hmac<sha2::Sha224>>(val, key, ctx)
+            }
+            (ValueRef::Scalar(key), ValueRef::Scalar(256 | 0)) => {
+                hmac_hex_with_const_key::<Hmac<sha2::Sha256>>(val, key, ctx)
+            }
+            (ValueRef::Scalar(key), ValueRef::Scalar(384)) => {
+                hmac_hex_with_const_key::<Hmac<sha2::Sha384>>(val, key, ctx)
+            }
+            (ValueRef::Scalar(key), ValueRef::Scalar(512)) => {
+                hmac_hex_with_const_key::<Hmac<sha2::Sha512>>(val, key, ctx)
+            }
+            _ => vectorize_with_builder_3_arg::<StringType, StringType, NumberType<u64>, StringType>(
+                |val, key, l, output, ctx| {
+                    match hmac_sha2_hex(val, key, l) {
+                        Ok(res) => output.put_str(&res),
+                        Err(err) => ctx.set_error(output.len(), err),
+                    }
+                    output.commit_row();
+                },
+            )(val, key, l, ctx),
+        },
+    );
+
+    registry.register_passthrough_nullable_2_arg::<StringType, StringType, StringType, _, _>(
+        "hmac_sha256",
+        |_, _, _| FunctionDomain::Full,
+        |val, key, ctx| match key {
+            // a constant key is hashed into the HMAC state once, then reused for every row.
+            ValueRef::Scalar(key) => hmac_hex_with_const_key::<Hmac<sha2::Sha256>>(val, key, ctx),
+            ValueRef::Column(_) => {
+                vectorize_with_builder_2_arg::<StringType, StringType, StringType>(
+                    |val, key, output, _| {
+                        output.put_str(&hmac_sha2_hex(val, key, 256).unwrap());
+                        output.commit_row();
+                    },
+                )(val, key, ctx)
+            }
+        },
+    );
+
+    // different seeds give independent hashes of the same keys, e.g. to shard them twice.
+    registry
+        .register_passthrough_nullable_2_arg::<StringType, NumberType<u64>, NumberType<u64>, _, _>(
+            "xxhash64",
+            |_, _, _| FunctionDomain::Full,
+            vectorize_with_builder_2_arg::<StringType, NumberType<u64>, NumberType<u64>>(
+                |val, seed, output, _| {
+                    let mut hasher = XxHash64::with_seed(seed);
+                    DFHash::hash(val, &mut hasher);
+                    output.push(hasher.finish());
+                },
+            ),
+        );
+
+    // the arguments of any types are combined into one hash, e.g. to shard by several columns.
+    registry.register_function_factory("murmur3", |_, args_type| {
+        if args_type.is_empty() {
+            return None;
+        }
+        Some(Arc::new(Function {
+            signature: FunctionSignature {
+                name: "murmur3".to_string(),
+                args_type: args_type.to_vec(),
+                return_type: DataType::Number(NumberDataType::UInt64),
+            },
+            eval: FunctionEval::Scalar {
+                calc_domain: Box::new(|_, _| FunctionDomain::Full),
+                eval: Box::new(murmur3_fn),
+            },
+        }))
+    });
+}
+
+/// Hashes the borsh serialized values of the arguments of each row together, so that the hash
+/// is stable across versions and nodes.
+fn murmur3_fn(args: &[ValueRef<AnyType>], _: &mut EvalContext) -> Value<AnyType> {
+    let len = args.iter().find_map(|arg| match arg {
+        ValueRef::Column(col) => Some(col.len()),
+        _ => None,
+    });
+
+    let size = len.unwrap_or(1);
+    let mut hashes = Vec::with_capacity(size);
+    let mut buf = Vec::new();
+    for idx in 0..size {
+        buf.clear();
+        for arg in args {
+            let val = unsafe { arg.index_unchecked(idx) };
+            // writing into a `Vec` never fails.
+            val.to_owned().serialize(&mut buf).unwrap();
+        }
+        hashes.push(murmur3_x64_64(&buf));
+    }
+
+    match len {
+        Some(_) => Value::Column(UInt64Type::from_data(hashes)),
+        _ => Value::Scalar(Scalar::Number(NumberScalar::UInt64(hashes[0]))),
+    }
+}
+
+/// The first 64 bits of the 128-bit MurmurHash3 of `data` for x64, with the seed 0.
+fn murmur3_x64_64(data: &[u8]) -> u64 {
+    const C1: u64 = 0x87c3_7b91_1142_53d5;
+    const C2: u64 = 0x4cf5_ad43_2745_937f;
+    let mix_k1 = |k1: u64| k1.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2);
+    let mix_k2 = |k2: u64| k2.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1);
+
+    let (mut h1, mut h2) = (0u64, 0u64);
+    let mut blocks = data.chunks_exact(16);
+    for block in &mut blocks {
+        let k1 = u64::from_le_bytes(block[..8].try_into().unwrap());
+        let k2 = u64::from_le_bytes(block[8..].try_into().unwrap());
+        h1 ^= mix_k1(k1);
+        h1 = h1
+            .rotate_left(27)
+            .wrapping_add(h2)
+            .wrapping_mul(5)
+            .wrapping_add(0x52dc_e729);
+        h2 ^= mix_k2(k2);
+        h2 = h2
+            .rotate_left(31)
+            .wrapping_add(h1)
+            .wrapping_mul(5)
+            .wrapping_add(0x3849_5ab5);
+    }
+
+    let tail = blocks.remainder();
+    let (mut k1, mut k2) = (0u64, 0u64);
+    for (i, byte) in tail.iter().enumerate() {
+        match i {
+            0..=7 => k1 |= (*byte as u64) << (8 * i),
+            _ => k2 |= (*byte as u64) << (8 * (i - 8)),
+        }
+    }
+    if tail.len() > 8 {
+        h2 ^= mix_k2(k2);
+    }
+    if !tail.is_empty() {
+        h1 ^= mix_k1(k1);
+    }
+
+    h1 ^= data.len() as u64;
+    h2 ^= data.len() as u64;
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    h1 = fmix64(h1);
+    h2 = fmix64(h2);
+    h1.wrapping_add(h2)
+}
+
+fn fmix64(mut k: u64) -> u64 {
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    k ^= k >> 33;
+    k
+}
+
+/// Hex HMAC of `val` with `key`, by the SHA-2 function of `l` bits, 0 means 256.
+fn hmac_sha2_hex(val: &str, key: &str, l: u64) -> Result<String, String> {
+    fn hmac_hex<M: Mac + hmac::digest::KeyInit>(val: &str, key: &str) -> String {
+        // HMAC takes keys of any length.
+        let mut mac = <M as Mac>::new_from_slice(key.as_bytes()).unwrap();
+        mac.update(val.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    match l {
+        224 => Ok(hmac_hex::<Hmac<sha2::Sha224>>(val, key)),
+        256 | 0 => Ok(hmac_hex::<Hmac<sha2::Sha256>>(val, key)),
+        384 => Ok(hmac_hex::<Hmac<sha2::Sha384>>(val, key)),
+        512 => Ok(hmac_hex::<Hmac<sha2::Sha512>>(val, key)),
+        v => Err(format!(
+            "Expected [0, 224, 256, 384, 512] as hmac_sha2 encode options, but got {}",
+            v
+        )),
+    }
+}
+
+/// Hex HMACs of the rows of `val` with the constant `key`, the state of the key is cloned for
+/// each row instead of being built again.
+fn hmac_hex_with_const_key<M: Mac + hmac::digest::KeyInit + Clone>(
+    val: ValueRef<StringType>,
+    key: &str,
+    ctx: &mut EvalContext,
+) -> Value<StringType> {
+    // HMAC takes keys of any length.
+    let mac = <M as Mac>::new_from_slice(key.as_bytes()).unwrap();
+    vectorize_with_builder_1_arg::<StringType, StringType>(|val, output, _| {
+        let mut mac = mac.clone();
+        mac.update(val.as_bytes());
+        output.put_str(&hex::encode(mac.finalize().into_bytes()));
+        output.commit_row();
+    })(val, ctx)
+}
+
+/// Hex digest of `val` by the SHA-2 function of `l` bits, 0 means 256.
+fn sha2_hex(val: &str, l: u64) -> Result<String, String> {
+    sha2_digest(val, l).map(hex::encode)
+}
+
+/// Digest of `val` by the SHA-2 function of `l` bits, 0 means 256.
+fn sha2_digest(val: &str, l: u64) -> Result<Vec<u8>, String> {
+    match l {
+        224 => {
+            let mut h = sha2::Sha224::new();
+            sha2::digest::Update::update(&mut h, val.as_bytes());
+            Ok(h.finalize().to_vec())
+        }
+        256 | 0 => {
+            let mut h = sha2::Sha256::new();
+            sha2::digest::Update::update(&mut h, val.as_bytes());
+            Ok(h.finalize().to_vec())
+        }
+        384 => {
+            let mut h = sha2::Sha384::new();
+            sha2::digest::Update::update(&mut h, val.as_bytes());
+            Ok(h.finalize().to_vec())
+        }
+        512 => {
+            let mut h = sha2::Sha512::new();
+            sha2::digest::Update::update(&mut h, val.as_bytes());
+            Ok(h.finalize().to_vec())
+        }
+        v => Err(format!(
+            "Expected [0, 224, 256, 384, 512] as sha2 encode options, but got {}",
+            v
+        )),
+    }
+}
+
+/// Hex digest of `val` by BLAKE3 of `len` bytes, the first 32 bytes are the same as `blake3(val)`.
+fn blake3_xof_hex(val: &str, len: u64) -> Result<String, String> {
+    if len == 0 || len > BLAKE3_MAX_OUTPUT_LEN {
+        return Err(format!(
+            "Expected blake3 output length between 1 and {}, but got {}",
+            BLAKE3_MAX_OUTPUT_LEN, len
+        ));
+    }
+    let mut digest = vec![0; len as usize];
+    blake3::Hasher::new()
+        .update(val.as_bytes())
+        .finalize_xof()
+        .fill(&mut digest);
+    Ok(hex::encode(digest))
+}
+
+/// The bucket of `val` in `[0, num_buckets)`, from the high 64 bits of its SHA-256 digest.
+fn hash_bucket(val: &str, num_buckets: u64) -> Result<u32, String> {
+    if num_buckets == 0 || num_buckets > u32::MAX as u64 {
+        return Err(format!(
+            "Expected the number of buckets between 1 and {}, but got {}",
+            u32::MAX,
+            num_buckets
+        ));
+    }
+    let digest = sha2::Sha256::digest(val.as_bytes());
+    let high = u64::from_be_bytes(digest[..8].try_into().unwrap());
+    Ok((high % num_buckets) as u32)
+}
+
+/// Hex digest of `val` by the SHA-3 function of `l` bits, 0 is taken as 256 like `sha2`.
+fn sha3_hex(val: &str, l: u64) -> Result<String, String> {
+    match l {
+        224 => Ok(hex::encode(sha3::Sha3_224::digest(val))),
+        0 | 256 => Ok(hex::encode(sha3::Sha3_256::digest(val))),
+        384 => Ok(hex::encode(sha3::Sha3_384::digest(val))),
+        512 => Ok(hex::encode(sha3::Sha3_512::digest(val))),
+        v => Err(format!(
+            "Expected [0, 224, 256, 384, 512] as sha3 encode options, but got {}",
+            v
+        )),
+    }
 }
 
 fn register_simple_domain_type_hash<T: ArgType>(registry: &mut FunctionRegistry)
@@ -19,6 +19,7 @@ use std::marker::PhantomData;
 
 use databend_common_expression::types::number::SimpleDomain;
 use databend_common_expression::types::number::F64;
+use databend_common_expression::types::BinaryType;
 use databend_common_expression::types::NumberDataType;
 use databend_common_expression::types::NumberType;
 use databend_common_expression::types::StringType;
@@ -212,6 +213,25 @@ pub fn register(registry: &mut FunctionRegistry) {
         |val, _| crc32fast::hash(val.as_bytes()),
     );
 
+    registry.register_1_arg::<BinaryType, NumberType<u32>, _, _>(
+        "crc32",
+        |_, _| FunctionDomain::Full,
+        |val, _| crc32fast::hash(val),
+    );
+
+    // the Castagnoli polynomial, as used by iSCSI, ext4 and the checksums of Kafka.
+    registry.register_1_arg::<StringType, NumberType<u32>, _, _>(
+        "crc32c",
+        |_, _| FunctionDomain::Full,
+        |val, _| crc32c::crc32c(val.as_bytes()),
+    );
+
+    registry.register_1_arg::<BinaryType, NumberType<u32>, _, _>(
+        "crc32c",
+        |_, _| FunctionDomain::Full,
+        |val, _| crc32c::crc32c(val),
+    );
+
     registry.register_1_arg::<NumberType<F64>, NumberType<F64>, _, _>(
         "degrees",
         |_, _| FunctionDomain::Full,
@@ -29,10 +29,16 @@ fn test_hash() {
     test_sha(file);
     test_blake3(file);
     test_sha2(file);
+    test_hmac_sha2(file);
+    test_hmac_sha256(file);
+    test_sha3(file);
+    test_hash_bucket(file);
+    test_keccak256(file);
     test_city64withseed(file);
     test_siphash64(file);
     test_xxhash64(file);
     test_xxhash32(file);
+    test_murmur3(file);
 }
 
 fn test_md5(file: &mut impl Write) {
@@ -60,6 +66,13 @@ fn test_blake3(file: &mut impl Write) {
         "a",
         StringType::from_data(vec!["Abc", "Dobrý den", "ß😀山"]),
     )]);
+    run_ast(file, "blake3('Abc',16)", &[]);
+    run_ast(file, "blake3('Abc',64)", &[]);
+    run_ast(file, "blake3('Abc',0)", &[]);
+    run_ast(file, "blake3(a,8)", &[(
+        "a",
+        StringType::from_data(vec!["Abc", "Dobrý den", "ß😀山"]),
+    )]);
 }
 
 fn test_sha2(file: &mut impl Write) {
@@ -73,6 +86,136 @@ fn test_sha2(file: &mut impl Write) {
         ),
         ("b", UInt16Type::from_data(vec![224u16, 384, 512])),
     ]);
+    run_ast(file, "sha2('Abc',NULL)", &[]);
+    run_ast(file, "sha2(a,b)", &[
+        (
+            "a",
+            StringType::from_data_with_validity(vec!["Abc", "", "ß😀山"], vec![
+                true, false, true,
+            ]),
+        ),
+        (
+            "b",
+            UInt64Type::from_data_with_validity(vec![256u64, 224, 512], vec![true, true, false]),
+        ),
+    ]);
+    run_ast(file, "sha2(a,256)", &[(
+        "a",
+        StringType::from_data_with_validity(vec!["Abc", "", "Dobrý den", "", "ß😀山"], vec![
+            true, false, true, false, true,
+        ]),
+    )]);
+    run_ast(file, "sha2('Abc',b)", &[(
+        "b",
+        UInt64Type::from_data(vec![224u64, 256, 384, 512]),
+    )]);
+    run_ast(file, "sha2('Abc',256,16)", &[]);
+    run_ast(file, "sha2('Abc',224,57)", &[]);
+    run_ast(file, "sha2('abc',256)", &[]);
+    run_ast(file, "sha2('abc',256,false)", &[]);
+    run_ast(file, "sha2('abc',256,true)", &[]);
+    run_ast(file, "sha2('Abc',b,true)", &[(
+        "b",
+        UInt64Type::from_data(vec![224u64, 512]),
+    )]);
+    run_ast(file, "sha2(1234567890,256)", &[]);
+    run_ast(file, "sha2(a,256)", &[(
+        "a",
+        Int32Type::from_data(vec![1i32, -2, 300]),
+    )]);
+    run_ast(file, "to_hex(sha2_binary('Abc',256))", &[]);
+    run_ast(file, "length(sha2_binary('Abc',512))", &[]);
+    run_ast(file, "sha2_binary('Abc',1)", &[]);
+}
+
+fn test_hmac_sha2(file: &mut impl Write) {
+    run_ast(
+        file,
+        "hmac_sha2('The quick brown fox jumps over the lazy dog','key',256)",
+        &[],
+    );
+    run_ast(file, "hmac_sha2('Abc','key',0)", &[]);
+    run_ast(file, "hmac_sha2(NULL,'key',256)", &[]);
+    run_ast(file, "hmac_sha2('Abc','key',1)", &[]);
+    run_ast(file, "hmac_sha2(a,b,c)", &[
+        (
+            "a",
+            StringType::from_data(vec!["Abc", "Dobry den", "hello"]),
+        ),
+        ("b", StringType::from_data(vec!["key", "secret", ""])),
+        ("c", UInt16Type::from_data(vec![224u16, 384, 512])),
+    ]);
+    // the constant key of each length.
+    run_ast(file, "hmac_sha2(a,'key',224)", &[(
+        "a",
+        StringType::from_data(vec!["Abc", "Dobry den", "hello"]),
+    )]);
+    run_ast(file, "hmac_sha2(a,'key',256)", &[(
+        "a",
+        StringType::from_data(vec!["Abc", "Dobry den", "hello"]),
+    )]);
+    run_ast(file, "hmac_sha2(a,'key',384)", &[(
+        "a",
+        StringType::from_data(vec!["Abc", "Dobry den", "hello"]),
+    )]);
+    run_ast(file, "hmac_sha2(a,'key',512)", &[(
+        "a",
+        StringType::from_data(vec!["Abc", "Dobry den", "hello"]),
+    )]);
+}
+
+fn test_hmac_sha256(file: &mut impl Write) {
+    run_ast(
+        file,
+        "hmac_sha256('The quick brown fox jumps over the lazy dog','key')",
+        &[],
+    );
+    run_ast(file, "hmac_sha256(NULL,'key')", &[]);
+    run_ast(file, "hmac_sha256(a,'key')", &[(
+        "a",
+        StringType::from_data(vec!["Abc", "Dobry den", "hello"]),
+    )]);
+    run_ast(file, "hmac_sha256(a,b)", &[
+        (
+            "a",
+            StringType::from_data(vec!["Abc", "Dobry den", "hello"]),
+        ),
+        ("b", StringType::from_data(vec!["key", "secret", ""])),
+    ]);
+}
+
+fn test_sha3(file: &mut impl Write) {
+    run_ast(file, "sha3('Abc',256)", &[]);
+    run_ast(file, "sha3(NULL,256)", &[]);
+    run_ast(file, "sha3('Abc',0)", &[]);
+    run_ast(file, "sha3('Abc',128)", &[]);
+    run_ast(file, "sha3(a,b)", &[
+        (
+            "a",
+            StringType::from_data(vec!["Abc", "Dobrý den", "ß😀山"]),
+        ),
+        ("b", UInt16Type::from_data(vec![224u16, 384, 512])),
+    ]);
+}
+
+fn test_hash_bucket(file: &mut impl Write) {
+    run_ast(file, "hash_bucket('Abc',16)", &[]);
+    run_ast(file, "hash_bucket('Abc',1)", &[]);
+    run_ast(file, "hash_bucket(NULL,16)", &[]);
+    run_ast(file, "hash_bucket('Abc',0)", &[]);
+    run_ast(file, "hash_bucket(a,8)", &[(
+        "a",
+        StringType::from_data(vec!["Abc", "", "ß😀山", "databend"]),
+    )]);
+}
+
+fn test_keccak256(file: &mut impl Write) {
+    run_ast(file, "keccak256('Abc')", &[]);
+    run_ast(file, "keccak256(NULL)", &[]);
+    run_ast(file, "keccak256(a)", &[(
+        "a",
+        StringType::from_data(vec!["Abc", "Dobrý den", "ß😀山"]),
+    )]);
 }
 
 fn test_city64withseed(file: &mut impl Write) {
@@ -123,6 +266,16 @@ fn test_xxhash64(file: &mut impl Write) {
         "a",
         StringType::from_data(vec!["Dobrý den", "ß😀山"]),
     )]);
+    run_ast(file, "xxhash64('Abc',0)", &[]);
+    run_ast(file, "xxhash64('Abc',1)", &[]);
+    run_ast(file, "xxhash64(a,42)", &[(
+        "a",
+        StringType::from_data(vec!["Dobrý den", "ß😀山"]),
+    )]);
+    run_ast(file, "xxhash64(a,b)", &[
+        ("a", StringType::from_data(vec!["Dobrý den", "ß😀山"])),
+        ("b", UInt64Type::from_data(vec![0u64, 42])),
+    ]);
 }
 
 fn test_xxhash32(file: &mut impl Write) {
@@ -139,3 +292,15 @@ fn test_xxhash32(file: &mut impl Write) {
         StringType::from_data(vec!["Dobrý den", "ß😀山"]),
     )]);
 }
+
+fn test_murmur3(file: &mut impl Write) {
+    run_ast(file, "murmur3('Abc')", &[]);
+    run_ast(file, "murmur3('Abc', 1)", &[]);
+    run_ast(file, "murmur3(a, b)", &[
+        (
+            "a",
+            StringType::from_data(vec!["Abc", "Dobrý den", "ß😀山"]),
+        ),
+        ("b", Int32Type::from_data(vec![1i32, 2, 3])),
+    ]);
+}
@@ -36,6 +36,7 @@ fn test_math() {
     test_truncate(file);
     test_log_function(file);
     test_factorial(file);
+    test_crc32(file);
 }
 
 fn test_abs(file: &mut impl Write) {
@@ -161,3 +162,19 @@ fn test_log_function(file: &mut impl Write) {
         Int64Type::from_data(vec![22i64, 65536, 10]),
     )]);
 }
+
+fn test_crc32(file: &mut impl Write) {
+    run_ast(file, "crc32('Abc')", &[]);
+    run_ast(file, "crc32(to_binary('Abc'))", &[]);
+    run_ast(file, "crc32('')", &[]);
+    run_ast(file, "crc32(a)", &[(
+        "a",
+        StringType::from_data(vec!["Abc", "", "ß😀山"]),
+    )]);
+    run_ast(file, "crc32c('Abc')", &[]);
+    run_ast(file, "crc32c(to_binary('Abc'))", &[]);
+    run_ast(file, "crc32c(a)", &[(
+        "a",
+        StringType::from_data(vec!["Abc", "", "ß😀山"]),
+    )]);
+}
@@ -15,13 +15,15 @@
 use databend_common_exception::Result;
 use databend_common_meta_app::principal::StageFile;
 use databend_common_meta_app::principal::StageInfo;
+use databend_common_meta_app::schema::CreateOption;
 use databend_common_meta_types::MatchSeq;
 use databend_common_meta_types::SeqV;
 
 #[async_trait::async_trait]
 pub trait StageApi: Sync + Send {
-    // Add a stage info to /tenant/stage-name.
-    async fn add_stage(&self, stage: StageInfo) -> Result<u64>;
+    // Add a stage info to /tenant/stage-name, or replace the existing one with
+    // `CreateOption::CreateOrReplace`, in which case the seq returned is always 0.
+    async fn add_stage(&self, stage: StageInfo, create_option: &CreateOption) -> Result<u64>;
 
     async fn get_stage(&self, name: &str, seq: MatchSeq) -> Result<SeqV<StageInfo>>;
 
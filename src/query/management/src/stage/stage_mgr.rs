@@ -24,6 +24,7 @@ use databend_common_meta_api::txn_op_put;
 use databend_common_meta_app::app_error::TxnRetryMaxTimes;
 use databend_common_meta_app::principal::StageFile;
 use databend_common_meta_app::principal::StageInfo;
+use databend_common_meta_app::schema::CreateOption;
 use databend_common_meta_kvapi::kvapi;
 use databend_common_meta_kvapi::kvapi::UpsertKVReq;
 use databend_common_meta_types::ConditionResult::Eq;
@@ -63,13 +64,61 @@ impl StageMgr {
             stage_file_prefix: format!("{}/{}", STAGE_FILE_API_KEY_PREFIX, escape_for_key(tenant)?),
         })
     }
+
+    // Drop the stage along with its files and add it again in one transaction, so that the
+    // stage never goes missing for a reader in between.
+    #[async_backtrace::framed]
+    async fn replace_stage(&self, info: StageInfo) -> Result<u64> {
+        let name = &info.stage_name;
+        let stage_key = format!("{}/{}", self.stage_prefix, escape_for_key(name)?);
+        let file_key_prefix = format!("{}/{}/", self.stage_file_prefix, escape_for_key(name)?);
+        let value = serialize_struct(&info, ErrorCode::IllegalUserStageFormat, || "")?;
+
+        let mut retry = 0;
+        while retry < TXN_MAX_RETRY_TIMES {
+            retry += 1;
+
+            // the seq of a stage that does not exist is 0
+            let stage_seq = match self.kv_api.get_kv(&stage_key).await? {
+                Some(seq_v) => seq_v.seq,
+                None => 0,
+            };
+
+            let file_keys = self.kv_api.prefix_list_kv(&file_key_prefix).await?;
+            let mut ops: Vec<TxnOp> = file_keys.iter().map(|(key, _)| txn_op_del(key)).collect();
+            ops.push(txn_op_put(&stage_key, value.clone()));
+
+            let txn_req = TxnRequest {
+                condition: vec![
+                    // no file is added to the stage, nor is it created or dropped in between
+                    txn_cond_seq(&stage_key, Eq, stage_seq),
+                ],
+                if_then: ops,
+                else_then: vec![],
+            };
+            let tx_reply = self.kv_api.transaction(txn_req).await?;
+            let (succ, _) = txn_reply_to_api_result(tx_reply)?;
+
+            if succ {
+                return Ok(0);
+            }
+        }
+
+        Err(ErrorCode::TxnRetryMaxTimes(
+            TxnRetryMaxTimes::new("replace_stage", TXN_MAX_RETRY_TIMES).to_string(),
+        ))
+    }
 }
 
 #[async_trait::async_trait]
 impl StageApi for StageMgr {
     #[async_backtrace::framed]
     #[minitrace::trace]
-    async fn add_stage(&self, info: StageInfo) -> Result<u64> {
+    async fn add_stage(&self, info: StageInfo, create_option: &CreateOption) -> Result<u64> {
+        if let CreateOption::CreateOrReplace = create_option {
+            return self.replace_stage(info).await;
+        }
+
         let seq = MatchSeq::Exact(0);
         let val = Operation::Update(serialize_struct(
             &info,
@@ -21,6 +21,7 @@ use databend_common_management::*;
 use databend_common_meta_app::principal::StageFile;
 use databend_common_meta_app::principal::StageInfo;
 use databend_common_meta_app::principal::StageParams;
+use databend_common_meta_app::schema::CreateOption;
 use databend_common_meta_app::storage::StorageParams;
 use databend_common_meta_app::storage::StorageS3Config;
 use databend_common_meta_embedded::MetaEmbedded;
@@ -33,7 +34,9 @@ async fn test_add_stage() -> Result<()> {
     let (kv_api, stage_api) = new_stage_api().await?;
 
     let stage_info = create_test_stage_info();
-    stage_api.add_stage(stage_info.clone()).await?;
+    stage_api
+        .add_stage(stage_info.clone(), &CreateOption::CreateIfNotExists(false))
+        .await?;
     let value = kv_api.get_kv("__fd_stages/admin/mystage").await?;
 
     match value {
@@ -58,9 +61,14 @@ async fn test_already_exists_add_stage() -> Result<()> {
     let (_, stage_api) = new_stage_api().await?;
 
     let stage_info = create_test_stage_info();
-    stage_api.add_stage(stage_info.clone()).await?;
+    stage_api
+        .add_stage(stage_info.clone(), &CreateOption::CreateIfNotExists(false))
+        .await?;
 
-    match stage_api.add_stage(stage_info.clone()).await {
+    match stage_api
+        .add_stage(stage_info.clone(), &CreateOption::CreateIfNotExists(false))
+        .await
+    {
         Ok(_) => panic!("Already exists add stage must be return Err."),
         Err(cause) => assert_eq!(cause.code(), 2502),
     }
@@ -68,6 +76,42 @@ async fn test_already_exists_add_stage() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn test_replace_stage() -> Result<()> {
+    let (_, stage_api) = new_stage_api().await?;
+
+    // replace a stage that does not exist
+    let stage_info = create_test_stage_info();
+    stage_api
+        .add_stage(stage_info.clone(), &CreateOption::CreateOrReplace)
+        .await?;
+    let stages = stage_api.get_stages().await?;
+    assert_eq!(stages, vec![stage_info.clone()]);
+
+    stage_api
+        .add_file("mystage", StageFile {
+            path: "books.csv".to_string(),
+            size: 100,
+            ..Default::default()
+        })
+        .await?;
+    assert_eq!(stage_api.list_files("mystage").await?.len(), 1);
+
+    let new_stage_info = StageInfo {
+        comment: "replaced".to_string(),
+        ..stage_info
+    };
+    stage_api
+        .add_stage(new_stage_info.clone(), &CreateOption::CreateOrReplace)
+        .await?;
+
+    let stages = stage_api.get_stages().await?;
+    assert_eq!(stages, vec![new_stage_info]);
+    assert_eq!(stage_api.list_files("mystage").await?, vec![]);
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
 async fn test_successfully_get_stages() -> Result<()> {
     let (_, stage_api) = new_stage_api().await?;
@@ -76,7 +120,9 @@ async fn test_successfully_get_stages() -> Result<()> {
     assert_eq!(stages, vec![]);
 
     let stage_info = create_test_stage_info();
-    stage_api.add_stage(stage_info.clone()).await?;
+    stage_api
+        .add_stage(stage_info.clone(), &CreateOption::CreateIfNotExists(false))
+        .await?;
 
     let stages = stage_api.get_stages().await?;
     assert_eq!(stages[0], stage_info);
@@ -88,7 +134,9 @@ async fn test_successfully_drop_stage() -> Result<()> {
     let (_, stage_api) = new_stage_api().await?;
 
     let stage_info = create_test_stage_info();
-    stage_api.add_stage(stage_info.clone()).await?;
+    stage_api
+        .add_stage(stage_info.clone(), &CreateOption::CreateIfNotExists(false))
+        .await?;
 
     let stages = stage_api.get_stages().await?;
     assert_eq!(stages, vec![stage_info.clone()]);
@@ -136,7 +184,9 @@ async fn test_add_stage_file() -> Result<()> {
     let (kv_api, stage_api) = new_stage_api().await?;
 
     let stage_info = create_test_stage_info();
-    let seq = stage_api.add_stage(stage_info.clone()).await?;
+    let seq = stage_api
+        .add_stage(stage_info.clone(), &CreateOption::CreateIfNotExists(false))
+        .await?;
     let mystage = stage_api
         .get_stage("mystage", MatchSeq::Exact(seq))
         .await?
@@ -176,7 +226,9 @@ async fn test_add_stage_file() -> Result<()> {
 async fn test_remove_files() -> Result<()> {
     let (_kv_api, stage_api) = new_stage_api().await?;
     let stage_info = create_test_stage_info();
-    let seq = stage_api.add_stage(stage_info.clone()).await?;
+    let seq = stage_api
+        .add_stage(stage_info.clone(), &CreateOption::CreateIfNotExists(false))
+        .await?;
     let mystage = stage_api
         .get_stage("mystage", MatchSeq::Exact(seq))
         .await?
@@ -23,6 +23,7 @@ use databend_common_catalog::catalog::CatalogManager;
 use databend_common_cloud_control::cloud_api::CloudControlApiProvider;
 use databend_common_config::GlobalConfig;
 use databend_common_config::InnerConfig;
+use databend_common_exception::ErrorCode;
 use databend_common_exception::Result;
 use databend_common_meta_app::schema::CatalogType;
 use databend_common_sharing::ShareEndpointManager;
@@ -67,7 +68,8 @@ impl GlobalServices {
         log_labels.insert("tenant_id".to_string(), config.query.tenant_id.clone());
         log_labels.insert("cluster_id".to_string(), config.query.cluster_id.clone());
         log_labels.insert("node_id".to_string(), config.query.node_id.clone());
-        GlobalLogger::init(&app_name_shuffle, &config.log, log_labels);
+        GlobalLogger::init(&app_name_shuffle, &config.log, log_labels)
+            .map_err(ErrorCode::InvalidConfig)?;
 
         // 3. runtime init.
         GlobalIORuntime::init(config.storage.num_cpus as usize)?;
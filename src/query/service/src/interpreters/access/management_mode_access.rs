@@ -54,8 +54,6 @@ impl AccessChecker for ManagementModeAccess {
                             | RewriteKind::ShowTableFunctions
                             | RewriteKind::ShowUsers
                             | RewriteKind::ShowStages
-                            | RewriteKind::DescribeStage
-                            | RewriteKind::ListStage
                             | RewriteKind::Call
                             | RewriteKind::ShowRoles
                             | RewriteKind::ShowLocks
@@ -101,6 +99,8 @@ impl AccessChecker for ManagementModeAccess {
                 // Stage.
                 | Plan::CreateStage(_)
                 | Plan::DropStage(_)
+                | Plan::DescribeStage(_)
+                | Plan::ListStage(_)
                 // Network policy.
                 | Plan::CreateNetworkPolicy(_)
                 | Plan::AlterNetworkPolicy(_)
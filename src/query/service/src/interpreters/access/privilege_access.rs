@@ -873,9 +873,17 @@ impl AccessChecker for PrivilegeAccess {
                 let from = plan.from.clone();
                 return self.check(ctx, &from).await;
             }
+            Plan::ListStage(plan) => {
+                self.validate_stage_access(&plan.stage, UserPrivilegeType::Read).await?;
+            }
             Plan::RemoveStage(plan) => {
                 self.validate_stage_access(&plan.stage, UserPrivilegeType::Write).await?;
             }
+            Plan::DescribeStage(plan) => {
+                if enable_experimental_rbac_check {
+                    self.validate_access(&GrantObject::Stage(plan.name.clone()), vec![UserPrivilegeType::Read]).await?;
+                }
+            }
             Plan::CreateShareEndpoint(_)
             | Plan::ShowShareEndpoint(_)
             | Plan::DropShareEndpoint(_)
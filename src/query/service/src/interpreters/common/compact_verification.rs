@@ -0,0 +1,173 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::Arc;
+
+use databend_common_ast::parser::quote::quote_ident;
+use databend_common_catalog::catalog::Catalog;
+use databend_common_catalog::table::Table;
+use databend_common_exception::ErrorCode;
+use databend_common_exception::Result;
+use databend_common_expression::DataBlock;
+use databend_common_sql::plans::OptimizeTablePlan;
+use databend_common_sql::Planner;
+use databend_common_storages_fuse::FuseTable;
+use databend_storages_common_table_meta::meta::SnapshotId;
+use futures_util::TryStreamExt;
+use log::info;
+use log::warn;
+
+use crate::interpreters::InterpreterFactory;
+use crate::sessions::QueryContext;
+use crate::sessions::TableContext;
+
+/// An order-independent digest of the rows of a table.
+///
+/// Every row is hashed the same way as the `siphash64` function does, and the
+/// row hashes are summed up, so the digest does not depend on how the rows are
+/// spread over blocks and segments.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TableDigest {
+    pub num_rows: u64,
+    pub hash: u64,
+}
+
+impl TableDigest {
+    pub fn update(&mut self, block: &DataBlock) {
+        for row in 0..block.num_rows() {
+            let mut hasher = DefaultHasher::default();
+            for entry in block.columns() {
+                entry.value.index(row).unwrap().hash(&mut hasher);
+            }
+            self.hash = self.hash.wrapping_add(hasher.finish());
+        }
+        self.num_rows += block.num_rows() as u64;
+    }
+
+    /// Computes the digest of the table at the given snapshot.
+    #[async_backtrace::framed]
+    pub async fn compute(
+        ctx: Arc<QueryContext>,
+        plan: &OptimizeTablePlan,
+        snapshot_id: SnapshotId,
+    ) -> Result<TableDigest> {
+        let sql = format!(
+            "SELECT * FROM {}.{}.{} AT (SNAPSHOT => '{}')",
+            quote_ident(&plan.catalog, '`', false),
+            quote_ident(&plan.database, '`', false),
+            quote_ident(&plan.table, '`', false),
+            snapshot_id.simple()
+        );
+
+        let ctx = QueryContext::create_from(ctx);
+        let mut planner = Planner::new(ctx.clone());
+        let (plan, _) = planner.plan_sql(&sql).await?;
+        let interpreter = InterpreterFactory::get(ctx.clone(), &plan).await?;
+        let mut stream = interpreter.execute(ctx).await?;
+
+        let mut digest = TableDigest::default();
+        while let Some(block) = stream.try_next().await? {
+            digest.update(&block);
+        }
+        Ok(digest)
+    }
+}
+
+/// Checks that a compaction does not change the contents of the table,
+/// by comparing the digests of the table before and after the compaction.
+pub struct CompactionVerifier {
+    ctx: Arc<QueryContext>,
+    catalog: Arc<dyn Catalog>,
+    plan: OptimizeTablePlan,
+    snapshot_id: SnapshotId,
+    digest: TableDigest,
+}
+
+impl CompactionVerifier {
+    /// Takes the digest of the table before compaction, returns `None` if the table is empty.
+    #[async_backtrace::framed]
+    pub async fn try_create(
+        ctx: Arc<QueryContext>,
+        catalog: Arc<dyn Catalog>,
+        plan: &OptimizeTablePlan,
+        table: &dyn Table,
+    ) -> Result<Option<Self>> {
+        let fuse_table = FuseTable::try_from_table(table)?;
+        let Some(snapshot) = fuse_table.read_table_snapshot().await? else {
+            return Ok(None);
+        };
+
+        let digest = TableDigest::compute(ctx.clone(), plan, snapshot.snapshot_id).await?;
+        Ok(Some(CompactionVerifier {
+            ctx,
+            catalog,
+            plan: plan.clone(),
+            snapshot_id: snapshot.snapshot_id,
+            digest,
+        }))
+    }
+
+    #[async_backtrace::framed]
+    pub async fn verify(self) -> Result<()> {
+        // currently, context caches the table, we have to "refresh"
+        // the table by using the catalog API directly
+        let table = self
+            .catalog
+            .get_table(
+                self.ctx.get_tenant().as_str(),
+                &self.plan.database,
+                &self.plan.table,
+            )
+            .await?;
+        let fuse_table = FuseTable::try_from_table(table.as_ref())?;
+        let Some(snapshot) = fuse_table.read_table_snapshot().await? else {
+            return Err(ErrorCode::Internal(format!(
+                "compaction verification failed, table '{}' has no snapshot after compaction",
+                self.plan.table
+            )));
+        };
+
+        if snapshot.snapshot_id == self.snapshot_id {
+            // nothing has been compacted.
+            return Ok(());
+        }
+
+        let prev_snapshot_id = snapshot.prev_snapshot_id.map(|(id, _)| id);
+        if prev_snapshot_id != Some(self.snapshot_id) {
+            warn!(
+                "table '{}' has been modified concurrently, skip the compaction verification",
+                self.plan.table
+            );
+            return Ok(());
+        }
+
+        let digest =
+            TableDigest::compute(self.ctx.clone(), &self.plan, snapshot.snapshot_id).await?;
+        if digest != self.digest {
+            return Err(ErrorCode::Internal(format!(
+                "compaction verification failed, contents of table '{}' changed: {:?} before, {:?} after",
+                self.plan.table, self.digest, digest
+            )));
+        }
+
+        info!(
+            "compaction verification of table '{}' passed, {} rows checked",
+            self.plan.table, digest.num_rows
+        );
+        Ok(())
+    }
+}
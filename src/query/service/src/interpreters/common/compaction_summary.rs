@@ -0,0 +1,146 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use databend_common_catalog::catalog::Catalog;
+use databend_common_exception::Result;
+use databend_common_expression::types::StringType;
+use databend_common_expression::types::UInt64Type;
+use databend_common_expression::DataBlock;
+use databend_common_expression::FromData;
+use databend_common_sql::plans::OptimizeTableAction;
+use databend_common_sql::plans::OptimizeTablePlan;
+use databend_common_storages_fuse::FuseTable;
+
+use crate::sessions::QueryContext;
+use crate::sessions::TableContext;
+
+/// Number of segments and blocks of the latest snapshot of a table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TableLayout {
+    pub segments: u64,
+    pub blocks: u64,
+    /// Size of the block objects, the index objects excluded.
+    pub block_bytes: u64,
+}
+
+impl TableLayout {
+    /// Reads the layout of the table of the plan, a table without snapshot or not
+    /// of the fuse engine is taken as empty.
+    #[async_backtrace::framed]
+    pub async fn read(
+        ctx: &QueryContext,
+        catalog: &dyn Catalog,
+        plan: &OptimizeTablePlan,
+    ) -> Result<TableLayout> {
+        // read the table from the catalog directly, the table cached by the context is stale.
+        let table = catalog
+            .get_table(ctx.get_tenant().as_str(), &plan.database, &plan.table)
+            .await?;
+        let Ok(fuse_table) = FuseTable::try_from_table(table.as_ref()) else {
+            return Ok(TableLayout::default());
+        };
+        let layout = match fuse_table.read_table_snapshot().await? {
+            Some(snapshot) => TableLayout {
+                segments: snapshot.segments.len() as u64,
+                blocks: snapshot.summary.block_count,
+                block_bytes: snapshot.summary.compressed_byte_size,
+            },
+            None => TableLayout::default(),
+        };
+        Ok(layout)
+    }
+}
+
+/// What an `OPTIMIZE TABLE` did to the table, returned to the client as a one-row result.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompactionSummary {
+    pub before: TableLayout,
+    pub after: TableLayout,
+    /// Uncompressed bytes of the blocks written by the compaction and recluster.
+    pub bytes_rewritten: u64,
+    /// Bytes of the block objects kept as is by a segment-only compaction, which a compaction
+    /// of the blocks may have rewritten.
+    pub bytes_saved: u64,
+    /// Skipped since another compaction of the table is running.
+    pub already_compacting: bool,
+}
+
+impl CompactionSummary {
+    /// Builds the result block, in the order of `OptimizeTablePlan::schema`.
+    pub fn to_block(&self) -> DataBlock {
+        DataBlock::new_from_columns(vec![
+            UInt64Type::from_data(vec![self.before.segments]),
+            UInt64Type::from_data(vec![self.after.segments]),
+            UInt64Type::from_data(vec![self.before.blocks]),
+            UInt64Type::from_data(vec![self.after.blocks]),
+            UInt64Type::from_data(vec![self.bytes_rewritten]),
+            UInt64Type::from_data(vec![self.bytes_saved]),
+            StringType::from_data(vec![match self.already_compacting {
+                true => "already compacting",
+                false => "compacted",
+            }]),
+        ])
+    }
+}
+
+/// Collects the summary of a compaction, from the start of the compaction.
+pub struct CompactionSummaryCollector {
+    ctx: Arc<QueryContext>,
+    catalog: Arc<dyn Catalog>,
+    plan: OptimizeTablePlan,
+    before: TableLayout,
+    written_bytes: usize,
+}
+
+impl CompactionSummaryCollector {
+    #[async_backtrace::framed]
+    pub async fn try_create(
+        ctx: Arc<QueryContext>,
+        catalog: Arc<dyn Catalog>,
+        plan: &OptimizeTablePlan,
+    ) -> Result<Self> {
+        let before = TableLayout::read(&ctx, catalog.as_ref(), plan).await?;
+        let written_bytes = ctx.get_write_progress_value().bytes;
+        Ok(CompactionSummaryCollector {
+            ctx,
+            catalog,
+            plan: plan.clone(),
+            before,
+            written_bytes,
+        })
+    }
+
+    /// Called once the compaction is done.
+    #[async_backtrace::framed]
+    pub async fn finish(self) -> Result<CompactionSummary> {
+        let after = TableLayout::read(&self.ctx, self.catalog.as_ref(), &self.plan).await?;
+        let written_bytes = self.ctx.get_write_progress_value().bytes;
+        // the new segments refer to the same block objects, none of them is uploaded again.
+        let segments_compacted = matches!(self.plan.action, OptimizeTableAction::CompactSegments)
+            && after.segments < self.before.segments;
+        let bytes_saved = match segments_compacted {
+            true => after.block_bytes,
+            false => 0,
+        };
+        Ok(CompactionSummary {
+            before: self.before,
+            after,
+            bytes_rewritten: written_bytes.saturating_sub(self.written_bytes) as u64,
+            bytes_saved,
+            already_compacting: false,
+        })
+    }
+}
@@ -12,6 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod compact_verification;
+mod compaction_summary;
 mod grant;
 mod metrics;
 mod query_log;
@@ -20,6 +22,10 @@ mod table;
 mod task;
 mod util;
 
+pub use compact_verification::CompactionVerifier;
+pub use compact_verification::TableDigest;
+pub use compaction_summary::CompactionSummary;
+pub use compaction_summary::CompactionSummaryCollector;
 pub use grant::validate_grant_object_exists;
 pub use query_log::InterpreterQueryLog;
 pub use stream::build_update_stream_meta_seq;
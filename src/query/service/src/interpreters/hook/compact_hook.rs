@@ -13,24 +13,28 @@
 // limitations under the License.
 
 use std::sync::Arc;
+use std::time::Duration;
 use std::time::Instant;
 
+use chrono::Utc;
 use databend_common_base::runtime::GlobalIORuntime;
 use databend_common_catalog::table_context::TableContext;
 use databend_common_exception::Result;
 use databend_common_pipeline_core::Pipeline;
 use databend_common_sql::plans::OptimizeTableAction;
 use databend_common_sql::plans::OptimizeTablePlan;
+use databend_common_storages_fuse::operations::segment_growth_per_hour;
+use databend_common_storages_fuse::FuseTable;
 use log::info;
 
 use crate::interpreters::common::metrics_inc_compact_hook_compact_time_ms;
 use crate::interpreters::common::metrics_inc_compact_hook_main_operation_time_ms;
-use crate::interpreters::Interpreter;
 use crate::interpreters::OptimizeTableInterpreter;
 use crate::pipelines::executor::ExecutorSettings;
 use crate::pipelines::executor::PipelineCompleteExecutor;
 use crate::sessions::QueryContext;
 
+#[derive(Clone)]
 pub struct CompactTargetTableDescription {
     pub catalog: String,
     pub database: String,
@@ -70,9 +74,19 @@ async fn do_hook_compact(
     }
 
     if ctx.get_settings().get_enable_compact_after_write()? {
+        // the growth is checked before the write runs, the snapshot history is not read in the
+        // on-finished callback, which blocks the pipeline.
+        let growing_fast = segments_growing_fast(ctx.clone(), compact_target.clone())
+            .await
+            .unwrap_or_else(|e| {
+                info!("check segments growth with error (ignored): {}", e);
+                false
+            });
         {
             pipeline.set_on_finished(move |err| {
-                if !ctx.get_need_compact_after_write() {
+                let need_compact =
+                    ctx.get_need_compact_after_write() || (err.is_ok() && growing_fast);
+                if !need_compact {
                     return Ok(());
                 }
 
@@ -100,6 +114,51 @@ async fn do_hook_compact(
     Ok(())
 }
 
+/// The window over which the growth of the segments is measured.
+const SEGMENT_GROWTH_WINDOW: Duration = Duration::from_secs(3600);
+
+/// The max number of snapshots read to measure the growth of the segments.
+const SEGMENT_GROWTH_MAX_SNAPSHOTS: usize = 256;
+
+/// Whether the table gained at least `auto_compaction_segments_growth_per_hour` segments per
+/// hour over the last hour.
+async fn segments_growing_fast(
+    ctx: Arc<QueryContext>,
+    compact_target: CompactTargetTableDescription,
+) -> Result<bool> {
+    let threshold = ctx
+        .get_settings()
+        .get_auto_compaction_segments_growth_per_hour()?;
+    if threshold == 0 {
+        return Ok(false);
+    }
+    // read the table from the catalog directly, the table cached by the context is stale.
+    let catalog = ctx.get_catalog(&compact_target.catalog).await?;
+    let table = catalog
+        .get_table(
+            ctx.get_tenant().as_str(),
+            &compact_target.database,
+            &compact_target.table,
+        )
+        .await?;
+    let Ok(fuse_table) = FuseTable::try_from_table(table.as_ref()) else {
+        return Ok(false);
+    };
+    let since = Utc::now() - chrono::Duration::from_std(SEGMENT_GROWTH_WINDOW).unwrap();
+    let history = fuse_table
+        .segment_count_history(since, SEGMENT_GROWTH_MAX_SNAPSHOTS)
+        .await?;
+    let growth = segment_growth_per_hour(&history, SEGMENT_GROWTH_WINDOW);
+    if growth < threshold as f64 {
+        return Ok(false);
+    }
+    info!(
+        "segments of {}.{} grow by {:.1} per hour, over the threshold {}",
+        compact_target.database, compact_target.table, growth, threshold
+    );
+    Ok(true)
+}
+
 /// compact the target table, will do optimize table actions, including:
 ///  - compact blocks
 ///  - re-cluster if the cluster keys are defined
@@ -126,7 +185,7 @@ async fn compact_table(
             need_lock,
         })?;
 
-    let mut build_res = optimize_interpreter.execute2().await?;
+    let mut build_res = optimize_interpreter.build_optimize_pipeline().await?;
 
     if build_res.main_pipeline.is_empty() {
         return Ok(());
@@ -47,6 +47,12 @@ pub trait Interpreter: Sync + Send {
     /// Return the name of Interpreter, such as "CreateDatabaseInterpreter"
     fn name(&self) -> &str;
 
+    /// Whether the statement only sets the settings of the session, which leaves the settings
+    /// of `SET LOCAL` to the next statement.
+    fn is_setting(&self) -> bool {
+        false
+    }
+
     /// The core of the databend processor which will execute the logical plan and get the DataBlock
     #[async_backtrace::framed]
     #[minitrace::trace]
@@ -54,6 +60,9 @@ pub trait Interpreter: Sync + Send {
         ctx.set_status_info("building pipeline");
         InterpreterMetrics::record_query_start(&ctx);
         log_query_start(&ctx);
+        if !self.is_setting() {
+            ctx.consume_local_settings();
+        }
 
         if let Err(err) = ctx.check_aborting() {
             log_query_finished(&ctx, Some(err.clone()), false);
@@ -332,6 +332,14 @@ impl InterpreterFactory {
                 ctx,
                 *s.clone(),
             )?)),
+            Plan::DescribeStage(s) => Ok(Arc::new(DescribeUserStageInterpreter::try_create(
+                ctx,
+                *s.clone(),
+            )?)),
+            Plan::ListStage(s) => Ok(Arc::new(ListUserStageInterpreter::try_create(
+                ctx,
+                *s.clone(),
+            )?)),
             Plan::RemoveStage(s) => Ok(Arc::new(RemoveUserStageInterpreter::try_create(
                 ctx,
                 *s.clone(),
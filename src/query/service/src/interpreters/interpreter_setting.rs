@@ -18,6 +18,7 @@ use chrono_tz::Tz;
 use databend_common_exception::ErrorCode;
 use databend_common_exception::Result;
 use databend_common_sql::plans::SettingPlan;
+use log::warn;
 
 use crate::interpreters::Interpreter;
 use crate::pipelines::PipelineBuildResult;
@@ -42,6 +43,10 @@ impl Interpreter for SettingInterpreter {
         "SettingInterpreter"
     }
 
+    fn is_setting(&self) -> bool {
+        true
+    }
+
     #[async_backtrace::framed]
     async fn execute2(&self) -> Result<PipelineBuildResult> {
         let plan = self.set.clone();
@@ -60,13 +65,16 @@ impl Interpreter for SettingInterpreter {
                     })?;
                     let settings = self.ctx.get_shared_settings();
 
-                    match var.is_global {
-                        true => {
+                    match (var.is_global, var.is_local) {
+                        (true, _) => {
                             settings
                                 .set_global_setting(var.variable.clone(), tz.to_string())
                                 .await
                         }
-                        false => {
+                        (false, true) => {
+                            settings.set_local_setting(var.variable.clone(), tz.to_string())
+                        }
+                        (false, false) => {
                             settings
                                 .set_setting(var.variable.clone(), tz.to_string())
                                 .await
@@ -78,13 +86,16 @@ impl Interpreter for SettingInterpreter {
                 _ => {
                     let settings = self.ctx.get_shared_settings();
 
-                    match var.is_global {
-                        true => {
+                    match (var.is_global, var.is_local) {
+                        (true, _) => {
                             settings
                                 .set_global_setting(var.variable.clone(), var.value.clone())
                                 .await
                         }
-                        false => {
+                        (false, true) => {
+                            settings.set_local_setting(var.variable.clone(), var.value.clone())
+                        }
+                        (false, false) => {
                             settings
                                 .set_setting(var.variable.clone(), var.value.clone())
                                 .await
@@ -95,6 +106,15 @@ impl Interpreter for SettingInterpreter {
                 }
             };
             if ok {
+                let settings = self.ctx.get_shared_settings();
+                if let Some(warning) = settings.deprecation_warning(&var.variable) {
+                    warn!("{}", warning);
+                    self.ctx.push_warning(warning);
+                }
+                // the session is not changed by `SET LOCAL`.
+                if var.is_local {
+                    continue;
+                }
                 keys.push(var.variable.clone());
                 values.push(var.value.clone());
                 is_globals.push(var.is_global);
@@ -12,6 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::SystemTime;
 
@@ -19,11 +21,13 @@ use databend_common_base::runtime::GlobalIORuntime;
 use databend_common_catalog::catalog::Catalog;
 use databend_common_catalog::lock::LockExt;
 use databend_common_catalog::plan::Partitions;
+use databend_common_catalog::table::BlockSize;
 use databend_common_catalog::table::CompactTarget;
 use databend_common_catalog::table::Table;
 use databend_common_catalog::table::TableExt;
 use databend_common_exception::ErrorCode;
 use databend_common_exception::Result;
+use databend_common_expression::SendableDataBlockStream;
 use databend_common_meta_app::schema::CatalogInfo;
 use databend_common_meta_app::schema::TableInfo;
 use databend_common_pipeline_core::Pipeline;
@@ -38,10 +42,16 @@ use databend_common_sql::plans::OptimizeTablePlan;
 use databend_common_storages_factory::NavigationPoint;
 use databend_common_storages_fuse::FuseTable;
 use databend_storages_common_table_meta::meta::TableSnapshot;
+use log::info;
+use parking_lot::Mutex;
 
+use crate::interpreters::common::CompactionSummary;
+use crate::interpreters::common::CompactionSummaryCollector;
+use crate::interpreters::common::CompactionVerifier;
 use crate::interpreters::interpreter_table_recluster::build_recluster_physical_plan;
 use crate::interpreters::Interpreter;
 use crate::interpreters::InterpreterClusteringHistory;
+use crate::locks::CompactionLock;
 use crate::locks::LockManager;
 use crate::pipelines::executor::ExecutorSettings;
 use crate::pipelines::executor::PipelineCompleteExecutor;
@@ -49,15 +59,25 @@ use crate::pipelines::PipelineBuildResult;
 use crate::schedulers::build_query_pipeline_without_render_result_set;
 use crate::sessions::QueryContext;
 use crate::sessions::TableContext;
+use crate::stream::DataBlockStream;
 
 pub struct OptimizeTableInterpreter {
     ctx: Arc<QueryContext>,
     plan: OptimizeTablePlan,
+    // set once the optimize pipeline is finished.
+    summary: Arc<Mutex<Option<CompactionSummary>>>,
+    // set if the compaction is skipped, for another compaction of the table is running.
+    already_compacting: AtomicBool,
 }
 
 impl OptimizeTableInterpreter {
     pub fn try_create(ctx: Arc<QueryContext>, plan: OptimizeTablePlan) -> Result<Self> {
-        Ok(OptimizeTableInterpreter { ctx, plan })
+        Ok(OptimizeTableInterpreter {
+            ctx,
+            plan,
+            summary: Arc::new(Mutex::new(None)),
+            already_compacting: AtomicBool::new(false),
+        })
     }
 }
 
@@ -69,6 +89,50 @@ impl Interpreter for OptimizeTableInterpreter {
 
     #[async_backtrace::framed]
     async fn execute2(&self) -> Result<PipelineBuildResult> {
+        if matches!(self.plan.action, OptimizeTableAction::Purge(_)) {
+            return self.build_optimize_pipeline().await;
+        }
+
+        let catalog = self.ctx.get_catalog(&self.plan.catalog).await?;
+        let collector =
+            CompactionSummaryCollector::try_create(self.ctx.clone(), catalog, &self.plan).await?;
+
+        let mut build_res = self.build_optimize_pipeline().await?;
+        if build_res.main_pipeline.is_empty() {
+            // the compaction is done already, e.g. compact segments, or skipped.
+            let mut summary = collector.finish().await?;
+            summary.already_compacting = self.already_compacting.load(Ordering::Relaxed);
+            return PipelineBuildResult::from_blocks(vec![summary.to_block()]);
+        }
+
+        let summary = self.summary.clone();
+        build_res
+            .main_pipeline
+            .set_on_finished(move |may_error| match may_error {
+                Ok(_) => {
+                    let collected = GlobalIORuntime::instance()
+                        .block_on(async move { collector.finish().await })?;
+                    *summary.lock() = Some(collected);
+                    Ok(())
+                }
+                Err(error_code) => Err(error_code.clone()),
+            });
+        Ok(build_res)
+    }
+
+    fn inject_result(&self) -> Result<SendableDataBlockStream> {
+        let blocks = match self.summary.lock().take() {
+            Some(summary) => vec![summary.to_block()],
+            None => vec![],
+        };
+        Ok(Box::pin(DataBlockStream::create(None, blocks)))
+    }
+}
+
+impl OptimizeTableInterpreter {
+    /// Builds the pipeline of the optimization, without the summary as the result set.
+    #[async_backtrace::framed]
+    pub async fn build_optimize_pipeline(&self) -> Result<PipelineBuildResult> {
         let ctx = self.ctx.clone();
         let plan = self.plan.clone();
 
@@ -82,8 +146,10 @@ impl Interpreter for OptimizeTableInterpreter {
 
         match self.plan.action.clone() {
             OptimizeTableAction::CompactBlocks => {
-                self.build_pipeline(catalog, table, CompactTarget::Blocks, false)
-                    .await
+                let target = CompactTarget::Blocks {
+                    block_size: self.compact_block_size()?,
+                };
+                self.build_pipeline(catalog, table, target, false).await
             }
             OptimizeTableAction::CompactSegments => {
                 self.build_pipeline(catalog, table, CompactTarget::Segments, false)
@@ -94,19 +160,36 @@ impl Interpreter for OptimizeTableInterpreter {
                 Ok(PipelineBuildResult::create())
             }
             OptimizeTableAction::All => {
-                self.build_pipeline(catalog, table, CompactTarget::Blocks, true)
-                    .await
+                let target = CompactTarget::Blocks {
+                    block_size: self.compact_block_size()?,
+                };
+                self.build_pipeline(catalog, table, target, true).await
             }
         }
     }
-}
 
-impl OptimizeTableInterpreter {
+    // the size of the compacted blocks set by the settings, `None` if of the table.
+    fn compact_block_size(&self) -> Result<Option<BlockSize>> {
+        let settings = self.ctx.get_settings();
+        match (
+            settings.get_compact_block_rows()?,
+            settings.get_compact_block_bytes()?,
+        ) {
+            (0, 0) => Ok(None),
+            (rows, 0) => Ok(Some(BlockSize::Rows(rows as usize))),
+            (0, bytes) => Ok(Some(BlockSize::Bytes(bytes as usize))),
+            _ => Err(ErrorCode::BadArguments(
+                "only one of compact_block_rows and compact_block_bytes can be set",
+            )),
+        }
+    }
+
     pub fn build_physical_plan(
         parts: Partitions,
         table_info: TableInfo,
         snapshot: Arc<TableSnapshot>,
         catalog_info: CatalogInfo,
+        block_size: Option<BlockSize>,
         is_distributed: bool,
         need_lock: bool,
     ) -> Result<PhysicalPlan> {
@@ -116,6 +199,7 @@ impl OptimizeTableInterpreter {
             table_info: table_info.clone(),
             catalog_info: catalog_info.clone(),
             column_ids: snapshot.schema.to_leaf_column_id_set(),
+            block_size,
         }));
 
         if is_distributed {
@@ -161,15 +245,49 @@ impl OptimizeTableInterpreter {
             )));
         }
 
-        if matches!(target, CompactTarget::Segments) {
+        // keep the other compactions of the table off until this one is committed or aborted.
+        let Some(compaction_lock) =
+            CompactionLock::try_acquire(self.ctx.clone(), table_info.ident.table_id).await?
+        else {
+            info!(
+                "table '{}' is being compacted by another query, skip compaction",
+                self.plan.table
+            );
+            self.already_compacting.store(true, Ordering::Relaxed);
+            return Ok(PipelineBuildResult::create());
+        };
+
+        // take the digest of the table before compaction, if verification is enabled.
+        let verifier = if self.ctx.get_settings().get_enable_compact_verification()? {
+            CompactionVerifier::try_create(
+                self.ctx.clone(),
+                catalog.clone(),
+                &self.plan,
+                table.as_ref(),
+            )
+            .await?
+        } else {
+            None
+        };
+
+        if !matches!(target, CompactTarget::Blocks { .. }) {
             table
-                .compact_segments(self.ctx.clone(), table_lock, self.plan.limit)
+                .compact_segments(
+                    self.ctx.clone(),
+                    table_lock,
+                    target.bytes_per_segment(),
+                    self.plan.limit,
+                )
                 .await?;
+            if let Some(verifier) = verifier {
+                verifier.verify().await?;
+            }
+            compaction_lock.release().await;
             return Ok(PipelineBuildResult::create());
         }
 
         let res = table
-            .compact_blocks(self.ctx.clone(), self.plan.limit)
+            .compact_blocks(self.ctx.clone(), target.block_size(), self.plan.limit)
             .await?;
 
         let catalog_info = catalog.info();
@@ -183,12 +301,22 @@ impl OptimizeTableInterpreter {
                 table_info,
                 snapshot,
                 catalog_info,
+                target.block_size(),
                 compact_is_distributed,
                 self.plan.need_lock,
             )?;
 
-            let build_res =
+            let mut build_res =
                 build_query_pipeline_without_render_result_set(&self.ctx, &physical_plan).await?;
+            if let Some(verifier) = verifier {
+                build_res
+                    .main_pipeline
+                    .set_on_finished(move |may_error| match may_error {
+                        Ok(_) => GlobalIORuntime::instance()
+                            .block_on(async move { verifier.verify().await }),
+                        Err(error_code) => Err(error_code.clone()),
+                    });
+            }
             build_res.main_pipeline
         } else {
             Pipeline::create()
@@ -277,6 +405,17 @@ impl OptimizeTableInterpreter {
             }
         }
 
+        if build_res.main_pipeline.is_empty() {
+            compaction_lock.release().await;
+        } else {
+            build_res.main_pipeline.set_on_finished(move |_| {
+                GlobalIORuntime::instance().block_on(async move {
+                    compaction_lock.release().await;
+                    Ok(())
+                })
+            });
+        }
+
         Ok(build_res)
     }
 }
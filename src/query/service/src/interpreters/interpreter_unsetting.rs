@@ -17,6 +17,7 @@ use std::sync::Arc;
 use databend_common_config::GlobalConfig;
 use databend_common_exception::Result;
 use databend_common_sql::plans::UnSettingPlan;
+use log::warn;
 
 use crate::interpreters::Interpreter;
 use crate::pipelines::PipelineBuildResult;
@@ -33,6 +34,24 @@ impl UnSettingInterpreter {
     pub fn try_create(ctx: Arc<QueryContext>, set: UnSettingPlan) -> Result<Self> {
         Ok(UnSettingInterpreter { ctx, set })
     }
+
+    // the value the variable is reset to, fails if the variable can not be unset.
+    fn default_value(&self, setting_key: &str) -> Result<String> {
+        let settings = self.ctx.get_shared_settings();
+        settings.check_unset_setting(setting_key)?;
+
+        let conf = GlobalConfig::instance();
+        let value = match setting_key {
+            "max_memory_usage" if conf.query.max_server_memory_usage != 0 => {
+                conf.query.max_server_memory_usage.to_string()
+            }
+            "max_threads" if conf.query.num_cpus != 0 => conf.query.num_cpus.to_string(),
+            _ => settings
+                .check_and_get_default_value(setting_key)?
+                .to_string(),
+        };
+        Ok(value)
+    }
 }
 
 #[async_trait::async_trait]
@@ -41,6 +60,10 @@ impl Interpreter for UnSettingInterpreter {
         "SettingInterpreter"
     }
 
+    fn is_setting(&self) -> bool {
+        true
+    }
+
     #[async_backtrace::framed]
     async fn execute2(&self) -> Result<PipelineBuildResult> {
         let plan = self.set.clone();
@@ -49,53 +72,36 @@ impl Interpreter for UnSettingInterpreter {
         let mut is_globals: Vec<bool> = vec![];
 
         let settings = self.ctx.get_shared_settings();
+
+        // check all the variables before unsetting any of them, so that the statement either
+        // unsets all of them or none.
+        let mut unset_vars = vec![];
         for var in plan.vars {
-            let (ok, value) = match var.to_lowercase().as_str() {
-                // To be compatible with some drivers
-                "sql_mode" | "autocommit" => (false, String::from("")),
-                setting_key => {
-                    // TODO(liyz): why drop the global setting without checking the variable is global or not?
-                    self.ctx
-                        .get_shared_settings()
-                        .try_drop_global_setting(setting_key)
-                        .await?;
+            let setting_key = var.to_lowercase();
+            // To be compatible with some drivers
+            if matches!(setting_key.as_str(), "sql_mode" | "autocommit") {
+                continue;
+            }
+            let default_val = self
+                .default_value(&setting_key)
+                .map_err(|e| e.add_message(format!("Failed to unset variable {:?}", var)))?;
+            unset_vars.push((var, setting_key, default_val));
+        }
+
+        for (var, setting_key, value) in unset_vars {
+            // TODO(liyz): why drop the global setting without checking the variable is global or not?
+            settings.try_drop_global_setting(&setting_key).await?;
 
-                    let default_val = {
-                        if setting_key == "max_memory_usage" {
-                            let conf = GlobalConfig::instance();
-                            if conf.query.max_server_memory_usage == 0 {
-                                settings
-                                    .check_and_get_default_value(setting_key)?
-                                    .to_string()
-                            } else {
-                                conf.query.max_server_memory_usage.to_string()
-                            }
-                        } else if setting_key == "max_threads" {
-                            let conf = GlobalConfig::instance();
-                            if conf.query.num_cpus == 0 {
-                                settings
-                                    .check_and_get_default_value(setting_key)?
-                                    .to_string()
-                            } else {
-                                conf.query.num_cpus.to_string()
-                            }
-                        } else {
-                            settings
-                                .check_and_get_default_value(setting_key)?
-                                .to_string()
-                        }
-                    };
-                    (true, default_val)
-                }
-            };
-            if ok {
-                // reset the current ctx settings, just remove it.
-                self.ctx.get_shared_settings().unset_setting(&var);
-                // set effect, this can be considered to be removed in the future.
-                keys.push(var);
-                values.push(value);
-                is_globals.push(false);
+            if let Some(warning) = settings.deprecation_warning(&var) {
+                warn!("{}", warning);
+                self.ctx.push_warning(warning);
             }
+            // reset the current ctx settings, just remove it.
+            settings.unset_setting(&var);
+            // set effect, this can be considered to be removed in the future.
+            keys.push(var);
+            values.push(value);
+            is_globals.push(false);
         }
         self.ctx.set_affect(QueryAffect::ChangeSettings {
             keys,
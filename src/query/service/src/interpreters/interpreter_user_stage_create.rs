@@ -20,6 +20,7 @@ use databend_common_exception::Result;
 use databend_common_management::RoleApi;
 use databend_common_meta_app::principal::OwnershipObject;
 use databend_common_meta_app::principal::StageType;
+use databend_common_meta_app::schema::CreateOption;
 use databend_common_meta_types::MatchSeq;
 use databend_common_sql::plans::CreateStagePlan;
 use databend_common_users::RoleCacheManager;
@@ -68,7 +69,10 @@ impl Interpreter for CreateUserStageInterpreter {
         let quota_api = user_mgr.get_tenant_quota_api_client(&plan.tenant)?;
         let quota = quota_api.get_quota(MatchSeq::GE(0)).await?.data;
         let stages = user_mgr.get_stages(&plan.tenant).await?;
-        if quota.max_stages != 0 && stages.len() >= quota.max_stages as usize {
+        // Replacing an existing stage does not add up to the quota.
+        let replace_existing = matches!(plan.create_option, CreateOption::CreateOrReplace)
+            && stages.iter().any(|s| s.stage_name == user_stage.stage_name);
+        if !replace_existing && quota.max_stages != 0 && stages.len() >= quota.max_stages as usize {
             return Err(ErrorCode::TenantQuotaExceeded(format!(
                 "Max stages quota exceeded {}",
                 quota.max_stages
@@ -84,7 +88,7 @@ impl Interpreter for CreateUserStageInterpreter {
         user_stage.creator = Some(self.ctx.get_current_user()?.identity());
         user_stage.created_on = Utc::now();
         let _create_stage = user_mgr
-            .add_stage(&plan.tenant, user_stage, plan.if_not_exists)
+            .add_stage(&plan.tenant, user_stage, &plan.create_option)
             .await?;
 
         // Grant ownership as the current role
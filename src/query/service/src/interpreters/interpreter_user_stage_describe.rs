@@ -0,0 +1,157 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use databend_common_base::base::mask_string;
+use databend_common_exception::Result;
+use databend_common_expression::types::StringType;
+use databend_common_expression::DataBlock;
+use databend_common_expression::FromData;
+use databend_common_meta_app::principal::StageParams;
+use databend_common_meta_app::principal::StageType;
+use databend_common_sql::plans::DescribeStagePlan;
+use databend_common_users::UserApiProvider;
+use log::debug;
+use serde_json::Value;
+
+use crate::interpreters::Interpreter;
+use crate::pipelines::PipelineBuildResult;
+use crate::sessions::QueryContext;
+use crate::sessions::TableContext;
+
+#[derive(Debug)]
+pub struct DescribeUserStageInterpreter {
+    ctx: Arc<QueryContext>,
+    plan: DescribeStagePlan,
+}
+
+impl DescribeUserStageInterpreter {
+    pub fn try_create(ctx: Arc<QueryContext>, plan: DescribeStagePlan) -> Result<Self> {
+        Ok(DescribeUserStageInterpreter { ctx, plan })
+    }
+}
+
+#[async_trait::async_trait]
+impl Interpreter for DescribeUserStageInterpreter {
+    fn name(&self) -> &str {
+        "DescribeUserStageInterpreter"
+    }
+
+    #[minitrace::trace]
+    #[async_backtrace::framed]
+    async fn execute2(&self) -> Result<PipelineBuildResult> {
+        debug!("ctx.id" = self.ctx.get_id().as_str(); "describe_user_stage_execute");
+
+        let tenant = self.ctx.get_tenant();
+        let stage = UserApiProvider::instance()
+            .get_stage(&tenant, &self.plan.name)
+            .await?;
+
+        // The Display of StorageParams only carries the location.
+        let url = match stage.stage_type {
+            StageType::External => Some(stage.stage_params.storage.to_string()),
+            StageType::LegacyInternal | StageType::Internal | StageType::User => None,
+        };
+
+        PipelineBuildResult::from_blocks(vec![DataBlock::new_from_columns(vec![
+            StringType::from_data(vec![stage.stage_name.clone()]),
+            StringType::from_data(vec![stage.stage_type.to_string()]),
+            StringType::from_opt_data(vec![url]),
+            StringType::from_data(vec![masked_stage_params(&stage.stage_params)?]),
+            StringType::from_data(vec![format!("{:?}", stage.file_format_params)]),
+            StringType::from_data(vec![format!("{:?}", stage.copy_options)]),
+            StringType::from_data(vec![stage.comment.clone()]),
+        ])])
+    }
+}
+
+/// Keys of the storage configs that are never credentials, the values of the other keys are
+/// masked, so that a new credential of a storage config is not shown by default.
+const PLAIN_KEYS: &[&str] = &[
+    "type",
+    "endpoint",
+    "endpoint_url",
+    "presign_endpoint_url",
+    "bucket",
+    "container",
+    "root",
+    "region",
+    "name_node",
+    "paths",
+    "repo_id",
+    "repo_type",
+    "revision",
+    "role_arn",
+    "external_id",
+    "username",
+    "account_name",
+];
+
+/// Number of the trailing characters of a credential left unmasked.
+const UNMASK_LEN: usize = 4;
+
+/// The stage params as JSON, with the credentials masked but their last 4 characters.
+fn masked_stage_params(params: &StageParams) -> Result<String> {
+    let mut value = serde_json::to_value(params)?;
+    mask_credentials(&mut value);
+    Ok(value.to_string())
+}
+
+fn mask_credentials(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if PLAIN_KEYS.contains(&key.as_str()) {
+                    continue;
+                }
+                match value {
+                    Value::String(s) => *s = mask_string(s, UNMASK_LEN),
+                    value => mask_credentials(value),
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(mask_credentials),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use databend_common_exception::Result;
+    use databend_common_meta_app::principal::StageParams;
+    use databend_common_meta_app::storage::StorageCosConfig;
+    use databend_common_meta_app::storage::StorageParams;
+
+    use super::masked_stage_params;
+
+    #[test]
+    fn test_masked_stage_params() -> Result<()> {
+        let params = StageParams {
+            storage: StorageParams::Cos(StorageCosConfig {
+                secret_id: "AKIDabcdefgh".to_string(),
+                secret_key: "0123456789".to_string(),
+                bucket: "load".to_string(),
+                endpoint_url: "https://cos.ap-beijing.myqcloud.com".to_string(),
+                root: "/files/".to_string(),
+            }),
+        };
+
+        assert_eq!(
+            masked_stage_params(&params)?,
+            r#"{"storage":{"type":"Cos","secret_id":"******efgh","secret_key":"******6789","bucket":"load","endpoint_url":"https://cos.ap-beijing.myqcloud.com","root":"/files/"}}"#
+        );
+        Ok(())
+    }
+}
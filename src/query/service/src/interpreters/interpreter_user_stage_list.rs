@@ -0,0 +1,91 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use databend_common_exception::Result;
+use databend_common_expression::types::StringType;
+use databend_common_expression::types::UInt64Type;
+use databend_common_expression::DataBlock;
+use databend_common_expression::FromData;
+use databend_common_sql::plans::ListStagePlan;
+use databend_common_storage::StageFilesInfo;
+use databend_common_storages_stage::StageTable;
+use log::debug;
+
+use crate::interpreters::Interpreter;
+use crate::pipelines::PipelineBuildResult;
+use crate::sessions::QueryContext;
+use crate::sessions::TableContext;
+
+#[derive(Debug)]
+pub struct ListUserStageInterpreter {
+    ctx: Arc<QueryContext>,
+    plan: ListStagePlan,
+}
+
+impl ListUserStageInterpreter {
+    pub fn try_create(ctx: Arc<QueryContext>, plan: ListStagePlan) -> Result<Self> {
+        Ok(ListUserStageInterpreter { ctx, plan })
+    }
+}
+
+#[async_trait::async_trait]
+impl Interpreter for ListUserStageInterpreter {
+    fn name(&self) -> &str {
+        "ListUserStageInterpreter"
+    }
+
+    #[minitrace::trace]
+    #[async_backtrace::framed]
+    async fn execute2(&self) -> Result<PipelineBuildResult> {
+        debug!("ctx.id" = self.ctx.get_id().as_str(); "list_user_stage_execute");
+
+        let plan = self.plan.clone();
+        let op = StageTable::get_op(&plan.stage)?;
+        // the pattern is compiled by the binder, the same as REMOVE.
+        let files = StageFilesInfo::list_files_with_pattern(
+            &op,
+            &plan.path,
+            plan.matcher,
+            false,
+            usize::MAX,
+        )
+        .await?;
+
+        let names: Vec<String> = files.iter().map(|file| file.path.clone()).collect();
+        let sizes: Vec<u64> = files.iter().map(|file| file.size).collect();
+        let etags: Vec<Option<String>> = files.iter().map(|file| file.etag.clone()).collect();
+        let last_modifieds: Vec<String> = files
+            .iter()
+            .map(|file| {
+                file.last_modified
+                    .format("%Y-%m-%d %H:%M:%S.%3f %z")
+                    .to_string()
+            })
+            .collect();
+        let creators: Vec<Option<String>> = files
+            .iter()
+            .map(|file| file.creator.as_ref().map(|c| c.to_string()))
+            .collect();
+
+        PipelineBuildResult::from_blocks(vec![DataBlock::new_from_columns(vec![
+            StringType::from_data(names),
+            UInt64Type::from_data(sizes),
+            StringType::from_opt_data(etags),
+            StringType::from_data(last_modifieds),
+            StringType::from_opt_data(creators),
+        ])])
+    }
+}
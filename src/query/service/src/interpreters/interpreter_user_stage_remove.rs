@@ -52,22 +52,18 @@ impl Interpreter for RemoveUserStageInterpreter {
 
         let plan = self.plan.clone();
         let op = StageTable::get_op(&self.plan.stage)?;
-        let pattern = if plan.pattern.is_empty() {
-            None
-        } else {
-            Some(plan.pattern.clone())
-        };
-        let files_info = StageFilesInfo {
-            path: plan.path.clone(),
-            files: None,
-            pattern,
-        };
-        let files: Vec<String> = files_info
-            .list(&op, false, None)
-            .await?
-            .into_iter()
-            .map(|file_with_meta| file_with_meta.path)
-            .collect::<Vec<_>>();
+        // the pattern is compiled by the binder.
+        let files: Vec<String> = StageFilesInfo::list_files_with_pattern(
+            &op,
+            &plan.path,
+            plan.matcher,
+            false,
+            usize::MAX,
+        )
+        .await?
+        .into_iter()
+        .map(|file_with_meta| file_with_meta.path)
+        .collect::<Vec<_>>();
 
         let table_ctx: Arc<dyn TableContext> = self.ctx.clone();
         let file_op = Files::create(table_ctx, op);
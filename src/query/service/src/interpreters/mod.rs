@@ -117,7 +117,9 @@ mod interpreter_user_alter;
 mod interpreter_user_create;
 mod interpreter_user_drop;
 mod interpreter_user_stage_create;
+mod interpreter_user_stage_describe;
 mod interpreter_user_stage_drop;
+mod interpreter_user_stage_list;
 mod interpreter_user_stage_remove;
 mod interpreter_user_udf_alter;
 mod interpreter_user_udf_create;
@@ -133,6 +135,7 @@ mod interpreter_virtual_column_refresh;
 
 pub use access::ManagementModeAccess;
 pub use common::InterpreterQueryLog;
+pub use common::TableDigest;
 pub use hook::HookOperator;
 pub use interpreter::Interpreter;
 pub use interpreter::InterpreterPtr;
@@ -211,7 +214,9 @@ pub use interpreter_user_alter::AlterUserInterpreter;
 pub use interpreter_user_create::CreateUserInterpreter;
 pub use interpreter_user_drop::DropUserInterpreter;
 pub use interpreter_user_stage_create::CreateUserStageInterpreter;
+pub use interpreter_user_stage_describe::DescribeUserStageInterpreter;
 pub use interpreter_user_stage_drop::DropUserStageInterpreter;
+pub use interpreter_user_stage_list::ListUserStageInterpreter;
 pub use interpreter_user_stage_remove::RemoveUserStageInterpreter;
 pub use interpreter_user_udf_alter::AlterUserUDFInterpreter;
 pub use interpreter_user_udf_create::CreateUserUDFInterpreter;
@@ -0,0 +1,166 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use databend_common_base::base::tokio::sync::oneshot;
+use databend_common_base::base::tokio::sync::Notify;
+use databend_common_base::base::tokio::time::sleep;
+use databend_common_base::runtime::GlobalIORuntime;
+use databend_common_base::runtime::TrySpawn;
+use databend_common_base::GLOBAL_TASK;
+use databend_common_catalog::table_context::TableContext;
+use databend_common_exception::Result;
+use databend_common_meta_kvapi::kvapi::KVApi;
+use databend_common_meta_kvapi::kvapi::UpsertKVReq;
+use databend_common_meta_store::MetaStore;
+use databend_common_meta_types::MatchSeq;
+use databend_common_meta_types::MetaSpec;
+use databend_common_meta_types::Operation;
+use databend_common_users::UserApiProvider;
+use futures::future::select;
+use futures::future::Either;
+use log::info;
+use log::warn;
+
+/// __fd_compaction_lock/table_id -> id of the query holding the lock
+const PREFIX_COMPACTION_LOCK: &str = "__fd_compaction_lock";
+
+/// A best-effort advisory lock in the meta, held for the whole run of a compaction so
+/// that two compactions of the same table do not run at the same time.
+///
+/// Unlike the table lock, it does not block the other mutations of the table. The key
+/// expires in `table_lock_expire_secs` unless extended by the holder, so the lock of a
+/// crashed node is released eventually.
+pub struct CompactionLock;
+
+impl CompactionLock {
+    /// Takes the compaction lock of the table, `None` means another compaction holds it.
+    #[async_backtrace::framed]
+    pub async fn try_acquire(
+        ctx: Arc<dyn TableContext>,
+        table_id: u64,
+    ) -> Result<Option<CompactionLockGuard>> {
+        let expire_secs = ctx.get_settings().get_table_lock_expire_secs()?.max(1);
+        let meta = UserApiProvider::instance().get_meta_store_client();
+        let key = format!("{}/{}", PREFIX_COMPACTION_LOCK, table_id);
+        let holder = ctx.get_id();
+
+        let reply = meta
+            .upsert_kv(UpsertKVReq::new(
+                &key,
+                MatchSeq::Exact(0),
+                Operation::Update(holder.clone().into_bytes()),
+                Some(MetaSpec::new_ttl(Duration::from_secs(expire_secs))),
+            ))
+            .await?;
+        let Ok(seq) = reply.added_seq_or_else(|prev| prev) else {
+            return Ok(None);
+        };
+
+        let notify = Arc::new(Notify::new());
+        let (tx, rx) = oneshot::channel();
+        GlobalIORuntime::instance().spawn(GLOBAL_TASK, {
+            let notify = notify.clone();
+            async move {
+                hold_lock(meta, key, holder, seq, expire_secs, notify).await;
+                let _ = tx.send(());
+            }
+        });
+        Ok(Some(CompactionLockGuard {
+            notify,
+            released: Some(rx),
+        }))
+    }
+}
+
+/// Releases the compaction lock once dropped, in the background.
+pub struct CompactionLockGuard {
+    notify: Arc<Notify>,
+    released: Option<oneshot::Receiver<()>>,
+}
+
+impl CompactionLockGuard {
+    /// Releases the lock and waits for it to be deleted from the meta, so that the next
+    /// compaction of the table started right after is not skipped.
+    #[async_backtrace::framed]
+    pub async fn release(mut self) {
+        self.notify.notify_one();
+        if let Some(released) = self.released.take() {
+            let _ = released.await;
+        }
+    }
+}
+
+impl Drop for CompactionLockGuard {
+    fn drop(&mut self) {
+        // a permit is stored if the holder is not waiting yet.
+        self.notify.notify_one();
+    }
+}
+
+/// Extends the lock until notified, then deletes it.
+async fn hold_lock(
+    meta: Arc<MetaStore>,
+    key: String,
+    holder: String,
+    mut seq: u64,
+    expire_secs: u64,
+    notify: Arc<Notify>,
+) {
+    let interval = Duration::from_millis(expire_secs * 1000 / 3);
+    let mut notified = Box::pin(notify.notified());
+    loop {
+        match select(notified, Box::pin(sleep(interval))).await {
+            Either::Left((_, _)) => break,
+            Either::Right((_, new_notified)) => {
+                notified = new_notified;
+                let extend = meta
+                    .upsert_kv(UpsertKVReq::new(
+                        &key,
+                        MatchSeq::Exact(seq),
+                        Operation::Update(holder.clone().into_bytes()),
+                        Some(MetaSpec::new_ttl(Duration::from_secs(expire_secs))),
+                    ))
+                    .await;
+                match extend {
+                    Ok(reply) => match (reply.is_changed(), reply.result) {
+                        (true, Some(current)) => seq = current.seq,
+                        _ => {
+                            // expired, maybe taken by another compaction, nothing to release.
+                            warn!("compaction lock {} is lost by query {}", key, holder);
+                            return;
+                        }
+                    },
+                    Err(e) => warn!("failed to extend compaction lock {}: {}", key, e),
+                }
+            }
+        }
+    }
+
+    let delete = meta
+        .upsert_kv(UpsertKVReq::new(
+            &key,
+            MatchSeq::Exact(seq),
+            Operation::Delete,
+            None,
+        ))
+        .await;
+    match delete {
+        Ok(_) => info!("compaction lock {} is released by query {}", key, holder),
+        // the key expires anyway.
+        Err(e) => warn!("failed to release compaction lock {}: {}", key, e),
+    }
+}
@@ -12,8 +12,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod compaction_lock;
 mod lock_holder;
 mod lock_manager;
 mod table_lock;
 
+pub use compaction_lock::CompactionLock;
+pub use compaction_lock::CompactionLockGuard;
 pub use lock_manager::LockManager;
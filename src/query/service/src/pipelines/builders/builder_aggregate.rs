@@ -103,6 +103,10 @@ impl PipelineBuilder {
             &aggregate.group_by,
             &aggregate.agg_funcs,
             None,
+            None,
+            None,
+            None,
+            false,
         )?;
 
         if params.group_columns.is_empty() {
@@ -193,11 +197,28 @@ impl PipelineBuilder {
     }
 
     pub(crate) fn build_aggregate_final(&mut self, aggregate: &AggregateFinal) -> Result<()> {
+        let max_groups = match self.settings.get_group_by_max_groups()? {
+            0 => None,
+            max_groups => Some(max_groups as usize),
+        };
+        let max_arena_bytes = match self.settings.get_group_by_final_max_arena_bytes()? {
+            0 => None,
+            max_arena_bytes => Some(max_arena_bytes as usize),
+        };
+        let final_merge_threads = match self.settings.get_group_by_final_merge_threads()? {
+            0 | 1 => None,
+            threads => Some(threads as usize),
+        };
+        let final_preserve_order = self.settings.get_group_by_final_preserve_order()?;
         let params = Self::build_aggregator_params(
             aggregate.before_group_by_schema.clone(),
             &aggregate.group_by,
             &aggregate.agg_funcs,
             aggregate.limit,
+            max_groups,
+            max_arena_bytes,
+            final_merge_threads,
+            final_preserve_order,
         )?;
 
         if params.group_columns.is_empty() {
@@ -261,6 +282,10 @@ impl PipelineBuilder {
         group_by: &[IndexType],
         agg_funcs: &[AggregateFunctionDesc],
         limit: Option<usize>,
+        max_groups: Option<usize>,
+        max_arena_bytes: Option<usize>,
+        final_merge_threads: Option<usize>,
+        final_preserve_order: bool,
     ) -> Result<Arc<AggregatorParams>> {
         let mut agg_args = Vec::with_capacity(agg_funcs.len());
         let (group_by, group_data_types) = group_by
@@ -300,6 +325,10 @@ impl PipelineBuilder {
             &aggs,
             &agg_args,
             limit,
+            max_groups,
+            max_arena_bytes,
+            final_merge_threads,
+            final_preserve_order,
         )?;
 
         Ok(params)
@@ -36,6 +36,7 @@ impl PipelineBuilder {
             self.ctx.clone(),
             compact_block.parts.clone(),
             compact_block.column_ids.clone(),
+            compact_block.block_size,
             &mut self.main_pipeline,
         )
     }
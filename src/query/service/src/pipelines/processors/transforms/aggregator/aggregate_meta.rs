@@ -17,6 +17,7 @@ use std::fmt::Formatter;
 use std::ops::Range;
 
 use databend_common_expression::BlockMetaInfo;
+use databend_common_expression::BlockMetaInfoDowncast;
 use databend_common_expression::BlockMetaInfoPtr;
 use databend_common_expression::Column;
 use databend_common_expression::DataBlock;
@@ -150,3 +151,32 @@ impl<Method: HashMethodBounds, V: Send + Sync + 'static> BlockMetaInfo
         unimplemented!("Unimplemented clone for AggregateMeta")
     }
 }
+
+/// Attached to the output of `TransformFinalGroupBy`, the actual cardinality of a bucket
+/// to compare against the estimation of the planner.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct GroupByStats {
+    // Number of the distinct keys of the bucket, including the ones cut by the limit.
+    pub distinct_keys: usize,
+    pub estimated_value_size: usize,
+}
+
+impl GroupByStats {
+    pub fn create(distinct_keys: usize, estimated_value_size: usize) -> BlockMetaInfoPtr {
+        Box::new(GroupByStats {
+            distinct_keys,
+            estimated_value_size,
+        })
+    }
+}
+
+#[typetag::serde(name = "group_by_stats")]
+impl BlockMetaInfo for GroupByStats {
+    fn equals(&self, info: &Box<dyn BlockMetaInfo>) -> bool {
+        GroupByStats::downcast_ref_from(info).is_some_and(|other| self == other)
+    }
+
+    fn clone_self(&self) -> Box<dyn BlockMetaInfo> {
+        Box::new(self.clone())
+    }
+}
@@ -40,6 +40,14 @@ pub struct AggregatorParams {
 
     // Limit is push down to AggregatorTransform
     pub limit: Option<usize>,
+    // The GROUP BY fails once it has more distinct groups.
+    pub max_groups: Option<usize>,
+    // The final GROUP BY of a bucket fails once the arena of its keys takes more bytes.
+    pub max_arena_bytes: Option<usize>,
+    // The final GROUP BY of a bucket merges its payloads on this many threads.
+    pub final_merge_threads: Option<usize>,
+    // The final GROUP BY of a bucket emits its groups in the order they are first seen.
+    pub final_preserve_order: bool,
 }
 
 impl AggregatorParams {
@@ -50,6 +58,10 @@ impl AggregatorParams {
         agg_funcs: &[AggregateFunctionRef],
         agg_args: &[Vec<usize>],
         limit: Option<usize>,
+        max_groups: Option<usize>,
+        max_arena_bytes: Option<usize>,
+        final_merge_threads: Option<usize>,
+        final_preserve_order: bool,
     ) -> Result<Arc<AggregatorParams>> {
         let mut states_offsets: Vec<usize> = Vec::with_capacity(agg_funcs.len());
         let mut states_layout = None;
@@ -67,6 +79,10 @@ impl AggregatorParams {
             layout: states_layout,
             offsets_aggregate_states: states_offsets,
             limit,
+            max_groups,
+            max_arena_bytes,
+            final_merge_threads,
+            final_preserve_order,
         }))
     }
 
@@ -12,23 +12,32 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use bumpalo::Bump;
+use databend_common_base::runtime::ThreadPool;
 use databend_common_exception::ErrorCode;
 use databend_common_exception::Result;
 use databend_common_expression::DataBlock;
 use databend_common_hashtable::HashtableEntryRefLike;
 use databend_common_hashtable::HashtableLike;
+use databend_common_metrics::transform::metrics_inc_group_by_final_arena_allocated_bytes;
+use databend_common_metrics::transform::metrics_inc_group_by_final_distinct_keys;
 use databend_common_pipeline_core::processors::InputPort;
 use databend_common_pipeline_core::processors::OutputPort;
 use databend_common_pipeline_core::processors::Processor;
 use databend_common_pipeline_transforms::processors::BlockMetaTransform;
 use databend_common_pipeline_transforms::processors::BlockMetaTransformer;
 
+use crate::pipelines::processors::transforms::aggregator::aggregate_cell::GroupByHashTableDropper;
 use crate::pipelines::processors::transforms::aggregator::aggregate_meta::AggregateMeta;
+use crate::pipelines::processors::transforms::aggregator::aggregate_meta::HashTablePayload;
 use crate::pipelines::processors::transforms::aggregator::estimated_key_size;
 use crate::pipelines::processors::transforms::aggregator::AggregatorParams;
+use crate::pipelines::processors::transforms::aggregator::GroupByStats;
+use crate::pipelines::processors::transforms::aggregator::HashTableCell;
 use crate::pipelines::processors::transforms::group_by::GroupColumnsBuilder;
 use crate::pipelines::processors::transforms::group_by::HashMethodBounds;
 use crate::pipelines::processors::transforms::group_by::KeysColumnIter;
@@ -36,6 +45,15 @@ use crate::pipelines::processors::transforms::group_by::KeysColumnIter;
 pub struct TransformFinalGroupBy<Method: HashMethodBounds> {
     method: Method,
     params: Arc<AggregatorParams>,
+    // emits the groups of a bucket in the order they are first seen, instead of the order
+    // of the hashtable.
+    preserve_order: bool,
+    // groups of the buckets finished by all the transforms of the pipeline, checked against
+    // `max_groups` of the params.
+    num_groups: Arc<AtomicUsize>,
+    // threads merging the payloads of a bucket in parallel, see `final_merge_threads` of
+    // the params.
+    merge_pool: Option<Arc<ThreadPool>>,
 }
 
 impl<Method: HashMethodBounds> TransformFinalGroupBy<Method> {
@@ -44,75 +62,239 @@ impl<Method: HashMethodBounds> TransformFinalGroupBy<Method> {
         output: Arc<OutputPort>,
         method: Method,
         params: Arc<AggregatorParams>,
+        preserve_order: bool,
+        num_groups: Arc<AtomicUsize>,
+        merge_pool: Option<Arc<ThreadPool>>,
     ) -> Result<Box<dyn Processor>> {
         Ok(Box::new(BlockMetaTransformer::create(
             input,
             output,
-            TransformFinalGroupBy::<Method> { method, params },
+            TransformFinalGroupBy::<Method> {
+                method,
+                params,
+                preserve_order,
+                num_groups,
+                merge_pool,
+            },
         )))
     }
 }
 
-impl<Method> BlockMetaTransform<AggregateMeta<Method, ()>> for TransformFinalGroupBy<Method>
-where Method: HashMethodBounds
-{
-    const NAME: &'static str = "TransformFinalGroupBy";
+impl<Method: HashMethodBounds> TransformFinalGroupBy<Method> {
+    // `new_value` gives the value of a group from the index it is inserted at, only the
+    // indexes are kept if the order is preserved.
+    fn merge_buckets<V: Copy + Ord + Send + Sync + 'static>(
+        &mut self,
+        bucket: isize,
+        data: Vec<AggregateMeta<Method, ()>>,
+        new_value: impl Fn(usize) -> V,
+    ) -> Result<DataBlock> {
+        let arena = Arc::new(Bump::new());
+        let mut hashtable = self.method.create_hash_table::<V>(arena.clone())?;
+        let limit = self.params.limit.unwrap_or(usize::MAX);
+        // the buckets of other transforms may be merged at the same time, the total is
+        // checked again once this bucket is done.
+        let max_groups = self.params.max_groups.unwrap_or(usize::MAX);
+        let max_bucket_groups = max_groups.saturating_sub(self.num_groups.load(Ordering::Relaxed));
+        merge_payloads(
+            &self.method,
+            &self.params,
+            &mut hashtable,
+            &arena,
+            bucket,
+            data,
+            max_bucket_groups,
+            new_value,
+        )?;
 
-    fn transform(&mut self, meta: AggregateMeta<Method, ()>) -> Result<DataBlock> {
-        if let AggregateMeta::Partitioned { bucket, data } = meta {
-            let arena = Arc::new(Bump::new());
-            let mut hashtable = self.method.create_hash_table::<()>(arena)?;
-            'merge_hashtable: for bucket_data in data {
-                match bucket_data {
-                    AggregateMeta::Spilled(_) => unreachable!(),
-                    AggregateMeta::BucketSpilled(_) => unreachable!(),
-                    AggregateMeta::Spilling(_) => unreachable!(),
-                    AggregateMeta::Partitioned { .. } => unreachable!(),
-                    AggregateMeta::Serialized(payload) => {
-                        debug_assert!(bucket == payload.bucket);
-                        let column = payload.get_group_by_column();
-                        let keys_iter = self.method.keys_iter_from_column(column)?;
-
-                        unsafe {
-                            for key in keys_iter.iter() {
-                                let _ = hashtable.insert_and_entry(key);
-                            }
+        if self.params.max_groups.is_some() {
+            let groups = self
+                .num_groups
+                .fetch_add(hashtable.len(), Ordering::Relaxed);
+            if groups + hashtable.len() > max_groups {
+                return Err(too_many_groups(max_groups));
+            }
+        }
 
-                            if let Some(limit) = self.params.limit {
-                                if hashtable.len() >= limit {
-                                    break 'merge_hashtable;
-                                }
-                            }
-                        }
-                    }
-                    AggregateMeta::HashTable(payload) => unsafe {
-                        debug_assert!(bucket == payload.bucket);
+        // the arena is allocated through the tracked global allocator, so it is already
+        // accounted to the memory of the query, the bytes are recorded for observability.
+        metrics_inc_group_by_final_arena_allocated_bytes(arena.allocated_bytes() as u64);
+        // the actual cardinality of the bucket, to compare against the estimation of the planner.
+        metrics_inc_group_by_final_distinct_keys(hashtable.len() as u64);
 
-                        for key in payload.cell.hashtable.iter() {
-                            let _ = hashtable.insert_and_entry(key.key());
-                        }
+        let value_size = estimated_key_size(&hashtable);
+        let keys_len = hashtable.len().min(limit);
+
+        let mut group_columns_builder =
+            self.method
+                .group_columns_builder(keys_len, value_size, &self.params);
+
+        if self.preserve_order {
+            // sorted by the insertion indexes, as the values of the groups.
+            let mut group_entities = hashtable.iter().collect::<Vec<_>>();
+            group_entities.sort_unstable_by_key(|group_entity| *group_entity.get());
+            for group_entity in group_entities.into_iter().take(keys_len) {
+                group_columns_builder.append_value(group_entity.key());
+            }
+        } else {
+            for group_entity in hashtable.iter().take(keys_len) {
+                group_columns_builder.append_value(group_entity.key());
+            }
+        }
+
+        let stats = GroupByStats::create(hashtable.len(), value_size);
+        DataBlock::new_from_columns(group_columns_builder.finish()?).add_meta(Some(stats))
+    }
+
+    // merges the payloads into a hashtable per sub-group of them on the threads of the pool,
+    // the hashtables are then merged as the payloads of the bucket.
+    fn merge_in_parallel(
+        &self,
+        pool: &ThreadPool,
+        bucket: isize,
+        data: Vec<AggregateMeta<Method, ()>>,
+    ) -> Result<Vec<AggregateMeta<Method, ()>>> {
+        let threads = self.params.final_merge_threads.unwrap_or(1).min(data.len());
+        let mut sub_groups = (0..threads).map(|_| vec![]).collect::<Vec<_>>();
+        for (index, bucket_data) in data.into_iter().enumerate() {
+            sub_groups[index % threads].push(bucket_data);
+        }
+
+        // the keys of a sub-group are part of the keys of the bucket.
+        let max_groups = self.params.max_groups.unwrap_or(usize::MAX);
+        let max_bucket_groups = max_groups.saturating_sub(self.num_groups.load(Ordering::Relaxed));
+        let mut handles = Vec::with_capacity(threads);
+        for sub_group in sub_groups {
+            let method = self.method.clone();
+            let params = self.params.clone();
+            handles.push(pool.execute(move || -> Result<AggregateMeta<Method, ()>> {
+                let arena = Arc::new(Bump::new());
+                let mut hashtable = method.create_hash_table::<()>(arena.clone())?;
+                merge_payloads(
+                    &method,
+                    &params,
+                    &mut hashtable,
+                    &arena,
+                    bucket,
+                    sub_group,
+                    max_bucket_groups,
+                    |_| (),
+                )?;
+                metrics_inc_group_by_final_arena_allocated_bytes(arena.allocated_bytes() as u64);
+
+                let dropper = GroupByHashTableDropper::<Method>::create();
+                let cell = HashTableCell::create(hashtable, dropper);
+                Ok(AggregateMeta::HashTable(HashTablePayload { bucket, cell }))
+            }));
+        }
+
+        handles.into_iter().map(|handle| handle.join()).collect()
+    }
+}
+
+// merges the keys of the payloads of a bucket into the hashtable.
+#[allow(clippy::too_many_arguments)]
+fn merge_payloads<Method: HashMethodBounds, V: Copy + Send + Sync + 'static>(
+    method: &Method,
+    params: &AggregatorParams,
+    hashtable: &mut Method::HashTable<V>,
+    arena: &Bump,
+    bucket: isize,
+    data: Vec<AggregateMeta<Method, ()>>,
+    max_bucket_groups: usize,
+    new_value: impl Fn(usize) -> V,
+) -> Result<()> {
+    // no new group is inserted once there are enough groups for the limit.
+    let limit = params.limit.unwrap_or(usize::MAX);
+    let max_groups = params.max_groups.unwrap_or(usize::MAX);
+    'merge_hashtable: for bucket_data in data {
+        match bucket_data {
+            // the spilled buckets are read back by `TransformGroupBySpillReader` before
+            // this transform, so they are never left in the payloads of a bucket.
+            AggregateMeta::Spilled(_) | AggregateMeta::BucketSpilled(_) => {
+                return Err(ErrorCode::Internal(
+                    "TransformFinalGroupBy recv a spilled bucket that is not read back",
+                ));
+            }
+            AggregateMeta::Spilling(_) => {
+                return Err(ErrorCode::Internal(
+                    "TransformFinalGroupBy recv AggregateMeta::Spilling",
+                ));
+            }
+            AggregateMeta::Partitioned { .. } => {
+                return Err(ErrorCode::Internal(
+                    "TransformFinalGroupBy recv nested AggregateMeta::Partitioned",
+                ));
+            }
+            AggregateMeta::Serialized(payload) => {
+                debug_assert!(bucket == payload.bucket);
+                let column = payload.get_group_by_column();
+                let keys_iter = method.keys_iter_from_column(column)?;
 
-                        if let Some(limit) = self.params.limit {
-                            if hashtable.len() >= limit {
-                                break 'merge_hashtable;
+                unsafe {
+                    for key in keys_iter.iter() {
+                        let index = hashtable.len();
+                        if index >= limit {
+                            break 'merge_hashtable;
+                        }
+                        if let Ok(value) = hashtable.insert(key) {
+                            value.write(new_value(index));
+                            if index >= max_bucket_groups {
+                                return Err(too_many_groups(max_groups));
                             }
                         }
-                    },
+                    }
                 }
             }
+            AggregateMeta::HashTable(payload) => unsafe {
+                debug_assert!(bucket == payload.bucket);
 
-            let value_size = estimated_key_size(&hashtable);
-            let keys_len = hashtable.len();
-
-            let mut group_columns_builder =
-                self.method
-                    .group_columns_builder(keys_len, value_size, &self.params);
+                for key in payload.cell.hashtable.iter() {
+                    let index = hashtable.len();
+                    if index >= limit {
+                        break 'merge_hashtable;
+                    }
+                    if let Ok(value) = hashtable.insert(key.key()) {
+                        value.write(new_value(index));
+                        if index >= max_bucket_groups {
+                            return Err(too_many_groups(max_groups));
+                        }
+                    }
+                }
+            },
+        }
 
-            for group_entity in hashtable.iter() {
-                group_columns_builder.append_value(group_entity.key());
+        // the arena only grows with the keys, it is checked once per payload.
+        if let Some(max_arena_bytes) = params.max_arena_bytes {
+            if arena.allocated_bytes() > max_arena_bytes {
+                return Err(arena_exceeded(max_arena_bytes));
             }
+        }
+    }
+
+    Ok(())
+}
+
+impl<Method> BlockMetaTransform<AggregateMeta<Method, ()>> for TransformFinalGroupBy<Method>
+where Method: HashMethodBounds
+{
+    const NAME: &'static str = "TransformFinalGroupBy";
 
-            return Ok(DataBlock::new_from_columns(group_columns_builder.finish()?));
+    fn transform(&mut self, meta: AggregateMeta<Method, ()>) -> Result<DataBlock> {
+        if let AggregateMeta::Partitioned { bucket, data } = meta {
+            return match self.preserve_order {
+                true => self.merge_buckets(bucket, data, |index| index),
+                false => {
+                    // a limit stops the merge early, which the sub-groups can not share.
+                    let data = match &self.merge_pool {
+                        Some(pool) if self.params.limit.is_none() && data.len() > 1 => {
+                            self.merge_in_parallel(pool, bucket, data)?
+                        }
+                        _ => data,
+                    };
+                    self.merge_buckets(bucket, data, |_| ())
+                }
+            };
         }
 
         Err(ErrorCode::Internal(
@@ -120,3 +302,126 @@ where Method: HashMethodBounds
         ))
     }
 }
+
+fn too_many_groups(max_groups: usize) -> ErrorCode {
+    ErrorCode::TooManyGroups(format!(
+        "GROUP BY has more than {} distinct groups, the limit is set by the setting group_by_max_groups",
+        max_groups
+    ))
+}
+
+fn arena_exceeded(max_arena_bytes: usize) -> ErrorCode {
+    ErrorCode::GroupByArenaExceeded(format!(
+        "the keys of a GROUP BY bucket take more than {} bytes, the limit is set by the setting group_by_final_max_arena_bytes",
+        max_arena_bytes
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    use databend_common_exception::ErrorCode;
+    use databend_common_exception::Result;
+    use databend_common_expression::types::DataType;
+    use databend_common_expression::types::NumberDataType;
+    use databend_common_expression::types::UInt64Type;
+    use databend_common_expression::BlockMetaInfoDowncast;
+    use databend_common_expression::DataBlock;
+    use databend_common_expression::DataField;
+    use databend_common_expression::DataSchemaRefExt;
+    use databend_common_expression::FromData;
+    use databend_common_expression::HashMethodKeysU64;
+    use databend_common_pipeline_transforms::processors::BlockMetaTransform;
+
+    use super::TransformFinalGroupBy;
+    use crate::pipelines::processors::transforms::aggregator::aggregate_meta::AggregateMeta;
+    use crate::pipelines::processors::transforms::aggregator::aggregate_meta::BucketSpilledPayload;
+    use crate::pipelines::processors::transforms::aggregator::aggregate_meta::SerializedPayload;
+    use crate::pipelines::processors::transforms::aggregator::AggregatorParams;
+    use crate::pipelines::processors::transforms::aggregator::GroupByStats;
+
+    fn serialized(keys: Vec<u64>) -> AggregateMeta<HashMethodKeysU64, ()> {
+        AggregateMeta::Serialized(SerializedPayload {
+            bucket: 0,
+            data_block: DataBlock::new_from_columns(vec![UInt64Type::from_data(keys)]),
+        })
+    }
+
+    fn create_test_transform(
+        preserve_order: bool,
+    ) -> Result<TransformFinalGroupBy<HashMethodKeysU64>> {
+        let schema = DataSchemaRefExt::create(vec![DataField::new(
+            "a",
+            DataType::Number(NumberDataType::UInt64),
+        )]);
+        let params = AggregatorParams::try_create(
+            schema,
+            vec![DataType::Number(NumberDataType::UInt64)],
+            &[0],
+            &[],
+            &[],
+            None,
+            None,
+            None,
+            None,
+            preserve_order,
+        )?;
+
+        Ok(TransformFinalGroupBy::<HashMethodKeysU64> {
+            method: HashMethodKeysU64::default(),
+            params: params.clone(),
+            preserve_order: params.final_preserve_order,
+            num_groups: Arc::new(AtomicUsize::new(0)),
+            merge_pool: None,
+        })
+    }
+
+    #[test]
+    fn test_preserve_order_emits_first_seen_keys() -> Result<()> {
+        let mut transform = create_test_transform(true)?;
+        let data = vec![
+            serialized(vec![1000, 3, 77, 3, 42]),
+            serialized(vec![42, 5, 1000, 999_999, 0, 77]),
+        ];
+        let block = transform.transform(AggregateMeta::Partitioned { bucket: 0, data })?;
+
+        let expected = UInt64Type::from_data(vec![1000, 3, 77, 42, 5, 999_999, 0]);
+        assert_eq!(block.num_columns(), 1);
+        assert_eq!(block.columns()[0].value.as_column(), Some(&expected));
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_by_stats_of_output() -> Result<()> {
+        for preserve_order in [false, true] {
+            let mut transform = create_test_transform(preserve_order)?;
+            let data = vec![serialized(vec![1, 2, 2, 3]), serialized(vec![3, 4, 1])];
+            let block = transform.transform(AggregateMeta::Partitioned { bucket: 0, data })?;
+
+            let stats = block.get_meta().and_then(GroupByStats::downcast_ref_from);
+            assert_eq!(stats.map(|stats| stats.distinct_keys), Some(4));
+            assert_eq!(block.num_rows(), 4);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_spilled_payload_is_internal_error() -> Result<()> {
+        let mut transform = create_test_transform(false)?;
+        let data = vec![
+            serialized(vec![1, 2]),
+            AggregateMeta::BucketSpilled(BucketSpilledPayload {
+                bucket: 0,
+                location: "_spill_test".to_string(),
+                data_range: 0..0,
+                columns_layout: vec![],
+            }),
+        ];
+        let result = transform.transform(AggregateMeta::Partitioned { bucket: 0, data });
+
+        assert_eq!(result.err().map(|e| e.code()), Some(ErrorCode::INTERNAL));
+        Ok(())
+    }
+}
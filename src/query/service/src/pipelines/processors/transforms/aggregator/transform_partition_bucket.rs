@@ -17,8 +17,10 @@ use std::collections::btree_map::Entry;
 use std::collections::BTreeMap;
 use std::marker::PhantomData;
 use std::mem::take;
+use std::sync::atomic::AtomicUsize;
 use std::sync::Arc;
 
+use databend_common_base::runtime::ThreadPool;
 use databend_common_exception::ErrorCode;
 use databend_common_exception::Result;
 use databend_common_expression::BlockMetaInfoDowncast;
@@ -463,6 +465,14 @@ pub fn build_partition_bucket<Method: HashMethodBounds, V: Copy + Send + Sync +
         }
     })?;
 
+    let num_groups = Arc::new(AtomicUsize::new(0));
+    // the threads are shared by the final GROUP BY transforms of the pipeline.
+    let merge_pool = match params.final_merge_threads {
+        Some(threads) if params.aggregate_functions.is_empty() => {
+            Some(Arc::new(ThreadPool::create(threads)?))
+        }
+        _ => None,
+    };
     pipeline.add_transform(|input, output| {
         Ok(ProcessorPtr::create(
             match params.aggregate_functions.is_empty() {
@@ -471,6 +481,9 @@ pub fn build_partition_bucket<Method: HashMethodBounds, V: Copy + Send + Sync +
                     output,
                     method.clone(),
                     params.clone(),
+                    params.final_preserve_order,
+                    num_groups.clone(),
+                    merge_pool.clone(),
                 )?,
                 false => TransformFinalAggregate::try_create(
                     input,
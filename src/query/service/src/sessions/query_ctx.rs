@@ -21,6 +21,7 @@ use std::collections::VecDeque;
 use std::future::Future;
 use std::net::SocketAddr;
 use std::str::FromStr;
+use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
@@ -256,6 +257,15 @@ impl QueryContext {
         self.shared.pop_warnings()
     }
 
+    // The settings set by `SET LOCAL` for this query, which are already in `get_settings`,
+    // no longer wait for the next statement of the session.
+    pub fn consume_local_settings(&self) {
+        self.shared
+            .session
+            .get_settings()
+            .consume_local_settings(&self.shared.local_settings);
+    }
+
     pub fn get_data_metrics(&self) -> StorageMetrics {
         self.shared.get_data_metrics()
     }
@@ -528,6 +538,10 @@ impl TableContext for QueryContext {
         self.shared.check_aborting()
     }
 
+    fn get_aborting(&self) -> Arc<AtomicBool> {
+        self.shared.get_aborting()
+    }
+
     fn get_error(&self) -> Option<ErrorCode> {
         self.shared.get_error()
     }
@@ -631,6 +645,8 @@ impl TableContext for QueryContext {
             unsafe {
                 self.query_settings
                     .unchecked_apply_changes(&self.shared.get_settings());
+                self.query_settings
+                    .unchecked_apply_changes(&self.shared.local_settings);
             }
         }
 
@@ -120,6 +120,10 @@ pub struct QueryContextShared {
 
     // Records query level data cache metrics
     pub(in crate::sessions) query_cache_metrics: DataCacheMetrics,
+
+    /// The settings set by `SET LOCAL` before the query, which apply to this query only.
+    /// They are consumed once the query runs a statement other than `SET` and `UNSET`.
+    pub(in crate::sessions) local_settings: Arc<Settings>,
 }
 
 impl QueryContextShared {
@@ -127,6 +131,7 @@ impl QueryContextShared {
         session: Arc<Session>,
         cluster_cache: Arc<Cluster>,
     ) -> Result<Arc<QueryContextShared>> {
+        let local_settings = session.get_settings().local_settings();
         Ok(Arc::new(QueryContextShared {
             session,
             cluster_cache,
@@ -167,6 +172,7 @@ impl QueryContextShared {
             query_profiles: Arc::new(RwLock::new(HashMap::new())),
             runtime_filters: Default::default(),
             merge_into_join: Default::default(),
+            local_settings,
         }))
     }
 
@@ -0,0 +1,62 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use databend_common_base::base::tokio;
+use databend_common_exception::Result;
+use databend_common_sql::Planner;
+use databend_query::interpreters::InterpreterFactory;
+use databend_query::sessions::QueryContext;
+use databend_query::sessions::TableContext;
+use databend_query::test_kits::TestFixture;
+use futures_util::TryStreamExt;
+
+async fn execute_sql(ctx: &Arc<QueryContext>, sql: &str) -> Result<()> {
+    let mut planner = Planner::new(ctx.clone());
+    let (plan, _) = planner.plan_sql(sql).await?;
+    let interpreter = InterpreterFactory::get(ctx.clone(), &plan).await?;
+    interpreter
+        .execute(ctx.clone())
+        .await?
+        .try_collect::<Vec<_>>()
+        .await?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn test_deprecated_setting_warning() -> Result<()> {
+    let fixture = TestFixture::setup().await?;
+    let expected = "Setting 'storage_read_buffer_size' is deprecated and will be removed, use 'input_read_buffer_size' instead";
+
+    let ctx = fixture.new_query_ctx().await?;
+    execute_sql(&ctx, "SET storage_read_buffer_size = 2097152").await?;
+    // the deprecated setting still takes effect.
+    assert_eq!(
+        ctx.get_shared_settings().get_storage_read_buffer_size()?,
+        2097152
+    );
+    assert_eq!(ctx.pop_warnings(), vec![expected.to_string()]);
+
+    let ctx = fixture.new_query_ctx().await?;
+    execute_sql(&ctx, "UNSET storage_read_buffer_size").await?;
+    assert_eq!(ctx.pop_warnings(), vec![expected.to_string()]);
+
+    // no warning for settings which are not deprecated.
+    let ctx = fixture.new_query_ctx().await?;
+    execute_sql(&ctx, "SET max_threads = 4").await?;
+    assert!(ctx.pop_warnings().is_empty());
+
+    Ok(())
+}
@@ -54,6 +54,29 @@ async fn test_generic_code_with_on_query() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "current_thread")]
+async fn test_ping() -> Result<()> {
+    let _fixture = TestFixture::setup().await?;
+
+    let tcp_keepalive_timeout_secs = 120;
+    let mut handler = MySQLHandler::create(tcp_keepalive_timeout_secs, MySQLTlsConfig::default())?;
+
+    let listening = "127.0.0.1:0".parse::<SocketAddr>()?;
+    let runnable_server = handler.start(listening).await?;
+    let mut connection = create_connection(runnable_server.port(), false).await?;
+
+    // COM_PING is answered with an OK packet by opensrv-mysql, the connection is still
+    // usable after it.
+    connection
+        .ping()
+        .await
+        .map_err_to_code(ErrorCode::UnknownException, || "Ping failed")?;
+    let result = connection.query_iter("SELECT 1;").await;
+    assert!(result.is_ok());
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "current_thread")]
 async fn test_connect_with_tls() -> Result<()> {
     let _fixture = TestFixture::setup().await?;
@@ -14,6 +14,7 @@
 use std::any::Any;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicUsize;
 use std::sync::Arc;
 
@@ -524,6 +525,10 @@ impl TableContext for CtxDelegation {
         todo!()
     }
 
+    fn get_aborting(&self) -> Arc<AtomicBool> {
+        todo!()
+    }
+
     fn get_error(&self) -> Option<ErrorCode> {
         todo!()
     }
@@ -357,6 +357,7 @@ fn build_test_segment_info(
         index_size: 0,
         col_stats: col_stats.clone(),
         cluster_stats: None,
+        create_on: None,
     };
 
     Ok(SegmentInfo::new(block_metas, statistics))
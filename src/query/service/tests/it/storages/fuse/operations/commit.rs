@@ -15,6 +15,7 @@
 use std::any::Any;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
 use dashmap::DashMap;
@@ -500,6 +501,10 @@ impl TableContext for CtxDelegation {
         todo!()
     }
 
+    fn get_aborting(&self) -> Arc<AtomicBool> {
+        todo!()
+    }
+
     fn get_error(&self) -> Option<ErrorCode> {
         todo!()
     }
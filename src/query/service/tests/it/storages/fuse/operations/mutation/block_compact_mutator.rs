@@ -16,6 +16,7 @@ use std::collections::HashSet;
 use std::sync::Arc;
 
 use databend_common_base::base::tokio;
+use databend_common_catalog::table::BlockSize;
 use databend_common_catalog::table::Table;
 use databend_common_exception::Result;
 use databend_common_expression::BlockThresholds;
@@ -104,10 +105,77 @@ async fn test_compact() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compact_block_size() -> Result<()> {
+    let fixture = TestFixture::setup().await?;
+    let ctx = fixture.new_query_ctx().await?;
+    let tbl_name = fixture.default_table_name();
+    let db_name = fixture.default_db_name();
+
+    fixture.create_default_database().await?;
+    fixture.create_normal_table().await?;
+
+    // 3 blocks of 10 rows
+    let values = (0..10).map(|i| format!("({})", i)).collect::<Vec<_>>();
+    for _ in 0..3 {
+        let qry = format!(
+            "insert into {}.{}(id) values{}",
+            db_name,
+            tbl_name,
+            values.join(",")
+        );
+        fixture.execute_command(qry.as_str()).await?;
+    }
+
+    let catalog = ctx
+        .get_catalog(fixture.default_catalog_name().as_str())
+        .await?;
+    let table = catalog
+        .get_table(ctx.get_tenant().as_str(), &db_name, &tbl_name)
+        .await?;
+
+    // the blocks are large enough for the target of 5 rows, nothing to compact.
+    let res = table
+        .compact_blocks(ctx.clone(), Some(BlockSize::Rows(5)), None)
+        .await?;
+    assert!(res.is_none());
+
+    // the blocks are smaller than the target of 100 rows, and are compacted into 1 block.
+    assert!(do_compact_to(ctx.clone(), table.clone(), Some(BlockSize::Rows(100))).await?);
+
+    let expected = vec![
+        "+----------+----------+",
+        "| Column 0 | Column 1 |",
+        "+----------+----------+",
+        "| 30       | 1        |",
+        "+----------+----------+",
+    ];
+    let qry = format!(
+        "select row_count, block_count as count from fuse_snapshot('{}', '{}') limit 1",
+        db_name, tbl_name
+    );
+    expects_ok(
+        "check row and block count",
+        fixture.execute_query(qry.as_str()).await,
+        expected,
+    )
+    .await?;
+
+    Ok(())
+}
+
 async fn do_compact(ctx: Arc<QueryContext>, table: Arc<dyn Table>) -> Result<bool> {
+    do_compact_to(ctx, table, None).await
+}
+
+async fn do_compact_to(
+    ctx: Arc<QueryContext>,
+    table: Arc<dyn Table>,
+    block_size: Option<BlockSize>,
+) -> Result<bool> {
     let settings = ctx.get_settings();
     let mut pipeline = databend_common_pipeline_core::Pipeline::create();
-    let res = table.compact_blocks(ctx.clone(), None).await?;
+    let res = table.compact_blocks(ctx.clone(), block_size, None).await?;
 
     let table_info = table.get_table_info().clone();
     let catalog_info = ctx.get_catalog("default").await?.info();
@@ -117,6 +185,7 @@ async fn do_compact(ctx: Arc<QueryContext>, table: Arc<dyn Table>) -> Result<boo
             table_info,
             snapshot,
             catalog_info,
+            block_size,
             false,
             true,
         )?;
@@ -219,6 +288,7 @@ async fn test_safety() -> Result<()> {
         let compact_params = CompactOptions {
             base_snapshot: Arc::new(snapshot),
             block_per_seg: 10,
+            bytes_per_seg: None,
             num_segment_limit: Some(limit),
         };
 
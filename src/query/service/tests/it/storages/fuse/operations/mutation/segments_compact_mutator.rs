@@ -12,8 +12,13 @@
 //  See the License for the specific language governing permissions and
 //  limitations under the License.
 
+use std::collections::HashMap;
 use std::collections::HashSet;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Duration;
 
 use chrono::Utc;
 use databend_common_base::base::tokio;
@@ -40,29 +45,49 @@ use databend_common_storages_fuse::io::SegmentWriter;
 use databend_common_storages_fuse::io::SegmentsIO;
 use databend_common_storages_fuse::io::TableMetaLocationGenerator;
 use databend_common_storages_fuse::io::WriteSettings;
+use databend_common_storages_fuse::operations::segment_growth_per_hour;
+use databend_common_storages_fuse::operations::segment_partition;
+use databend_common_storages_fuse::operations::CommitStrategy;
 use databend_common_storages_fuse::operations::CompactOptions;
+use databend_common_storages_fuse::operations::CompactSegmentOrder;
+use databend_common_storages_fuse::operations::CompactionReport;
 use databend_common_storages_fuse::operations::SegmentCompactMutator;
+use databend_common_storages_fuse::operations::SegmentCompactionPlan;
 use databend_common_storages_fuse::operations::SegmentCompactionState;
 use databend_common_storages_fuse::operations::SegmentCompactor;
+use databend_common_storages_fuse::operations::SegmentGrouper;
 use databend_common_storages_fuse::statistics::gen_columns_statistics;
 use databend_common_storages_fuse::statistics::reducers::merge_statistics_mut;
 use databend_common_storages_fuse::statistics::sort_by_cluster_stats;
 use databend_common_storages_fuse::statistics::StatisticsAccumulator;
 use databend_common_storages_fuse::FuseStorageFormat;
 use databend_common_storages_fuse::FuseTable;
+use databend_common_storages_fuse::FUSE_TBL_SEGMENT_PREFIX;
 use databend_query::locks::LockManager;
 use databend_query::sessions::QueryContext;
 use databend_query::sessions::TableContext;
 use databend_query::test_kits::*;
+use databend_storages_common_cache::CacheAccessor;
 use databend_storages_common_cache::LoadParams;
+use databend_storages_common_cache_manager::CacheManager;
 use databend_storages_common_table_meta::meta::BlockMeta;
 use databend_storages_common_table_meta::meta::ClusterStatistics;
+use databend_storages_common_table_meta::meta::ColumnStatistics;
 use databend_storages_common_table_meta::meta::Compression;
 use databend_storages_common_table_meta::meta::Location;
 use databend_storages_common_table_meta::meta::SegmentInfo;
 use databend_storages_common_table_meta::meta::Statistics;
 use databend_storages_common_table_meta::meta::Versioned;
 use futures_util::TryStreamExt;
+use opendal::raw::Accessor;
+use opendal::raw::Layer;
+use opendal::raw::LayeredAccessor;
+use opendal::raw::OpList;
+use opendal::raw::OpRead;
+use opendal::raw::OpWrite;
+use opendal::raw::RpList;
+use opendal::raw::RpRead;
+use opendal::raw::RpWrite;
 use rand::thread_rng;
 use rand::Rng;
 
@@ -92,8 +117,28 @@ async fn test_compact_segment_normal_case() -> Result<()> {
     let mutator = build_mutator(fuse_table, ctx.clone(), None).await?;
     assert!(mutator.is_some());
     let mutator = mutator.unwrap();
+    assert_eq!(mutator.report(), None);
     mutator.try_commit(table.clone()).await?;
 
+    let summary = fuse_table
+        .read_table_snapshot()
+        .await?
+        .unwrap()
+        .summary
+        .clone();
+    assert_eq!(
+        mutator.report(),
+        Some(CompactionReport {
+            segments_before: num_inserts,
+            segments_after: 1,
+            blocks_before: num_inserts as u64,
+            blocks_after: num_inserts as u64,
+            bytes_before: summary.compressed_byte_size,
+            bytes_after: summary.compressed_byte_size,
+            rebases: 0,
+        })
+    );
+
     // check segment count
     let qry = "select segment_count as count from fuse_snapshot('default', 't') limit 1";
     let stream = fixture.execute_query(qry).await?;
@@ -108,6 +153,47 @@ async fn test_compact_segment_normal_case() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compact_segment_create_on() -> Result<()> {
+    let fixture = TestFixture::setup().await?;
+
+    let qry = "create table t(c int)  block_per_segment=10";
+    fixture.execute_command(qry).await?;
+    fixture.append_rows(3).await?;
+
+    let ctx = fixture.new_query_ctx().await?;
+    let catalog = ctx.get_catalog("default").await?;
+    let table = catalog
+        .get_table(ctx.get_tenant().as_str(), "default", "t")
+        .await?;
+    let fuse_table = FuseTable::try_from_table(table.as_ref())?;
+    let compact_start = Utc::now().timestamp_micros();
+    let mutator = build_mutator(fuse_table, ctx.clone(), None).await?.unwrap();
+    mutator.try_commit(table.clone()).await?;
+    let compact_end = Utc::now().timestamp_micros();
+
+    // the merged segment is stamped while being written, within the compaction.
+    let qry = "select create_on from fuse_segment('default', 't')";
+    let blocks: Vec<DataBlock> = fixture.execute_query(qry).await?.try_collect().await?;
+    assert_eq!(1, blocks.iter().map(|b| b.num_rows()).sum::<usize>());
+    let create_on = blocks[0]
+        .get_by_offset(0)
+        .value
+        .index(0)
+        .unwrap()
+        .to_owned();
+    let Scalar::Timestamp(create_on) = create_on else {
+        panic!(
+            "expect the create_on of the merged segment, got {:?}",
+            create_on
+        );
+    };
+    assert!(create_on <= compact_end);
+    assert!(create_on >= compact_start);
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_compact_segment_resolvable_conflict() -> Result<()> {
     let fixture = TestFixture::setup().await?;
@@ -134,6 +220,12 @@ async fn test_compact_segment_resolvable_conflict() -> Result<()> {
     let mutator = build_mutator(fuse_table, ctx.clone(), None).await?;
     assert!(mutator.is_some());
     let mutator = mutator.unwrap();
+    let base_summary = fuse_table
+        .read_table_snapshot()
+        .await?
+        .unwrap()
+        .summary
+        .clone();
 
     // before commit compact segments, gives 9 append commits
     let num_inserts = 9;
@@ -141,6 +233,20 @@ async fn test_compact_segment_resolvable_conflict() -> Result<()> {
 
     mutator.try_commit(table.clone()).await?;
 
+    // the appended segments, which are not compacted, are not counted.
+    assert_eq!(
+        mutator.report(),
+        Some(CompactionReport {
+            segments_before: num_inserts,
+            segments_after: 1,
+            blocks_before: num_inserts as u64,
+            blocks_after: num_inserts as u64,
+            bytes_before: base_summary.compressed_byte_size,
+            bytes_after: base_summary.compressed_byte_size,
+            rebases: 1,
+        })
+    );
+
     // check segment count
     let count_seg = "select segment_count as count from fuse_snapshot('default', 't') limit 1";
     let stream = fixture.execute_query(count_seg).await?;
@@ -168,6 +274,136 @@ async fn test_compact_segment_resolvable_conflict() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compact_segment_commit_retries_exhausted() -> Result<()> {
+    let fixture = TestFixture::setup().await?;
+    let create_tbl_command = "create table t(c int)  block_per_segment=10";
+    fixture.execute_command(create_tbl_command).await?;
+
+    let num_inserts = 9;
+    fixture.append_rows(num_inserts).await?;
+
+    let ctx = fixture.new_query_ctx().await?;
+    ctx.get_settings().set_setting(
+        "compact_segments_max_commit_retries".to_string(),
+        "0".to_string(),
+    )?;
+    let catalog = ctx.get_catalog("default").await?;
+    let table = catalog
+        .get_table(ctx.get_tenant().as_str(), "default", "t")
+        .await?;
+    let fuse_table = FuseTable::try_from_table(table.as_ref())?;
+    let mutator = build_mutator(fuse_table, ctx.clone(), None).await?;
+    assert!(mutator.is_some());
+    let mutator = mutator.unwrap();
+
+    // appends interleaved with the compaction, the commit has to be rebased on them.
+    let num_appends = 3;
+    fixture.append_rows(num_appends).await?;
+
+    let r = mutator.try_commit(table.clone()).await;
+    assert!(r.is_err());
+    assert_eq!(r.err().unwrap().code(), ErrorCode::COMMIT_RETRIES_EXHAUSTED);
+    assert!(mutator.report().is_none());
+
+    // the table is left as it is, and the compacted segment is removed.
+    let count_seg = "select segment_count as count from fuse_snapshot('default', 't') limit 1";
+    let stream = fixture.execute_query(count_seg).await?;
+    assert_eq!(
+        (num_inserts + num_appends) as u64,
+        check_count(stream).await?
+    );
+    assert_eq!(
+        list_segment_files(fuse_table).await?.len(),
+        num_inserts + num_appends
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compact_segment_replan_appends() -> Result<()> {
+    let fixture = TestFixture::setup().await?;
+    let create_tbl_command = "create table t(c int)  block_per_segment=10";
+    fixture.execute_command(create_tbl_command).await?;
+
+    let num_inserts = 9;
+    fixture.append_rows(num_inserts).await?;
+
+    let ctx = fixture.new_query_ctx().await?;
+    let catalog = ctx.get_catalog("default").await?;
+    let table = catalog
+        .get_table(ctx.get_tenant().as_str(), "default", "t")
+        .await?;
+    let fuse_table = FuseTable::try_from_table(table.as_ref())?;
+    let mutator = build_mutator(fuse_table, ctx.clone(), None).await?;
+    assert!(mutator.is_some());
+    let mutator = mutator
+        .unwrap()
+        .with_commit_strategy(CommitStrategy::ReplanAppends);
+
+    // before commit compact segments, gives 9 append commits
+    fixture.append_rows(num_inserts).await?;
+
+    mutator.try_commit(table.clone()).await?;
+
+    // the appended segments are compacted as well: the segment of 9 blocks compacted before
+    // the appends takes 1 of them, and the other 8 are compacted into 1 segment.
+    let count_seg = "select segment_count as count from fuse_snapshot('default', 't') limit 1";
+    let stream = fixture.execute_query(count_seg).await?;
+    assert_eq!(2, check_count(stream).await?);
+
+    // the compaction is committed against the snapshot with the appended segments.
+    let report = mutator.report().unwrap();
+    assert_eq!(report.segments_before, num_inserts * 2);
+    assert_eq!(report.segments_after, 2);
+    assert_eq!(report.blocks_before, num_inserts as u64 * 2);
+    assert_eq!(report.blocks_after, num_inserts as u64 * 2);
+
+    let count_block = "select block_count as count from fuse_snapshot('default', 't') limit 1";
+    let stream = fixture.execute_query(count_block).await?;
+    assert_eq!(num_inserts as u64 * 2, check_count(stream).await?);
+
+    let count_qry = "select count(*) from t";
+    let stream = fixture.execute_query(count_qry).await?;
+    assert_eq!(num_inserts as u64 * 2, check_count(stream).await?);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compact_segment_replan_appends_unresolvable_conflict() -> Result<()> {
+    let fixture = TestFixture::setup().await?;
+    let create_tbl_command = "create table t(c int)  block_per_segment=10";
+    fixture.execute_command(create_tbl_command).await?;
+
+    let num_inserts = 9;
+    fixture.append_rows(num_inserts).await?;
+
+    let ctx = fixture.new_query_ctx().await?;
+    let catalog = ctx.get_catalog("default").await?;
+    let table = catalog
+        .get_table(ctx.get_tenant().as_str(), "default", "t")
+        .await?;
+    let fuse_table = FuseTable::try_from_table(table.as_ref())?;
+    let mutator = build_mutator(fuse_table, ctx.clone(), None).await?;
+    assert!(mutator.is_some());
+    let mutator = mutator
+        .unwrap()
+        .with_commit_strategy(CommitStrategy::ReplanAppends);
+
+    // the segments compacted by the mutator are removed by another compaction, which can not
+    // be replanned.
+    compact_segment(ctx.clone(), &table).await?;
+    fixture.append_rows(1).await?;
+
+    let r = mutator.try_commit(table.clone()).await;
+    assert!(r.is_err());
+    assert_eq!(r.err().unwrap().code(), ErrorCode::UNRESOLVABLE_CONFLICT);
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_compact_segment_unresolvable_conflict() -> Result<()> {
     let fixture = TestFixture::setup().await?;
@@ -191,9 +427,16 @@ async fn test_compact_segment_unresolvable_conflict() -> Result<()> {
         .get_table(ctx.get_tenant().as_str(), "default", "t")
         .await?;
     let fuse_table = FuseTable::try_from_table(table.as_ref())?;
+    let segment_files = list_segment_files(fuse_table).await?;
     let mutator = build_mutator(fuse_table, ctx.clone(), None).await?;
     assert!(mutator.is_some());
     let mutator = mutator.unwrap();
+    let new_segment_files = list_segment_files(fuse_table)
+        .await?
+        .into_iter()
+        .filter(|file| !segment_files.contains(file))
+        .collect::<Vec<_>>();
+    assert_eq!(new_segment_files.len(), 1);
 
     {
         // inject a unresolvable commit
@@ -205,122 +448,435 @@ async fn test_compact_segment_unresolvable_conflict() -> Result<()> {
     assert!(r.is_err());
     assert_eq!(r.err().unwrap().code(), ErrorCode::UNRESOLVABLE_CONFLICT);
 
+    // the new segment of the rejected compaction is removed
+    let segment_files = list_segment_files(fuse_table).await?;
+    assert!(
+        new_segment_files
+            .iter()
+            .all(|file| !segment_files.contains(file))
+    );
+
     Ok(())
 }
 
-#[async_trait::async_trait]
-trait AppendRow {
-    async fn append_rows(&self, n: usize) -> Result<()>;
-}
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compact_segment_abort() -> Result<()> {
+    let fixture = TestFixture::setup().await?;
 
-#[async_trait::async_trait]
-impl AppendRow for TestFixture {
-    async fn append_rows(&self, n: usize) -> Result<()> {
-        let qry = "insert into t values(1)";
-        for _ in 0..n {
-            self.execute_command(qry).await?;
-        }
-        Ok(())
-    }
-}
+    let create_tbl_command = "create table t(c int)  block_per_segment=10";
+    fixture.execute_command(create_tbl_command).await?;
 
-async fn check_count(result_stream: SendableDataBlockStream) -> Result<u64> {
-    let blocks: Vec<DataBlock> = result_stream.try_collect().await?;
-    match &blocks[0].get_by_offset(0).value {
-        Value::Scalar(Scalar::Number(NumberScalar::UInt64(s))) => Ok(*s),
-        Value::Column(Column::Number(NumberColumn::UInt64(c))) => Ok(c[0]),
-        _ => Err(ErrorCode::BadDataValueType(format!(
-            "Expected UInt64, but got {:?}",
-            blocks[0].get_by_offset(0).value
-        ))),
-    }
-}
+    let num_inserts = 9;
+    fixture.append_rows(num_inserts).await?;
 
-pub async fn compact_segment(ctx: Arc<QueryContext>, table: &Arc<dyn Table>) -> Result<()> {
+    let ctx = fixture.new_query_ctx().await?;
+    let catalog = ctx.get_catalog("default").await?;
+    let table = catalog
+        .get_table(ctx.get_tenant().as_str(), "default", "t")
+        .await?;
     let fuse_table = FuseTable::try_from_table(table.as_ref())?;
-    let mutator = build_mutator(fuse_table, ctx.clone(), None).await?.unwrap();
-    mutator.try_commit(table.clone()).await
-}
+    let segment_files = list_segment_files(fuse_table).await?;
+    assert_eq!(segment_files.len(), num_inserts);
 
-async fn build_mutator(
-    tbl: &FuseTable,
-    ctx: Arc<dyn TableContext>,
-    limit: Option<usize>,
-) -> Result<Option<SegmentCompactMutator>> {
-    let snapshot_opt = tbl.read_table_snapshot().await?;
-    let base_snapshot = if let Some(val) = snapshot_opt {
-        val
-    } else {
-        // no snapshot, no compaction.
-        return Ok(None);
-    };
+    let mutator = build_mutator(fuse_table, ctx.clone(), None).await?;
+    assert!(mutator.is_some());
+    let mut mutator = mutator.unwrap();
+    assert_eq!(list_segment_files(fuse_table).await?.len(), num_inserts + 1);
 
-    if base_snapshot.summary.block_count <= 1 {
-        return Ok(None);
-    }
+    // the new segment is removed, aborting again does nothing.
+    mutator.abort().await?;
+    assert_eq!(list_segment_files(fuse_table).await?, segment_files);
+    mutator.abort().await?;
+    assert_eq!(list_segment_files(fuse_table).await?, segment_files);
 
-    let block_per_seg = tbl.get_option("block_per_segment", 1000);
+    // nothing is left to commit.
+    mutator.try_commit(table.clone()).await?;
+    let count_seg = "select segment_count as count from fuse_snapshot('default', 't') limit 1";
+    let stream = fixture.execute_query(count_seg).await?;
+    assert_eq!(num_inserts as u64, check_count(stream).await?);
 
-    let compact_params = CompactOptions {
-        base_snapshot,
-        block_per_seg,
-        num_segment_limit: limit,
-    };
+    Ok(())
+}
 
-    let table_lock = LockManager::create_table_lock(tbl.get_table_info().clone())?;
-    let mut segment_mutator = SegmentCompactMutator::try_create(
-        ctx.clone(),
-        table_lock,
-        compact_params,
-        tbl.meta_location_generator().clone(),
-        tbl.get_operator(),
-        tbl.cluster_key_id(),
-    )?;
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compact_segment_abort_after_commit() -> Result<()> {
+    let fixture = TestFixture::setup().await?;
 
-    if segment_mutator.target_select().await? {
-        Ok(Some(segment_mutator))
-    } else {
-        Ok(None)
-    }
+    let create_tbl_command = "create table t(c int)  block_per_segment=10";
+    fixture.execute_command(create_tbl_command).await?;
+    fixture.append_rows(9).await?;
+
+    let ctx = fixture.new_query_ctx().await?;
+    let catalog = ctx.get_catalog("default").await?;
+    let table = catalog
+        .get_table(ctx.get_tenant().as_str(), "default", "t")
+        .await?;
+    let fuse_table = FuseTable::try_from_table(table.as_ref())?;
+    let mut mutator = build_mutator(fuse_table, ctx.clone(), None).await?.unwrap();
+    mutator.try_commit(table.clone()).await?;
+
+    // the committed segment is kept.
+    mutator.abort().await?;
+    let latest = table.refresh(ctx.as_ref()).await?;
+    let latest_fuse_table = FuseTable::try_from_table(latest.as_ref())?;
+    let snapshot = latest_fuse_table.read_table_snapshot().await?.unwrap();
+    assert_eq!(snapshot.segments.len(), 1);
+    let segment_files = list_segment_files(latest_fuse_table).await?;
+    assert!(segment_files.contains(&snapshot.segments[0].0));
+
+    Ok(())
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn test_segment_compactor() -> Result<()> {
+async fn test_compact_segment_dry_run() -> Result<()> {
     let fixture = TestFixture::setup().await?;
-    let ctx = fixture.new_query_ctx().await?;
 
-    {
-        let case_name = "highly fragmented segments";
-        let threshold = 10;
-        let case = CompactCase {
-            // 3 fragmented segments
-            // - each of them have number blocks lesser than `threshold`
-            blocks_number_of_input_segments: vec![1, 2, 3],
-            // - these segments should be compacted into one
-            expected_number_of_output_segments: 1,
-            // - which contains 6 blocks
-            expected_block_number_of_new_segments: vec![1 + 2 + 3],
-            case_name,
-        };
+    let create_tbl_command = "create table t(c int)  block_per_segment=10";
+    fixture.execute_command(create_tbl_command).await?;
 
-        // run, and verify that
-        // - numbers are as expected
-        //   - number of the newly created segments (which are compacted)
-        //   - number of segments unchanged
-        // - other general invariants
-        //   - unchanged segments still be there
-        //   - blocks and the order of them are not changed
-        //   - statistics are as expected
-        //   - the output segments could not be compacted further
-        case.run_and_verify(&ctx, threshold, None).await?;
-    }
+    let num_inserts = 9;
+    fixture.append_rows(num_inserts).await?;
 
-    {
-        let case_name = "greedy compact, but not too greedy(1), right assoc";
-        let threshold = 10;
-        let case = CompactCase {
-            // - 4 segments
-            blocks_number_of_input_segments: vec![1, 8, 2, 8],
+    let ctx = fixture.new_query_ctx().await?;
+    let catalog = ctx.get_catalog("default").await?;
+    let table = catalog
+        .get_table(ctx.get_tenant().as_str(), "default", "t")
+        .await?;
+    let fuse_table = FuseTable::try_from_table(table.as_ref())?;
+    let snapshot_loc = fuse_table.snapshot_loc().await?;
+    let segment_files = list_segment_files(fuse_table).await?;
+
+    let plan = fuse_table.plan_compaction(ctx.clone(), None, None).await?;
+    assert_eq!(
+        plan,
+        Some(SegmentCompactionPlan {
+            segments_before: num_inserts,
+            segments_after: 1,
+            segments_merged: num_inserts,
+            new_segments: 1,
+            segments_remaining: 0,
+            blocks_rewritten: num_inserts,
+            bytes_rewritten: fuse_table
+                .read_table_snapshot()
+                .await?
+                .unwrap()
+                .summary
+                .compressed_byte_size,
+            unreadable_segments: vec![],
+        })
+    );
+
+    // nothing is written or committed.
+    assert_eq!(list_segment_files(fuse_table).await?, segment_files);
+    let latest = table.refresh(ctx.as_ref()).await?;
+    let latest_fuse_table = FuseTable::try_from_table(latest.as_ref())?;
+    assert_eq!(latest_fuse_table.snapshot_loc().await?, snapshot_loc);
+
+    // the compaction does what is planned.
+    fixture
+        .execute_command("optimize table t compact segment")
+        .await?;
+    let count_seg = "select segment_count as count from fuse_snapshot('default', 't') limit 1";
+    let stream = fixture.execute_query(count_seg).await?;
+    assert_eq!(1, check_count(stream).await?);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compact_segment_dry_run_unreadable_segment() -> Result<()> {
+    let fixture = TestFixture::setup().await?;
+
+    let create_tbl_command = "create table t(c int)  block_per_segment=10";
+    fixture.execute_command(create_tbl_command).await?;
+
+    let num_inserts = 9;
+    fixture.append_rows(num_inserts).await?;
+
+    let ctx = fixture.new_query_ctx().await?;
+    let catalog = ctx.get_catalog("default").await?;
+    let table = catalog
+        .get_table(ctx.get_tenant().as_str(), "default", "t")
+        .await?;
+    let fuse_table = FuseTable::try_from_table(table.as_ref())?;
+    let snapshot = fuse_table.read_table_snapshot().await?.unwrap();
+
+    // corrupt the segment in the middle of the snapshot.
+    let corrupted = snapshot.segments[4].clone();
+    let segment_reader =
+        MetaReaders::segment_info_reader(fuse_table.get_operator(), table.schema());
+    let compact_segment = segment_reader
+        .read(&LoadParams {
+            location: corrupted.0.clone(),
+            len_hint: None,
+            ver: corrupted.1,
+            put_cache: false,
+        })
+        .await?;
+    let corrupted_bytes = SegmentInfo::try_from(compact_segment)?
+        .summary
+        .compressed_byte_size;
+    fuse_table
+        .get_operator()
+        .write(&corrupted.0, b"not a segment".to_vec())
+        .await?;
+    if let Some(segment_cache) = CacheManager::instance().get_table_segment_cache() {
+        segment_cache.evict(&corrupted.0);
+    }
+    let segment_files = list_segment_files(fuse_table).await?;
+
+    // the unreadable segment is kept, the segments on each side of it are merged.
+    let plan = fuse_table.plan_compaction(ctx.clone(), None, None).await?;
+    assert_eq!(
+        plan,
+        Some(SegmentCompactionPlan {
+            segments_before: num_inserts,
+            segments_after: 3,
+            segments_merged: num_inserts - 1,
+            new_segments: 2,
+            segments_remaining: 0,
+            blocks_rewritten: num_inserts - 1,
+            bytes_rewritten: snapshot.summary.compressed_byte_size - corrupted_bytes,
+            unreadable_segments: vec![corrupted.0.clone()],
+        })
+    );
+
+    // nothing is written or committed.
+    assert_eq!(list_segment_files(fuse_table).await?, segment_files);
+    let latest = table.refresh(ctx.as_ref()).await?;
+    let latest_fuse_table = FuseTable::try_from_table(latest.as_ref())?;
+    assert_eq!(
+        latest_fuse_table.snapshot_loc().await?,
+        fuse_table.snapshot_loc().await?
+    );
+
+    // the compaction itself still fails on the unreadable segment.
+    let r = fixture
+        .execute_command("optimize table t compact segment")
+        .await;
+    assert!(r.is_err());
+
+    Ok(())
+}
+
+async fn list_segment_files(fuse_table: &FuseTable) -> Result<Vec<String>> {
+    let prefix = format!(
+        "{}/{}/",
+        fuse_table.meta_location_generator().prefix(),
+        FUSE_TBL_SEGMENT_PREFIX
+    );
+    let mut files = fuse_table
+        .get_operator()
+        .list(&prefix)
+        .await?
+        .into_iter()
+        .map(|entry| entry.path().to_string())
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compact_segment_verify_blocks() -> Result<()> {
+    let fixture = TestFixture::setup().await?;
+
+    // setup
+    let create_tbl_command = "create table t(c int)  block_per_segment=10";
+    fixture.execute_command(create_tbl_command).await?;
+
+    let num_inserts = 9;
+    fixture.append_rows(num_inserts).await?;
+
+    let ctx = fixture.new_query_ctx().await?;
+    ctx.get_settings().set_setting(
+        "compact_segments_verify_blocks".to_string(),
+        "1".to_string(),
+    )?;
+    let catalog = ctx.get_catalog("default").await?;
+    let table = catalog
+        .get_table(ctx.get_tenant().as_str(), "default", "t")
+        .await?;
+    let fuse_table = FuseTable::try_from_table(table.as_ref())?;
+    let mutator = build_mutator(fuse_table, ctx.clone(), None).await?;
+    assert!(mutator.is_some());
+    let mutator = mutator.unwrap();
+
+    // purge a block referenced by the compacted segment, before the compaction is committed.
+    let snapshot = fuse_table.read_table_snapshot().await?.unwrap();
+    let segment_reader =
+        MetaReaders::segment_info_reader(fuse_table.get_operator(), table.schema());
+    let compact_segment = segment_reader
+        .read(&LoadParams {
+            location: snapshot.segments[0].0.clone(),
+            len_hint: None,
+            ver: snapshot.segments[0].1,
+            put_cache: false,
+        })
+        .await?;
+    let segment = SegmentInfo::try_from(compact_segment)?;
+    let block_location = segment.blocks[0].location.0.clone();
+    fuse_table.get_operator().delete(&block_location).await?;
+
+    let r = mutator.try_commit(table.clone()).await;
+    let err = r.err().unwrap();
+    assert_eq!(err.code(), ErrorCode::STORAGE_NOT_FOUND);
+    assert!(err.message().contains(&block_location), "{}", err.message());
+
+    // the compaction is not committed.
+    let count_seg = "select segment_count as count from fuse_snapshot('default', 't') limit 1";
+    let stream = fixture.execute_query(count_seg).await?;
+    assert_eq!(num_inserts as u64, check_count(stream).await?);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compact_segment_threshold() -> Result<()> {
+    let fixture = TestFixture::setup().await?;
+
+    let qry = "create table t(c int)  block_per_segment=10";
+    fixture.execute_command(qry).await?;
+
+    let num_inserts = 9;
+    fixture.append_rows(num_inserts).await?;
+
+    // merge the segments into segments of 3 blocks, regardless of the block_per_segment.
+    let ctx = fixture.new_query_ctx().await?;
+    ctx.get_settings()
+        .set_setting("compact_segment_threshold".to_string(), "3".to_string())?;
+    let catalog = ctx.get_catalog("default").await?;
+    let table = catalog
+        .get_table(ctx.get_tenant().as_str(), "default", "t")
+        .await?;
+    let fuse_table = FuseTable::try_from_table(table.as_ref())?;
+    let mutator = build_mutator(fuse_table, ctx.clone(), None).await?.unwrap();
+    mutator.try_commit(table.clone()).await?;
+
+    let qry = "select segment_count as count from fuse_snapshot('default', 't') limit 1";
+    let stream = fixture.execute_query(qry).await?;
+    assert_eq!(3, check_count(stream).await?);
+
+    let qry = "select block_count as count from fuse_snapshot('default', 't') limit 1";
+    let stream = fixture.execute_query(qry).await?;
+    assert_eq!(num_inserts as u64, check_count(stream).await?);
+
+    Ok(())
+}
+
+#[async_trait::async_trait]
+trait AppendRow {
+    async fn append_rows(&self, n: usize) -> Result<()>;
+}
+
+#[async_trait::async_trait]
+impl AppendRow for TestFixture {
+    async fn append_rows(&self, n: usize) -> Result<()> {
+        let qry = "insert into t values(1)";
+        for _ in 0..n {
+            self.execute_command(qry).await?;
+        }
+        Ok(())
+    }
+}
+
+async fn check_count(result_stream: SendableDataBlockStream) -> Result<u64> {
+    let blocks: Vec<DataBlock> = result_stream.try_collect().await?;
+    match &blocks[0].get_by_offset(0).value {
+        Value::Scalar(Scalar::Number(NumberScalar::UInt64(s))) => Ok(*s),
+        Value::Column(Column::Number(NumberColumn::UInt64(c))) => Ok(c[0]),
+        _ => Err(ErrorCode::BadDataValueType(format!(
+            "Expected UInt64, but got {:?}",
+            blocks[0].get_by_offset(0).value
+        ))),
+    }
+}
+
+pub async fn compact_segment(ctx: Arc<QueryContext>, table: &Arc<dyn Table>) -> Result<()> {
+    let fuse_table = FuseTable::try_from_table(table.as_ref())?;
+    let mutator = build_mutator(fuse_table, ctx.clone(), None).await?.unwrap();
+    mutator.try_commit(table.clone()).await
+}
+
+async fn build_mutator(
+    tbl: &FuseTable,
+    ctx: Arc<dyn TableContext>,
+    limit: Option<usize>,
+) -> Result<Option<SegmentCompactMutator>> {
+    let snapshot_opt = tbl.read_table_snapshot().await?;
+    let base_snapshot = if let Some(val) = snapshot_opt {
+        val
+    } else {
+        // no snapshot, no compaction.
+        return Ok(None);
+    };
+
+    if base_snapshot.summary.block_count <= 1 {
+        return Ok(None);
+    }
+
+    let block_per_seg = tbl.get_option("block_per_segment", 1000);
+
+    let compact_params = CompactOptions {
+        base_snapshot,
+        block_per_seg,
+        bytes_per_seg: None,
+        num_segment_limit: limit,
+    };
+
+    let table_lock = LockManager::create_table_lock(tbl.get_table_info().clone())?;
+    let mut segment_mutator = SegmentCompactMutator::try_create(
+        ctx.clone(),
+        table_lock,
+        compact_params,
+        tbl.meta_location_generator().clone(),
+        tbl.get_operator(),
+        tbl.cluster_key_id(),
+    )?;
+
+    if segment_mutator.target_select().await? {
+        Ok(Some(segment_mutator))
+    } else {
+        Ok(None)
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_segment_compactor() -> Result<()> {
+    let fixture = TestFixture::setup().await?;
+    let ctx = fixture.new_query_ctx().await?;
+
+    {
+        let case_name = "highly fragmented segments";
+        let threshold = 10;
+        let case = CompactCase {
+            // 3 fragmented segments
+            // - each of them have number blocks lesser than `threshold`
+            blocks_number_of_input_segments: vec![1, 2, 3],
+            // - these segments should be compacted into one
+            expected_number_of_output_segments: 1,
+            // - which contains 6 blocks
+            expected_block_number_of_new_segments: vec![1 + 2 + 3],
+            case_name,
+        };
+
+        // run, and verify that
+        // - numbers are as expected
+        //   - number of the newly created segments (which are compacted)
+        //   - number of segments unchanged
+        // - other general invariants
+        //   - unchanged segments still be there
+        //   - blocks and the order of them are not changed
+        //   - statistics are as expected
+        //   - the output segments could not be compacted further
+        case.run_and_verify(&ctx, threshold, None).await?;
+    }
+
+    {
+        let case_name = "greedy compact, but not too greedy(1), right assoc";
+        let threshold = 10;
+        let case = CompactCase {
+            // - 4 segments
+            blocks_number_of_input_segments: vec![1, 8, 2, 8],
             // - these segments should be compacted into 2 new segments
             expected_number_of_output_segments: 2,
             // compaction is right-assoc
@@ -636,6 +1192,82 @@ async fn test_segment_compactor() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_segment_compactor_plan() -> Result<()> {
+    // the segments are given in the order they are accumulated, which is the reversed order
+    // of the snapshot.
+    let cases: Vec<(&str, u64, Vec<usize>, Vec<Vec<usize>>)> = vec![
+        ("highly fragmented segments", 10, vec![3, 2, 1], vec![vec![
+            0, 1, 2,
+        ]]),
+        ("not too greedy", 10, vec![8, 2, 8, 1], vec![
+            vec![0, 1],
+            vec![2, 3],
+        ]),
+        ("barrier", 10, vec![10, 2, 11, 6, 5], vec![
+            vec![0],
+            vec![1, 2],
+            vec![3, 4],
+        ]),
+        ("single fragment is kept", 10, vec![6, 5, 19, 1], vec![
+            vec![0, 1],
+            vec![2],
+            vec![3],
+        ]),
+        (
+            "empty segments are dropped",
+            10,
+            vec![0, 6, 0, 5, 0, 19, 1],
+            vec![vec![1, 3], vec![5], vec![6]],
+        ),
+        ("oversized segment", 3, vec![2, 7], vec![vec![0], vec![1]]),
+    ];
+    for (case_name, threshold, blocks_of_segments, expected) in cases {
+        let segments = segments_of_blocks(&blocks_of_segments);
+        assert_eq!(
+            SegmentCompactor::plan(&SegmentGrouper::new(threshold, None), &segments),
+            expected,
+            "case {}",
+            case_name
+        );
+    }
+
+    // the oversized segment of 7 blocks is split into pieces of 3 and 4 blocks
+    let segments = segments_of_blocks(&[2, 7]);
+    let grouper = SegmentGrouper::new(3, None).split_oversized(BlockThresholds::default());
+    let plan = SegmentCompactor::plan(&grouper, &segments);
+    assert_eq!(plan, vec![vec![0], vec![1], vec![1]]);
+
+    Ok(())
+}
+
+// segments of the given numbers of blocks, which are not written.
+fn segments_of_blocks(blocks_of_segments: &[usize]) -> Vec<SegmentInfo> {
+    blocks_of_segments
+        .iter()
+        .map(|num_blocks| {
+            let blocks = (0..*num_blocks)
+                .map(|_| {
+                    Arc::new(BlockMeta::new(
+                        1,
+                        1,
+                        1,
+                        HashMap::default(),
+                        HashMap::default(),
+                        None,
+                        ("".to_string(), 0),
+                        None,
+                        0,
+                        Compression::Lz4Raw,
+                        None,
+                    ))
+                })
+                .collect();
+            SegmentInfo::new(blocks, Statistics::default())
+        })
+        .collect()
+}
+
 pub struct CompactSegmentTestFixture {
     threshold: u64,
     ctx: Arc<dyn TableContext>,
@@ -900,7 +1532,7 @@ impl CompactCase {
         )
         .await?;
 
-        // invariants 4 - 6 are general rules, for all the cases.
+        // invariants 4 - 7 are general rules, for all the cases.
         let mut idx = 0;
         let mut statistics_of_input_segments = Statistics::default();
         let mut block_num_of_output_segments = vec![];
@@ -960,6 +1592,36 @@ impl CompactCase {
             );
         }
 
+        // 7. every output segment is either newly created or passed through
+        assert_eq!(
+            r.segments_locations.len(),
+            r.new_segment_paths.len() + r.segments_passed_through,
+            "case: {}, verify number of segments passed through",
+            self.case_name
+        );
+
+        // 8. the new segments, written concurrently, are listed in the order they are compacted
+        let new_segments_in_order = r
+            .segments_locations
+            .iter()
+            .rev()
+            .filter(|location| r.new_segment_paths.contains(&location.0))
+            .map(|location| location.0.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            new_segments_in_order, r.new_segment_paths,
+            "case: {}, verify order of new segments",
+            self.case_name
+        );
+
+        // 9. every new segment is written
+        assert_eq!(
+            r.num_new_segments,
+            r.new_segment_paths.len(),
+            "case: {}, verify number of new segments",
+            self.case_name
+        );
+
         Ok(())
     }
 }
@@ -1094,3 +1756,761 @@ async fn test_compact_segment_with_cluster() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compact_segment_within_partition() -> Result<()> {
+    // 5 segments of 1 block, the leading cluster key of the first 3 ones is 2, and 1 for the others.
+    // segments are sorted by cluster key: [1, 1, 2, 2, 2]. without taking the partitions into
+    // account, the first 3 segments would have been compacted together.
+    let output =
+        compact_segments_of_cluster_ranges(&[(2, 2), (2, 2), (2, 2), (1, 1), (1, 1)]).await?;
+    assert_eq!(output, vec![
+        (2, Some(int32_scalar(1))),
+        (3, Some(int32_scalar(2)))
+    ]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compact_segment_unpartitioned_within_partition() -> Result<()> {
+    // the first 2 segments span the cluster keys [1, 2], they belong to no partition, and are
+    // not merged with the segments of partition 2 that follow them.
+    let output =
+        compact_segments_of_cluster_ranges(&[(2, 2), (1, 2), (2, 2), (1, 2), (2, 2)]).await?;
+    assert_eq!(output, vec![(2, None), (3, Some(int32_scalar(2)))]);
+
+    Ok(())
+}
+
+fn int32_scalar(v: i32) -> Scalar {
+    Scalar::Number(NumberScalar::Int32(v))
+}
+
+// Compacts segments of 1 block by 3 blocks per segment, the leading cluster key of each segment
+// is in the given (min, max) range. Returns the number of blocks and the partition of each new
+// segment, ordered by the number of blocks.
+async fn compact_segments_of_cluster_ranges(
+    ranges: &[(i32, i32)],
+) -> Result<Vec<(usize, Option<Scalar>)>> {
+    let block_per_seg = 3;
+    let cluster_key_id = 0;
+
+    let fixture = TestFixture::setup().await?;
+    let ctx = fixture.new_query_ctx().await?;
+    let location_gen = TableMetaLocationGenerator::with_prefix("test/".to_owned());
+    let data_accessor = ctx.get_data_operator()?.operator();
+    let schema = TestFixture::default_table_schema();
+
+    let segment_writer = SegmentWriter::new(&data_accessor, &location_gen);
+    let compact_segment_reader =
+        MetaReaders::segment_info_reader(data_accessor.clone(), schema.clone());
+    let fuse_segment_io = SegmentsIO::create(ctx.clone(), data_accessor.clone(), schema);
+
+    let (_, _, segments) = CompactSegmentTestFixture::gen_segments(
+        ctx.clone(),
+        vec![1; ranges.len()],
+        vec![1; ranges.len()],
+        BlockThresholds::default(),
+        Some(cluster_key_id),
+        block_per_seg as usize,
+    )
+    .await?;
+    let mut locations = vec![];
+    for (mut segment, (min, max)) in segments.into_iter().zip(ranges.iter()) {
+        segment.summary.cluster_stats = Some(ClusterStatistics::new(
+            cluster_key_id,
+            vec![int32_scalar(*min)],
+            vec![int32_scalar(*max)],
+            0,
+            None,
+        ));
+        let path = location_gen.gen_segment_info_location();
+        segment.write_meta(&data_accessor, &path).await?;
+        locations.push((path, SegmentInfo::VERSION));
+    }
+
+    let seg_acc = SegmentCompactor::new(
+        block_per_seg,
+        Some(cluster_key_id),
+        8,
+        &fuse_segment_io,
+        segment_writer,
+    );
+    let state = seg_acc
+        .compact(locations, usize::MAX, |status| {
+            ctx.set_status_info(&status);
+        })
+        .await?;
+
+    assert_eq!(state.new_segment_paths.len(), 2);
+    assert_eq!(state.segments_locations.len(), 2);
+
+    let mut output = vec![];
+    for location in &state.segments_locations {
+        let load_params = LoadParams {
+            location: location.0.clone(),
+            len_hint: None,
+            ver: location.1,
+            put_cache: false,
+        };
+        let compact_segment = compact_segment_reader.read(&load_params).await?;
+        let segment = SegmentInfo::try_from(compact_segment)?;
+        let partition = segment_partition(&segment, cluster_key_id).cloned();
+        output.push((segment.blocks.len(), partition));
+    }
+    output.sort_by_key(|(num_blocks, _)| *num_blocks);
+    Ok(output)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compact_segment_merge_col_stats() -> Result<()> {
+    let block_per_seg = 2;
+
+    let fixture = TestFixture::setup().await?;
+    let ctx = fixture.new_query_ctx().await?;
+    let location_gen = TableMetaLocationGenerator::with_prefix("test/".to_owned());
+    let data_accessor = ctx.get_data_operator()?.operator();
+    let schema = TestFixture::default_table_schema();
+
+    let segment_writer = SegmentWriter::new(&data_accessor, &location_gen);
+    let compact_segment_reader =
+        MetaReaders::segment_info_reader(data_accessor.clone(), schema.clone());
+    let fuse_segment_io = SegmentsIO::create(ctx.clone(), data_accessor.clone(), schema);
+
+    // 2 segments of 1 block, the values of the first column are in disjoint ranges.
+    let (_, _, segments) = CompactSegmentTestFixture::gen_segments(
+        ctx.clone(),
+        vec![1; 2],
+        vec![1; 2],
+        BlockThresholds::default(),
+        None,
+        block_per_seg as usize,
+    )
+    .await?;
+    let int32 = |v: i32| Scalar::Number(NumberScalar::Int32(v));
+    let col_id = 0;
+    let mut locations = vec![];
+    for (mut segment, (min, max, null_count)) in
+        segments.into_iter().zip([(100, 200, 3), (1, 10, 2)])
+    {
+        segment.summary.col_stats.insert(
+            col_id,
+            ColumnStatistics::new(int32(min), int32(max), null_count, 10, None),
+        );
+        let path = location_gen.gen_segment_info_location();
+        segment.write_meta(&data_accessor, &path).await?;
+        locations.push((path, SegmentInfo::VERSION));
+    }
+
+    let seg_acc = SegmentCompactor::new(block_per_seg, None, 8, &fuse_segment_io, segment_writer);
+    let state = seg_acc
+        .compact(locations, usize::MAX, |status| {
+            ctx.set_status_info(&status);
+        })
+        .await?;
+    assert_eq!(state.new_segment_paths.len(), 1);
+
+    let load_params = LoadParams {
+        location: state.new_segment_paths[0].clone(),
+        len_hint: None,
+        ver: SegmentInfo::VERSION,
+        put_cache: false,
+    };
+    let compact_segment = compact_segment_reader.read(&load_params).await?;
+    let segment = SegmentInfo::try_from(compact_segment)?;
+
+    // min and max span both of the ranges, and the null counts and sizes add up.
+    let col_stats = segment.summary.col_stats.get(&col_id).unwrap();
+    assert_eq!(col_stats.min(), &int32(1));
+    assert_eq!(col_stats.max(), &int32(200));
+    assert_eq!(col_stats.null_count, 5);
+    assert_eq!(col_stats.in_memory_size, 20);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compact_segment_by_size() -> Result<()> {
+    let bytes_per_seg = 25;
+
+    let fixture = TestFixture::setup().await?;
+    let ctx = fixture.new_query_ctx().await?;
+    let location_gen = TableMetaLocationGenerator::with_prefix("test/".to_owned());
+    let data_accessor = ctx.get_data_operator()?.operator();
+    let schema = TestFixture::default_table_schema();
+
+    let segment_writer = SegmentWriter::new(&data_accessor, &location_gen);
+    let compact_segment_reader =
+        MetaReaders::segment_info_reader(data_accessor.clone(), schema.clone());
+    let fuse_segment_io = SegmentsIO::create(ctx.clone(), data_accessor.clone(), schema);
+
+    // 5 segments of 1 block, measured in blocks, the first 3 ones and the last 2 ones would
+    // have been compacted with 3 blocks per segment.
+    let (_, _, segments) = CompactSegmentTestFixture::gen_segments(
+        ctx.clone(),
+        vec![1; 5],
+        vec![1; 5],
+        BlockThresholds::default(),
+        None,
+        3,
+    )
+    .await?;
+    let mut locations = vec![];
+    for (mut segment, size) in segments.into_iter().zip([10, 10, 10, 40, 10]) {
+        segment.summary.compressed_byte_size = size;
+        let path = location_gen.gen_segment_info_location();
+        segment.write_meta(&data_accessor, &path).await?;
+        locations.push((path, SegmentInfo::VERSION));
+    }
+
+    let seg_acc = SegmentCompactor::new(bytes_per_seg, None, 8, &fuse_segment_io, segment_writer)
+        .by_compressed_size();
+    let state = seg_acc
+        .compact(locations, usize::MAX, |status| {
+            ctx.set_status_info(&status);
+        })
+        .await?;
+
+    // [10, 10, 10] are compacted, the segment of 40 bytes is in [threshold, 2 * threshold) and
+    // the last fragment is left alone.
+    assert_eq!(state.new_segment_paths.len(), 1);
+    assert_eq!(state.segments_locations.len(), 3);
+    assert_eq!(state.num_fragments_compacted, 3);
+    assert_eq!(state.segments_passed_through, 2);
+    assert_eq!(state.bytes_rewritten, 30);
+
+    let mut sizes = vec![];
+    for location in &state.segments_locations {
+        let load_params = LoadParams {
+            location: location.0.clone(),
+            len_hint: None,
+            ver: location.1,
+            put_cache: false,
+        };
+        let compact_segment = compact_segment_reader.read(&load_params).await?;
+        let segment = SegmentInfo::try_from(compact_segment)?;
+        sizes.push(segment.summary.compressed_byte_size);
+    }
+    sizes.sort();
+    assert_eq!(sizes, vec![10, 30, 40]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compact_segment_split_oversized() -> Result<()> {
+    let block_per_seg = 3;
+
+    let fixture = TestFixture::setup().await?;
+    let ctx = fixture.new_query_ctx().await?;
+    let location_gen = TableMetaLocationGenerator::with_prefix("test/".to_owned());
+    let data_accessor = ctx.get_data_operator()?.operator();
+    let schema = TestFixture::default_table_schema();
+
+    let segment_writer = SegmentWriter::new(&data_accessor, &location_gen);
+    let compact_segment_reader =
+        MetaReaders::segment_info_reader(data_accessor.clone(), schema.clone());
+    let fuse_segment_io = SegmentsIO::create(ctx.clone(), data_accessor.clone(), schema);
+
+    // one segment of 10 blocks, far more than 2 * block_per_seg, as written by a bulk load.
+    let (locations, _, segments) = CompactSegmentTestFixture::gen_segments(
+        ctx.clone(),
+        vec![10],
+        vec![1],
+        BlockThresholds::default(),
+        None,
+        10,
+    )
+    .await?;
+    let input_blocks = segments[0]
+        .blocks
+        .iter()
+        .map(|b| b.location.clone())
+        .collect::<Vec<_>>();
+
+    // without splitting, the segment is kept as it is.
+    let seg_acc = SegmentCompactor::new(
+        block_per_seg,
+        None,
+        8,
+        &fuse_segment_io,
+        SegmentWriter::new(&data_accessor, &location_gen),
+    );
+    let state = seg_acc
+        .compact(locations.clone(), usize::MAX, |status| {
+            ctx.set_status_info(&status);
+        })
+        .await?;
+    assert!(state.new_segment_paths.is_empty());
+    assert_eq!(state.segments_locations, locations);
+
+    let seg_acc = SegmentCompactor::new(block_per_seg, None, 8, &fuse_segment_io, segment_writer)
+        .split_oversized(BlockThresholds::default());
+    let state = seg_acc
+        .compact(locations, usize::MAX, |status| {
+            ctx.set_status_info(&status);
+        })
+        .await?;
+
+    // the remaining block goes to the last segment.
+    assert_eq!(state.new_segment_paths.len(), 3);
+    assert_eq!(state.num_fragments_compacted, 1);
+    assert_eq!(state.segments_passed_through, 0);
+    assert_eq!(
+        state.bytes_rewritten,
+        segments[0].summary.compressed_byte_size
+    );
+
+    let mut output_blocks = vec![];
+    let mut num_blocks = vec![];
+    let mut merged = Statistics::default();
+    for location in &state.segments_locations {
+        let load_params = LoadParams {
+            location: location.0.clone(),
+            len_hint: None,
+            ver: location.1,
+            put_cache: false,
+        };
+        let compact_segment = compact_segment_reader.read(&load_params).await?;
+        let segment = SegmentInfo::try_from(compact_segment)?;
+        assert!(segment.summary.create_on.is_some());
+        assert_eq!(segment.summary.block_count, segment.blocks.len() as u64,);
+        num_blocks.push(segment.blocks.len());
+        output_blocks.extend(segment.blocks.iter().map(|b| b.location.clone()));
+        merge_statistics_mut(&mut merged, &segment.summary, None);
+    }
+    assert_eq!(num_blocks, vec![3, 3, 4]);
+    assert_eq!(output_blocks, input_blocks);
+    merged.create_on = None;
+    let mut expected = segments[0].summary.clone();
+    expected.create_on = None;
+    assert_eq!(merged, expected);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compact_segment_write_concurrency() -> Result<()> {
+    let block_per_seg = 2;
+
+    let fixture = TestFixture::setup().await?;
+    let ctx = fixture.new_query_ctx().await?;
+    let location_gen = TableMetaLocationGenerator::with_prefix("test/".to_owned());
+    let data_accessor = ctx.get_data_operator()?.operator();
+    let schema = TestFixture::default_table_schema();
+
+    let compact_segment_reader =
+        MetaReaders::segment_info_reader(data_accessor.clone(), schema.clone());
+    let fuse_segment_io = SegmentsIO::create(ctx.clone(), data_accessor.clone(), schema);
+
+    let num_segments = 9;
+    let (locations, _, _) = CompactSegmentTestFixture::gen_segments(
+        ctx.clone(),
+        vec![1; num_segments],
+        vec![1; num_segments],
+        BlockThresholds::default(),
+        None,
+        block_per_seg,
+    )
+    .await?;
+
+    // the blocks of each output segment, in the order of the output segments
+    let mut outputs = vec![];
+    for write_concurrency in [1, 4] {
+        let seg_acc = SegmentCompactor::new(
+            block_per_seg as u64,
+            None,
+            8,
+            &fuse_segment_io,
+            SegmentWriter::new(&data_accessor, &location_gen),
+        )
+        .with_write_concurrency(write_concurrency);
+        let state = seg_acc
+            .compact(locations.clone(), usize::MAX, |status| {
+                ctx.set_status_info(&status);
+            })
+            .await?;
+
+        // every new segment is written, and listed in the order it is compacted
+        let new_locations = state
+            .segments_locations
+            .iter()
+            .filter(|l| !locations.contains(l))
+            .map(|l| l.0.clone())
+            .rev()
+            .collect::<Vec<_>>();
+        assert_eq!(new_locations, state.new_segment_paths);
+        assert_eq!(
+            state.segments_locations.len(),
+            state.new_segment_paths.len() + state.segments_passed_through
+        );
+
+        let mut blocks_of_segments = vec![];
+        for location in &state.segments_locations {
+            let load_params = LoadParams {
+                location: location.0.clone(),
+                len_hint: None,
+                ver: location.1,
+                put_cache: false,
+            };
+            let compact_segment = compact_segment_reader.read(&load_params).await?;
+            let segment = SegmentInfo::try_from(compact_segment)?;
+            blocks_of_segments.push(
+                segment
+                    .blocks
+                    .iter()
+                    .map(|b| b.location.clone())
+                    .collect::<Vec<_>>(),
+            );
+        }
+        outputs.push(blocks_of_segments);
+    }
+
+    // segments written concurrently end up the same as those written one by one
+    assert_eq!(outputs[0], outputs[1]);
+    assert_eq!(outputs[1].len(), num_segments.div_ceil(block_per_seg));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compact_segment_aborted() -> Result<()> {
+    let block_per_seg = 2;
+
+    let fixture = TestFixture::setup().await?;
+    let ctx = fixture.new_query_ctx().await?;
+    let location_gen = TableMetaLocationGenerator::with_prefix("test_aborted/".to_owned());
+    let data_accessor = ctx.get_data_operator()?.operator();
+    let schema = TestFixture::default_table_schema();
+    let fuse_segment_io = SegmentsIO::create(ctx.clone(), data_accessor.clone(), schema);
+
+    let (locations, _, _) = CompactSegmentTestFixture::gen_segments(
+        ctx.clone(),
+        vec![1; 8],
+        vec![1; 8],
+        BlockThresholds::default(),
+        None,
+        block_per_seg,
+    )
+    .await?;
+
+    // the first chunk of 4 segments is compacted into 2 new segments, which are written one
+    // by one, then the compaction is aborted.
+    let aborting = Arc::new(AtomicBool::new(false));
+    let seg_acc = SegmentCompactor::new(
+        block_per_seg as u64,
+        None,
+        4,
+        &fuse_segment_io,
+        SegmentWriter::new(&data_accessor, &location_gen),
+    )
+    .with_write_concurrency(1)
+    .with_abort_flag(aborting.clone());
+    let result = seg_acc
+        .compact(locations, usize::MAX, |status| {
+            ctx.set_status_info(&status);
+            aborting.store(true, Ordering::Release);
+        })
+        .await;
+    assert_eq!(result.unwrap_err().code(), ErrorCode::ABORTED_QUERY);
+
+    // the new segments written before the abort are removed
+    let prefix = format!("test_aborted/{}/", FUSE_TBL_SEGMENT_PREFIX);
+    assert!(data_accessor.list(&prefix).await?.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compact_segment_write_failure() -> Result<()> {
+    let block_per_seg = 2;
+
+    let fixture = TestFixture::setup().await?;
+    let ctx = fixture.new_query_ctx().await?;
+    let location_gen = TableMetaLocationGenerator::with_prefix("test_write_failure/".to_owned());
+    let data_accessor = ctx.get_data_operator()?.operator();
+    let schema = TestFixture::default_table_schema();
+    let fuse_segment_io = SegmentsIO::create(ctx.clone(), data_accessor.clone(), schema);
+
+    let (locations, _, _) = CompactSegmentTestFixture::gen_segments(
+        ctx.clone(),
+        vec![1; 8],
+        vec![1; 8],
+        BlockThresholds::default(),
+        None,
+        block_per_seg,
+    )
+    .await?;
+
+    // the 8 segments are compacted into 4 new segments, written in one batch, and the second
+    // write of the batch fails after the others are done.
+    let writes = Arc::new(AtomicUsize::new(0));
+    let failing_accessor = data_accessor.clone().layer(FailingWriteLayer {
+        nth: 2,
+        writes: writes.clone(),
+    });
+    let seg_acc = SegmentCompactor::new(
+        block_per_seg as u64,
+        None,
+        8,
+        &fuse_segment_io,
+        SegmentWriter::new(&failing_accessor, &location_gen),
+    )
+    .with_write_concurrency(4);
+    let result = seg_acc
+        .compact(locations, usize::MAX, |status| {
+            ctx.set_status_info(&status);
+        })
+        .await;
+    assert!(
+        result
+            .unwrap_err()
+            .message()
+            .contains("injected write failure")
+    );
+    assert_eq!(writes.load(Ordering::SeqCst), 4);
+
+    // the new segments written before the failure are removed
+    let prefix = format!("test_write_failure/{}/", FUSE_TBL_SEGMENT_PREFIX);
+    assert!(data_accessor.list(&prefix).await?.is_empty());
+
+    Ok(())
+}
+
+// Fails the `nth` write through the operator, once the other writes in flight are done.
+#[derive(Clone, Debug)]
+struct FailingWriteLayer {
+    nth: usize,
+    writes: Arc<AtomicUsize>,
+}
+
+impl<A: Accessor> Layer<A> for FailingWriteLayer {
+    type LayeredAccessor = FailingWriteAccessor<A>;
+
+    fn layer(&self, inner: A) -> Self::LayeredAccessor {
+        FailingWriteAccessor {
+            inner,
+            nth: self.nth,
+            writes: self.writes.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct FailingWriteAccessor<A> {
+    inner: A,
+    nth: usize,
+    writes: Arc<AtomicUsize>,
+}
+
+#[async_trait::async_trait]
+impl<A: Accessor> LayeredAccessor for FailingWriteAccessor<A> {
+    type Inner = A;
+    type Reader = A::Reader;
+    type BlockingReader = A::BlockingReader;
+    type Writer = A::Writer;
+    type BlockingWriter = A::BlockingWriter;
+    type Lister = A::Lister;
+    type BlockingLister = A::BlockingLister;
+
+    fn inner(&self) -> &Self::Inner {
+        &self.inner
+    }
+
+    async fn read(&self, path: &str, args: OpRead) -> opendal::Result<(RpRead, Self::Reader)> {
+        self.inner.read(path, args).await
+    }
+
+    async fn write(&self, path: &str, args: OpWrite) -> opendal::Result<(RpWrite, Self::Writer)> {
+        if self.writes.fetch_add(1, Ordering::SeqCst) + 1 == self.nth {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            return Err(opendal::Error::new(
+                opendal::ErrorKind::Unexpected,
+                "injected write failure",
+            ));
+        }
+        self.inner.write(path, args).await
+    }
+
+    async fn list(&self, path: &str, args: OpList) -> opendal::Result<(RpList, Self::Lister)> {
+        self.inner.list(path, args).await
+    }
+
+    fn blocking_read(
+        &self,
+        path: &str,
+        args: OpRead,
+    ) -> opendal::Result<(RpRead, Self::BlockingReader)> {
+        self.inner.blocking_read(path, args)
+    }
+
+    fn blocking_write(
+        &self,
+        path: &str,
+        args: OpWrite,
+    ) -> opendal::Result<(RpWrite, Self::BlockingWriter)> {
+        self.inner.blocking_write(path, args)
+    }
+
+    fn blocking_list(
+        &self,
+        path: &str,
+        args: OpList,
+    ) -> opendal::Result<(RpList, Self::BlockingLister)> {
+        self.inner.blocking_list(path, args)
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compact_segment_limit_runs() -> Result<()> {
+    let block_per_seg = 2;
+
+    let fixture = TestFixture::setup().await?;
+    let ctx = fixture.new_query_ctx().await?;
+    let location_gen = TableMetaLocationGenerator::with_prefix("test/".to_owned());
+    let data_accessor = ctx.get_data_operator()?.operator();
+    let schema = TestFixture::default_table_schema();
+    let fuse_segment_io = SegmentsIO::create(ctx.clone(), data_accessor.clone(), schema);
+
+    // 8 fragmented segments
+    let (mut locations, _, _) = CompactSegmentTestFixture::gen_segments(
+        ctx.clone(),
+        vec![1; 8],
+        vec![1; 8],
+        BlockThresholds::default(),
+        None,
+        block_per_seg,
+    )
+    .await?;
+
+    // each run compacts 4 of the segments, the others are left to the next run.
+    let mut remaining = vec![];
+    let mut new_segments = vec![];
+    for _ in 0..2 {
+        let seg_acc = SegmentCompactor::new(
+            block_per_seg as u64,
+            None,
+            8,
+            &fuse_segment_io,
+            SegmentWriter::new(&data_accessor, &location_gen),
+        );
+        let state = seg_acc
+            .compact(locations.clone(), 4, |status| {
+                ctx.set_status_info(&status);
+            })
+            .await?;
+        remaining.push(state.segments_remaining);
+        new_segments.push(state.new_segment_paths.len());
+        // the segments are taken in the reversed order by the compactor.
+        locations = state.segments_locations;
+        locations.reverse();
+    }
+    assert_eq!(remaining, vec![4, 0]);
+    assert_eq!(new_segments, vec![2, 2]);
+    assert_eq!(locations.len(), 4);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compact_segment_order() -> Result<()> {
+    let block_per_seg = 4;
+
+    let fixture = TestFixture::setup().await?;
+    let ctx = fixture.new_query_ctx().await?;
+    let location_gen = TableMetaLocationGenerator::with_prefix("test/".to_owned());
+    let data_accessor = ctx.get_data_operator()?.operator();
+    let schema = TestFixture::default_table_schema();
+    let fuse_segment_io = SegmentsIO::create(ctx.clone(), data_accessor.clone(), schema);
+
+    // from the oldest, 2 segments of 2 blocks and 4 segments of 1 block
+    let (mut locations, _, _) = CompactSegmentTestFixture::gen_segments(
+        ctx.clone(),
+        vec![2, 2, 1, 1, 1, 1],
+        vec![1; 6],
+        BlockThresholds::default(),
+        None,
+        block_per_seg,
+    )
+    .await?;
+    // the segments are listed from the newest, and taken from the oldest by the compactor.
+    locations.reverse();
+
+    // each run stops once 2 of the segments are compacted.
+    for (order, compacted) in [
+        (CompactSegmentOrder::Oldest, &locations[0..2]),
+        (CompactSegmentOrder::Smallest, &locations[2..6]),
+    ] {
+        let seg_acc = SegmentCompactor::new(
+            block_per_seg as u64,
+            None,
+            8,
+            &fuse_segment_io,
+            SegmentWriter::new(&data_accessor, &location_gen),
+        )
+        .with_order(order);
+        let state = seg_acc
+            .compact(locations.clone(), 2, |status| {
+                ctx.set_status_info(&status);
+            })
+            .await?;
+
+        assert_eq!(state.new_segment_paths.len(), 1, "{order:?}");
+        assert_eq!(state.num_fragments_compacted, compacted.len(), "{order:?}");
+        assert_eq!(
+            state.segments_remaining,
+            locations.len() - compacted.len(),
+            "{order:?}"
+        );
+
+        let kept = state
+            .segments_locations
+            .iter()
+            .filter(|location| locations.contains(location))
+            .collect::<HashSet<_>>();
+        let expected_kept = locations
+            .iter()
+            .filter(|location| !compacted.contains(location))
+            .collect::<HashSet<_>>();
+        assert_eq!(kept, expected_kept, "{order:?}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_segment_growth_per_hour() {
+    let window = Duration::from_secs(3600);
+    let now = Utc::now();
+    let minutes_ago = |m: i64| now - chrono::Duration::minutes(m);
+
+    // 60 segments in half an hour, taken over the whole window.
+    let growing = (0..=30)
+        .map(|m| (minutes_ago(m), 100 + 2 * (30 - m) as u64))
+        .collect::<Vec<_>>();
+    let growth = segment_growth_per_hour(&growing, window);
+    assert_eq!(growth, 60.0);
+    assert!(growth >= 50.0);
+
+    // over more than the window, the real span is taken.
+    let history = vec![(minutes_ago(0), 220), (minutes_ago(120), 100)];
+    assert_eq!(segment_growth_per_hour(&history, window), 60.0);
+
+    // flat, or shrinking after a compaction.
+    let flat = (0..=30).map(|m| (minutes_ago(m), 100)).collect::<Vec<_>>();
+    assert_eq!(segment_growth_per_hour(&flat, window), 0.0);
+    let compacted = vec![(minutes_ago(0), 10), (minutes_ago(30), 100)];
+    assert_eq!(segment_growth_per_hour(&compacted, window), 0.0);
+
+    // the segments added before and after a compaction are all counted.
+    let history = vec![
+        (minutes_ago(0), 70),
+        (minutes_ago(30), 10),
+        (minutes_ago(40), 160),
+        (minutes_ago(60), 100),
+    ];
+    assert_eq!(segment_growth_per_hour(&history, window), 120.0);
+
+    assert_eq!(segment_growth_per_hour(&[], window), 0.0);
+}
@@ -14,9 +14,18 @@
 
 use databend_common_base::base::tokio;
 use databend_common_exception::Result;
+use databend_common_expression::types::number::Int32Type;
+use databend_common_expression::types::number::NumberScalar;
+use databend_common_expression::types::StringType;
+use databend_common_expression::DataBlock;
+use databend_common_expression::FromData;
+use databend_common_expression::Scalar;
+use databend_common_expression::ScalarRef;
 use databend_common_sql::Planner;
 use databend_common_storages_fuse::TableContext;
 use databend_query::interpreters::InterpreterFactory;
+use databend_query::interpreters::TableDigest;
+use databend_query::locks::CompactionLock;
 use databend_query::test_kits::*;
 use futures_util::TryStreamExt;
 
@@ -85,3 +94,402 @@ async fn test_fuse_table_optimize() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fuse_table_optimize_with_verification() -> Result<()> {
+    let fixture = TestFixture::setup().await?;
+    let ctx = fixture.new_query_ctx().await?;
+    let tbl_name = fixture.default_table_name();
+    let db_name = fixture.default_db_name();
+
+    fixture.create_default_database().await?;
+    fixture.create_normal_table().await?;
+
+    let n = 5;
+    for _ in 0..n {
+        let table = fixture.latest_default_table().await?;
+        let stream = TestFixture::gen_sample_blocks_stream(1, 1);
+
+        let blocks = stream.try_collect().await?;
+        fixture
+            .append_commit_blocks(table.clone(), blocks, false, true)
+            .await?;
+    }
+
+    ctx.get_settings().set_max_threads(1)?;
+    ctx.get_settings()
+        .set_setting("enable_compact_verification".to_string(), "1".to_string())
+        .await?;
+
+    let query = format!("optimize table {db_name}.{tbl_name} compact");
+    let mut planner = Planner::new(ctx.clone());
+    let (plan, _) = planner.plan_sql(&query).await?;
+    let interpreter = InterpreterFactory::get(ctx.clone(), &plan).await?;
+    let data_stream = interpreter.execute(ctx.clone()).await?;
+    // the compaction keeps the table contents, so the verification passes.
+    data_stream.try_collect::<Vec<_>>().await?;
+
+    let table = fixture.latest_default_table().await?;
+    let (_, parts) = table.read_partitions(ctx.clone(), None, true).await?;
+    assert_eq!(parts.len(), 1);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fuse_table_optimize_with_prefetch() -> Result<()> {
+    let fixture = TestFixture::setup().await?;
+    fixture
+        .execute_command("create table default.t(c int) row_per_block=10")
+        .await?;
+
+    // 20 blocks of 3 rows, which are compacted into several compact tasks.
+    for i in 0..20 {
+        let qry = format!(
+            "insert into default.t values({}), ({}), ({})",
+            i * 3,
+            i * 3 + 1,
+            i * 3 + 2
+        );
+        fixture.execute_command(&qry).await?;
+    }
+
+    let ctx = fixture.new_query_ctx().await?;
+    let settings = ctx.get_settings();
+    settings.set_max_threads(1)?;
+    settings
+        .set_setting("compact_read_prefetch_depth".to_string(), "2".to_string())
+        .await?;
+    // the contents of the table are checked after compaction.
+    settings
+        .set_setting("enable_compact_verification".to_string(), "1".to_string())
+        .await?;
+
+    let mut planner = Planner::new(ctx.clone());
+    let (plan, _) = planner.plan_sql("optimize table default.t compact").await?;
+    let interpreter = InterpreterFactory::get(ctx.clone(), &plan).await?;
+    let data_stream = interpreter.execute(ctx.clone()).await?;
+    data_stream.try_collect::<Vec<_>>().await?;
+
+    let stream = fixture
+        .execute_query("select count(*) from fuse_block('default', 't')")
+        .await?;
+    let num_blocks = query_count(stream).await?;
+    assert!(num_blocks > 1 && num_blocks < 20, "{num_blocks} blocks");
+
+    let stream = fixture
+        .execute_query("select count(*) from default.t")
+        .await?;
+    assert_eq!(query_count(stream).await?, 60);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fuse_table_optimize_nested_columns() -> Result<()> {
+    let fixture = TestFixture::setup().await?;
+    fixture
+        .execute_command(
+            "create table default.t(id int, v variant, s tuple(a int, b string), m map(string, int))",
+        )
+        .await?;
+
+    for i in 0..4 {
+        let qry = format!(
+            "insert into default.t values \
+            ({}, parse_json('{{\"k\":[1,{}],\"n\":null}}'), ({}, 'x{}'), {{'a':{}}}), \
+            ({}, parse_json('\"s{}\"'), (0, ''), {{}})",
+            i * 2,
+            i,
+            i,
+            i,
+            i,
+            i * 2 + 1,
+            i
+        );
+        fixture.execute_command(&qry).await?;
+    }
+
+    let rows_before = table_rows(&fixture).await?;
+    assert_eq!(rows_before.len(), 8);
+    let (_, blocks_before) = table_layout(&fixture).await?;
+
+    let ctx = fixture.new_query_ctx().await?;
+    ctx.get_settings()
+        .set_setting("enable_compact_verification".to_string(), "1".to_string())
+        .await?;
+    let mut planner = Planner::new(ctx.clone());
+    let (plan, _) = planner.plan_sql("optimize table default.t compact").await?;
+    let interpreter = InterpreterFactory::get(ctx.clone(), &plan).await?;
+    let data_stream = interpreter.execute(ctx.clone()).await?;
+    data_stream.try_collect::<Vec<_>>().await?;
+
+    let (_, blocks_after) = table_layout(&fixture).await?;
+    assert!(blocks_after < blocks_before);
+    // the variant values are kept as the same jsonb bytes.
+    assert_eq!(table_rows(&fixture).await?, rows_before);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fuse_table_optimize_dictionary_columns() -> Result<()> {
+    let fixture = TestFixture::setup().await?;
+    for table in ["t", "t_plain"] {
+        fixture
+            .execute_command(&format!("create table default.{table}(id int, c string)"))
+            .await?;
+        for i in 0..4 {
+            let qry = format!(
+                "insert into default.{table} select number + {}, \
+                concat('low cardinality category ', to_string(number % 4)) from numbers(1000)",
+                i * 1000
+            );
+            fixture.execute_command(&qry).await?;
+        }
+    }
+    let rows_before = table_rows(&fixture).await?;
+    assert_eq!(rows_before.len(), 4000);
+
+    // compact `t` with `c` dictionary encoded, and `t_plain` as usual.
+    for (table, columns) in [("t", "c"), ("t_plain", "")] {
+        let ctx = fixture.new_query_ctx().await?;
+        ctx.get_settings()
+            .set_setting(
+                "compact_dictionary_columns".to_string(),
+                columns.to_string(),
+            )
+            .await?;
+        let mut planner = Planner::new(ctx.clone());
+        let (plan, _) = planner
+            .plan_sql(&format!("optimize table default.{table} compact"))
+            .await?;
+        let interpreter = InterpreterFactory::get(ctx.clone(), &plan).await?;
+        let data_stream = interpreter.execute(ctx.clone()).await?;
+        data_stream.try_collect::<Vec<_>>().await?;
+    }
+
+    let mut block_bytes = vec![];
+    for table in ["t", "t_plain"] {
+        let stream = fixture
+            .execute_query(&format!(
+                "select sum(file_size) from fuse_block('default', '{table}')"
+            ))
+            .await?;
+        block_bytes.push(query_count(stream).await?);
+    }
+    assert!(block_bytes[0] < block_bytes[1]);
+    assert_eq!(table_rows(&fixture).await?, rows_before);
+
+    Ok(())
+}
+
+/// All the rows of `default.t`, ordered by the first column.
+async fn table_rows(fixture: &TestFixture) -> Result<Vec<Vec<Scalar>>> {
+    let stream = fixture
+        .execute_query("select * from default.t order by id")
+        .await?;
+    let blocks: Vec<DataBlock> = stream.try_collect().await?;
+    let mut rows = vec![];
+    for block in blocks {
+        for row in 0..block.num_rows() {
+            rows.push(
+                block
+                    .columns()
+                    .iter()
+                    .map(|entry| entry.value.index(row).unwrap().to_owned())
+                    .collect(),
+            );
+        }
+    }
+    Ok(rows)
+}
+
+/// Number of segments and blocks of `default.t`.
+async fn table_layout(fixture: &TestFixture) -> Result<(u64, u64)> {
+    let stream = fixture
+        .execute_query("select count(*) from fuse_segment('default', 't')")
+        .await?;
+    let segments = query_count(stream).await?;
+    let stream = fixture
+        .execute_query("select count(*) from fuse_block('default', 't')")
+        .await?;
+    let blocks = query_count(stream).await?;
+    Ok((segments, blocks))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fuse_table_optimize_summary() -> Result<()> {
+    let fixture = TestFixture::setup().await?;
+    fixture
+        .execute_command("create table default.t(c int)")
+        .await?;
+
+    let n = 5;
+    for i in 0..n {
+        let qry = format!("insert into default.t values({i})");
+        fixture.execute_command(&qry).await?;
+    }
+
+    let (segments_before, blocks_before) = table_layout(&fixture).await?;
+    assert_eq!((segments_before, blocks_before), (n, n));
+
+    let stream = fixture
+        .execute_query("optimize table default.t compact")
+        .await?;
+    let blocks: Vec<DataBlock> = stream.try_collect().await?;
+    let (segments_after, blocks_after) = table_layout(&fixture).await?;
+    assert!(blocks_after < blocks_before);
+
+    // one row of `segments_before, segments_after, blocks_before, blocks_after, bytes_rewritten,
+    // bytes_saved, status`.
+    let (summary, status) = optimize_summary(&blocks);
+    assert_eq!(summary[..4], [
+        segments_before,
+        segments_after,
+        blocks_before,
+        blocks_after
+    ]);
+    assert!(summary[4] > 0);
+    assert_eq!(summary[5], 0);
+    assert_eq!(status, "compacted");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fuse_table_optimize_segments_summary() -> Result<()> {
+    let fixture = TestFixture::setup().await?;
+    fixture
+        .execute_command("create table default.t(c int)")
+        .await?;
+
+    let n = 5;
+    for i in 0..n {
+        let qry = format!("insert into default.t values({i})");
+        fixture.execute_command(&qry).await?;
+    }
+
+    let stream = fixture
+        .execute_query("select sum(file_size) from fuse_block('default', 't')")
+        .await?;
+    let block_bytes = query_count(stream).await?;
+    assert!(block_bytes > 0);
+
+    let stream = fixture
+        .execute_query("optimize table default.t compact segment")
+        .await?;
+    let blocks: Vec<DataBlock> = stream.try_collect().await?;
+    let (summary, status) = optimize_summary(&blocks);
+    assert_eq!(status, "compacted");
+    // the segments are merged into one, which refers to the same blocks.
+    assert_eq!(summary[..4], [n, 1, n, n]);
+    assert_eq!(summary[4], 0);
+    assert_eq!(summary[5], block_bytes);
+    assert_eq!(table_layout(&fixture).await?, (1, n));
+
+    Ok(())
+}
+
+/// The numbers and the status of the summary returned by `OPTIMIZE TABLE`.
+fn optimize_summary(blocks: &[DataBlock]) -> (Vec<u64>, String) {
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(blocks[0].num_rows(), 1);
+    let columns = blocks[0].columns();
+    let numbers = columns[..6]
+        .iter()
+        .map(|entry| match entry.value.index(0) {
+            Some(ScalarRef::Number(NumberScalar::UInt64(v))) => v,
+            other => panic!("unexpected value {other:?}"),
+        })
+        .collect::<Vec<_>>();
+    let status = match columns[6].value.index(0) {
+        Some(ScalarRef::String(v)) => v.to_string(),
+        other => panic!("unexpected value {other:?}"),
+    };
+    (numbers, status)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fuse_table_compaction_lock() -> Result<()> {
+    let fixture = TestFixture::setup().await?;
+    fixture
+        .execute_command("create table default.t(c int)")
+        .await?;
+    for i in 0..3 {
+        let qry = format!("insert into default.t values({i})");
+        fixture.execute_command(&qry).await?;
+    }
+    let ctx = fixture.new_query_ctx().await?;
+    let table = ctx
+        .get_table(ctx.get_current_catalog().as_str(), "default", "t")
+        .await?;
+    let table_id = table.get_id();
+
+    // the first compaction holds the lock.
+    let guard = CompactionLock::try_acquire(ctx.clone(), table_id).await?;
+    assert!(guard.is_some());
+    assert!(
+        CompactionLock::try_acquire(ctx.clone(), table_id)
+            .await?
+            .is_none()
+    );
+
+    // the second one defers, and leaves the table as is.
+    let layout_before = table_layout(&fixture).await?;
+    let stream = fixture
+        .execute_query("optimize table default.t compact")
+        .await?;
+    let blocks: Vec<DataBlock> = stream.try_collect().await?;
+    let (summary, status) = optimize_summary(&blocks);
+    assert_eq!(status, "already compacting");
+    assert_eq!(summary[4], 0);
+    assert_eq!(summary[5], 0);
+    assert_eq!(table_layout(&fixture).await?, layout_before);
+
+    Ok(())
+}
+
+#[test]
+fn test_table_digest() -> Result<()> {
+    let digest_of = |blocks: &[DataBlock]| {
+        let mut digest = TableDigest::default();
+        for block in blocks {
+            digest.update(block);
+        }
+        digest
+    };
+
+    let blocks = vec![
+        DataBlock::new_from_columns(vec![
+            Int32Type::from_data(vec![1, 2, 3]),
+            StringType::from_data(vec!["a", "b", "c"]),
+        ]),
+        DataBlock::new_from_columns(vec![
+            Int32Type::from_data(vec![4, 5]),
+            StringType::from_data(vec!["d", "e"]),
+        ]),
+    ];
+    let expected = digest_of(&blocks);
+    assert_eq!(expected.num_rows, 5);
+
+    // a correct rewrite: rows are merged into one block, in a different order.
+    let merged = DataBlock::new_from_columns(vec![
+        Int32Type::from_data(vec![5, 3, 1, 4, 2]),
+        StringType::from_data(vec!["e", "c", "a", "d", "b"]),
+    ]);
+    assert_eq!(digest_of(&[merged]), expected);
+
+    // a corrupted rewrite: values are swapped between rows.
+    let corrupted = DataBlock::new_from_columns(vec![
+        Int32Type::from_data(vec![1, 2, 3, 4, 5]),
+        StringType::from_data(vec!["b", "a", "c", "d", "e"]),
+    ]);
+    assert_ne!(digest_of(&[corrupted]), expected);
+
+    // a lossy rewrite: one row is missing.
+    let lossy = DataBlock::concat(&blocks)?.slice(0..4);
+    assert_ne!(digest_of(&[lossy]), expected);
+
+    Ok(())
+}
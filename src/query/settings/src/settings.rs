@@ -15,6 +15,7 @@
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::fmt::Formatter;
+use std::future::Future;
 use std::sync::Arc;
 
 use dashmap::DashMap;
@@ -35,6 +36,7 @@ pub enum ScopeLevel {
     Local,
     Global,
     Session,
+    Statement,
 }
 
 impl Debug for ScopeLevel {
@@ -52,6 +54,9 @@ impl Debug for ScopeLevel {
             ScopeLevel::Session => {
                 write!(f, "SESSION")
             }
+            ScopeLevel::Statement => {
+                write!(f, "STATEMENT")
+            }
         }
     }
 }
@@ -67,6 +72,9 @@ pub struct Settings {
     pub(crate) tenant: String,
     pub(crate) changes: DashMap<String, ChangeValue>,
     pub(crate) configs: HashMap<String, UserSettingValue>,
+    // changes made by `SET LOCAL`, waiting for the next statement of the session
+    #[serde(skip)]
+    pub(crate) local_changes: DashMap<String, ChangeValue>,
 }
 
 impl Settings {
@@ -79,6 +87,7 @@ impl Settings {
             tenant,
             changes: DashMap::new(),
             configs,
+            local_changes: DashMap::new(),
         })
     }
 
@@ -86,6 +95,11 @@ impl Settings {
         Ok(DefaultSettings::instance()?.settings.contains_key(key))
     }
 
+    /// Returns the warning to emit when a deprecated setting is set or unset.
+    pub fn deprecation_warning(&self, key: &str) -> Option<String> {
+        DefaultSettings::deprecation_warning(key)
+    }
+
     pub fn check_and_get_default_value(&self, key: &str) -> Result<UserSettingValue> {
         match DefaultSettings::instance()?.settings.get(key) {
             Some(v) => Ok(v.value.clone()),
@@ -96,10 +110,72 @@ impl Settings {
         }
     }
 
+    /// Checks that the setting `key` exists and can be unset.
+    pub fn check_unset_setting(&self, key: &str) -> Result<()> {
+        DefaultSettings::check_setting_mode(key, SettingMode::Write)
+    }
+
     pub fn unset_setting(&self, k: &str) {
         self.changes.remove(k);
     }
 
+    /// Runs `f` with the setting `key` set to `value` until its future is done, then restores
+    /// the previous value of the setting, or unsets it if it was not set before. The setting is
+    /// restored even if `f` panics.
+    #[async_backtrace::framed]
+    pub async fn with_setting<F, Fut, T>(&self, key: &str, value: &str, f: F) -> Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let _restore = RestoreSetting {
+            settings: self,
+            key: key.to_string(),
+            prev: self.changes.get(key).map(|change| change.value().clone()),
+        };
+        self.set_setting(key.to_string(), value.to_string()).await?;
+        Ok(f().await)
+    }
+
+    /// Sets the setting `k` for the next statement of the session only, which consumes it with
+    /// [`Settings::consume_local_settings`].
+    pub fn set_local_setting(&self, k: String, v: String) -> Result<()> {
+        DefaultSettings::check_setting_mode(&k, SettingMode::Write)?;
+
+        let (key, value) = DefaultSettings::convert_value(k, v)?;
+        self.local_changes.insert(key, ChangeValue {
+            value,
+            level: ScopeLevel::Statement,
+        });
+        Ok(())
+    }
+
+    /// The settings set by [`Settings::set_local_setting`], they keep waiting for the next
+    /// statement until consumed.
+    pub fn local_settings(&self) -> Arc<Settings> {
+        let changes = DashMap::new();
+        for change in self.local_changes.iter() {
+            changes.insert(change.key().clone(), change.value().clone());
+        }
+        Arc::new(Settings {
+            tenant: self.tenant.clone(),
+            changes,
+            configs: HashMap::new(),
+            local_changes: DashMap::new(),
+        })
+    }
+
+    /// Consumes the settings got by [`Settings::local_settings`] for a statement, unless they
+    /// are set again since.
+    pub fn consume_local_settings(&self, local_settings: &Settings) {
+        for change in local_settings.changes.iter() {
+            self.local_changes
+                .remove_if(change.key(), |_, local_change| {
+                    local_change.value == change.value().value
+                });
+        }
+    }
+
     pub async fn set_batch_settings(&self, settings: &HashMap<String, String>) -> Result<()> {
         for (k, v) in settings.iter() {
             if self.has_setting(k.as_str())? {
@@ -125,6 +201,24 @@ impl Settings {
     }
 }
 
+/// Restores a setting changed by [`Settings::with_setting`] once dropped.
+struct RestoreSetting<'a> {
+    settings: &'a Settings,
+    key: String,
+    prev: Option<ChangeValue>,
+}
+
+impl Drop for RestoreSetting<'_> {
+    fn drop(&mut self) {
+        match self.prev.take() {
+            Some(prev) => {
+                self.settings.changes.insert(self.key.clone(), prev);
+            }
+            None => self.settings.unset_setting(&self.key),
+        }
+    }
+}
+
 pub struct SettingsItem {
     pub name: String,
     pub level: ScopeLevel,
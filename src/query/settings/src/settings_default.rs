@@ -25,6 +25,13 @@ use once_cell::sync::OnceCell;
 
 static DEFAULT_SETTINGS: OnceCell<Arc<DefaultSettings>> = OnceCell::new();
 
+// Settings which still take effect but are going to be removed,
+// with the name of the setting replacing it, if any.
+static DEPRECATED_SETTINGS: &[(&str, Option<&str>)] = &[
+    // reading from storage is buffered by `input_read_buffer_size`.
+    ("storage_read_buffer_size", Some("input_read_buffer_size")),
+];
+
 // Default value of cost factor settings
 #[allow(dead_code)]
 static COST_FACTOR_COMPUTE_PER_ROW: u64 = 1;
@@ -217,6 +224,30 @@ impl DefaultSettings {
                     mode: SettingMode::Both,
                     range: None,
                 }),
+                ("group_by_max_groups", DefaultSettingValue {
+                    value: UserSettingValue::UInt64(0),
+                    desc: "Sets the maximum number of distinct groups of a GROUP BY without aggregate functions, the query fails once exceeded. 0 means no limit.",
+                    mode: SettingMode::Both,
+                    range: None,
+                }),
+                ("group_by_final_max_arena_bytes", DefaultSettingValue {
+                    value: UserSettingValue::UInt64(0),
+                    desc: "Sets the maximum bytes of the arena of the keys merged by the final GROUP BY of a bucket, the query fails once exceeded. 0 means no limit.",
+                    mode: SettingMode::Both,
+                    range: None,
+                }),
+                ("group_by_final_merge_threads", DefaultSettingValue {
+                    value: UserSettingValue::UInt64(0),
+                    desc: "Sets the number of threads merging the payloads of a bucket in the final GROUP BY without aggregate functions, 0 or 1 merges them serially.",
+                    mode: SettingMode::Both,
+                    range: None,
+                }),
+                ("group_by_final_preserve_order", DefaultSettingValue {
+                    value: UserSettingValue::UInt64(0),
+                    desc: "Emits the groups of a bucket in the final GROUP BY without aggregate functions in the order they are first seen, instead of the order of the hashtable.",
+                    mode: SettingMode::Both,
+                    range: Some(SettingRange::Numeric(0..=1)),
+                }),
                 ("max_inlist_to_or", DefaultSettingValue {
                     value: UserSettingValue::UInt64(3),
                     desc: "Sets the maximum number of values that can be included in an IN expression to be converted to an OR operator.",
@@ -485,6 +516,84 @@ impl DefaultSettings {
                     mode: SettingMode::Both,
                     range: Some(SettingRange::Numeric(0..=1)),
                 }),
+                ("enable_compact_verification", DefaultSettingValue {
+                    value: UserSettingValue::UInt64(0),
+                    desc: "Verify the table contents are unchanged after compaction, this reads the table twice.",
+                    mode: SettingMode::Both,
+                    range: Some(SettingRange::Numeric(0..=1)),
+                }),
+                ("compact_read_prefetch_depth", DefaultSettingValue {
+                    value: UserSettingValue::UInt64(0),
+                    desc: "Sets the number of compact tasks whose blocks are read ahead while rewriting the current one, 0 disables the prefetching.",
+                    mode: SettingMode::Both,
+                    range: Some(SettingRange::Numeric(0..=64)),
+                }),
+                ("compact_segment_order", DefaultSettingValue {
+                    value: UserSettingValue::String("oldest".to_string()),
+                    desc: "Sets the order in which the segments are compacted by the segment compaction with a segment limit, \"oldest\" or \"smallest\" first.",
+                    mode: SettingMode::Both,
+                    range: Some(SettingRange::String(vec!["oldest", "smallest"])),
+                }),
+                ("compact_segments_verify_blocks", DefaultSettingValue {
+                    value: UserSettingValue::UInt64(0),
+                    desc: "Checks that the blocks referenced by the segments merged by the segment compaction still exist in the storage before committing.",
+                    mode: SettingMode::Both,
+                    range: Some(SettingRange::Numeric(0..=1)),
+                }),
+                ("compact_segment_threshold", DefaultSettingValue {
+                    value: UserSettingValue::UInt64(0),
+                    desc: "Sets the number of blocks per segment that the segment compaction merges the smaller segments into, 0 means the block_per_segment of the table.",
+                    mode: SettingMode::Both,
+                    range: None,
+                }),
+                ("compact_segments_split_oversized", DefaultSettingValue {
+                    value: UserSettingValue::UInt64(0),
+                    desc: "Splits the segments of twice the compact_segment_threshold or more into segments of the threshold by the segment compaction.",
+                    mode: SettingMode::Both,
+                    range: Some(SettingRange::Numeric(0..=1)),
+                }),
+                ("compact_segments_replan_appends", DefaultSettingValue {
+                    value: UserSettingValue::UInt64(0),
+                    desc: "Compacts the segments appended during the segment compaction together with the compacted segments when it is committed, instead of keeping them as they are.",
+                    mode: SettingMode::Both,
+                    range: Some(SettingRange::Numeric(0..=1)),
+                }),
+                ("compact_segments_write_concurrency", DefaultSettingValue {
+                    value: UserSettingValue::UInt64(0),
+                    desc: "Sets the number of new segments written at the same time by the segment compaction, 0 means 4.",
+                    mode: SettingMode::Both,
+                    range: None,
+                }),
+                ("compact_segments_max_commit_retries", DefaultSettingValue {
+                    value: UserSettingValue::UInt64(10),
+                    desc: "Sets the maximum times that the commit of the segment compaction is rebased on the segments appended concurrently, before it gives up.",
+                    mode: SettingMode::Both,
+                    range: None,
+                }),
+                ("compact_block_rows", DefaultSettingValue {
+                    value: UserSettingValue::UInt64(0),
+                    desc: "Sets the number of rows of the blocks that the block compaction merges the smaller blocks into, 0 means the row_per_block of the table.",
+                    mode: SettingMode::Both,
+                    range: None,
+                }),
+                ("compact_block_bytes", DefaultSettingValue {
+                    value: UserSettingValue::UInt64(0),
+                    desc: "Sets the bytes of the blocks that the block compaction merges the smaller blocks into, 0 means the block_size_threshold of the table. Only one of compact_block_rows and compact_block_bytes can be set.",
+                    mode: SettingMode::Both,
+                    range: None,
+                }),
+                ("compact_dictionary_columns", DefaultSettingValue {
+                    value: UserSettingValue::String("".to_string()),
+                    desc: "Sets the comma separated columns to be dictionary encoded when rewritten by compaction, if their cardinality in the block is low. Only applies to the parquet format.",
+                    mode: SettingMode::Both,
+                    range: None,
+                }),
+                ("compact_dictionary_max_cardinality", DefaultSettingValue {
+                    value: UserSettingValue::UInt64(1000),
+                    desc: "Sets the max number of distinct values in a block of a column of `compact_dictionary_columns` to be dictionary encoded.",
+                    mode: SettingMode::Both,
+                    range: Some(SettingRange::Numeric(1..=u64::MAX)),
+                }),
                 ("enable_aggregating_index_scan", DefaultSettingValue {
                     value: UserSettingValue::UInt64(1),
                     desc: "Enable scanning aggregating index data while querying.",
@@ -503,6 +612,12 @@ impl DefaultSettings {
                     mode: SettingMode::Both,
                     range: None,
                 }),
+                ("auto_compaction_segments_growth_per_hour", DefaultSettingValue {
+                    value: UserSettingValue::UInt64(0),
+                    desc: "Triggers auto compaction after write operations when the table gains at least this many segments per hour over the last hour, 0 disables it.",
+                    mode: SettingMode::Both,
+                    range: None,
+                }),
                 ("use_parquet2", DefaultSettingValue {
                     value: UserSettingValue::UInt64(0),
                     desc: "Use parquet2 instead of parquet_rs when infer_schema().",
@@ -749,6 +864,18 @@ impl DefaultSettings {
         Ok(Self::instance()?.settings.contains_key(key))
     }
 
+    /// Returns the warning to emit if the setting is deprecated.
+    pub fn deprecation_warning(key: &str) -> Option<String> {
+        let (_, replacement) = DEPRECATED_SETTINGS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))?;
+        let warning = format!("Setting '{}' is deprecated and will be removed", key);
+        Some(match replacement {
+            Some(replacement) => format!("{}, use '{}' instead", warning, replacement),
+            None => warning,
+        })
+    }
+
     /// Converts and validates a setting value based on its key.
     pub fn convert_value(k: String, v: String) -> Result<(String, UserSettingValue)> {
         // Retrieve the default settings instance
@@ -803,7 +930,7 @@ impl DefaultSettings {
                 // If not a valid u64, try parsing as f64
                 match v.parse::<f64>() {
                     Ok(f) if f.fract() == 0.0 && f >= 0.0 && f <= u64::MAX as f64 => {
-                        Ok(f.trunc() as u64) /* Convert to u64 if no fractional part, non-negative, and within u64 range */
+                        Ok(f.trunc() as u64) // Convert to u64 if no fractional part, non-negative, and within u64 range
                     }
                     _ => Err(ErrorCode::WrongValueForVariable(format!(
                         "{} is not a valid integer value",
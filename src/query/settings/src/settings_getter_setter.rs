@@ -243,6 +243,22 @@ impl Settings {
         self.try_get_u64("group_by_two_level_threshold")
     }
 
+    pub fn get_group_by_max_groups(&self) -> Result<u64> {
+        self.try_get_u64("group_by_max_groups")
+    }
+
+    pub fn get_group_by_final_max_arena_bytes(&self) -> Result<u64> {
+        self.try_get_u64("group_by_final_max_arena_bytes")
+    }
+
+    pub fn get_group_by_final_merge_threads(&self) -> Result<u64> {
+        self.try_get_u64("group_by_final_merge_threads")
+    }
+
+    pub fn get_group_by_final_preserve_order(&self) -> Result<bool> {
+        Ok(self.try_get_u64("group_by_final_preserve_order")? == 1)
+    }
+
     pub fn get_max_inlist_to_or(&self) -> Result<u64> {
         self.try_get_u64("max_inlist_to_or")
     }
@@ -443,6 +459,63 @@ impl Settings {
         Ok(self.try_get_u64("enable_distributed_compact")? != 0)
     }
 
+    pub fn get_enable_compact_verification(&self) -> Result<bool> {
+        Ok(self.try_get_u64("enable_compact_verification")? != 0)
+    }
+
+    pub fn get_compact_read_prefetch_depth(&self) -> Result<u64> {
+        self.try_get_u64("compact_read_prefetch_depth")
+    }
+
+    pub fn get_compact_segment_order(&self) -> Result<String> {
+        self.try_get_string("compact_segment_order")
+    }
+
+    pub fn get_compact_segments_verify_blocks(&self) -> Result<bool> {
+        Ok(self.try_get_u64("compact_segments_verify_blocks")? != 0)
+    }
+
+    pub fn get_compact_segment_threshold(&self) -> Result<u64> {
+        self.try_get_u64("compact_segment_threshold")
+    }
+
+    pub fn get_compact_segments_split_oversized(&self) -> Result<bool> {
+        Ok(self.try_get_u64("compact_segments_split_oversized")? != 0)
+    }
+
+    pub fn get_compact_segments_replan_appends(&self) -> Result<bool> {
+        Ok(self.try_get_u64("compact_segments_replan_appends")? != 0)
+    }
+
+    pub fn get_compact_segments_write_concurrency(&self) -> Result<u64> {
+        self.try_get_u64("compact_segments_write_concurrency")
+    }
+
+    pub fn get_compact_segments_max_commit_retries(&self) -> Result<u64> {
+        self.try_get_u64("compact_segments_max_commit_retries")
+    }
+
+    pub fn get_compact_block_rows(&self) -> Result<u64> {
+        self.try_get_u64("compact_block_rows")
+    }
+
+    pub fn get_compact_block_bytes(&self) -> Result<u64> {
+        self.try_get_u64("compact_block_bytes")
+    }
+
+    pub fn get_compact_dictionary_columns(&self) -> Result<Vec<String>> {
+        Ok(self
+            .try_get_string("compact_dictionary_columns")?
+            .split(',')
+            .map(|column| column.trim().to_string())
+            .filter(|column| !column.is_empty())
+            .collect())
+    }
+
+    pub fn get_compact_dictionary_max_cardinality(&self) -> Result<u64> {
+        self.try_get_u64("compact_dictionary_max_cardinality")
+    }
+
     pub fn get_enable_aggregating_index_scan(&self) -> Result<bool> {
         Ok(self.try_get_u64("enable_aggregating_index_scan")? != 0)
     }
@@ -459,6 +532,10 @@ impl Settings {
         self.try_set_u64("auto_compaction_imperfect_blocks_threshold", val)
     }
 
+    pub fn get_auto_compaction_segments_growth_per_hour(&self) -> Result<u64> {
+        self.try_get_u64("auto_compaction_segments_growth_per_hour")
+    }
+
     pub fn get_use_parquet2(&self) -> Result<bool> {
         Ok(self.try_get_u64("use_parquet2")? != 0)
     }
@@ -218,3 +218,86 @@ async fn test_set_data_retention_time_in_days_from_config() {
         assert_eq!(expect, format!("{}", result.unwrap_err()));
     }
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn test_with_setting() {
+    let settings = Settings::create("test".to_string());
+    let default_threads = settings.get_max_threads().unwrap();
+
+    // not set before, unset after.
+    let threads = settings
+        .with_setting("max_threads", "3", || async {
+            // still set after the future yields.
+            tokio::task::yield_now().await;
+            settings.get_max_threads().unwrap()
+        })
+        .await
+        .unwrap();
+    assert_eq!(threads, 3);
+    assert_eq!(settings.get_max_threads().unwrap(), default_threads);
+    assert!(!settings.is_changed());
+
+    // set before, the previous value is restored.
+    settings.set_max_threads(2).unwrap();
+    settings
+        .with_setting("max_threads", "5", || async {
+            assert_eq!(settings.get_max_threads().unwrap(), 5);
+        })
+        .await
+        .unwrap();
+    assert_eq!(settings.get_max_threads().unwrap(), 2);
+
+    // an invalid value is not set, and the closure is not run.
+    let result = settings
+        .with_setting("max_threads", "1025", || async { unreachable!() })
+        .await;
+    assert!(result.is_err());
+    assert_eq!(settings.get_max_threads().unwrap(), 2);
+
+    // restored after a panic in the closure.
+    let handle = tokio::spawn({
+        let settings = settings.clone();
+        async move {
+            settings
+                .with_setting("max_threads", "7", || async {
+                    panic!("panic in with_setting")
+                })
+                .await
+        }
+    });
+    assert!(handle.await.unwrap_err().is_panic());
+    assert_eq!(settings.get_max_threads().unwrap(), 2);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn test_set_local_setting() {
+    let settings = Settings::create("test".to_string());
+    settings.set_max_threads(2).unwrap();
+
+    // not applied to the settings of the session.
+    settings
+        .set_local_setting("max_threads".to_string(), "3".to_string())
+        .unwrap();
+    assert_eq!(settings.get_max_threads().unwrap(), 2);
+
+    let result = settings.set_local_setting("max_threads".to_string(), "1025".to_string());
+    assert!(result.is_err());
+
+    // waiting until consumed by a statement.
+    let local_settings = settings.local_settings();
+    assert_eq!(local_settings.get_max_threads().unwrap(), 3);
+    assert_eq!(settings.local_settings().get_max_threads().unwrap(), 3);
+    settings.consume_local_settings(&local_settings);
+    assert!(!settings.local_settings().is_changed());
+
+    // kept for the next statement if set again since.
+    settings
+        .set_local_setting("max_threads".to_string(), "3".to_string())
+        .unwrap();
+    let local_settings = settings.local_settings();
+    settings
+        .set_local_setting("max_threads".to_string(), "4".to_string())
+        .unwrap();
+    settings.consume_local_settings(&local_settings);
+    assert_eq!(settings.local_settings().get_max_threads().unwrap(), 4);
+}
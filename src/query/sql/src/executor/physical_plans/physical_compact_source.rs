@@ -15,6 +15,7 @@
 use std::collections::HashSet;
 
 use databend_common_catalog::plan::Partitions;
+use databend_common_catalog::table::BlockSize;
 use databend_common_expression::ColumnId;
 use databend_common_meta_app::schema::CatalogInfo;
 use databend_common_meta_app::schema::TableInfo;
@@ -25,4 +26,6 @@ pub struct CompactSource {
     pub table_info: TableInfo,
     pub catalog_info: CatalogInfo,
     pub column_ids: HashSet<ColumnId>,
+    // the size of the compacted blocks, of the table if not set
+    pub block_size: Option<BlockSize>,
 }
@@ -46,6 +46,7 @@ use crate::optimizer::SExpr;
 use crate::plans::CreateFileFormatPlan;
 use crate::plans::CreateRolePlan;
 use crate::plans::DescConnectionPlan;
+use crate::plans::DescribeStagePlan;
 use crate::plans::DropConnectionPlan;
 use crate::plans::DropFileFormatPlan;
 use crate::plans::DropRolePlan;
@@ -359,14 +360,11 @@ impl<'a> Binder {
             // Stages
             Statement::ShowStages => self.bind_rewrite_to_query(bind_context, "SELECT name, stage_type, number_of_files, creator, created_on, comment FROM system.stages ORDER BY name", RewriteKind::ShowStages).await?,
             Statement::ListStage { location, pattern } => {
-                let pattern = if let Some(pattern) = pattern {
-                    format!(", pattern => '{pattern}'")
-                } else {
-                    "".to_string()
-                };
-                self.bind_rewrite_to_query(bind_context, format!("SELECT * FROM LIST_STAGE(location => '@{location}'{pattern})").as_str(), RewriteKind::ListStage).await?
+                self.bind_list_stage(location, pattern.as_deref().unwrap_or_default()).await?
             }
-            Statement::DescribeStage { stage_name } => self.bind_rewrite_to_query(bind_context, format!("SELECT * FROM system.stages WHERE name = '{stage_name}'").as_str(), RewriteKind::DescribeStage).await?,
+            Statement::DescribeStage { stage_name } => Plan::DescribeStage(Box::new(DescribeStagePlan {
+                name: stage_name.clone(),
+            })),
             Statement::CreateStage(stmt) => self.bind_create_stage(stmt).await?,
             Statement::DropStage {
                 stage_name,
@@ -476,10 +474,11 @@ impl<'a> Binder {
 
             Statement::SetVariable {
                 is_global,
+                is_local,
                 variable,
                 value,
             } => {
-                self.bind_set_variable(bind_context, *is_global, variable, value)
+                self.bind_set_variable(bind_context, *is_global, *is_local, variable, value)
                     .await?
             }
 
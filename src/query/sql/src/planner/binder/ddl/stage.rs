@@ -24,15 +24,39 @@ use databend_common_meta_app::principal::FileFormatParams;
 use databend_common_meta_app::principal::OnErrorMode;
 use databend_common_meta_app::principal::StageInfo;
 use databend_common_storage::init_operator;
+use regex::Regex;
 
 use super::super::copy_into_table::resolve_stage_location;
 use crate::binder::location::parse_storage_params_from_uri;
 use crate::binder::Binder;
 use crate::plans::CreateStagePlan;
+use crate::plans::ListStagePlan;
 use crate::plans::Plan;
 use crate::plans::RemoveStagePlan;
 
 impl Binder {
+    #[async_backtrace::framed]
+    pub(in crate::planner::binder) async fn bind_list_stage(
+        &mut self,
+        location: &str,
+        pattern: &str,
+    ) -> Result<Plan> {
+        let (stage, path) = resolve_stage_location(self.ctx.as_ref(), location).await?;
+        let matcher = if pattern.is_empty() {
+            None
+        } else {
+            Some(compile_stage_pattern(pattern, "LIST")?)
+        };
+        let plan_node = ListStagePlan {
+            path,
+            stage,
+            pattern: pattern.to_string(),
+            matcher,
+        };
+
+        Ok(Plan::ListStage(Box::new(plan_node)))
+    }
+
     #[async_backtrace::framed]
     pub(in crate::planner::binder) async fn bind_remove_stage(
         &mut self,
@@ -40,10 +64,16 @@ impl Binder {
         pattern: &str,
     ) -> Result<Plan> {
         let (stage, path) = resolve_stage_location(self.ctx.as_ref(), location).await?;
+        let matcher = if pattern.is_empty() {
+            None
+        } else {
+            Some(compile_stage_pattern(pattern, "REMOVE")?)
+        };
         let plan_node = RemoveStagePlan {
             path,
             stage,
             pattern: pattern.to_string(),
+            matcher,
         };
 
         Ok(Plan::RemoveStage(Box::new(plan_node)))
@@ -55,7 +85,7 @@ impl Binder {
         stmt: &CreateStageStmt,
     ) -> Result<Plan> {
         let CreateStageStmt {
-            if_not_exists,
+            create_option,
             stage_name,
             location,
             file_format_options,
@@ -116,7 +146,7 @@ impl Binder {
         }
 
         Ok(Plan::CreateStage(Box::new(CreateStagePlan {
-            if_not_exists: *if_not_exists,
+            create_option: create_option.clone(),
             tenant: self.ctx.get_tenant(),
             stage_info,
         })))
@@ -136,3 +166,13 @@ impl Binder {
         }
     }
 }
+
+// The pattern matches the whole path of a file, as in the other statements on stage files.
+// It is parsed on its own first, so that the position of a syntax error is of the pattern given.
+fn compile_stage_pattern(pattern: &str, stmt: &str) -> Result<Regex> {
+    Regex::new(pattern)
+        .and_then(|_| Regex::new(&format!("^{pattern}$")))
+        .map_err(|e| {
+            ErrorCode::SyntaxException(format!("invalid pattern {:?} of {stmt}, {}", pattern, e))
+        })
+}
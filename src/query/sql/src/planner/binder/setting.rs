@@ -37,6 +37,7 @@ impl Binder {
         &mut self,
         bind_context: &mut BindContext,
         is_global: bool,
+        is_local: bool,
         variable: &Identifier,
         value: &Expr,
     ) -> Result<Plan> {
@@ -61,6 +62,7 @@ impl Binder {
                 let value = scalar.into_string().unwrap();
                 let vars = vec![VarValue {
                     is_global,
+                    is_local,
                     variable,
                     value,
                 }];
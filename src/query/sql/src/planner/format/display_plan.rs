@@ -121,6 +121,8 @@ impl Plan {
             // Stages
             Plan::CreateStage(_) => Ok("CreateStage".to_string()),
             Plan::DropStage(_) => Ok("DropStage".to_string()),
+            Plan::DescribeStage(_) => Ok("DescribeStage".to_string()),
+            Plan::ListStage(_) => Ok("ListStage".to_string()),
             Plan::RemoveStage(_) => Ok("RemoveStage".to_string()),
 
             // FileFormat
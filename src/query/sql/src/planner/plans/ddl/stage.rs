@@ -14,11 +14,18 @@
 
 use std::fmt::Debug;
 
+use databend_common_expression::types::DataType;
+use databend_common_expression::types::NumberDataType;
+use databend_common_expression::DataField;
+use databend_common_expression::DataSchemaRef;
+use databend_common_expression::DataSchemaRefExt;
 use databend_common_meta_app::principal::StageInfo;
+use databend_common_meta_app::schema::CreateOption;
+use regex::Regex;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct CreateStagePlan {
-    pub if_not_exists: bool,
+    pub create_option: CreateOption,
     pub tenant: String,
     pub stage_info: StageInfo,
 }
@@ -30,10 +37,55 @@ pub struct DropStagePlan {
     pub name: String,
 }
 
-/// Remove.
+/// Describe.
 #[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DescribeStagePlan {
+    pub name: String,
+}
+
+impl DescribeStagePlan {
+    pub fn schema(&self) -> DataSchemaRef {
+        DataSchemaRefExt::create(vec![
+            DataField::new("name", DataType::String),
+            DataField::new("stage_type", DataType::String),
+            DataField::new("url", DataType::String.wrap_nullable()),
+            DataField::new("stage_params", DataType::String),
+            DataField::new("file_format_options", DataType::String),
+            DataField::new("copy_options", DataType::String),
+            DataField::new("comment", DataType::String),
+        ])
+    }
+}
+
+/// List.
+#[derive(Clone, Debug)]
+pub struct ListStagePlan {
+    pub stage: StageInfo,
+    pub path: String,
+    pub pattern: String,
+    // `pattern` compiled by the binder, the same as the one of REMOVE
+    pub matcher: Option<Regex>,
+}
+
+impl ListStagePlan {
+    // the same as the table function LIST_STAGE, `md5` is the etag of the file.
+    pub fn schema(&self) -> DataSchemaRef {
+        DataSchemaRefExt::create(vec![
+            DataField::new("name", DataType::String),
+            DataField::new("size", DataType::Number(NumberDataType::UInt64)),
+            DataField::new("md5", DataType::String.wrap_nullable()),
+            DataField::new("last_modified", DataType::String),
+            DataField::new("creator", DataType::String.wrap_nullable()),
+        ])
+    }
+}
+
+/// Remove.
+#[derive(Clone, Debug)]
 pub struct RemoveStagePlan {
     pub stage: StageInfo,
     pub path: String,
     pub pattern: String,
+    // `pattern` compiled by the binder, None if no pattern is given
+    pub matcher: Option<Regex>,
 }
@@ -162,8 +162,20 @@ pub struct OptimizeTablePlan {
 }
 
 impl OptimizeTablePlan {
+    /// A summary of the compaction, before and after it, purge returns nothing.
     pub fn schema(&self) -> DataSchemaRef {
-        Arc::new(DataSchema::empty())
+        if matches!(self.action, OptimizeTableAction::Purge(_)) {
+            return Arc::new(DataSchema::empty());
+        }
+        Arc::new(DataSchema::new(vec![
+            DataField::new("segments_before", DataType::Number(NumberDataType::UInt64)),
+            DataField::new("segments_after", DataType::Number(NumberDataType::UInt64)),
+            DataField::new("blocks_before", DataType::Number(NumberDataType::UInt64)),
+            DataField::new("blocks_after", DataType::Number(NumberDataType::UInt64)),
+            DataField::new("bytes_rewritten", DataType::Number(NumberDataType::UInt64)),
+            DataField::new("bytes_saved", DataType::Number(NumberDataType::UInt64)),
+            DataField::new("status", DataType::String),
+        ]))
     }
 }
 
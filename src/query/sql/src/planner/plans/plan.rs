@@ -65,6 +65,7 @@ use crate::plans::DescDatamaskPolicyPlan;
 use crate::plans::DescNetworkPolicyPlan;
 use crate::plans::DescPasswordPolicyPlan;
 use crate::plans::DescSharePlan;
+use crate::plans::DescribeStagePlan;
 use crate::plans::DescribeTablePlan;
 use crate::plans::DescribeTaskPlan;
 use crate::plans::DropCatalogPlan;
@@ -95,6 +96,7 @@ use crate::plans::GrantRolePlan;
 use crate::plans::GrantShareObjectPlan;
 use crate::plans::Insert;
 use crate::plans::KillPlan;
+use crate::plans::ListStagePlan;
 use crate::plans::MergeInto;
 use crate::plans::ModifyTableColumnPlan;
 use crate::plans::OptimizeTablePlan;
@@ -263,6 +265,8 @@ pub enum Plan {
     // Stages
     CreateStage(Box<CreateStagePlan>),
     DropStage(Box<DropStagePlan>),
+    DescribeStage(Box<DescribeStagePlan>),
+    ListStage(Box<ListStagePlan>),
     RemoveStage(Box<RemoveStagePlan>),
 
     // Connection
@@ -345,8 +349,6 @@ pub enum RewriteKind {
 
     ShowUsers,
     ShowStages,
-    DescribeStage,
-    ListStage,
     ShowRoles,
     ShowPasswordPolicies,
 
@@ -403,6 +405,7 @@ impl Plan {
             Plan::DescribeTable(plan) => plan.schema(),
             Plan::VacuumTable(plan) => plan.schema(),
             Plan::VacuumDropTable(plan) => plan.schema(),
+            Plan::OptimizeTable(plan) => plan.schema(),
             Plan::ExistsTable(plan) => plan.schema(),
             Plan::ShowRoles(plan) => plan.schema(),
             Plan::ShowGrants(plan) => plan.schema(),
@@ -429,6 +432,8 @@ impl Plan {
             Plan::DescribeTask(plan) => plan.schema(),
             Plan::ShowTasks(plan) => plan.schema(),
             Plan::ExecuteTask(plan) => plan.schema(),
+            Plan::DescribeStage(plan) => plan.schema(),
+            Plan::ListStage(plan) => plan.schema(),
 
             Plan::DescConnection(plan) => plan.schema(),
             Plan::ShowConnections(plan) => plan.schema(),
@@ -463,6 +468,7 @@ impl Plan {
                 | Plan::Presign(_)
                 | Plan::VacuumTable(_)
                 | Plan::VacuumDropTable(_)
+                | Plan::OptimizeTable(_)
                 | Plan::DescDatamaskPolicy(_)
                 | Plan::DescNetworkPolicy(_)
                 | Plan::ShowNetworkPolicies(_)
@@ -471,6 +477,8 @@ impl Plan {
                 | Plan::CopyIntoLocation(_)
                 | Plan::ShowTasks(_)
                 | Plan::DescribeTask(_)
+                | Plan::DescribeStage(_)
+                | Plan::ListStage(_)
                 | Plan::DescConnection(_)
                 | Plan::ShowConnections(_)
                 | Plan::MergeInto(_)
@@ -15,6 +15,8 @@
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct VarValue {
     pub is_global: bool,
+    // `SET LOCAL`, only for the next statement of the session
+    pub is_local: bool,
     pub variable: String,
     pub value: String,
 }
@@ -1083,6 +1083,30 @@ impl<'a> TypeChecker<'a> {
         }
     }
 
+    // The length of `sha2` selects the hash function, it must be a constant of the supported
+    // lengths rather than fail on each row.
+    fn check_sha2_length(&self, span: Span, length: &ScalarExpr) -> Result<()> {
+        let (expr, _) =
+            ConstantFolder::fold(&length.as_expr()?, &self.func_ctx, &BUILTIN_FUNCTIONS);
+        let value = match expr {
+            databend_common_expression::Expr::Constant { scalar, .. } => match scalar {
+                Scalar::Null => return Ok(()),
+                Scalar::Number(n) => n.integer_to_i128(),
+                Scalar::String(s) => s.trim().parse::<i128>().ok(),
+                _ => None,
+            },
+            _ => None,
+        };
+        if !matches!(value, Some(0 | 224 | 256 | 384 | 512)) {
+            return Err(ErrorCode::BadArguments(format!(
+                "sha2 expects a constant hash length of 224, 256, 384 or 512, but got {}",
+                length.as_raw_expr()
+            ))
+            .set_span(span));
+        }
+        Ok(())
+    }
+
     #[async_backtrace::framed]
     async fn resolve_window(
         &mut self,
@@ -1978,6 +2002,10 @@ impl<'a> TypeChecker<'a> {
             Self::rewrite_substring(&mut args);
         }
 
+        if func_name == "sha2" && args.len() >= 2 {
+            self.check_sha2_length(span, &args[1])?;
+        }
+
         if func_name == "grouping" {
             // `grouping` will be rewritten again after resolving grouping sets.
             return Ok(Box::new((
@@ -41,3 +41,26 @@ pub fn blocks_to_parquet(
             .map(ParquetFileMeta::ParquetRs),
     }
 }
+
+/// Like [`blocks_to_parquet`], with the `dictionary_columns` dictionary encoded.
+/// The parquet2 writer does not support it, the columns are plain encoded as usual.
+pub fn blocks_to_parquet_with_dictionary(
+    schema: &TableSchema,
+    blocks: Vec<DataBlock>,
+    write_buffer: &mut Vec<u8>,
+    compression: TableCompression,
+    use_parquet2: bool,
+    dictionary_columns: &[String],
+) -> Result<ParquetFileMeta> {
+    match use_parquet2 {
+        true => blocks_to_parquet(schema, blocks, write_buffer, compression, true),
+        false => parquet_rs::blocks_to_parquet_with_dictionary(
+            schema,
+            blocks,
+            write_buffer,
+            compression,
+            dictionary_columns,
+        )
+        .map(ParquetFileMeta::ParquetRs),
+    }
+}
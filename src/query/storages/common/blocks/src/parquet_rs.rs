@@ -24,6 +24,7 @@ use parquet_rs::basic::Encoding;
 use parquet_rs::file::properties::EnabledStatistics;
 use parquet_rs::file::properties::WriterProperties;
 use parquet_rs::format::FileMetaData;
+use parquet_rs::schema::types::ColumnPath;
 
 /// Serialize data blocks to parquet format.
 pub fn blocks_to_parquet(
@@ -31,17 +32,32 @@ pub fn blocks_to_parquet(
     blocks: Vec<DataBlock>,
     write_buffer: &mut Vec<u8>,
     compression: TableCompression,
+) -> Result<FileMetaData> {
+    blocks_to_parquet_with_dictionary(schema, blocks, write_buffer, compression, &[])
+}
+
+/// Serialize data blocks to parquet format, the top-level columns of `dictionary_columns`
+/// are dictionary encoded, the others are plain encoded.
+pub fn blocks_to_parquet_with_dictionary(
+    schema: &TableSchema,
+    blocks: Vec<DataBlock>,
+    write_buffer: &mut Vec<u8>,
+    compression: TableCompression,
+    dictionary_columns: &[String],
 ) -> Result<FileMetaData> {
     assert!(!blocks.is_empty());
-    let props = WriterProperties::builder()
+    let mut builder = WriterProperties::builder()
         .set_compression(compression.into())
         // use `usize::MAX` to effectively limit the number of row groups to 1
         .set_max_row_group_size(usize::MAX)
         .set_encoding(Encoding::PLAIN)
         .set_dictionary_enabled(false)
         .set_statistics_enabled(EnabledStatistics::None)
-        .set_bloom_filter_enabled(false)
-        .build();
+        .set_bloom_filter_enabled(false);
+    for column in dictionary_columns {
+        builder = builder.set_column_dictionary_enabled(ColumnPath::from(column.as_str()), true);
+    }
+    let props = builder.build();
     let batches = blocks
         .into_iter()
         .map(|block| block.to_record_batch(&schema.into()))
@@ -17,6 +17,8 @@ use std::fmt;
 use std::hash::Hash;
 use std::marker::PhantomData;
 
+use chrono::DateTime;
+use chrono::Utc;
 use databend_common_expression::converts::datavalues::from_scalar;
 use databend_common_expression::converts::meta::IndexScalar;
 use databend_common_expression::types::DataType;
@@ -75,6 +77,10 @@ pub struct Statistics {
     #[serde(deserialize_with = "crate::meta::v2::statistics::deserialize_col_stats")]
     pub col_stats: HashMap<ColumnId, ColumnStatistics>,
     pub cluster_stats: Option<ClusterStatistics>,
+
+    // when the segment is written, only set for the summary of a segment.
+    #[serde(default)]
+    pub create_on: Option<DateTime<Utc>>,
 }
 
 // conversions from old meta data
@@ -225,6 +231,7 @@ impl Statistics {
             index_size: v0.index_size,
             col_stats,
             cluster_stats: None,
+            create_on: None,
         }
     }
 }
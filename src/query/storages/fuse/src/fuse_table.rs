@@ -26,6 +26,7 @@ use databend_common_catalog::plan::Partitions;
 use databend_common_catalog::plan::PushDownInfo;
 use databend_common_catalog::plan::StreamColumn;
 use databend_common_catalog::table::AppendMode;
+use databend_common_catalog::table::BlockSize;
 use databend_common_catalog::table::ColumnStatisticsProvider;
 use databend_common_catalog::table::NavigationDescriptor;
 use databend_common_catalog::table_context::TableContext;
@@ -260,6 +261,7 @@ impl FuseTable {
             table_compression: self.table_compression,
             max_page_size,
             block_per_seg,
+            dictionary_rebuild: None,
         }
     }
 
@@ -827,18 +829,21 @@ impl Table for FuseTable {
         &self,
         ctx: Arc<dyn TableContext>,
         lock: Arc<dyn Lock>,
+        bytes_per_segment: Option<u64>,
         limit: Option<usize>,
     ) -> Result<()> {
-        self.do_compact_segments(ctx, lock, limit).await
+        self.do_compact_segments(ctx, lock, bytes_per_segment, limit)
+            .await
     }
 
     #[async_backtrace::framed]
     async fn compact_blocks(
         &self,
         ctx: Arc<dyn TableContext>,
+        block_size: Option<BlockSize>,
         limit: Option<usize>,
     ) -> Result<Option<(Partitions, Arc<TableSnapshot>)>> {
-        self.do_compact_blocks(ctx, limit).await
+        self.do_compact_blocks(ctx, block_size, limit).await
     }
 
     #[async_backtrace::framed]
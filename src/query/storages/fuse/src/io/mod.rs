@@ -44,6 +44,7 @@ pub use write::write_data;
 pub use write::BlockBuilder;
 pub use write::BlockSerialization;
 pub use write::CachedMetaWriter;
+pub use write::DictionaryRebuild;
 pub use write::MetaWriter;
 pub use write::SegmentWriter;
 pub use write::WriteSettings;
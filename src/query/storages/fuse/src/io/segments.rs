@@ -54,6 +54,14 @@ impl SegmentsIO {
         }
     }
 
+    pub fn ctx(&self) -> Arc<dyn TableContext> {
+        self.ctx.clone()
+    }
+
+    pub fn operator(&self) -> Operator {
+        self.operator.clone()
+    }
+
     // Read one segment file by location.
     #[async_backtrace::framed]
     pub async fn read_compact_segment(
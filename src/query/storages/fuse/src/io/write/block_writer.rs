@@ -14,6 +14,7 @@
 
 use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use chrono::Utc;
@@ -26,9 +27,11 @@ use databend_common_expression::DataBlock;
 use databend_common_expression::FieldIndex;
 use databend_common_expression::TableField;
 use databend_common_expression::TableSchemaRef;
+use databend_common_expression::Value;
 use databend_common_io::constants::DEFAULT_BLOCK_BUFFER_SIZE;
 use databend_common_io::constants::DEFAULT_BLOCK_INDEX_BUFFER_SIZE;
 use databend_storages_common_blocks::blocks_to_parquet;
+use databend_storages_common_blocks::blocks_to_parquet_with_dictionary;
 use databend_storages_common_index::BloomIndex;
 use databend_storages_common_table_meta::meta::BlockMeta;
 use databend_storages_common_table_meta::meta::ClusterStatistics;
@@ -37,6 +40,7 @@ use databend_storages_common_table_meta::meta::Location;
 use databend_storages_common_table_meta::table::TableCompression;
 use opendal::Operator;
 
+use crate::io::write::DictionaryRebuild;
 use crate::io::write::WriteSettings;
 use crate::io::TableMetaLocationGenerator;
 use crate::operations::util;
@@ -55,12 +59,17 @@ pub fn serialize_block(
     let schema = Arc::new(schema.remove_virtual_computed_fields());
     match write_settings.storage_format {
         FuseStorageFormat::Parquet => {
-            let result = blocks_to_parquet(
+            let dictionary_columns = match &write_settings.dictionary_rebuild {
+                Some(rebuild) => low_cardinality_columns(rebuild, &schema, &block),
+                None => vec![],
+            };
+            let result = blocks_to_parquet_with_dictionary(
                 &schema,
                 vec![block],
                 buf,
                 write_settings.table_compression,
                 use_parquet2,
+                &dictionary_columns,
             )?;
             let meta = util::column_parquet_metas(&result, &schema)?;
             Ok(meta)
@@ -103,6 +112,37 @@ pub fn serialize_block(
     }
 }
 
+/// The columns of `rebuild` with no more than `max_cardinality` distinct values in the block.
+fn low_cardinality_columns(
+    rebuild: &DictionaryRebuild,
+    schema: &TableSchemaRef,
+    block: &DataBlock,
+) -> Vec<String> {
+    rebuild
+        .columns
+        .iter()
+        .filter(|name| {
+            let Some(index) = schema.fields().iter().position(|f| f.name() == *name) else {
+                return false;
+            };
+            match &block.get_by_offset(index).value {
+                Value::Scalar(_) => true,
+                Value::Column(column) => {
+                    let mut distinct = HashSet::new();
+                    for value in column.iter() {
+                        distinct.insert(value);
+                        if distinct.len() > rebuild.max_cardinality {
+                            return false;
+                        }
+                    }
+                    true
+                }
+            }
+        })
+        .cloned()
+        .collect()
+}
+
 /// Take ownership here to avoid extra copy.
 #[async_backtrace::framed]
 pub async fn write_data(data: Vec<u8>, data_accessor: &Operator, location: &str) -> Result<()> {
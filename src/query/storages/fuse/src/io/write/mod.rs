@@ -24,4 +24,5 @@ pub use block_writer::BlockSerialization;
 pub use meta_writer::CachedMetaWriter;
 pub use meta_writer::MetaWriter;
 pub use segment_writer::SegmentWriter;
+pub use write_settings::DictionaryRebuild;
 pub use write_settings::WriteSettings;
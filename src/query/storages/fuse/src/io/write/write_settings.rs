@@ -26,6 +26,17 @@ pub struct WriteSettings {
     pub max_page_size: usize,
 
     pub block_per_seg: usize,
+
+    /// Rebuilds the dictionaries of the low-cardinality columns, set by compaction only.
+    pub dictionary_rebuild: Option<DictionaryRebuild>,
+}
+
+/// The columns to be dictionary encoded if the number of distinct values in the block is not
+/// more than `max_cardinality`, only applies to the parquet format.
+#[derive(Clone, Debug, Default)]
+pub struct DictionaryRebuild {
+    pub columns: Vec<String>,
+    pub max_cardinality: usize,
 }
 
 impl Default for WriteSettings {
@@ -35,6 +46,7 @@ impl Default for WriteSettings {
             table_compression: TableCompression::default(),
             max_page_size: DEFAULT_ROW_PER_PAGE,
             block_per_seg: DEFAULT_BLOCK_PER_SEGMENT,
+            dictionary_rebuild: None,
         }
     }
 }
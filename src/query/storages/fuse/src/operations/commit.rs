@@ -282,6 +282,9 @@ impl FuseTable {
     }
 
     // TODO refactor, it is called by segment compaction
+    //
+    // Returns the times that the commit is rebased on the concurrently appended segments,
+    // which is no more than `max_retries` if it is given.
     #[async_backtrace::framed]
     pub async fn commit_mutation(
         &self,
@@ -291,7 +294,8 @@ impl FuseTable {
         base_summary: Statistics,
         abort_operation: AbortOperation,
         max_retry_elapsed: Option<Duration>,
-    ) -> Result<()> {
+        max_retries: Option<u64>,
+    ) -> Result<u64> {
         let mut retries = 0;
         let mut backoff = set_backoff(None, None, max_retry_elapsed);
 
@@ -370,14 +374,24 @@ impl FuseTable {
                                     &latest_snapshot,
                                 )
                             {
+                                if max_retries.is_some_and(|max| retries >= max) {
+                                    abort_operation
+                                        .abort_best_effort(ctx.clone(), self.operator.clone())
+                                        .await;
+                                    metrics_inc_commit_mutation_retries_exhausted();
+                                    break Err(ErrorCode::CommitRetriesExhausted(format!(
+                                        "segment compact conflict with concurrent appends, gave up after {} retries",
+                                        retries
+                                    )));
+                                }
                                 info!("resolvable conflicts detected");
                                 metrics_inc_commit_mutation_latest_snapshot_append_only();
                                 concurrently_appended_segment_locations =
                                     &latest_snapshot.segments[range_of_newly_append];
                             } else {
                                 abort_operation
-                                    .abort(ctx.clone(), self.operator.clone())
-                                    .await?;
+                                    .abort_best_effort(ctx.clone(), self.operator.clone())
+                                    .await;
                                 metrics_inc_commit_mutation_unresolvable_conflict();
                                 break Err(ErrorCode::UnresolvableConflict(
                                     "segment compact conflict with other operations",
@@ -394,8 +408,8 @@ impl FuseTable {
                             // Note that, here the last error we have seen is TableVersionMismatched,
                             // otherwise we should have been returned, thus it is safe to abort the operation here.
                             abort_operation
-                                .abort(ctx.clone(), self.operator.clone())
-                                .await?;
+                                .abort_best_effort(ctx.clone(), self.operator.clone())
+                                .await;
                             break Err(ErrorCode::StorageOther(format!(
                                 "commit mutation failed after {} retries",
                                 retries
@@ -411,7 +425,7 @@ impl FuseTable {
                 Ok(_) => {
                     break {
                         metrics_inc_commit_mutation_success();
-                        Ok(())
+                        Ok(retries)
                     };
                 }
             }
@@ -19,6 +19,7 @@ use databend_common_exception::Result;
 use databend_storages_common_cache::CacheAccessor;
 use databend_storages_common_cache_manager::CacheManager;
 use databend_storages_common_table_meta::meta::BlockMeta;
+use log::warn;
 use opendal::Operator;
 
 use crate::io::Files;
@@ -66,4 +67,14 @@ impl AbortOperation {
             .chain(self.segments.into_iter());
         fuse_file.remove_file_in_batch(locations).await
     }
+
+    /// Aborts the operation once its commit is rejected. A failure to remove the files is
+    /// only logged, the caller returns the rejection of the commit rather than it, and the
+    /// files left behind are removed by the purge.
+    #[async_backtrace::framed]
+    pub async fn abort_best_effort(self, ctx: Arc<dyn TableContext>, operator: Operator) {
+        if let Err(e) = self.abort(ctx, operator).await {
+            warn!("failed to remove the files of the aborted operation: {}", e);
+        }
+    }
 }
@@ -18,6 +18,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Instant;
 
+use chrono::Utc;
 use databend_common_base::runtime::execute_futures_in_parallel;
 use databend_common_catalog::table::Table;
 use databend_common_catalog::table_context::TableContext;
@@ -368,6 +369,7 @@ impl TableMutationAggregator {
                     }
                 }
                 // create new segment info
+                new_summary.create_on = Some(Utc::now());
                 let new_segment = SegmentInfo::new(new_blocks, new_summary.clone());
 
                 // write the segment info.
@@ -39,6 +39,7 @@ use opendal::Operator;
 use crate::io::write_data;
 use crate::io::BlockBuilder;
 use crate::io::BlockSerialization;
+use crate::io::DictionaryRebuild;
 use crate::operations::common::BlockMetaIndex;
 use crate::operations::common::MutationLogEntry;
 use crate::operations::common::MutationLogs;
@@ -102,11 +103,22 @@ impl TransformSerializeBlock {
         let bloom_columns_map = table
             .bloom_index_cols
             .bloom_index_fields(source_schema.clone(), BloomIndex::supported_type)?;
+        let mut write_settings = table.get_write_settings();
+        if matches!(kind, MutationKind::Compact) {
+            let settings = ctx.get_settings();
+            let columns = settings.get_compact_dictionary_columns()?;
+            if !columns.is_empty() {
+                write_settings.dictionary_rebuild = Some(DictionaryRebuild {
+                    columns,
+                    max_cardinality: settings.get_compact_dictionary_max_cardinality()? as usize,
+                });
+            }
+        }
         let block_builder = BlockBuilder {
             ctx,
             meta_locations: table.meta_location_generator().clone(),
             source_schema,
-            write_settings: table.get_write_settings(),
+            write_settings,
             cluster_stats_gen,
             bloom_columns_map,
         };
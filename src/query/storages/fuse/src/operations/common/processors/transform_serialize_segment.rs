@@ -16,6 +16,7 @@ use std::any::Any;
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use chrono::Utc;
 use databend_common_catalog::table_context::TableContext;
 use databend_common_exception::ErrorCode;
 use databend_common_exception::Result;
@@ -185,7 +186,8 @@ impl Processor for TransformSerializeSegment {
         match std::mem::replace(&mut self.state, State::None) {
             State::GenerateSegment => {
                 let acc = std::mem::take(&mut self.accumulator);
-                let summary = acc.summary(self.thresholds, self.default_cluster_key_id);
+                let mut summary = acc.summary(self.thresholds, self.default_cluster_key_id);
+                summary.create_on = Some(Utc::now());
 
                 let segment_info = SegmentInfo::new(acc.blocks_metas, summary);
 
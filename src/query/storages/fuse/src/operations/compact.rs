@@ -14,14 +14,19 @@
 
 use std::collections::HashSet;
 use std::sync::Arc;
+use std::time::Duration;
 
+use chrono::DateTime;
+use chrono::Utc;
 use databend_common_base::runtime::Runtime;
 use databend_common_catalog::lock::Lock;
 use databend_common_catalog::plan::Partitions;
 use databend_common_catalog::plan::PartitionsShuffleKind;
 use databend_common_catalog::plan::Projection;
+use databend_common_catalog::table::BlockSize;
 use databend_common_exception::ErrorCode;
 use databend_common_exception::Result;
+use databend_common_expression::BlockThresholds;
 use databend_common_expression::ColumnId;
 use databend_common_pipeline_core::processors::ProcessorPtr;
 use databend_common_pipeline_core::Pipeline;
@@ -29,13 +34,19 @@ use databend_common_pipeline_transforms::processors::AsyncAccumulatingTransforme
 use databend_common_sql::executor::physical_plans::MutationKind;
 use databend_common_sql::gen_mutation_stream_operator;
 use databend_storages_common_table_meta::meta::TableSnapshot;
+use futures_util::TryStreamExt;
 
+use crate::io::MetaReaders;
+use crate::io::SnapshotHistoryReader;
+use crate::io::TableMetaLocationGenerator;
 use crate::operations::common::TableMutationAggregator;
 use crate::operations::common::TransformSerializeBlock;
 use crate::operations::mutation::BlockCompactMutator;
+use crate::operations::mutation::CommitStrategy;
 use crate::operations::mutation::CompactLazyPartInfo;
 use crate::operations::mutation::CompactSource;
 use crate::operations::mutation::SegmentCompactMutator;
+use crate::operations::mutation::SegmentCompactionPlan;
 use crate::FuseTable;
 use crate::Table;
 use crate::TableContext;
@@ -47,18 +58,86 @@ pub struct CompactOptions {
     // the snapshot that compactor working on, it never changed during phases compaction.
     pub base_snapshot: Arc<TableSnapshot>,
     pub block_per_seg: usize,
+    // compacts the segments by their compressed bytes instead of `block_per_seg`, only taken
+    // by the segment compaction.
+    pub bytes_per_seg: Option<u64>,
     pub num_segment_limit: Option<usize>,
 }
 
+/// Segments added per hour over `history`, the `(timestamp, number of segments)` of the
+/// snapshots of a table in any order.
+///
+/// The segments added between consecutive snapshots are summed up, the segments removed by a
+/// compaction in between do not hide the ones added before or after it.
+///
+/// A history shorter than `min_span` is taken as lasting `min_span`, so that a few commits in
+/// a row do not look like a fast growth.
+pub fn segment_growth_per_hour(history: &[(DateTime<Utc>, u64)], min_span: Duration) -> f64 {
+    let mut history = history.to_vec();
+    history.sort_by_key(|(timestamp, _)| *timestamp);
+    let (Some(oldest), Some(newest)) = (history.first(), history.last()) else {
+        return 0.0;
+    };
+    let span = (newest.0 - oldest.0)
+        .to_std()
+        .unwrap_or_default()
+        .max(min_span);
+    if span.is_zero() {
+        return 0.0;
+    }
+    let added: u64 = history
+        .windows(2)
+        .map(|pair| pair[1].1.saturating_sub(pair[0].1))
+        .sum();
+    added as f64 * 3600.0 / span.as_secs_f64()
+}
+
 impl FuseTable {
+    /// The `(timestamp, number of segments)` of the snapshots committed since `since`, newest
+    /// first, along with the last snapshot before `since` as the base of the growth.
+    ///
+    /// At most `max_snapshots` snapshots are read, a table committed more often than that
+    /// gives the history of its last `max_snapshots` snapshots.
+    #[async_backtrace::framed]
+    pub async fn segment_count_history(
+        &self,
+        since: DateTime<Utc>,
+        max_snapshots: usize,
+    ) -> Result<Vec<(DateTime<Utc>, u64)>> {
+        let Some(location) = self.snapshot_loc().await? else {
+            return Ok(vec![]);
+        };
+        let version = TableMetaLocationGenerator::snapshot_version(location.as_str());
+        let reader = MetaReaders::table_snapshot_reader(self.get_operator());
+        let mut snapshot_stream =
+            reader.snapshot_history(location, version, self.meta_location_generator().clone());
+
+        let mut history = vec![];
+        while let Some((snapshot, _)) = snapshot_stream.try_next().await? {
+            // the snapshots of the old versions may have no timestamp.
+            let Some(timestamp) = snapshot.timestamp else {
+                break;
+            };
+            history.push((timestamp, snapshot.segments.len() as u64));
+            if timestamp < since || history.len() >= max_snapshots {
+                break;
+            }
+        }
+        Ok(history)
+    }
+
     #[async_backtrace::framed]
     pub(crate) async fn do_compact_segments(
         &self,
         ctx: Arc<dyn TableContext>,
         lock: Arc<dyn Lock>,
+        bytes_per_segment: Option<u64>,
         limit: Option<usize>,
     ) -> Result<()> {
-        let compact_options = if let Some(v) = self.compact_options(limit).await? {
+        let compact_options = if let Some(v) = self
+            .segment_compact_options(bytes_per_segment, limit)
+            .await?
+        {
             v
         } else {
             return Ok(());
@@ -72,18 +151,86 @@ impl FuseTable {
             self.operator.clone(),
             self.cluster_key_id(),
         )?;
+        if ctx.get_settings().get_compact_segments_split_oversized()? {
+            segment_mutator = segment_mutator.split_oversized(self.get_block_thresholds());
+        }
+        if ctx.get_settings().get_compact_segments_replan_appends()? {
+            segment_mutator = segment_mutator.with_commit_strategy(CommitStrategy::ReplanAppends);
+        }
 
         if !segment_mutator.target_select().await? {
             return Ok(());
         }
 
+        if let Err(e) = ctx.check_aborting() {
+            segment_mutator.abort().await?;
+            return Err(e);
+        }
+
         segment_mutator.try_commit(Arc::new(self.clone())).await
     }
 
+    /// Plans the segment compaction as `compact_segments` would run it with the same
+    /// arguments, without writing any segment or committing. Returns None if there is
+    /// nothing to compact.
+    #[async_backtrace::framed]
+    pub async fn plan_compaction(
+        &self,
+        ctx: Arc<dyn TableContext>,
+        bytes_per_segment: Option<u64>,
+        limit: Option<usize>,
+    ) -> Result<Option<SegmentCompactionPlan>> {
+        let compact_options = if let Some(v) = self
+            .segment_compact_options(bytes_per_segment, limit)
+            .await?
+        {
+            v
+        } else {
+            return Ok(None);
+        };
+
+        let mut segment_mutator = SegmentCompactMutator::try_create_dry_run(
+            ctx.clone(),
+            compact_options,
+            self.meta_location_generator().clone(),
+            self.operator.clone(),
+            self.cluster_key_id(),
+        )?;
+        if ctx.get_settings().get_compact_segments_split_oversized()? {
+            segment_mutator = segment_mutator.split_oversized(self.get_block_thresholds());
+        }
+
+        if !segment_mutator.target_select().await? {
+            return Ok(None);
+        }
+        Ok(Some(segment_mutator.plan()))
+    }
+
+    #[async_backtrace::framed]
+    async fn segment_compact_options(
+        &self,
+        bytes_per_segment: Option<u64>,
+        limit: Option<usize>,
+    ) -> Result<Option<CompactOptions>> {
+        if bytes_per_segment == Some(0) {
+            return Err(ErrorCode::BadArguments(
+                "the bytes per segment of the segment compaction must be greater than 0",
+            ));
+        }
+        let mut compact_options = if let Some(v) = self.compact_options(limit).await? {
+            v
+        } else {
+            return Ok(None);
+        };
+        compact_options.bytes_per_seg = bytes_per_segment;
+        Ok(Some(compact_options))
+    }
+
     #[async_backtrace::framed]
     pub(crate) async fn do_compact_blocks(
         &self,
         ctx: Arc<dyn TableContext>,
+        block_size: Option<BlockSize>,
         limit: Option<usize>,
     ) -> Result<Option<(Partitions, Arc<TableSnapshot>)>> {
         let compact_options = if let Some(v) = self.compact_options(limit).await? {
@@ -92,7 +239,7 @@ impl FuseTable {
             return Ok(None);
         };
 
-        let thresholds = self.get_block_thresholds();
+        let thresholds = self.compact_block_thresholds(block_size);
         let mut mutator = BlockCompactMutator::new(
             ctx.clone(),
             thresholds,
@@ -112,15 +259,35 @@ impl FuseTable {
         )))
     }
 
+    /// The block thresholds of the block compaction. The blocks are compacted into ones of
+    /// `block_size` if given, with the other thresholds of the table.
+    pub fn compact_block_thresholds(&self, block_size: Option<BlockSize>) -> BlockThresholds {
+        let thresholds = self.get_block_thresholds();
+        match block_size {
+            Some(BlockSize::Rows(rows)) => BlockThresholds::new(
+                rows,
+                (rows as f64 * 0.8) as usize,
+                thresholds.max_bytes_per_block,
+            ),
+            Some(BlockSize::Bytes(bytes)) => BlockThresholds::new(
+                thresholds.max_rows_per_block,
+                thresholds.min_rows_per_block,
+                bytes,
+            ),
+            None => thresholds,
+        }
+    }
+
     pub fn build_compact_source(
         &self,
         ctx: Arc<dyn TableContext>,
         parts: Partitions,
         column_ids: HashSet<ColumnId>,
+        block_size: Option<BlockSize>,
         pipeline: &mut Pipeline,
     ) -> Result<()> {
         let is_lazy = parts.is_lazy;
-        let thresholds = self.get_block_thresholds();
+        let thresholds = self.compact_block_thresholds(block_size);
         let cluster_key_id = self.cluster_key_id();
         let mut max_threads = ctx.get_settings().get_max_threads()? as usize;
         if is_lazy {
@@ -247,6 +414,7 @@ impl FuseTable {
         Ok(Some(CompactOptions {
             base_snapshot,
             block_per_seg,
+            bytes_per_seg: None,
             num_segment_limit: limit,
         }))
     }
@@ -36,6 +36,7 @@ mod update;
 pub mod util;
 pub use agg_index_sink::AggIndexSink;
 pub use common::*;
+pub use compact::segment_growth_per_hour;
 pub use compact::CompactOptions;
 pub use delete::MutationBlockPruningContext;
 pub use merge_into::*;
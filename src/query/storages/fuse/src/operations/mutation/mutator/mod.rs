@@ -18,6 +18,12 @@ mod segment_compact_mutator;
 
 pub use block_compact_mutator::BlockCompactMutator;
 pub use recluster_mutator::ReclusterMutator;
+pub use segment_compact_mutator::segment_partition;
+pub use segment_compact_mutator::CommitStrategy;
+pub use segment_compact_mutator::CompactSegmentOrder;
+pub use segment_compact_mutator::CompactionReport;
 pub use segment_compact_mutator::SegmentCompactMutator;
+pub use segment_compact_mutator::SegmentCompactionPlan;
 pub use segment_compact_mutator::SegmentCompactionState;
 pub use segment_compact_mutator::SegmentCompactor;
+pub use segment_compact_mutator::SegmentGrouper;
@@ -12,16 +12,30 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashSet;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::sync::OnceLock;
 use std::time::Instant;
 
+use chrono::Utc;
+use databend_common_base::runtime::execute_futures_in_parallel;
 use databend_common_catalog::lock::Lock;
 use databend_common_catalog::table::Table;
+use databend_common_exception::ErrorCode;
 use databend_common_exception::Result;
+use databend_common_expression::BlockThresholds;
+use databend_common_expression::Scalar;
+use databend_storages_common_table_meta::meta::BlockMeta;
 use databend_storages_common_table_meta::meta::Location;
 use databend_storages_common_table_meta::meta::SegmentInfo;
 use databend_storages_common_table_meta::meta::Statistics;
+use databend_storages_common_table_meta::meta::TableSnapshot;
+use futures_util::stream;
+use futures_util::StreamExt;
 use log::info;
+use log::warn;
 use metrics::gauge;
 use opendal::Operator;
 
@@ -29,8 +43,10 @@ use crate::io::SegmentWriter;
 use crate::io::SegmentsIO;
 use crate::io::TableMetaLocationGenerator;
 use crate::operations::common::AbortOperation;
+use crate::operations::common::ConflictResolveContext;
 use crate::operations::CompactOptions;
 use crate::statistics::reducers::merge_statistics_mut;
+use crate::statistics::reducers::reduce_block_metas;
 use crate::statistics::sort_by_cluster_stats;
 use crate::FuseTable;
 use crate::TableContext;
@@ -41,18 +57,106 @@ pub struct SegmentCompactionState {
     pub segments_locations: Vec<Location>,
     // paths of all the newly created segments (which are compacted), need this to rollback the compaction
     pub new_segment_paths: Vec<String>,
+    // number of new segments, which are not written (and have no paths) in a dry run
+    pub num_new_segments: usize,
     // number of fragmented segments compacted
     pub num_fragments_compacted: usize,
+    // number of segments kept as they are
+    pub segments_passed_through: usize,
+    // number of segments left unchecked once the limit is reached, which are kept as they are.
+    // the compaction can be run again to compact them.
+    pub segments_remaining: usize,
+    // compressed bytes of the blocks referenced by the newly created segments
+    pub bytes_rewritten: u64,
+    // locations of the blocks referenced by the newly created segments
+    pub referenced_blocks: Vec<String>,
+    // paths of the segments which can not be read by a dry run, they are kept as they are
+    pub unreadable_segments: Vec<String>,
+}
+
+/// What a segment compaction would do, planned by a dry run without writing or committing
+/// anything.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SegmentCompactionPlan {
+    pub segments_before: usize,
+    pub segments_after: usize,
+    // number of fragmented segments that would be merged or split
+    pub segments_merged: usize,
+    pub new_segments: usize,
+    pub segments_remaining: usize,
+    // number of blocks referenced by the new segments
+    pub blocks_rewritten: usize,
+    pub bytes_rewritten: u64,
+    // paths of the segments which can not be read, the plan is made without them
+    pub unreadable_segments: Vec<String>,
+}
+
+/// The table before and after a committed segment compaction, from the summary of the
+/// snapshot that the compaction is committed against. The segments appended concurrently and
+/// rebased on top of the compacted segments are not counted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CompactionReport {
+    pub segments_before: usize,
+    pub segments_after: usize,
+    pub blocks_before: u64,
+    pub blocks_after: u64,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    /// Times that the commit is rebased on the segments appended concurrently.
+    pub rebases: u64,
+}
+
+/// How the segment compaction is committed if segments are appended to the table since
+/// the compaction is planned.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CommitStrategy {
+    /// The appended segments are put on top of the compacted segments, as they are.
+    #[default]
+    RebaseOnly,
+    /// The appended segments are compacted together with the compacted segments before
+    /// committing, the commit still rebases on the segments appended after that.
+    ReplanAppends,
+}
+
+/// The order in which the segments are fed to the segment compaction, which decides the
+/// segments compacted if the number of segments is limited.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CompactSegmentOrder {
+    /// From the oldest segment, so that the old fragments are not left out by every limited run.
+    #[default]
+    Oldest,
+    /// From the smallest segment, so that a limited run merges the most fragmented segments.
+    /// All the segments are read before any of them is compacted.
+    Smallest,
+}
+
+impl CompactSegmentOrder {
+    pub fn from_setting(value: &str) -> Result<Self> {
+        match value {
+            "oldest" => Ok(CompactSegmentOrder::Oldest),
+            "smallest" => Ok(CompactSegmentOrder::Smallest),
+            _ => Err(ErrorCode::BadArguments(format!(
+                "unknown compact segment order {:?}",
+                value
+            ))),
+        }
+    }
 }
 
 pub struct SegmentCompactMutator {
     ctx: Arc<dyn TableContext>,
-    lock: Arc<dyn Lock>,
+    // not set for a dry run, which is never committed
+    lock: Option<Arc<dyn Lock>>,
     compact_params: CompactOptions,
     data_accessor: Operator,
     location_generator: TableMetaLocationGenerator,
     compaction: SegmentCompactionState,
     default_cluster_key_id: Option<u32>,
+    // the block thresholds of the table, set if the oversized segments are split
+    split_thresholds: Option<BlockThresholds>,
+    commit_strategy: CommitStrategy,
+    // set once the compaction is committed, the new segments are not removed by `abort` then
+    report: OnceLock<CompactionReport>,
 }
 
 impl SegmentCompactMutator {
@@ -66,17 +170,83 @@ impl SegmentCompactMutator {
     ) -> Result<Self> {
         Ok(Self {
             ctx,
-            lock,
+            lock: Some(lock),
             compact_params,
             data_accessor: operator,
             location_generator,
             compaction: Default::default(),
             default_cluster_key_id,
+            split_thresholds: None,
+            commit_strategy: CommitStrategy::default(),
+            report: OnceLock::new(),
         })
     }
 
+    /// Creates a mutator which only plans the compaction, see [`Self::plan`]. The new segments
+    /// are not written, and it can not be committed.
+    pub fn try_create_dry_run(
+        ctx: Arc<dyn TableContext>,
+        compact_params: CompactOptions,
+        location_generator: TableMetaLocationGenerator,
+        operator: Operator,
+        default_cluster_key_id: Option<u32>,
+    ) -> Result<Self> {
+        Ok(Self {
+            ctx,
+            lock: None,
+            compact_params,
+            data_accessor: operator,
+            location_generator,
+            compaction: Default::default(),
+            default_cluster_key_id,
+            split_thresholds: None,
+            commit_strategy: CommitStrategy::default(),
+            report: OnceLock::new(),
+        })
+    }
+
+    /// Splits the oversized segments while compacting, see [`SegmentCompactor::split_oversized`].
+    pub fn split_oversized(mut self, thresholds: BlockThresholds) -> Self {
+        self.split_thresholds = Some(thresholds);
+        self
+    }
+
+    pub fn with_commit_strategy(mut self, commit_strategy: CommitStrategy) -> Self {
+        self.commit_strategy = commit_strategy;
+        self
+    }
+
+    /// The number of segments left out by the limit of the compaction, see
+    /// [`SegmentCompactionState::segments_remaining`].
+    pub fn segments_remaining(&self) -> usize {
+        self.compaction.segments_remaining
+    }
+
+    /// The summary of the compaction selected by `target_select`.
+    pub fn plan(&self) -> SegmentCompactionPlan {
+        SegmentCompactionPlan {
+            segments_before: self.compact_params.base_snapshot.segments.len(),
+            segments_after: self.compaction.segments_locations.len(),
+            segments_merged: self.compaction.num_fragments_compacted,
+            new_segments: self.compaction.num_new_segments,
+            segments_remaining: self.compaction.segments_remaining,
+            blocks_rewritten: self.compaction.referenced_blocks.len(),
+            bytes_rewritten: self.compaction.bytes_rewritten,
+            unreadable_segments: self.compaction.unreadable_segments.clone(),
+        }
+    }
+
+    /// What the committed compaction did, None if nothing is committed.
+    pub fn report(&self) -> Option<CompactionReport> {
+        self.report.get().copied()
+    }
+
+    fn is_dry_run(&self) -> bool {
+        self.lock.is_none()
+    }
+
     fn has_compaction(&self) -> bool {
-        !self.compaction.new_segment_paths.is_empty()
+        self.compaction.num_new_segments > 0
     }
 
     #[async_backtrace::framed]
@@ -84,7 +254,9 @@ impl SegmentCompactMutator {
         let select_begin = Instant::now();
 
         let mut base_segment_locations = self.compact_params.base_snapshot.segments.clone();
-        if base_segment_locations.len() <= 1 {
+        if base_segment_locations.is_empty()
+            || (base_segment_locations.len() == 1 && self.split_thresholds.is_none())
+        {
             // no need to compact
             return Ok(false);
         }
@@ -106,21 +278,23 @@ impl SegmentCompactMutator {
         let schema = Arc::new(self.compact_params.base_snapshot.schema.clone());
         let fuse_segment_io =
             SegmentsIO::create(self.ctx.clone(), self.data_accessor.clone(), schema);
-        let segment_writer = SegmentWriter::new(&self.data_accessor, &self.location_generator);
-        let chunk_size = self.ctx.get_settings().get_max_threads()? as usize * 4;
-        let compactor = SegmentCompactor::new(
-            self.compact_params.block_per_seg as u64,
-            self.default_cluster_key_id,
-            chunk_size,
-            &fuse_segment_io,
-            segment_writer,
-        );
+        let compactor = self.build_compactor(&fuse_segment_io)?;
 
-        self.compaction = compactor
+        let compaction = compactor
             .compact(base_segment_locations, limit, |status| {
                 self.ctx.set_status_info(&status);
             })
             .await?;
+        self.compaction = compaction;
+        info!(
+            "compact segments{}: {} segments merged into {} segments, {} segments passed through, {} bytes of blocks rewritten, {} segments left for the next run",
+            if self.is_dry_run() { " (dry run)" } else { "" },
+            self.compaction.num_fragments_compacted,
+            self.compaction.num_new_segments,
+            self.compaction.segments_passed_through,
+            self.compaction.bytes_rewritten,
+            self.compaction.segments_remaining,
+        );
 
         gauge!(
             "fuse_compact_segments_select_duration_second",
@@ -130,34 +304,412 @@ impl SegmentCompactMutator {
         Ok(self.has_compaction())
     }
 
+    fn build_compactor<'a>(
+        &'a self,
+        fuse_segment_io: &'a SegmentsIO,
+    ) -> Result<SegmentCompactor<'a>> {
+        let segment_writer = SegmentWriter::new(&self.data_accessor, &self.location_generator);
+        let settings = self.ctx.get_settings();
+        let chunk_size = settings.get_max_threads()? as usize * 4;
+        // the segments written by the table are of `block_per_seg`, but they may be merged
+        // into segments of another size.
+        let compact_threshold = match settings.get_compact_segment_threshold()? {
+            0 => self.compact_params.block_per_seg as u64,
+            v => v,
+        };
+        let bytes_per_seg = self.compact_params.bytes_per_seg;
+        let mut compactor = SegmentCompactor::new(
+            bytes_per_seg.unwrap_or(compact_threshold),
+            self.default_cluster_key_id,
+            chunk_size,
+            fuse_segment_io,
+            segment_writer,
+        );
+        if bytes_per_seg.is_some() {
+            compactor = compactor.by_compressed_size();
+        }
+        if let Some(thresholds) = self.split_thresholds {
+            compactor = compactor.split_oversized(thresholds);
+        }
+        if self.is_dry_run() {
+            compactor = compactor.dry_run();
+        }
+        match settings.get_compact_segments_write_concurrency()? {
+            0 => {}
+            v => compactor = compactor.with_write_concurrency(v as usize),
+        }
+        let order = CompactSegmentOrder::from_setting(&settings.get_compact_segment_order()?)?;
+        compactor = compactor.with_order(order);
+        Ok(compactor.with_abort_flag(self.ctx.get_aborting()))
+    }
+
     #[async_backtrace::framed]
     pub async fn try_commit(&self, table: Arc<dyn Table>) -> Result<()> {
+        let Some(lock) = &self.lock else {
+            return Err(ErrorCode::Internal(
+                "a dry run of the segment compaction can not be committed",
+            ));
+        };
         if !self.has_compaction() {
             // defensive checking
             return Ok(());
         }
 
-        let abort_action = AbortOperation {
+        let mut abort_action = AbortOperation {
             segments: self.compaction.new_segment_paths.clone(),
             ..Default::default()
         };
 
-        // summary of snapshot is unchanged for compact segments.
-        let statistics = self.compact_params.base_snapshot.summary.clone();
+        if self
+            .ctx
+            .get_settings()
+            .get_compact_segments_verify_blocks()?
+        {
+            if let Err(e) = self.verify_referenced_blocks().await {
+                abort_action
+                    .abort_best_effort(self.ctx.clone(), self.data_accessor.clone())
+                    .await;
+                return Err(e);
+            }
+        }
+
         let fuse_table = FuseTable::try_from_table(table.as_ref())?;
 
-        let _guard = self.lock.try_lock(self.ctx.clone()).await?;
+        let _guard = match lock.try_lock(self.ctx.clone()).await {
+            Ok(guard) => guard,
+            Err(e) => {
+                abort_action
+                    .abort_best_effort(self.ctx.clone(), self.data_accessor.clone())
+                    .await;
+                return Err(e);
+            }
+        };
+
+        let mut base_snapshot = self.compact_params.base_snapshot.clone();
+        let mut replanned = None;
+        if self.commit_strategy == CommitStrategy::ReplanAppends {
+            let latest_table = table.refresh(self.ctx.as_ref()).await?;
+            let latest_fuse_table = FuseTable::try_from_table(latest_table.as_ref())?;
+            if let Some(latest_snapshot) = latest_fuse_table.read_table_snapshot().await? {
+                match self.replan_appends(&latest_snapshot).await {
+                    Ok(Some(compaction)) => {
+                        abort_action.segments = compaction.new_segment_paths.clone();
+                        base_snapshot = latest_snapshot;
+                        replanned = Some(compaction);
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        abort_action
+                            .abort_best_effort(self.ctx.clone(), self.data_accessor.clone())
+                            .await;
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        let compaction = replanned.as_ref().unwrap_or(&self.compaction);
 
-        fuse_table
+        // summary of snapshot is unchanged for compact segments.
+        let statistics = base_snapshot.summary.clone();
+        let mut report = CompactionReport {
+            segments_before: base_snapshot.segments.len(),
+            segments_after: compaction.segments_locations.len(),
+            blocks_before: statistics.block_count,
+            blocks_after: statistics.block_count,
+            bytes_before: statistics.compressed_byte_size,
+            bytes_after: statistics.compressed_byte_size,
+            rebases: 0,
+        };
+        let max_retries = self
+            .ctx
+            .get_settings()
+            .get_compact_segments_max_commit_retries()?;
+        report.rebases = fuse_table
             .commit_mutation(
                 &self.ctx,
-                self.compact_params.base_snapshot.clone(),
-                &self.compaction.segments_locations,
+                base_snapshot,
+                &compaction.segments_locations,
                 statistics,
                 abort_action,
                 None,
+                Some(max_retries),
             )
-            .await
+            .await?;
+        info!(
+            "compact segments: committed, {} segments compacted into {} segments",
+            report.segments_before, report.segments_after,
+        );
+        let _ = self.report.set(report);
+        Ok(())
+    }
+
+    /// Removes the new segments written by `target_select`, if the compaction is not going
+    /// to be committed. It can be called more than once, and does nothing once the compaction
+    /// is committed.
+    #[async_backtrace::framed]
+    pub async fn abort(&mut self) -> Result<()> {
+        if self.report.get().is_some() {
+            return Ok(());
+        }
+        let segments = std::mem::take(&mut self.compaction.new_segment_paths);
+        self.compaction.num_new_segments = 0;
+        if segments.is_empty() {
+            return Ok(());
+        }
+        AbortOperation {
+            segments,
+            ..Default::default()
+        }
+        .abort(self.ctx.clone(), self.data_accessor.clone())
+        .await
+    }
+
+    // Compacts the segments appended since the base snapshot together with the compacted
+    // segments, returns None if there is nothing more to compact. Segments appended to a
+    // snapshot other than the base are left to the conflict resolution of the commit.
+    #[async_backtrace::framed]
+    async fn replan_appends(
+        &self,
+        latest_snapshot: &TableSnapshot,
+    ) -> Result<Option<SegmentCompactionState>> {
+        let base_snapshot = &self.compact_params.base_snapshot;
+        let appended = match ConflictResolveContext::is_latest_snapshot_append_only(
+            base_snapshot,
+            latest_snapshot,
+        ) {
+            Some(range) if !range.is_empty() => &latest_snapshot.segments[range],
+            _ => return Ok(None),
+        };
+
+        let mut segment_locations = appended.to_vec();
+        segment_locations.extend(self.compaction.segments_locations.iter().cloned());
+        segment_locations.reverse();
+        let limit = std::cmp::max(
+            2,
+            self.compact_params
+                .num_segment_limit
+                .map_or(segment_locations.len(), |limit| limit + appended.len()),
+        );
+
+        let schema = Arc::new(latest_snapshot.schema.clone());
+        let fuse_segment_io =
+            SegmentsIO::create(self.ctx.clone(), self.data_accessor.clone(), schema);
+        let mut compaction = self
+            .build_compactor(&fuse_segment_io)?
+            .compact(segment_locations, limit, |status| {
+                self.ctx.set_status_info(&status);
+            })
+            .await?;
+        if compaction.new_segment_paths.is_empty() {
+            return Ok(None);
+        }
+        info!(
+            "compact segments: {} appended segments replanned, {} segments merged into {} segments",
+            appended.len(),
+            compaction.num_fragments_compacted,
+            compaction.new_segment_paths.len(),
+        );
+
+        // the new segments which are compacted again are no longer referenced
+        let referenced = compaction
+            .segments_locations
+            .iter()
+            .map(|location| location.0.as_str())
+            .collect::<HashSet<_>>();
+        let (kept, unreferenced): (Vec<_>, Vec<_>) = self
+            .compaction
+            .new_segment_paths
+            .iter()
+            .cloned()
+            .partition(|path| referenced.contains(path.as_str()));
+        AbortOperation {
+            segments: unreferenced,
+            ..Default::default()
+        }
+        .abort(self.ctx.clone(), self.data_accessor.clone())
+        .await?;
+
+        compaction.new_segment_paths.splice(0..0, kept);
+        Ok(Some(compaction))
+    }
+
+    // The new segments refer to the blocks of the base snapshot, which may have been purged
+    // by a concurrent vacuum since the snapshot is read.
+    #[async_backtrace::framed]
+    async fn verify_referenced_blocks(&self) -> Result<()> {
+        let blocks = &self.compaction.referenced_blocks;
+        let threads_nums = self.ctx.get_settings().get_max_threads()? as usize;
+        let chunk_size = std::cmp::max(1, blocks.len().div_ceil(threads_nums * 2));
+        let tasks = blocks.chunks(chunk_size).map(|chunk| {
+            let operator = self.data_accessor.clone();
+            let locations = chunk.to_vec();
+            async move {
+                for location in locations {
+                    match operator.stat(&location).await {
+                        Ok(_) => {}
+                        Err(e) if e.kind() == opendal::ErrorKind::NotFound => {
+                            return Err(ErrorCode::StorageNotFound(format!(
+                                "block {} referenced by the compacted segments does not exist, the compaction is not committed",
+                                location
+                            )));
+                        }
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+                Ok(())
+            }
+        });
+
+        execute_futures_in_parallel(
+            tasks,
+            threads_nums,
+            threads_nums * 2,
+            "verify-compact-blocks-worker".to_owned(),
+        )
+        .await?
+        .into_iter()
+        .collect::<Result<Vec<_>>>()?;
+        Ok(())
+    }
+}
+
+// the number of new segments written at the same time by the compactor, if not set.
+const DEFAULT_SEGMENT_WRITE_CONCURRENCY: usize = 4;
+
+// How a segment is grouped with the fragments collected before it.
+enum Grouping {
+    // the segment has no blocks, it is dropped
+    Empty,
+    // the segment is of another partition than the fragments so far, which are merged
+    // without it. the segment is then grouped as if there were no fragments.
+    NewPartition,
+    // the segment is a fragment, not enough to make a segment with the fragments so far
+    Fragment,
+    // the segment and the fragments so far make a segment of [threshold, 2 * threshold)
+    Complete,
+    // the segment is too large to be merged with the fragments so far, which are merged
+    // without it. it is split into pieces if `split` is set, or kept as it is.
+    Oversized { split: bool },
+}
+
+/// Decides how the segments are grouped by [`SegmentCompactor`], without reading or writing
+/// anything, see [`SegmentCompactor::plan`].
+#[derive(Clone, Copy, Debug)]
+pub struct SegmentGrouper {
+    // Size of compacted segment should be in range R == [threshold, 2 * threshold)
+    // within R, smaller one is preferred
+    threshold: u64,
+    // the size of a segment is measured in compressed bytes rather than in blocks
+    by_size: bool,
+    // the block thresholds of the table, to rebuild the statistics of the split segments.
+    // the oversized segments are kept as they are if not set.
+    split_thresholds: Option<BlockThresholds>,
+    default_cluster_key_id: Option<u32>,
+}
+
+impl SegmentGrouper {
+    pub fn new(threshold: u64, default_cluster_key_id: Option<u32>) -> Self {
+        Self {
+            threshold,
+            by_size: false,
+            split_thresholds: None,
+            default_cluster_key_id,
+        }
+    }
+
+    /// Measures the segments in compressed bytes, `threshold` is then the bytes per segment.
+    pub fn by_compressed_size(mut self) -> Self {
+        self.by_size = true;
+        self
+    }
+
+    /// Splits the segments of 2 * `threshold` or larger, in their block order.
+    pub fn split_oversized(mut self, thresholds: BlockThresholds) -> Self {
+        self.split_thresholds = Some(thresholds);
+        self
+    }
+
+    // how `segment` is grouped after the fragments collected so far, of which `first_fragment`
+    // is the first one and `accumulated_size` is the size.
+    fn grouping(
+        &self,
+        first_fragment: Option<&SegmentInfo>,
+        accumulated_size: u64,
+        segment: &SegmentInfo,
+    ) -> Grouping {
+        if segment.blocks.is_empty() {
+            return Grouping::Empty;
+        }
+        if let Some(fragment) = first_fragment {
+            if self.crosses_partition(fragment, segment) {
+                return Grouping::NewPartition;
+            }
+        }
+
+        let size = self.segment_size(segment);
+        let s = accumulated_size + size;
+        if s < self.threshold {
+            Grouping::Fragment
+        } else if s < 2 * self.threshold {
+            Grouping::Complete
+        } else {
+            Grouping::Oversized {
+                split: self.split_thresholds.is_some() && size >= 2 * self.threshold,
+            }
+        }
+    }
+
+    fn segment_size(&self, segment: &SegmentInfo) -> u64 {
+        match self.by_size {
+            true => segment.summary.compressed_byte_size,
+            false => segment.blocks.len() as u64,
+        }
+    }
+
+    // the segments of different partitions are not merged, see `segment_partition`. the
+    // segments of no partition are merged together, but never with the ones of a partition.
+    fn crosses_partition(&self, fragment: &SegmentInfo, segment: &SegmentInfo) -> bool {
+        let Some(cluster_key_id) = self.default_cluster_key_id else {
+            return false;
+        };
+        match (
+            segment_partition(fragment, cluster_key_id),
+            segment_partition(segment, cluster_key_id),
+        ) {
+            (Some(left), Some(right)) => left != right,
+            (None, None) => false,
+            _ => true,
+        }
+    }
+
+    fn block_size(&self, block: &BlockMeta) -> u64 {
+        match self.by_size {
+            true => block.file_size,
+            false => 1,
+        }
+    }
+
+    // the blocks of an oversized segment in pieces of `threshold`, the last one also takes the
+    // remaining blocks which are not enough for a piece of their own.
+    fn split_pieces(&self, segment_info: &SegmentInfo) -> Vec<Vec<Arc<BlockMeta>>> {
+        let mut pieces: Vec<Vec<Arc<BlockMeta>>> = vec![];
+        let mut piece = vec![];
+        let mut piece_size = 0;
+        for block in &segment_info.blocks {
+            piece_size += self.block_size(block);
+            piece.push(block.clone());
+            if piece_size >= self.threshold {
+                pieces.push(std::mem::take(&mut piece));
+                piece_size = 0;
+            }
+        }
+        if !piece.is_empty() {
+            match pieces.last_mut() {
+                Some(last) => last.append(&mut piece),
+                None => pieces.push(piece),
+            }
+        }
+        pieces
     }
 }
 
@@ -170,22 +722,39 @@ impl SegmentCompactMutator {
 //
 // To avoid this "ripple effects", consecutive segments are allowed to be compacted into
 // a new segment, if the size of compacted segment is lesser than 2 * threshold (exclusive).
+//
+// For tables with cluster key, segments are also grouped by partition (see `segment_partition`),
+// fragments of different partitions are never compacted into the same segment.
+//
+// The size of a segment is its number of blocks, or its compressed bytes if compacted by size
+// (see `by_compressed_size`), which gives evenly sized segments if the blocks vary in size.
+//
+// A segment already of 2 * threshold or larger, e.g. written by a bulk load, is kept as it is,
+// unless it is split into segments of [threshold, 2 * threshold) (see `split_oversized`), so
+// that the scans of it are spread over more segments.
 
 pub struct SegmentCompactor<'a> {
-    // Size of compacted segment should be in range R == [threshold, 2 * threshold)
-    // within R, smaller one is preferred
-    threshold: u64,
-    default_cluster_key_id: Option<u32>,
+    grouper: SegmentGrouper,
     // fragmented segment collected so far, it will be reset to empty if compaction occurs
     fragmented_segments: Vec<(SegmentInfo, Location)>,
-    // state which keep the number of blocks of all the fragmented segment collected so far,
+    // state which keep the size of all the fragmented segment collected so far,
     // it will be reset to 0 if compaction occurs
-    accumulated_num_blocks: u64,
+    accumulated_size: u64,
     chunk_size: usize,
     segment_reader: &'a SegmentsIO,
     segment_writer: SegmentWriter<'a>,
     // accumulated compaction state
     compacted_state: SegmentCompactionState,
+    // new segments to be written, along with their index in `segments_locations`
+    pending_segments: Vec<(usize, SegmentInfo)>,
+    // max number of new segments written at the same time
+    write_concurrency: usize,
+    // the new segments are planned but not written
+    dry_run: bool,
+    // set if the query is killed, the new segments written so far are removed then
+    aborting: Option<Arc<AtomicBool>>,
+    // the order in which the segments are fed, see `with_order`
+    order: CompactSegmentOrder,
 }
 
 impl<'a> SegmentCompactor<'a> {
@@ -197,17 +766,63 @@ impl<'a> SegmentCompactor<'a> {
         segment_writer: SegmentWriter<'a>,
     ) -> Self {
         Self {
-            threshold,
-            default_cluster_key_id,
-            accumulated_num_blocks: 0,
+            grouper: SegmentGrouper::new(threshold, default_cluster_key_id),
+            accumulated_size: 0,
             fragmented_segments: vec![],
             chunk_size,
             segment_reader,
             segment_writer,
             compacted_state: Default::default(),
+            pending_segments: vec![],
+            write_concurrency: DEFAULT_SEGMENT_WRITE_CONCURRENCY,
+            dry_run: false,
+            aborting: None,
+            order: CompactSegmentOrder::default(),
         }
     }
 
+    /// Measures the segments in compressed bytes, `threshold` is then the bytes per segment.
+    pub fn by_compressed_size(mut self) -> Self {
+        self.grouper = self.grouper.by_compressed_size();
+        self
+    }
+
+    /// Writes up to `write_concurrency` new segments at the same time, 4 by default.
+    pub fn with_write_concurrency(mut self, write_concurrency: usize) -> Self {
+        self.write_concurrency = std::cmp::max(1, write_concurrency);
+        self
+    }
+
+    /// Splits the segments of 2 * `threshold` or larger, in their block order.
+    pub fn split_oversized(mut self, thresholds: BlockThresholds) -> Self {
+        self.grouper = self.grouper.split_oversized(thresholds);
+        self
+    }
+
+    /// Compacts the segments without writing the new ones, which are left with a default
+    /// location in `segments_locations` and no path in `new_segment_paths`. The segments that
+    /// can not be read are kept as they are and listed in `unreadable_segments`, instead of
+    /// failing the compaction.
+    pub fn dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+
+    /// Stops the compaction once `aborting` is set, which is checked before each segment is
+    /// accumulated and each batch of new segments is written. The new segments written so far
+    /// are removed, and `ErrorCode::AbortedQuery` is returned.
+    pub fn with_abort_flag(mut self, aborting: Arc<AtomicBool>) -> Self {
+        self.aborting = Some(aborting);
+        self
+    }
+
+    /// Feeds the segments in `order`, which decides the segments compacted if the number of
+    /// segments is limited. The segments are fed from the oldest by default.
+    pub fn with_order(mut self, order: CompactSegmentOrder) -> Self {
+        self.order = order;
+        self
+    }
+
     #[async_backtrace::framed]
     pub async fn compact<T>(
         mut self,
@@ -220,78 +835,77 @@ impl<'a> SegmentCompactor<'a> {
     {
         let start = Instant::now();
         let number_segments = reverse_locations.len();
+        let report_status = |checked_end_at: usize| {
+            let status = format!(
+                "compact segment: read segment files:{}/{}, cost:{} sec",
+                checked_end_at,
+                number_segments,
+                start.elapsed().as_secs()
+            );
+            info!("{}", &status);
+            (status_callback)(status);
+        };
+
         // 1. feed segments into accumulator, taking limit into account
-        let segments_io = self.segment_reader;
         let chunk_size = self.chunk_size;
         let mut checked_end_at = 0;
-        let mut is_end = false;
-        for chunk in reverse_locations.chunks(chunk_size) {
-            let mut segment_infos = segments_io
-                .read_segments::<SegmentInfo>(chunk, false)
-                .await?
-                .into_iter()
-                .zip(chunk.iter())
-                .map(|(sg, chunk)| sg.map(|v| (v, chunk)))
-                .collect::<Result<Vec<_>>>()?;
-
-            if let Some(default_cluster_key) = self.default_cluster_key_id {
-                // sort ascending.
-                segment_infos.sort_by(|a, b| {
-                    sort_by_cluster_stats(
-                        &a.0.summary.cluster_stats,
-                        &b.0.summary.cluster_stats,
-                        default_cluster_key,
-                    )
-                });
-            }
-
-            for (segment, location) in segment_infos.into_iter() {
-                if is_end {
-                    self.compacted_state
-                        .segments_locations
-                        .push(location.clone());
-                    continue;
-                }
+        match self.order {
+            CompactSegmentOrder::Oldest => {
+                for chunk in reverse_locations.chunks(chunk_size) {
+                    let mut segment_infos = self.read_segments(chunk).await?;
+                    if let Some(default_cluster_key) = self.grouper.default_cluster_key_id {
+                        // sort ascending, the unreadable segments first.
+                        segment_infos.sort_by(|a, b| match (&a.0, &b.0) {
+                            (Some(a), Some(b)) => sort_by_cluster_stats(
+                                &a.summary.cluster_stats,
+                                &b.summary.cluster_stats,
+                                default_cluster_key,
+                            ),
+                            (a, b) => a.is_some().cmp(&b.is_some()),
+                        });
+                    }
 
-                self.add(segment, location.clone()).await?;
-                let compacted = self.num_fragments_compacted();
-                if compacted >= limit {
-                    if !self.fragmented_segments.is_empty() {
-                        // some fragments left, compact them
-                        self.compact_fragments().await?;
+                    let is_end = self.feed(segment_infos, limit).await?;
+                    checked_end_at += chunk.len();
+                    report_status(checked_end_at);
+                    if is_end {
+                        break;
                     }
-                    is_end = true;
                 }
             }
+            CompactSegmentOrder::Smallest => {
+                // the size of a segment is only known once it is read, so all the segments
+                // are read before the smallest ones are fed.
+                let mut segment_infos = Vec::with_capacity(number_segments);
+                for chunk in reverse_locations.chunks(chunk_size) {
+                    segment_infos.extend(self.read_segments(chunk).await?);
+                    checked_end_at += chunk.len();
+                    report_status(checked_end_at);
+                }
 
-            checked_end_at += chunk.len();
-
-            // Status.
-            {
-                let status = format!(
-                    "compact segment: read segment files:{}/{}, cost:{} sec",
-                    checked_end_at,
-                    number_segments,
-                    start.elapsed().as_secs()
-                );
-                info!("{}", &status);
-                (status_callback)(status);
-            }
-
-            if is_end {
-                break;
+                // stable, the segments of the same size are kept from the oldest, the
+                // unreadable segments first.
+                segment_infos.sort_by_key(|(segment, _)| {
+                    segment
+                        .as_ref()
+                        .map(|segment| self.grouper.segment_size(segment))
+                });
+                self.feed(segment_infos, limit).await?;
             }
         }
         let mut compaction = self.finalize().await?;
 
         // 2. combine with the unprocessed segments (which are outside of the limit)
-        let fragments_compacted = !compaction.new_segment_paths.is_empty();
+        compaction.segments_remaining += number_segments - checked_end_at;
+        let fragments_compacted = compaction.num_new_segments > 0;
         if fragments_compacted {
             // if some compaction occurred, the reminders
             // which are outside of the limit should also be collected
+            let remainders = &reverse_locations[checked_end_at..];
             compaction
                 .segments_locations
-                .extend(reverse_locations[checked_end_at..].iter().cloned());
+                .extend(remainders.iter().cloned());
+            compaction.segments_passed_through += remainders.len();
         }
         // reverse the segments back
         compaction.segments_locations.reverse();
@@ -299,35 +913,282 @@ impl<'a> SegmentCompactor<'a> {
         Ok(compaction)
     }
 
+    // reads the segments of a chunk, a segment that can not be read by a dry run is None.
+    #[async_backtrace::framed]
+    async fn read_segments<'l>(
+        &self,
+        chunk: &'l [Location],
+    ) -> Result<Vec<(Option<SegmentInfo>, &'l Location)>> {
+        let segments = self
+            .segment_reader
+            .read_segments::<SegmentInfo>(chunk, false)
+            .await?;
+        let mut segment_infos = Vec::with_capacity(chunk.len());
+        for (segment, location) in segments.into_iter().zip(chunk.iter()) {
+            match segment {
+                Ok(segment) => segment_infos.push((Some(segment), location)),
+                Err(e) if self.dry_run => {
+                    warn!(
+                        "compact segments (dry run): segment {} can not be read: {}",
+                        location.0, e
+                    );
+                    segment_infos.push((None, location));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(segment_infos)
+    }
+
+    // accumulates the segments in the given order until `limit` fragments are compacted, the
+    // segments after that are kept as they are. Returns true once the limit is reached.
+    #[async_backtrace::framed]
+    async fn feed(
+        &mut self,
+        segment_infos: Vec<(Option<SegmentInfo>, &Location)>,
+        limit: usize,
+    ) -> Result<bool> {
+        let mut is_end = false;
+        for (segment, location) in segment_infos.into_iter() {
+            if is_end {
+                self.compacted_state
+                    .segments_locations
+                    .push(location.clone());
+                self.compacted_state.segments_passed_through += 1;
+                self.compacted_state.segments_remaining += 1;
+                continue;
+            }
+
+            match segment {
+                Some(segment) => self.add(segment, location.clone()).await?,
+                None => self.keep_unreadable(location.clone()).await?,
+            }
+            let compacted = self.num_fragments_compacted();
+            if compacted >= limit {
+                if !self.fragmented_segments.is_empty() {
+                    // some fragments left, compact them
+                    self.compact_fragments().await?;
+                }
+                is_end = true;
+            }
+        }
+        Ok(is_end)
+    }
+
     // accumulate one segment
     #[async_backtrace::framed]
     pub async fn add(&mut self, segment_info: SegmentInfo, location: Location) -> Result<()> {
-        let num_blocks_current_segment = segment_info.blocks.len() as u64;
+        self.check_aborting().await?;
 
-        if num_blocks_current_segment == 0 {
-            return Ok(());
+        let first_fragment = self
+            .fragmented_segments
+            .first()
+            .map(|(fragment, _)| fragment);
+        let grouping = self
+            .grouper
+            .grouping(first_fragment, self.accumulated_size, &segment_info);
+        let grouping = match grouping {
+            Grouping::NewPartition => {
+                // partition changes, compact the fragments of the previous partition
+                self.compact_fragments().await?;
+                self.grouper.grouping(None, 0, &segment_info)
+            }
+            grouping => grouping,
+        };
+
+        match grouping {
+            Grouping::Empty | Grouping::NewPartition => {}
+            Grouping::Fragment => {
+                // not enough blocks yet, just keep this segment for later compaction
+                self.accumulated_size += self.grouper.segment_size(&segment_info);
+                self.fragmented_segments.push((segment_info, location));
+            }
+            Grouping::Complete => {
+                // compact the fragmented segments
+                self.fragmented_segments.push((segment_info, location));
+                self.compact_fragments().await?;
+            }
+            Grouping::Oversized { split } => {
+                // JackTan25: I think this won't happen, right? so need to remove this branch??
+                // no choice but to compact the fragmented segments collected so far.
+                // in this situation, after compaction, the size of compacted segments may be
+                // lesser than threshold. this happens if the size of segment BEFORE compaction
+                // is already larger than threshold.
+                self.compact_fragments().await?;
+                match self.grouper.split_thresholds {
+                    Some(thresholds) if split => {
+                        self.split_segment(segment_info, thresholds).await?;
+                    }
+                    _ => {
+                        self.compacted_state.segments_locations.push(location);
+                        self.compacted_state.segments_passed_through += 1;
+                    }
+                }
+            }
         }
 
-        let s = self.accumulated_num_blocks + num_blocks_current_segment;
+        Ok(())
+    }
 
-        if s < self.threshold {
-            // not enough blocks yet, just keep this segment for later compaction
-            self.accumulated_num_blocks = s;
-            self.fragmented_segments.push((segment_info, location));
-        } else if s >= self.threshold && s < 2 * self.threshold {
-            // compact the fragmented segments
-            self.fragmented_segments.push((segment_info, location));
-            self.compact_fragments().await?;
-        } else {
-            // JackTan25: I think this won't happen, right? so need to remove this branch??
-            // no choice but to compact the fragmented segments collected so far.
-            // in this situation, after compaction, the size of compacted segments may be
-            // lesser than threshold. this happens if the size of segment BEFORE compaction
-            // is already larger than threshold.
-            self.compact_fragments().await?;
-            self.compacted_state.segments_locations.push(location);
+    // the segment can not be read by a dry run, it is kept as it is, which is a barrier for
+    // the fragments collected before it.
+    #[async_backtrace::framed]
+    async fn keep_unreadable(&mut self, location: Location) -> Result<()> {
+        self.compact_fragments().await?;
+        self.compacted_state
+            .unreadable_segments
+            .push(location.0.clone());
+        self.compacted_state.segments_locations.push(location);
+        self.compacted_state.segments_passed_through += 1;
+        Ok(())
+    }
+
+    /// Groups the segments into the segments of the compaction, in the order they are given,
+    /// by the same decisions as [`Self::add`], without reading or writing anything. Each group
+    /// is the indices of the segments merged into one output segment, a group of one segment
+    /// is kept as it is, unless the segment is split, which gives a group of it for each of its
+    /// pieces. Empty segments are dropped.
+    ///
+    /// The limit of the compaction is not taken into account.
+    pub fn plan(grouper: &SegmentGrouper, segments: &[SegmentInfo]) -> Vec<Vec<usize>> {
+        let mut groups = vec![];
+        let mut fragments: Vec<usize> = vec![];
+        let mut accumulated_size = 0;
+        for (index, segment) in segments.iter().enumerate() {
+            let first_fragment = fragments.first().map(|first| &segments[*first]);
+            let grouping = match grouper.grouping(first_fragment, accumulated_size, segment) {
+                Grouping::NewPartition => {
+                    groups.push(std::mem::take(&mut fragments));
+                    accumulated_size = 0;
+                    grouper.grouping(None, 0, segment)
+                }
+                grouping => grouping,
+            };
+
+            match grouping {
+                Grouping::Empty | Grouping::NewPartition => {}
+                Grouping::Fragment => {
+                    accumulated_size += grouper.segment_size(segment);
+                    fragments.push(index);
+                }
+                Grouping::Complete => {
+                    fragments.push(index);
+                    groups.push(std::mem::take(&mut fragments));
+                    accumulated_size = 0;
+                }
+                Grouping::Oversized { split } => {
+                    if !fragments.is_empty() {
+                        groups.push(std::mem::take(&mut fragments));
+                    }
+                    accumulated_size = 0;
+                    let num_pieces = match split {
+                        true => grouper.split_pieces(segment).len(),
+                        false => 1,
+                    };
+                    groups.extend(std::iter::repeat(vec![index]).take(num_pieces));
+                }
+            }
+        }
+        if !fragments.is_empty() {
+            groups.push(fragments);
         }
+        groups
+    }
+
+    // split an oversized segment into segments of `threshold`.
+    #[async_backtrace::framed]
+    async fn split_segment(
+        &mut self,
+        segment_info: SegmentInfo,
+        thresholds: BlockThresholds,
+    ) -> Result<()> {
+        let pieces = self.grouper.split_pieces(&segment_info);
 
+        self.compacted_state.num_fragments_compacted += 1;
+        self.compacted_state.bytes_rewritten += segment_info.summary.compressed_byte_size;
+        self.compacted_state.referenced_blocks.extend(
+            segment_info
+                .blocks
+                .iter()
+                .map(|block| block.location.0.clone()),
+        );
+        // the locations are reversed back at the end of the compaction, the pieces are pushed
+        // backwards to keep the blocks in order.
+        for blocks in pieces.into_iter().rev() {
+            let mut statistics =
+                reduce_block_metas(&blocks, thresholds, self.grouper.default_cluster_key_id);
+            statistics.create_on = Some(Utc::now());
+            self.write_segment(SegmentInfo::new(blocks, statistics))
+                .await?;
+        }
+        Ok(())
+    }
+
+    // the new segments are written in batches of `write_concurrency`, the location of a new
+    // segment is filled in once it is written.
+    #[async_backtrace::framed]
+    async fn write_segment(&mut self, segment: SegmentInfo) -> Result<()> {
+        let index = self.compacted_state.segments_locations.len();
+        self.compacted_state
+            .segments_locations
+            .push(Location::default());
+        self.compacted_state.num_new_segments += 1;
+        if self.dry_run {
+            return Ok(());
+        }
+        self.pending_segments.push((index, segment));
+        if self.pending_segments.len() >= self.write_concurrency {
+            self.flush_segments().await?;
+        }
+        Ok(())
+    }
+
+    // write the pending segments concurrently, the first failure is returned and the writes
+    // in flight are dropped. each new segment is recorded as soon as it is written, so that
+    // the ones written before the failure are removed with the others.
+    #[async_backtrace::framed]
+    async fn flush_segments(&mut self) -> Result<()> {
+        self.check_aborting().await?;
+        let pending = std::mem::take(&mut self.pending_segments);
+        let segment_writer = &self.segment_writer;
+        let mut writes = stream::iter(pending)
+            .map(|(index, segment)| async move {
+                let location = segment_writer.write_segment(segment).await?;
+                Ok::<_, ErrorCode>((index, location))
+            })
+            .buffer_unordered(self.write_concurrency);
+        let num_written = self.compacted_state.new_segment_paths.len();
+        let mut written = vec![];
+        while let Some(result) = writes.next().await {
+            match result {
+                Ok((index, location)) => {
+                    self.compacted_state
+                        .new_segment_paths
+                        .push(location.0.clone());
+                    self.compacted_state.segments_locations[index] = location;
+                    written.push(index);
+                }
+                Err(e) => {
+                    drop(writes);
+                    let segments = std::mem::take(&mut self.compacted_state.new_segment_paths);
+                    AbortOperation {
+                        segments,
+                        ..Default::default()
+                    }
+                    .abort_best_effort(self.segment_reader.ctx(), self.segment_reader.operator())
+                    .await;
+                    return Err(e);
+                }
+            }
+        }
+
+        // the writes complete in any order, the paths are listed in the order of the segments.
+        written.sort_unstable();
+        self.compacted_state.new_segment_paths.truncate(num_written);
+        for index in written {
+            let path = self.compacted_state.segments_locations[index].0.clone();
+            self.compacted_state.new_segment_paths.push(path);
+        }
         Ok(())
     }
 
@@ -339,7 +1200,7 @@ impl<'a> SegmentCompactor<'a> {
 
         // 1. take the fragments and reset
         let fragments = std::mem::take(&mut self.fragmented_segments);
-        self.accumulated_num_blocks = 0;
+        self.accumulated_size = 0;
 
         // check if only one fragment left
         if fragments.len() == 1 {
@@ -347,12 +1208,17 @@ impl<'a> SegmentCompactor<'a> {
             self.compacted_state
                 .segments_locations
                 .push(fragments[0].1.clone());
+            self.compacted_state.segments_passed_through += 1;
             return Ok(());
         }
 
         // 2. build (and write down the compacted segment
         // 2.1 merge fragmented segments into new segment, and update the statistics
-        let mut blocks = Vec::with_capacity(self.threshold as usize);
+        let num_blocks = fragments
+            .iter()
+            .map(|(segment, _)| segment.blocks.len())
+            .sum();
+        let mut blocks = Vec::with_capacity(num_blocks);
         let mut new_statistics = Statistics::default();
 
         self.compacted_state.num_fragments_compacted += fragments.len();
@@ -360,19 +1226,47 @@ impl<'a> SegmentCompactor<'a> {
             merge_statistics_mut(
                 &mut new_statistics,
                 &segment.summary,
-                self.default_cluster_key_id,
+                self.grouper.default_cluster_key_id,
             );
             blocks.append(&mut segment.blocks.clone());
         }
-
-        // 2.2 write down new segment
-        let new_segment = SegmentInfo::new(blocks, new_statistics);
-        let location = self.segment_writer.write_segment(new_segment).await?;
         self.compacted_state
-            .new_segment_paths
-            .push(location.0.clone());
-        self.compacted_state.segments_locations.push(location);
-        Ok(())
+            .referenced_blocks
+            .extend(blocks.iter().map(|block| block.location.0.clone()));
+        self.compacted_state.bytes_rewritten += new_statistics.compressed_byte_size;
+
+        // 2.2 write down new segment, stamped with its own creation time since the
+        // fragments it is merged from are removed.
+        new_statistics.create_on = Some(Utc::now());
+        self.write_segment(SegmentInfo::new(blocks, new_statistics))
+            .await
+    }
+
+    // the new segments are not referenced by any snapshot yet, they are removed if the
+    // compaction is aborted, rather than left to the purge.
+    #[async_backtrace::framed]
+    async fn check_aborting(&mut self) -> Result<()> {
+        match &self.aborting {
+            Some(aborting) if aborting.load(Ordering::Acquire) => {}
+            _ => return Ok(()),
+        }
+        self.pending_segments.clear();
+        let segments = std::mem::take(&mut self.compacted_state.new_segment_paths);
+        info!(
+            "compact segments aborted, removing {} new segments",
+            segments.len()
+        );
+        if !segments.is_empty() {
+            AbortOperation {
+                segments,
+                ..Default::default()
+            }
+            .abort(self.segment_reader.ctx(), self.segment_reader.operator())
+            .await?;
+        }
+        Err(ErrorCode::AbortedQuery(
+            "Aborted query, because the server is shutting down or the query was killed.",
+        ))
     }
 
     // return the number of compacted segments so far
@@ -383,10 +1277,26 @@ impl<'a> SegmentCompactor<'a> {
     // finalize the compaction, compacts left fragments (if any)
     #[async_backtrace::framed]
     pub async fn finalize(mut self) -> Result<SegmentCompactionState> {
+        self.check_aborting().await?;
         if !self.fragmented_segments.is_empty() {
             // some fragments left, compact them
             self.compact_fragments().await?;
         }
+        self.flush_segments().await?;
         Ok(self.compacted_state)
     }
 }
+
+// The partition that a segment belongs to, which is the value of the leading cluster key if all
+// the rows of the segment share the same one, or `None` otherwise.
+//
+// Merging segments of different partitions widens the cluster key range of the merged segment
+// to cover all of them, which defeats the pruning by cluster key.
+pub fn segment_partition(segment: &SegmentInfo, cluster_key_id: u32) -> Option<&Scalar> {
+    let stats = segment.summary.cluster_stats.as_ref()?;
+    if stats.cluster_key_id != cluster_key_id {
+        return None;
+    }
+    let min = stats.min().first()?;
+    (stats.max().first() == Some(min)).then_some(min)
+}
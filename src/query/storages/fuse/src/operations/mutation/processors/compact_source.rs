@@ -13,10 +13,14 @@
 // limitations under the License.
 
 use std::any::Any;
+use std::collections::VecDeque;
 use std::sync::Arc;
 use std::time::Instant;
 
+use databend_common_base::base::tokio::task::JoinHandle;
 use databend_common_base::base::ProgressValues;
+use databend_common_base::runtime::GlobalIORuntime;
+use databend_common_base::runtime::TrySpawn;
 use databend_common_catalog::plan::gen_mutation_stream_meta;
 use databend_common_catalog::plan::PartInfoPtr;
 use databend_common_catalog::plan::StreamColumn;
@@ -54,6 +58,8 @@ enum State {
     Finish,
 }
 
+type ReadResult = Result<Vec<MergeIOReadResult>>;
+
 pub struct CompactSource {
     state: State,
     ctx: Arc<dyn TableContext>,
@@ -63,6 +69,13 @@ pub struct CompactSource {
     output: Arc<OutputPort>,
     stream_columns: Vec<StreamColumn>,
     stream_operators: Vec<BlockOperator>,
+
+    // number of parts whose blocks are read ahead, in the background.
+    prefetch_depth: usize,
+    // parts taken from the context in advance, along with the reading of their blocks.
+    prefetched: VecDeque<(PartInfoPtr, Option<JoinHandle<ReadResult>>)>,
+    // reading of the blocks of the next part, if it has been prefetched.
+    next_read: Option<JoinHandle<ReadResult>>,
 }
 
 impl CompactSource {
@@ -75,6 +88,7 @@ impl CompactSource {
         output: Arc<OutputPort>,
     ) -> Result<ProcessorPtr> {
         let func_ctx = ctx.get_function_context()?;
+        let prefetch_depth = ctx.get_settings().get_compact_read_prefetch_depth()? as usize;
         Ok(ProcessorPtr::create(Box::new(CompactSource {
             state: State::ReadData(None),
             ctx,
@@ -84,8 +98,87 @@ impl CompactSource {
             output,
             stream_columns,
             stream_operators,
+            prefetch_depth,
+            prefetched: VecDeque::new(),
+            next_read: None,
         })))
     }
+
+    fn next_part(&mut self) -> Option<PartInfoPtr> {
+        match self.prefetched.pop_front() {
+            Some((part, read)) => {
+                self.next_read = read;
+                Some(part)
+            }
+            None => self.ctx.get_partition(),
+        }
+    }
+
+    // starts reading the blocks of the following parts, until `prefetch_depth` parts are read ahead.
+    fn prefetch(&mut self) -> Result<()> {
+        while self.prefetched.len() < self.prefetch_depth {
+            let Some(part) = self.ctx.get_partition() else {
+                break;
+            };
+            let read = match CompactPartInfo::from_part(&part)? {
+                CompactPartInfo::CompactExtraInfo(_) => None,
+                CompactPartInfo::CompactTaskInfo(task) => {
+                    let block_reader = self.block_reader.clone();
+                    let settings = ReadSettings::from_ctx(&self.ctx)?;
+                    let blocks = task.blocks.clone();
+                    Some(
+                        GlobalIORuntime::instance().try_spawn(self.ctx.get_id(), async move {
+                            read_blocks(&block_reader, &settings, &blocks).await
+                        })?,
+                    )
+                }
+            };
+            self.prefetched.push_back((part, read));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for CompactSource {
+    fn drop(&mut self) {
+        // the prefetched blocks are useless if the compaction is aborted.
+        let reads = self.prefetched.iter().filter_map(|(_, read)| read.as_ref());
+        for read in reads.chain(self.next_read.as_ref()) {
+            read.abort();
+        }
+    }
+}
+
+async fn read_blocks(
+    block_reader: &BlockReader,
+    settings: &ReadSettings,
+    blocks: &[Arc<BlockMeta>],
+) -> ReadResult {
+    // block read tasks.
+    let mut task_futures = Vec::new();
+    for block in blocks {
+        // read block in parallel.
+        task_futures.push(async move {
+            // Perf
+            {
+                metrics_inc_compact_block_read_nums(1);
+                metrics_inc_compact_block_read_bytes(block.block_size);
+            }
+
+            block_reader
+                .read_columns_data_by_merge_io(settings, &block.location.0, &block.col_metas, &None)
+                .await
+        });
+    }
+
+    let start = Instant::now();
+
+    let read_res = futures::future::try_join_all(task_futures).await?;
+    // Perf.
+    {
+        metrics_inc_compact_block_read_milliseconds(start.elapsed().as_millis() as u64);
+    }
+    Ok(read_res)
 }
 
 #[async_trait::async_trait]
@@ -101,8 +194,7 @@ impl Processor for CompactSource {
     fn event(&mut self) -> Result<Event> {
         if matches!(self.state, State::ReadData(None)) {
             self.state = self
-                .ctx
-                .get_partition()
+                .next_part()
                 .map_or(State::Finish, |part| State::ReadData(Some(part)));
         }
 
@@ -189,7 +281,7 @@ impl Processor for CompactSource {
                 };
                 self.ctx.get_write_progress().incr(&progress_values);
 
-                self.state = State::Output(self.ctx.get_partition(), new_block);
+                self.state = State::Output(self.next_part(), new_block);
             }
             _ => return Err(ErrorCode::Internal("It's a bug.")),
         }
@@ -200,48 +292,29 @@ impl Processor for CompactSource {
     async fn async_process(&mut self) -> Result<()> {
         match std::mem::replace(&mut self.state, State::Finish) {
             State::ReadData(Some(part)) => {
-                let block_reader = self.block_reader.as_ref();
-
-                // block read tasks.
-                let mut task_futures = Vec::new();
-                let part = CompactPartInfo::from_part(&part)?;
-                match part {
+                let next_read = self.next_read.take();
+                match CompactPartInfo::from_part(&part)? {
                     CompactPartInfo::CompactExtraInfo(extra) => {
                         let meta = Box::new(SerializeDataMeta::CompactExtras(extra.clone()));
                         let block = DataBlock::empty_with_meta(meta);
-                        self.state = State::Output(self.ctx.get_partition(), block);
+                        self.state = State::Output(self.next_part(), block);
                     }
                     CompactPartInfo::CompactTaskInfo(task) => {
-                        for block in &task.blocks {
-                            let settings = ReadSettings::from_ctx(&self.ctx)?;
-                            // read block in parallel.
-                            task_futures.push(async move {
-                                // Perf
-                                {
-                                    metrics_inc_compact_block_read_nums(1);
-                                    metrics_inc_compact_block_read_bytes(block.block_size);
-                                }
-
-                                block_reader
-                                    .read_columns_data_by_merge_io(
-                                        &settings,
-                                        &block.location.0,
-                                        &block.col_metas,
-                                        &None,
-                                    )
-                                    .await
-                            });
-                        }
-
-                        let start = Instant::now();
+                        // read the blocks of the following parts while this one is being rewritten.
+                        self.prefetch()?;
 
-                        let read_res = futures::future::try_join_all(task_futures).await?;
-                        // Perf.
-                        {
-                            metrics_inc_compact_block_read_milliseconds(
-                                start.elapsed().as_millis() as u64,
-                            );
-                        }
+                        let read_res = match next_read {
+                            Some(handle) => handle.await.map_err(|e| {
+                                ErrorCode::Internal(format!(
+                                    "failed to join the prefetching of compact blocks: {}",
+                                    e
+                                ))
+                            })??,
+                            None => {
+                                let settings = ReadSettings::from_ctx(&self.ctx)?;
+                                read_blocks(&self.block_reader, &settings, &task.blocks).await?
+                            }
+                        };
                         self.state = State::Concat {
                             read_res,
                             metas: task.blocks.clone(),
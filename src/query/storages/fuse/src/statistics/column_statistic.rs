@@ -52,7 +52,8 @@ pub fn gen_columns_statistics(
     let leaves = get_traverse_columns_dfs(&data_block)?;
     let leaf_column_ids = schema.to_leaf_column_ids();
     for ((col_idx, col, data_type), column_id) in leaves.iter().zip(leaf_column_ids) {
-        // Ignore the range index does not supported type.
+        // Ignore the range index does not supported type, e.g. variant has no min/max,
+        // so there is nothing of it to merge when the blocks are compacted.
         if !RangeIndex::supported_type(data_type) {
             continue;
         }
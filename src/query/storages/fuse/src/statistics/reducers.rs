@@ -232,5 +232,6 @@ pub fn reduce_block_metas<T: Borrow<BlockMeta>>(
         index_size,
         col_stats: merged_col_stats,
         cluster_stats: merged_cluster_stats,
+        create_on: None,
     }
 }
@@ -18,6 +18,7 @@ use databend_common_catalog::table::Table;
 use databend_common_exception::Result;
 use databend_common_expression::types::NumberDataType;
 use databend_common_expression::types::StringType;
+use databend_common_expression::types::TimestampType;
 use databend_common_expression::types::UInt64Type;
 use databend_common_expression::DataBlock;
 use databend_common_expression::FromData;
@@ -102,6 +103,7 @@ impl<'a> FuseSegment<'a> {
         let mut compressed: Vec<u64> = Vec::with_capacity(len);
         let mut uncompressed: Vec<u64> = Vec::with_capacity(len);
         let mut file_location: Vec<String> = Vec::with_capacity(len);
+        let mut create_on: Vec<Option<i64>> = Vec::with_capacity(len);
 
         let segments_io = SegmentsIO::create(
             self.ctx.clone(),
@@ -118,14 +120,15 @@ impl<'a> FuseSegment<'a> {
                 .read_segments::<SegmentInfo>(chunk, true)
                 .await?;
 
-            for (idx, segment) in segments.into_iter().enumerate() {
+            for (location, segment) in chunk.iter().zip(segments) {
                 let segment = segment?;
-                format_versions.push(segment_locations[idx].1);
+                format_versions.push(location.1);
                 block_count.push(segment.summary.block_count);
                 row_count.push(segment.summary.row_count);
                 compressed.push(segment.summary.compressed_byte_size);
                 uncompressed.push(segment.summary.uncompressed_byte_size);
-                file_location.push(segment_locations[idx].0.clone());
+                file_location.push(location.0.clone());
+                create_on.push(segment.summary.create_on.map(|dt| dt.timestamp_micros()));
 
                 row_num += 1;
                 if row_num >= limit {
@@ -146,6 +149,7 @@ impl<'a> FuseSegment<'a> {
             UInt64Type::from_data(row_count),
             UInt64Type::from_data(uncompressed),
             UInt64Type::from_data(compressed),
+            TimestampType::from_opt_data(create_on),
         ]))
     }
 
@@ -166,6 +170,7 @@ impl<'a> FuseSegment<'a> {
                 "bytes_compressed",
                 TableDataType::Number(NumberDataType::UInt64),
             ),
+            TableField::new("create_on", TableDataType::Timestamp.wrap_nullable()),
         ])
     }
 }
@@ -15,6 +15,7 @@
 use databend_common_exception::ErrorCode;
 use databend_common_exception::Result;
 use databend_common_meta_app::principal::StageInfo;
+use databend_common_meta_app::schema::CreateOption;
 use databend_common_meta_types::MatchSeq;
 
 use crate::UserApiProvider;
@@ -27,14 +28,16 @@ impl UserApiProvider {
         &self,
         tenant: &str,
         info: StageInfo,
-        if_not_exists: bool,
+        create_option: &CreateOption,
     ) -> Result<u64> {
         let stage_api_provider = self.get_stage_api_client(tenant)?;
-        let add_stage = stage_api_provider.add_stage(info);
+        let add_stage = stage_api_provider.add_stage(info, create_option);
         match add_stage.await {
             Ok(res) => Ok(res),
             Err(e) => {
-                if if_not_exists && e.code() == ErrorCode::STAGE_ALREADY_EXISTS {
+                if matches!(create_option, CreateOption::CreateIfNotExists(true))
+                    && e.code() == ErrorCode::STAGE_ALREADY_EXISTS
+                {
                     Ok(u64::MIN)
                 } else {
                     Err(e)
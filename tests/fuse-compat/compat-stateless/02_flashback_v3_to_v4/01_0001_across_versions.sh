@@ -80,7 +80,7 @@ FST_SNAPSHOT_S2_ID_LOC=$(echo "select snapshot_location from fuse_snapshot('defa
 
 # compact the table
 echo "doing compact"
-echo "optimize table fuse_test_compaction compact" | $BENDSQL_CLIENT_CONNECT
+echo "optimize table fuse_test_compaction compact" | $BENDSQL_CLIENT_CONNECT >/dev/null
 
 # verify the following cases:
 
@@ -132,7 +132,7 @@ echo "insert into t2 values (4)" | $BENDSQL_CLIENT_CONNECT
 #
 # note that we should use `compact segment` here, otherwise if `compact` is used,
 # the blocks will also be compacted, which produces two new segments of version 3.
-echo "optimize table t2 compact segment" | $BENDSQL_CLIENT_CONNECT
+echo "optimize table t2 compact segment" | $BENDSQL_CLIENT_CONNECT >/dev/null
 
 # according to the table options segment_per_block=3,
 # v3 segment_2 and v4 segment_3 should be compacted -- the mixed version segments compaction,
@@ -17,7 +17,7 @@ for i in $(seq 1 10);do
 done
 wait
 
-echo "optimize table test_update.t compact" | $BENDSQL_CLIENT_CONNECT
+echo "optimize table test_update.t compact" | $BENDSQL_CLIENT_CONNECT >/dev/null
 echo "select count() from test_update.t where a + 1 = b" | $BENDSQL_CLIENT_CONNECT
 
 echo "Test table lock for update"
@@ -13,7 +13,7 @@ echo "insert into db_stream.t values(2)" | $BENDSQL_CLIENT_CONNECT
 
 BASE_ROW_ID=$(echo "select _base_row_id from default.test_s" | $BENDSQL_CLIENT_CONNECT)
 echo "select change\$row_id='$BASE_ROW_ID' from default.test_s" | $BENDSQL_CLIENT_CONNECT
-echo "optimize table db_stream.t compact" | $BENDSQL_CLIENT_CONNECT
+echo "optimize table db_stream.t compact" | $BENDSQL_CLIENT_CONNECT >/dev/null
 echo "select a, change\$action, change\$is_update, change\$row_id='$BASE_ROW_ID' from default.test_s" | $BENDSQL_CLIENT_CONNECT
 
 echo "create stream test_s1 on table db_stream.t at(stream => default.test_s) append_only=false comment = 'standard'" | $BENDSQL_CLIENT_CONNECT